@@ -0,0 +1,64 @@
+//! Run with `cargo bench`. The bot is a binary only crate, so the modules under test are pulled
+//! in by path rather than through a library. Only part of each is used here, the rest is used by
+//! the bot, and their tests' imports go unused as the bench has no test harness
+#![allow(dead_code)]
+#![cfg_attr(test, allow(unused_imports))]
+
+#[path = "../src/bench_corpus.rs"]
+mod bench_corpus;
+#[path = "../src/encryption.rs"]
+mod encryption;
+#[path = "../src/guild_config.rs"]
+mod guild_config;
+#[path = "../src/language_parsing.rs"]
+mod language_parsing;
+#[path = "../src/scheduler.rs"]
+mod scheduler;
+#[path = "../src/state.rs"]
+mod state;
+#[path = "../src/stats.rs"]
+mod stats;
+#[path = "../src/utils.rs"]
+mod utils;
+
+use chrono_tz::Tz;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+const MESSAGES: usize = 1_000;
+
+fn tokenise(c: &mut Criterion) {
+    let messages = bench_corpus::synthetic_messages(MESSAGES, 20);
+    let mut group = c.benchmark_group("tokenise");
+    group.throughput(Throughput::Elements(MESSAGES as u64));
+    group.bench_function("synthetic_corpus", |b| {
+        b.iter(|| {
+            messages
+                .iter()
+                .map(|message| language_parsing::tokenise(&message.content).len())
+                .sum::<usize>()
+        })
+    });
+    group.finish();
+}
+
+fn channel_data_update(c: &mut Criterion) {
+    let messages = bench_corpus::synthetic_messages(MESSAGES, 20);
+    let mut group = c.benchmark_group("channel_data_update");
+    group.throughput(Throughput::Elements(MESSAGES as u64));
+    group.bench_function("synthetic_corpus", |b| {
+        b.iter_batched(
+            state::ChannelData::default,
+            |mut channel_data| {
+                for message in messages.iter() {
+                    channel_data.update(message, &Tz::UTC, &language_parsing::WordRules::Standard);
+                }
+                channel_data
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, tokenise, channel_data_update);
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+use std::fs;
+use std::process::Command;
+
+/// Bakes the version, build date and serenity version into the binary for the [about] command
+fn main() {
+    let version = Command::new("git")
+        .args(["describe", "--tags", "--always", "--dirty"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|describe| describe.trim().to_string())
+        .filter(|describe| !describe.is_empty())
+        .unwrap_or_else(|| env!("CARGO_PKG_VERSION").to_string());
+    println!("cargo:rustc-env=SCRIVENER_VERSION={}", version);
+    println!(
+        "cargo:rustc-env=SCRIVENER_BUILD_DATE={}",
+        chrono::Utc::now().format("%Y-%m-%d")
+    );
+    let serenity_version = fs::read_to_string("Cargo.lock")
+        .ok()
+        .and_then(|lock| locked_version(&lock, "serenity"))
+        .unwrap_or_else(|| String::from("unknown"));
+    println!("cargo:rustc-env=SERENITY_VERSION={}", serenity_version);
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    println!("cargo:rerun-if-changed=Cargo.lock");
+}
+
+fn locked_version(lock: &str, package: &str) -> Option<String> {
+    let name_line = format!("name = \"{}\"", package);
+    let mut lines = lock.lines();
+    while let Some(line) = lines.next() {
+        if line == name_line {
+            return lines
+                .next()
+                .and_then(|line| line.strip_prefix("version = \""))
+                .and_then(|version| version.strip_suffix('"'))
+                .map(String::from);
+        }
+    }
+    None
+}
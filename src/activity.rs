@@ -0,0 +1,138 @@
+use crate::config::{ActivityKind, GeneralAppConfigData, StatusTemplate};
+use crate::state::{Store, StoreData};
+use serenity::model::gateway::Activity;
+use serenity::prelude::Context;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Discord only takes a handful of presence updates a minute, so configs can't rotate faster
+const MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Totals across every server, for status templates
+#[derive(Debug, Default, PartialEq)]
+struct LiveStats {
+    words: usize,
+    stories: usize,
+    servers: usize,
+}
+
+impl LiveStats {
+    fn from_store(store: &Store) -> Self {
+        let mut live_stats = LiveStats::default();
+        for (_, server_data) in store.servers() {
+            live_stats.servers += 1;
+            for (_, channel_data) in server_data.channels() {
+                live_stats.stories += 1;
+                live_stats.words += channel_data.general_stats.word_count;
+            }
+        }
+        live_stats
+    }
+}
+
+/// e.g. 1.2M, short enough for a status
+fn compact_number(n: usize) -> String {
+    match n {
+        n if n >= 1_000_000_000 => format!("{:.1}B", n as f64 / 1_000_000_000.0),
+        n if n >= 1_000_000 => format!("{:.1}M", n as f64 / 1_000_000.0),
+        n if n >= 1_000 => format!("{:.1}K", n as f64 / 1_000.0),
+        n => n.to_string(),
+    }
+}
+
+fn render_status(template: &StatusTemplate, prefix: &str, live_stats: &LiveStats) -> Activity {
+    let text = template
+        .text
+        .replace("{prefix}", prefix)
+        .replace("{words}", &compact_number(live_stats.words))
+        .replace("{stories}", &compact_number(live_stats.stories))
+        .replace("{servers}", &compact_number(live_stats.servers));
+    match template.kind {
+        ActivityKind::Listening => Activity::listening(&text),
+        ActivityKind::Playing => Activity::playing(&text),
+        ActivityKind::Competing => Activity::competing(&text),
+    }
+}
+
+/// Background task cycling the bot's status through the templates in the config, listening to
+/// the prefix if there aren't any
+pub async fn activity_worker(ctx: Arc<Context>) {
+    let mut next_status = 0;
+    loop {
+        let (activity_config, prefix) = {
+            let config_lock = {
+                let data_read = ctx.data.read().await;
+                data_read
+                    .get::<GeneralAppConfigData>()
+                    .expect("Expected GeneralAppConfigData in TypeMap.")
+                    .clone()
+            };
+            let config = config_lock.read().unwrap();
+            (config.activity.clone(), config.prefix.clone())
+        };
+        let activity = match activity_config.statuses.is_empty() {
+            true => Activity::listening(&prefix),
+            false => {
+                let live_stats = {
+                    let store_lock = {
+                        let data_read = ctx.data.read().await;
+                        data_read
+                            .get::<StoreData>()
+                            .expect("Expected StoryData in TypeMap.")
+                            .clone()
+                    };
+                    let store = store_lock.read().unwrap();
+                    LiveStats::from_store(&store)
+                };
+                let template =
+                    &activity_config.statuses[next_status % activity_config.statuses.len()];
+                next_status += 1;
+                render_status(template, &prefix, &live_stats)
+            }
+        };
+        ctx.set_activity(activity).await;
+        tokio::time::sleep(activity_config.interval.max(MIN_INTERVAL)).await;
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::activity::{compact_number, render_status, LiveStats};
+    use crate::config::{ActivityKind, StatusTemplate};
+    use serenity::model::gateway::ActivityType;
+
+    #[test]
+    fn compact_numbers() {
+        assert_eq!(compact_number(340), "340");
+        assert_eq!(compact_number(12_345), "12.3K");
+        assert_eq!(compact_number(1_234_567), "1.2M");
+        assert_eq!(compact_number(5_000_000_000), "5.0B");
+    }
+
+    #[test]
+    fn statuses_filled_in() {
+        let live_stats = LiveStats {
+            words: 1_200_000,
+            stories: 340,
+            servers: 12,
+        };
+        let template = StatusTemplate {
+            kind: ActivityKind::Playing,
+            text: String::from("with {words} words across {stories} stories on {servers} servers"),
+        };
+        let activity = render_status(&template, "!scriv", &live_stats);
+        assert_eq!(
+            activity.name,
+            "with 1.2M words across 340 stories on 12 servers"
+        );
+        assert_eq!(activity.kind, ActivityType::Playing);
+        let template = StatusTemplate {
+            kind: ActivityKind::Listening,
+            text: String::from("{prefix} help"),
+        };
+        assert_eq!(
+            render_status(&template, "!scriv", &live_stats).name,
+            "!scriv help"
+        );
+    }
+}
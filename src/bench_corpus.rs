@@ -0,0 +1,88 @@
+//! Synthetic story messages for benchmarking stats processing, shared by the criterion benches
+//! and the [bench-replay] command so the two measure the same thing
+use chrono::{Duration, TimeZone, Utc};
+use serenity::model::channel::Message;
+
+const SYLLABLES: [&str; 16] = [
+    "ka", "lo", "mi", "ren", "tor", "us", "vel", "an", "dra", "ith", "or", "sel", "qua", "bel",
+    "nor", "eth",
+];
+
+const DISCORD_EPOCH_MS: u64 = 1_420_070_400_000;
+
+/// Small deterministic generator, so every run replays the same corpus
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        self.0 >> 33
+    }
+}
+
+/// A word from a vocabulary of a few thousand, skewed so some words are far more common than
+/// others, like real writing
+fn word(rng: &mut Lcg) -> String {
+    let vocabulary_size = 4096;
+    let index = (rng.next() % vocabulary_size) * (rng.next() % vocabulary_size) / vocabulary_size;
+    let (first, rest) = (index % 16, index / 16);
+    let mut word = String::from(SYLLABLES[first as usize]);
+    word.push_str(SYLLABLES[(rest % 16) as usize]);
+    if rest >= 16 {
+        word.push_str(SYLLABLES[(rest / 16 % 16) as usize]);
+    }
+    word
+}
+
+fn make_message(id: u64, author_id: u64, content: String) -> Message {
+    let timestamp = Utc.ymd(2021, 1, 1).and_hms(0, 0, 0) + Duration::seconds(id as i64 * 30);
+    // Real snowflake ids carry the time they were sent, which session detection relies on
+    let snowflake = ((timestamp.timestamp_millis() as u64 - DISCORD_EPOCH_MS) << 22) + id;
+    serde_json::from_value(serde_json::json!({
+        "id": snowflake.to_string(),
+        "attachments": [],
+        "author": {
+            "id": author_id.to_string(),
+            "avatar": null,
+            "bot": false,
+            "discriminator": "0001",
+            "username": format!("writer{}", author_id),
+        },
+        "channel_id": "1",
+        "content": content,
+        "edited_timestamp": null,
+        "embeds": [],
+        "guild_id": "1",
+        "type": 0,
+        "member": null,
+        "mention_everyone": false,
+        "mention_roles": [],
+        "mentions": [],
+        "pinned": false,
+        "timestamp": timestamp.to_rfc3339(),
+        "tts": false,
+        "webhook_id": null,
+        "activity": null,
+        "application": null,
+        "message_reference": null,
+        "flags": null,
+        "referenced_message": null,
+    }))
+    .expect("Synthetic message should deserialise")
+}
+
+/// [count] messages of 10 to 200 words, spread across [authors] writers
+pub fn synthetic_messages(count: usize, authors: u64) -> Vec<Message> {
+    let mut rng = Lcg(42);
+    (0..count as u64)
+        .map(|id| {
+            let length = 10 + rng.next() % 190;
+            let words: Vec<String> = (0..length).map(|_| word(&mut rng)).collect();
+            let author_id = 1 + rng.next() % authors.max(1);
+            make_message(id, author_id, words.join(" ") + ".")
+        })
+        .collect()
+}
@@ -0,0 +1,84 @@
+use crate::commands::help::{CommandHelp, Permission};
+use crate::config::GeneralAppConfigData;
+use serenity::framework::standard::{macros::command, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub struct StartTimeData;
+
+impl TypeMapKey for StartTimeData {
+    type Value = Arc<Instant>;
+}
+
+fn format_uptime(uptime: Duration) -> String {
+    let minutes = uptime.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / (60 * 24), minutes / 60 % 24, minutes % 60);
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, hours) => format!("{}h {}m", hours, minutes),
+        (days, hours) => format!("{}d {}h {}m", days, hours, minutes),
+    }
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[],
+    permission: Permission::Anyone,
+};
+
+#[command("about")]
+#[description(
+    "Which version of the bot is running, how long it's been up, and where to get support"
+)]
+async fn about(ctx: &Context, msg: &Message) -> CommandResult {
+    let (started_at, support_server) = {
+        let data_read = ctx.data.read().await;
+        let started_at = data_read
+            .get::<StartTimeData>()
+            .expect("Expected StartTimeData in TypeMap.")
+            .clone();
+        let config_lock = data_read
+            .get::<GeneralAppConfigData>()
+            .expect("Expected GeneralAppConfigData in TypeMap.")
+            .clone();
+        let support_server = config_lock.read().unwrap().support_server.clone();
+        (started_at, support_server)
+    };
+    let uptime = format_uptime(started_at.elapsed());
+    msg.channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title("Scrivener");
+                e.field("Version", env!("SCRIVENER_VERSION"), true);
+                e.field("Built", env!("SCRIVENER_BUILD_DATE"), true);
+                e.field("Serenity", env!("SERENITY_VERSION"), true);
+                e.field("Uptime", uptime, true);
+                if let Some(support_server) = support_server {
+                    e.field("Support", support_server, true);
+                }
+                e
+            })
+        })
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::commands::about::format_uptime;
+    use std::time::Duration;
+
+    #[test]
+    fn uptime() {
+        assert_eq!(format_uptime(Duration::from_secs(59)), "0m");
+        assert_eq!(
+            format_uptime(Duration::from_secs(3 * 3600 + 5 * 60)),
+            "3h 5m"
+        );
+        assert_eq!(
+            format_uptime(Duration::from_secs(2 * 86400 + 3600 + 60)),
+            "2d 1h 1m"
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::state::StoreData;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+/// [Err] if nothing was changed
+async fn link_alias(
+    ctx: &Context,
+    server_id: &GuildId,
+    alt: UserId,
+    main: UserId,
+) -> Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let server_data = store.get_server_data_mut_maybe_create(server_id);
+    server_data.config.link_alias(alt, main).map(|main| {
+        MessageBuilder::new()
+            .push("Stats for ")
+            .push_bold_safe(server_data.author_name(&alt))
+            .push(" are now shown under ")
+            .push_bold_safe(server_data.author_name(&main))
+            .build()
+    })
+}
+
+/// [Err] if nothing was changed
+async fn unlink_alias(ctx: &Context, server_id: &GuildId, alt: UserId) -> Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let server_data = store.get_server_data_mut_maybe_create(server_id);
+    match server_data.config.aliases.remove(&alt) {
+        Some(_) => Ok(MessageBuilder::new()
+            .push("Stats for ")
+            .push_bold_safe(server_data.author_name(&alt))
+            .push(" are shown separately again")
+            .build()),
+        None => Err(String::from("That account isn't linked to another")),
+    }
+}
+
+async fn list_aliases(ctx: &Context, server_id: &GuildId) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    let server_data = match store.get_server_data(server_id) {
+        Some(server_data) if !server_data.config.aliases.is_empty() => server_data,
+        _ => return String::from("No accounts are linked on this server"),
+    };
+    let mut aliases: Vec<(String, String)> = server_data
+        .config
+        .aliases
+        .iter()
+        .map(|(alt, main)| (server_data.author_name(main), server_data.author_name(alt)))
+        .collect();
+    aliases.sort();
+    let mut builder = MessageBuilder::new();
+    builder.push_bold_line("Linked accounts, alt -> main");
+    for (main, alt) in aliases {
+        builder.push_line_safe(format!("{} -> {}", alt, main));
+    }
+    builder.build()
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        (
+            "link <@alt> <@main>",
+            "Show the alt account's stats as part of the main account's",
+        ),
+        (
+            "unlink <@alt>",
+            "Show the alt account's stats separately again",
+        ),
+        ("list", "Show the linked accounts on this server"),
+    ],
+    permission: Permission::AllowedRolesToChange,
+};
+
+#[command("alias")]
+#[usage("link <@alt> <@main> | unlink <@alt> | list")]
+#[description("Link a writer's alt accounts to their main one, so stats, summaries, word clouds and exports count them as one writer. Nothing recorded is changed, unlinking splits them up again. Linking and unlinking need the same role as init-channel")]
+#[example("link @Caligula_alt @Caligula")]
+#[example("unlink @Caligula_alt")]
+#[example("list")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn alias(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match args.single::<String>().as_deref() {
+            Ok("list") => list_aliases(ctx, &server_id).await,
+            Ok(action @ "link") | Ok(action @ "unlink") => {
+                match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+                    true => {
+                        let changed =
+                            match (action, args.single::<UserId>(), args.single::<UserId>()) {
+                                ("link", Ok(alt), Ok(main)) => {
+                                    link_alias(ctx, &server_id, alt, main).await
+                                }
+                                ("unlink", Ok(alt), Err(_)) => {
+                                    unlink_alias(ctx, &server_id, alt).await
+                                }
+                                _ => Err(String::from("Invalid arguments, try [help alias]")),
+                            };
+                        match changed {
+                            Ok(reply) => {
+                                record_admin_action(ctx, msg, "alias").await;
+                                reply
+                            }
+                            Err(e) => e,
+                        }
+                    }
+                    false => not_in_allowed_roles_response(),
+                }
+            }
+            _ => String::from("Invalid arguments, try [help alias]"),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::state::{AuditEntry, ServerData, StoreData};
+use chrono::Utc;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+const DEFAULT_AUDIT_ENTRIES: usize = 10;
+// Keeps the reply within a single message
+const MAX_SHOWN_AUDIT_ENTRIES: usize = 25;
+
+/// Records a change made by [msg]'s command in its server's audit log. Call once the change has
+/// actually been made
+pub async fn record_admin_action(ctx: &Context, msg: &Message, command_name: &str) {
+    if let Some(server_id) = msg.guild_id {
+        record_audit_entry(
+            ctx,
+            &server_id,
+            msg.author.id,
+            command_name,
+            msg.content.clone(),
+        )
+        .await;
+    }
+}
+
+/// As [record_admin_action], for changes made some other way than a command message, described
+/// by [invocation]
+pub async fn record_audit_entry(
+    ctx: &Context,
+    server_id: &GuildId,
+    actor: UserId,
+    command_name: &str,
+    invocation: String,
+) {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    store
+        .get_server_data_mut_maybe_create(server_id)
+        .record_audit(AuditEntry {
+            actor,
+            at: Utc::now(),
+            command: command_name.to_string(),
+            invocation,
+        });
+}
+
+/// Newest first
+fn make_audit_log_string(server_data: &ServerData, count: usize) -> String {
+    if server_data.audit_log.is_empty() {
+        return String::from("No admin actions recorded on this server");
+    }
+    let mut builder = MessageBuilder::new();
+    for entry in server_data.audit_log.iter().rev().take(count) {
+        builder
+            .push(format!("{} UTC ", entry.at.format("%Y-%m-%d %H:%M")))
+            .push_bold_safe(server_data.author_name(&entry.actor))
+            .push(format!(" {}: ", entry.command))
+            .push_mono_line_safe(&entry.invocation);
+    }
+    builder.build()
+}
+
+async fn get_audit_log(ctx: &Context, server_id: &GuildId, count: usize) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    match store.get_server_data(server_id) {
+        Some(server_data) => make_audit_log_string(server_data, count),
+        None => String::from("No admin actions recorded on this server"),
+    }
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[(
+        "[count]",
+        "How many of the latest actions to show, 10 if not given and at most 25",
+    )],
+    permission: Permission::AllowedRoles,
+};
+
+#[command("audit-log")]
+#[usage("[count]")]
+#[description("Show the latest admin and settings changes on this server, newest first: who made them, when, and the command as they wrote it. Covers initialising, deinitialising, merging and rebuilding channels and every settings command. Needs the same role as init-channel")]
+#[example("")]
+#[example("25")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn audit_log(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+            true => {
+                let count = if args.is_empty() {
+                    Ok(DEFAULT_AUDIT_ENTRIES)
+                } else {
+                    match args.single::<usize>() {
+                        Ok(count) if (1..=MAX_SHOWN_AUDIT_ENTRIES).contains(&count) => Ok(count),
+                        _ => Err(format!(
+                            "Count should be a whole number from 1 to {}, try [help audit-log]",
+                            MAX_SHOWN_AUDIT_ENTRIES
+                        )),
+                    }
+                };
+                match count {
+                    Ok(count) => get_audit_log(ctx, &server_id, count).await,
+                    Err(e) => e,
+                }
+            }
+            false => not_in_allowed_roles_response(),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::commands::audit_log::make_audit_log_string;
+    use crate::state::{AuditEntry, ServerData};
+    use chrono::{TimeZone, Utc};
+    use serenity::model::prelude::*;
+
+    #[test]
+    fn newest_first() {
+        let mut server_data = ServerData::new();
+        assert_eq!(
+            make_audit_log_string(&server_data, 10),
+            "No admin actions recorded on this server"
+        );
+        for (hour, command) in [(9, "set-prefix"), (10, "deinit-channel")] {
+            server_data.record_audit(AuditEntry {
+                actor: UserId(3),
+                at: Utc.ymd(2021, 2, 1).and_hms(hour, 30, 0),
+                command: command.to_string(),
+                invocation: format!("!scriv {} <#1>", command),
+            });
+        }
+        let log = make_audit_log_string(&server_data, 10);
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("2021-02-01 10:30 UTC"));
+        assert!(lines[0].contains("deinit-channel"));
+        assert_eq!(make_audit_log_string(&server_data, 1).lines().count(), 1);
+    }
+}
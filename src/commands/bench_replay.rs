@@ -0,0 +1,62 @@
+use crate::bench_corpus::synthetic_messages;
+use crate::language_parsing::WordRules;
+use crate::state::ChannelData;
+use crate::ADMINONLY_CHECK;
+use chrono_tz::Tz;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use std::time::{Duration, Instant};
+
+const DEFAULT_MESSAGES: usize = 10_000;
+const MAX_MESSAGES: usize = 200_000;
+
+fn messages_per_sec(messages: usize, elapsed: Duration) -> f64 {
+    messages as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+}
+
+/// Times tokenising on its own, then the full stats update, over the same corpus
+fn run_replay(message_count: usize) -> String {
+    let messages = synthetic_messages(message_count, 20);
+    let start = Instant::now();
+    let mut token_count = 0;
+    for message in messages.iter() {
+        token_count += crate::language_parsing::tokenise(&message.content).len();
+    }
+    let tokenise_elapsed = start.elapsed();
+    let mut channel_data = ChannelData::default();
+    let start = Instant::now();
+    for message in messages.iter() {
+        channel_data.update(message, &Tz::UTC, &WordRules::Standard);
+    }
+    let update_elapsed = start.elapsed();
+    format!(
+        "Replayed {} synthetic messages ({} tokens, {} distinct words)\nTokenise: {:.0} messages/sec ({:?})\nChannelData::update: {:.0} messages/sec ({:?})",
+        message_count,
+        token_count,
+        channel_data.general_stats.word_frequencies.len(),
+        messages_per_sec(message_count, tokenise_elapsed),
+        tokenise_elapsed,
+        messages_per_sec(message_count, update_elapsed),
+        update_elapsed,
+    )
+}
+
+#[command("bench-replay")]
+#[usage("[number of messages]")]
+#[description("Replays a synthetic corpus through tokenising and the stats update, and reports how many messages a second each manages")]
+#[example("50000")]
+#[checks("AdminOnly")]
+async fn bench_replay(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let message_count = args
+        .single::<usize>()
+        .unwrap_or(DEFAULT_MESSAGES)
+        .min(MAX_MESSAGES);
+    // Keep the replay off the async runtime, it's deliberately busy
+    let reply = match tokio::task::spawn_blocking(move || run_replay(message_count)).await {
+        Ok(report) => report,
+        Err(e) => format!("Replay failed: {}", e),
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
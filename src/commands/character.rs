@@ -0,0 +1,308 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::guild_config::{update_channel_config, Character};
+use crate::state::{ServerData, StoreData};
+use crate::utils::trait_extensions::MessageBuilderExt;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+const MAX_CHARACTERS: usize = 500;
+// Discord's limit on webhook names, which proxy bots post characters under
+const MAX_NAME_LENGTH: usize = 80;
+
+fn valid_name(name: &str) -> bool {
+    (1..=MAX_NAME_LENGTH).contains(&name.chars().count()) && !name.contains(['[', ']', '\n'])
+}
+
+async fn get_character(ctx: &Context, server_id: &GuildId, name: &str) -> Option<Character> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    store
+        .get_server_data(server_id)
+        .and_then(|server_data| server_data.config.characters.get(name).cloned())
+}
+
+/// Players can look after their own characters, anyone else's needs an allowed role
+async fn may_change(
+    ctx: &Context,
+    server_id: &GuildId,
+    author: &User,
+    player: Option<UserId>,
+) -> bool {
+    player == Some(author.id) || author_is_in_allowed_roles(ctx, server_id, author).await
+}
+
+/// [Err] if nothing was changed, as for [remove_character] and [assign_character]
+async fn register_character(
+    ctx: &Context,
+    server_id: &GuildId,
+    character: Character,
+) -> Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let server_data = store.get_server_data_mut_maybe_create(server_id);
+    let characters = &server_data.config.characters;
+    if characters.get(&character.name).is_none() && characters.len() >= MAX_CHARACTERS {
+        return Err(format!(
+            "This server already has {} characters, remove one with [character remove] first",
+            MAX_CHARACTERS
+        ));
+    }
+    let mut builder = MessageBuilder::new();
+    builder.push("Registered ").push_bold_safe(&character.name);
+    if let Some(player) = character.player {
+        builder.push_safe(format!(" for {}", server_data.shown_name_by_id(&player)));
+    }
+    builder.push(". Posts tagged [").push_safe(&character.name).push(
+        "] or made by a proxy bot under that name count towards them from now on, use [rebuild-stats] to count older ones",
+    );
+    server_data.config.characters.insert(character);
+    Ok(builder.build())
+}
+
+async fn remove_character(
+    ctx: &Context,
+    server_id: &GuildId,
+    name: &str,
+) -> Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let server_data = store.get_server_data_mut_maybe_create(server_id);
+    match server_data.config.characters.remove(name) {
+        Some(character) => Ok(MessageBuilder::new()
+            .push("Removed ")
+            .push_bold_safe(character.name)
+            .push(", their stats so far are kept until the channel is rebuilt")
+            .build()),
+        None => Err(String::from(
+            "No character by that name, see [character list]",
+        )),
+    }
+}
+
+async fn list_characters(ctx: &Context, server_id: &GuildId) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    match store.get_server_data(server_id) {
+        Some(server_data) if !server_data.config.characters.is_empty() => {
+            describe_characters(server_data)
+        }
+        _ => String::from("No characters registered on this server"),
+    }
+}
+
+fn describe_characters(server_data: &ServerData) -> String {
+    let mut characters: Vec<&Character> = server_data.config.characters.iter().collect();
+    characters.sort_by_key(|character| character.name.to_lowercase());
+    let mut builder = MessageBuilder::new();
+    builder.push_bold_line("Characters");
+    for character in characters {
+        builder.push_safe(&character.name);
+        if let Some(player) = character.player {
+            builder.push_safe(format!(
+                " (played by {})",
+                server_data.shown_name_by_id(&player)
+            ));
+        }
+        builder.newline();
+    }
+    builder.build()
+}
+
+async fn assign_character(
+    ctx: &Context,
+    server_id: &GuildId,
+    channel_id: &ChannelId,
+    author: &User,
+    name: &str,
+) -> Result<String, String> {
+    if name == "off" {
+        update_channel_config(ctx, server_id, channel_id, |config| {
+            config.characters.remove(&author.id);
+        })
+        .await;
+        return Ok(MessageBuilder::new()
+            .push("Your untagged posts in ")
+            .channel(channel_id)
+            .push(" no longer count towards a character")
+            .build());
+    }
+    match get_character(ctx, server_id, name).await {
+        Some(character) if !may_change(ctx, server_id, author, character.player).await => {
+            Err(String::from("That character is played by someone else"))
+        }
+        Some(character) => {
+            let response = MessageBuilder::new()
+                .push("Your untagged posts in ")
+                .channel(channel_id)
+                .push(" now count towards ")
+                .push_bold_safe(&character.name)
+                .build();
+            update_channel_config(ctx, server_id, channel_id, |config| {
+                config.characters.insert(author.id, character.name);
+            })
+            .await;
+            Ok(response)
+        }
+        None => Err(String::from(
+            "No character by that name, register them first",
+        )),
+    }
+}
+
+/// The reply to a [change], recorded in the audit log if it was made
+async fn record_change(ctx: &Context, msg: &Message, change: Result<String, String>) -> String {
+    match change {
+        Ok(reply) => {
+            record_admin_action(ctx, msg, "character").await;
+            reply
+        }
+        Err(reply) => reply,
+    }
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        (
+            "register <name>",
+            "Add a character, quote names with spaces in them",
+        ),
+        (
+            "[@player]",
+            "Who plays them, you if not given. Registering for someone else needs an allowed role",
+        ),
+        (
+            "remove <name>",
+            "Remove a character, yours or anyone's with an allowed role",
+        ),
+        (
+            "assign <#channel name> <name|off>",
+            "Count your untagged posts in a channel towards one of your characters",
+        ),
+        ("list", "Show this server's characters"),
+    ],
+    permission: Permission::Anyone,
+};
+
+#[command("character")]
+#[usage("register <name> [@player] | remove <name> | assign <#channel name> <name|off> | list")]
+#[description("Register roleplay characters so words can be counted for them as well as their players. Posts count towards a character when they start with a tag like [Kira], are made by a proxy bot under the character's name, or are in a channel where the player assigned themselves that character. See them with [show-stats -by-character]")]
+#[example("register Kira")]
+#[example("register \"Old Tom\" @Caligula")]
+#[example("assign #the-fall-of-rome Kira")]
+#[example("remove Kira")]
+#[example("list")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn character(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match args.single::<String>().as_deref() {
+            Ok("list") => list_characters(ctx, &server_id).await,
+            Ok("register") => match args.single_quoted::<String>() {
+                Ok(name) if valid_name(&name) => {
+                    let player = match args.single::<UserId>() {
+                        Ok(player) => player,
+                        Err(_) => msg.author.id,
+                    };
+                    let existing_player = get_character(ctx, &server_id, &name)
+                        .await
+                        .map_or(Some(player), |existing| existing.player);
+                    match may_change(ctx, &server_id, &msg.author, Some(player)).await
+                        && may_change(ctx, &server_id, &msg.author, existing_player).await
+                    {
+                        true => {
+                            let character = Character {
+                                name,
+                                player: Some(player),
+                            };
+                            let change = register_character(ctx, &server_id, character).await;
+                            record_change(ctx, msg, change).await
+                        }
+                        false => not_in_allowed_roles_response(),
+                    }
+                }
+                _ => format!(
+                    "Character names should be 1 to {} characters long, without square brackets",
+                    MAX_NAME_LENGTH
+                ),
+            },
+            Ok("remove") => match args.single_quoted::<String>() {
+                Ok(name) => match get_character(ctx, &server_id, &name).await {
+                    Some(existing) => {
+                        match may_change(ctx, &server_id, &msg.author, existing.player).await {
+                            true => {
+                                let change = remove_character(ctx, &server_id, &name).await;
+                                record_change(ctx, msg, change).await
+                            }
+                            false => not_in_allowed_roles_response(),
+                        }
+                    }
+                    None => String::from("No character by that name, see [character list]"),
+                },
+                Err(_) => String::from("Expected the name of the character to remove"),
+            },
+            Ok("assign") => match (args.single::<ChannelId>(), args.single_quoted::<String>()) {
+                (Ok(channel_id), Ok(name)) => {
+                    let change =
+                        assign_character(ctx, &server_id, &channel_id, &msg.author, &name).await;
+                    record_change(ctx, msg, change).await
+                }
+                _ => String::from("Invalid arguments, try [help character]"),
+            },
+            _ => String::from("Invalid arguments, try [help character]"),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::commands::character::describe_characters;
+    use crate::guild_config::Character;
+    use crate::state::ServerData;
+    use serenity::model::prelude::*;
+
+    #[test]
+    fn private_players() {
+        let mut server_data = ServerData::new();
+        server_data.display_names.update(&UserId(1), "Caligula");
+        server_data.config.characters.insert(Character {
+            name: String::from("Kira"),
+            player: Some(UserId(1)),
+        });
+        assert!(describe_characters(&server_data).contains("Kira (played by Caligula)"));
+        server_data.config.privacy_mode = true;
+        let described = describe_characters(&server_data);
+        assert!(described.contains("Kira (played by Writer A)"));
+        assert!(!described.contains("Caligula"));
+    }
+}
@@ -0,0 +1,196 @@
+use crate::commands::help::{CommandHelp, Permission};
+use crate::state::StoreData;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+/// What the bot needs in a story channel, and what for. Without the first two there's nothing to
+/// count, so channels aren't initialised without them
+const NEEDED_PERMISSIONS: [(Permissions, &str, &str); 5] = [
+    (
+        Permissions::READ_MESSAGES,
+        "View Channel",
+        "to see the channel at all",
+    ),
+    (
+        Permissions::READ_MESSAGE_HISTORY,
+        "Read Message History",
+        "to count what was written before",
+    ),
+    (
+        Permissions::SEND_MESSAGES,
+        "Send Messages",
+        "to reply and post nudges",
+    ),
+    (
+        Permissions::ATTACH_FILES,
+        "Attach Files",
+        "for wordclouds and exports",
+    ),
+    (
+        Permissions::EMBED_LINKS,
+        "Embed Links",
+        "for links to posts in recaps",
+    ),
+];
+const BLOCKING_PERMISSIONS: usize = 2;
+
+/// Names and reasons for each needed permission [granted] lacks
+fn missing_permissions(granted: Permissions) -> Vec<(&'static str, &'static str)> {
+    NEEDED_PERMISSIONS
+        .iter()
+        .filter(|(permission, _, _)| !granted.contains(*permission))
+        .map(|(_, name, reason)| (*name, *reason))
+        .collect()
+}
+
+fn can_count(granted: Permissions) -> bool {
+    NEEDED_PERMISSIONS[..BLOCKING_PERMISSIONS]
+        .iter()
+        .all(|(permission, _, _)| granted.contains(*permission))
+}
+
+fn describe_permissions(channel_id: &ChannelId, granted: Permissions) -> String {
+    let missing = missing_permissions(granted);
+    let mut builder = MessageBuilder::new();
+    if missing.is_empty() {
+        return builder
+            .push("I have everything I need in ")
+            .channel(channel_id)
+            .build();
+    }
+    builder
+        .push("In ")
+        .channel(channel_id)
+        .push(" I'm missing:");
+    for (name, reason) in missing {
+        builder.push(format!("\n• {}, {}", name, reason));
+    }
+    builder.build()
+}
+
+/// The bot's permissions in [channel], from the cache of the server and its roles
+async fn bot_permissions(
+    ctx: &Context,
+    channel: &GuildChannel,
+) -> std::result::Result<Permissions, String> {
+    let bot_id = ctx.cache.current_user_id().await;
+    channel
+        .permissions_for_user(&ctx.cache, bot_id)
+        .await
+        .map_err(|e| format!("Couldn't work out my permissions: {}", e))
+}
+
+/// An explanation of what's missing if [channel] can't be counted, checked before initialising
+/// it. Anything else missing is listed in [Ok]
+pub async fn preflight_permissions(
+    ctx: &Context,
+    channel: &GuildChannel,
+) -> std::result::Result<Option<String>, String> {
+    let granted = bot_permissions(ctx, channel).await?;
+    let description = describe_permissions(&channel.id, granted);
+    match (can_count(granted), missing_permissions(granted).is_empty()) {
+        (false, _) => Err(description),
+        (true, true) => Ok(None),
+        (true, false) => Ok(Some(description)),
+    }
+}
+
+async fn check_channels(ctx: &Context, channel_ids: Vec<ChannelId>) -> String {
+    let mut lines = vec![];
+    for channel_id in channel_ids {
+        let line = match channel_id
+            .to_channel(ctx)
+            .await
+            .ok()
+            .and_then(|c| c.guild())
+        {
+            Some(channel) => match bot_permissions(ctx, &channel).await {
+                Ok(granted) => describe_permissions(&channel_id, granted),
+                Err(e) => e,
+            },
+            None => MessageBuilder::new()
+                .push("I can't see ")
+                .channel(channel_id)
+                .push(" at all")
+                .build(),
+        };
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[(
+        "[#channel name]",
+        "The channel to check, every initialised channel if not given",
+    )],
+    permission: Permission::Anyone,
+};
+
+#[command("check-perms")]
+#[usage("[#channel name]")]
+#[description("Check the bot has the permissions it needs in a channel: View Channel and Read Message History to count stats, Send Messages to reply, Attach Files for wordclouds and Embed Links for links in recaps. Lists exactly what's missing")]
+#[example("#the-fall-of-rome")]
+#[example("")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn check_perms(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match args.single::<ChannelId>() {
+            Ok(channel_id) => check_channels(ctx, vec![channel_id]).await,
+            Err(_) if args.is_empty() => {
+                let channel_ids = {
+                    let store_lock = {
+                        let data_read = ctx.data.read().await;
+                        data_read
+                            .get::<StoreData>()
+                            .expect("Expected StoryData in TypeMap.")
+                            .clone()
+                    };
+                    let store = store_lock.read().unwrap();
+                    store.get_all_channels_in_server(&server_id)
+                };
+                match channel_ids.is_empty() {
+                    true => String::from(
+                        "No channels here are initialised yet, give one to check it before initialising",
+                    ),
+                    false => check_channels(ctx, channel_ids).await,
+                }
+            }
+            Err(_) => String::from("Invalid arguments, try [help check-perms]"),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::commands::check_perms::{can_count, describe_permissions, missing_permissions};
+    use serenity::model::prelude::*;
+
+    #[test]
+    fn missing_listed() {
+        let granted = Permissions::READ_MESSAGES
+            | Permissions::READ_MESSAGE_HISTORY
+            | Permissions::SEND_MESSAGES;
+        assert!(can_count(granted));
+        assert_eq!(
+            missing_permissions(granted)
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<&str>>(),
+            vec!["Attach Files", "Embed Links"]
+        );
+        assert!(!can_count(
+            Permissions::READ_MESSAGES | Permissions::SEND_MESSAGES
+        ));
+        assert!(missing_permissions(Permissions::all()).is_empty());
+        assert!(describe_permissions(&ChannelId(7), Permissions::all()).contains("everything"));
+        assert!(describe_permissions(&ChannelId(7), Permissions::empty())
+            .contains("Read Message History, to count what was written before"));
+    }
+}
@@ -0,0 +1,274 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::commands::set_prefix::validate_prefix;
+use crate::guild_config::{
+    ChannelConfigExport, GuildConfig, GuildConfigExport, GUILD_CONFIG_EXPORT_VERSION,
+};
+use crate::language_parsing::WordRules;
+use crate::state::StoreData;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::http::AttachmentType;
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::collections::{BTreeSet, HashMap};
+
+async fn export_config(
+    ctx: &Context,
+    server_id: &GuildId,
+    guild_channels: &HashMap<ChannelId, GuildChannel>,
+) -> serde_json::Result<String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    let export = match store.get_server_data(server_id) {
+        Some(server_data) => {
+            let tracked_channels = server_data.get_all_channel_ids();
+            let channel_ids: BTreeSet<ChannelId> = tracked_channels
+                .iter()
+                .chain(server_data.config.channels.keys())
+                .cloned()
+                .collect();
+            GuildConfigExport {
+                version: GUILD_CONFIG_EXPORT_VERSION,
+                timezone: server_data.config.timezone,
+                prefix: server_data.config.prefix.clone(),
+                quiet_hours: server_data.config.quiet_hours.clone(),
+                word_rules: server_data.config.word_rules,
+                characters: server_data.config.characters.iter().cloned().collect(),
+                channels: channel_ids
+                    .into_iter()
+                    .map(|channel_id| ChannelConfigExport {
+                        channel_id,
+                        channel_name: guild_channels
+                            .get(&channel_id)
+                            .map_or(String::new(), |channel| channel.name.clone()),
+                        tracked: tracked_channels.contains(&channel_id),
+                        config: server_data.config.channel(&channel_id),
+                    })
+                    .collect(),
+            }
+        }
+        None => GuildConfigExport {
+            version: GUILD_CONFIG_EXPORT_VERSION,
+            timezone: None,
+            prefix: None,
+            quiet_hours: None,
+            word_rules: WordRules::default(),
+            characters: vec![],
+            channels: vec![],
+        },
+    };
+    serde_json::to_string_pretty(&export)
+}
+
+/// Applies the export's server-wide settings to [config]. An export without a timezone leaves the
+/// server's as it is, and a prefix set-prefix wouldn't take is left out. Returns why if so
+fn apply_server_settings(config: &mut GuildConfig, export: &GuildConfigExport) -> Option<String> {
+    if export.timezone.is_some() {
+        config.timezone = export.timezone;
+    }
+    let prefix = export.prefix.as_deref().map(validate_prefix).transpose();
+    let skipped_prefix = match prefix {
+        Ok(prefix) => {
+            config.prefix = prefix;
+            None
+        }
+        Err(e) => Some(format!("Left the prefix as it was: {}", e)),
+    };
+    config.quiet_hours = export.quiet_hours.clone();
+    config.word_rules = export.word_rules;
+    for character in export.characters.iter() {
+        config.characters.insert(character.clone());
+    }
+    skipped_prefix
+}
+
+/// Applies the export to this server, returning a summary of what couldn't be applied
+async fn import_config(
+    ctx: &Context,
+    server_id: &GuildId,
+    guild_channels: &HashMap<ChannelId, GuildChannel>,
+    export: GuildConfigExport,
+) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let server_data = store.get_server_data_mut_maybe_create(server_id);
+    let tracked_channels = server_data.get_all_channel_ids();
+    let skipped_prefix = apply_server_settings(&mut server_data.config, &export);
+    let mut imported = 0;
+    let mut unmatched = vec![];
+    let mut to_initialise = vec![];
+    for channel_export in export.channels {
+        let local_channel_id = if guild_channels.contains_key(&channel_export.channel_id) {
+            Some(channel_export.channel_id)
+        } else {
+            guild_channels
+                .values()
+                .find(|channel| channel.name == channel_export.channel_name)
+                .map(|channel| channel.id)
+        };
+        match local_channel_id {
+            Some(channel_id) => {
+                server_data
+                    .config
+                    .channels
+                    .insert(channel_id, channel_export.config);
+                imported += 1;
+                if channel_export.tracked && !tracked_channels.contains(&channel_id) {
+                    to_initialise.push(channel_id);
+                }
+            }
+            None => unmatched.push(channel_export.channel_name),
+        }
+    }
+    let mut builder = MessageBuilder::new();
+    builder.push(format!(
+        "Imported settings for the server and {} channel(s)",
+        imported
+    ));
+    if let Some(skipped_prefix) = skipped_prefix {
+        builder.push("\n").push_safe(skipped_prefix);
+    }
+    if !unmatched.is_empty() {
+        builder
+            .push("\nNo channel here matched: ")
+            .push_safe(unmatched.join(", "));
+    }
+    if !to_initialise.is_empty() {
+        builder.push("\nThese were tracked but aren't here yet, use [init-channel] on them:");
+        for channel_id in to_initialise {
+            builder.push(" ").channel(channel_id);
+        }
+    }
+    builder.build()
+}
+
+async fn read_attached_export(msg: &Message) -> std::result::Result<GuildConfigExport, String> {
+    let attachment = msg
+        .attachments
+        .first()
+        .ok_or_else(|| String::from("Attach the exported config file to the message"))?;
+    let bytes = attachment
+        .download()
+        .await
+        .map_err(|e| format!("Failed downloading attachment: {}", e))?;
+    let export: GuildConfigExport = serde_json::from_slice(&bytes)
+        .map_err(|e| format!("That doesn't look like an exported config: {}", e))?;
+    if export.version > GUILD_CONFIG_EXPORT_VERSION {
+        return Err(String::from(
+            "That config was exported by a newer version of the bot",
+        ));
+    }
+    Ok(export)
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[(
+        "<export|import>",
+        "Export the settings as a file, or import the file attached to the message",
+    )],
+    permission: Permission::AllowedRoles,
+};
+
+#[command("config")]
+#[usage("<export|import>")]
+#[description("Export this server's settings (timezone, prefix, quiet hours, word counting rules, tracked channels and their settings) as a JSON file, or import one by attaching it to the message. Useful as a backup, or to copy a setup to another server, where channels are matched by name. Needs the same role as init-channel")]
+#[example("export")]
+#[example("import")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn config(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+            true => {
+                let guild_channels = server_id.channels(&ctx.http).await?;
+                match args.single::<String>().ok().as_deref() {
+                    Some("export") => match export_config(ctx, &server_id, &guild_channels).await {
+                        Ok(export) => {
+                            let files = vec![AttachmentType::Bytes {
+                                data: export.into_bytes().into(),
+                                filename: format!("scrivener-config-{}.json", server_id),
+                            }];
+                            msg.channel_id
+                                .send_files(&ctx.http, files, |create_message| {
+                                    create_message.content("Here's this server's config")
+                                })
+                                .await?;
+                            return Ok(());
+                        }
+                        Err(e) => format!("Failed serialising config: {}", e),
+                    },
+                    Some("import") => match read_attached_export(msg).await {
+                        Ok(export) => {
+                            let reply =
+                                import_config(ctx, &server_id, &guild_channels, export).await;
+                            record_admin_action(ctx, msg, "config").await;
+                            reply
+                        }
+                        Err(e) => e,
+                    },
+                    _ => String::from("Expected export or import, try [help config]"),
+                }
+            }
+            false => not_in_allowed_roles_response(),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::commands::config::apply_server_settings;
+    use crate::guild_config::{GuildConfig, GuildConfigExport, GUILD_CONFIG_EXPORT_VERSION};
+    use crate::language_parsing::WordRules;
+    use chrono_tz::Tz;
+
+    fn export(timezone: Option<Tz>, prefix: Option<&str>) -> GuildConfigExport {
+        GuildConfigExport {
+            version: GUILD_CONFIG_EXPORT_VERSION,
+            timezone,
+            prefix: prefix.map(String::from),
+            quiet_hours: None,
+            word_rules: WordRules::default(),
+            characters: vec![],
+            channels: vec![],
+        }
+    }
+
+    #[test]
+    fn imported_server_settings() {
+        let mut config = GuildConfig {
+            timezone: Some(Tz::Europe__London),
+            prefix: Some(String::from("?scriv")),
+            ..GuildConfig::default()
+        };
+        assert!(apply_server_settings(&mut config, &export(None, Some("s!"))).is_none());
+        assert_eq!(config.timezone, Some(Tz::Europe__London));
+        assert_eq!(config.prefix.as_deref(), Some("s!"));
+
+        for prefix in ["", "muchtoolong?prefix"] {
+            let skipped = apply_server_settings(&mut config, &export(Some(Tz::UTC), Some(prefix)));
+            assert!(skipped.unwrap().starts_with("Left the prefix as it was"));
+            assert_eq!(config.prefix.as_deref(), Some("s!"));
+        }
+        assert_eq!(config.timezone, Some(Tz::UTC));
+
+        assert!(apply_server_settings(&mut config, &export(None, None)).is_none());
+        assert_eq!(config.prefix, None);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::guild_config::update_channel_config;
+use crate::state::{StoreData, StoryKey};
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+async fn get_content_report(
+    ctx: &Context,
+    story_key: &StoryKey,
+    text_channel: &GuildChannel,
+) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    match (
+        store.get_server_data(&story_key.0),
+        store.get_channel_data(story_key),
+    ) {
+        (Some(server_data), Some(channel_data)) => {
+            let channel_config = server_data.config.channel(&story_key.1);
+            match channel_config.content_reports {
+                true => server_data.aliased(channel_data).make_content_report_string(
+                    text_channel,
+                    server_data,
+                    &channel_config.content_categories(),
+                ),
+                false => String::from(
+                    "Content reports are off for this channel, turn them on with [content-report #channel on]",
+                ),
+            }
+        }
+        _ => String::from("Channel not initialised, use [init-channel] to add it"),
+    }
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        (
+            "<#channel name>",
+            "An initialised channel, sends its report by DM if nothing else is given",
+        ),
+        ("on|off", "Turn reports for the channel on or off"),
+        (
+            "watch <category> <words...>",
+            "Add words to a custom category",
+        ),
+        ("unwatch <category>", "Remove a custom category"),
+    ],
+    permission: Permission::AllowedRoles,
+};
+
+#[command("content-report")]
+#[usage("<#channel name> [on|off|watch <category> <words...>|unwatch <category>]")]
+#[description("Moderation report of how often words from watch lists (profanity, plus any custom categories) are used in a channel, sent by DM. Off until turned on for the channel. Everything needs the same role as init-channel")]
+#[example("#the-fall-of-rome on")]
+#[example("#the-fall-of-rome")]
+#[example("#the-fall-of-rome watch spoilers rosebud sled")]
+#[example("#the-fall-of-rome unwatch spoilers")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn content_report(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+            true => match (args.single::<ChannelId>(), args.single::<String>().ok()) {
+                (Ok(channel_id), None) => match channel_id.to_channel(&ctx).await?.guild() {
+                    Some(text_channel) => {
+                        let report =
+                            get_content_report(ctx, &(server_id, channel_id), &text_channel).await;
+                        msg.author
+                            .direct_message(ctx, |m| m.content(report))
+                            .await?;
+                        String::from("Sent you the content report")
+                    }
+                    None => String::from("Channel must be a server text channel"),
+                },
+                (Ok(channel_id), Some(action)) => match action.as_str() {
+                    "on" | "off" => {
+                        let enabled = action == "on";
+                        update_channel_config(ctx, &server_id, &channel_id, |config| {
+                            config.content_reports = enabled
+                        })
+                        .await;
+                        record_admin_action(ctx, msg, "content-report").await;
+                        MessageBuilder::new()
+                            .push(format!("Content reports turned {} for ", action))
+                            .channel(channel_id)
+                            .build()
+                    }
+                    "watch" => match args.single::<String>() {
+                        Ok(category) if !args.is_empty() => {
+                            let words: Vec<String> = args
+                                .iter::<String>()
+                                .flatten()
+                                .map(|word| word.to_lowercase())
+                                .collect();
+                            let count = words.len();
+                            update_channel_config(ctx, &server_id, &channel_id, |config| {
+                                config
+                                    .watch_lists
+                                    .entry(category.clone())
+                                    .or_default()
+                                    .extend(words)
+                            })
+                            .await;
+                            record_admin_action(ctx, msg, "content-report").await;
+                            format!("Added {} word(s) to the {} watch list", count, category)
+                        }
+                        _ => String::from("Expected a category and some words to watch for"),
+                    },
+                    "unwatch" => match args.single::<String>() {
+                        Ok(category) => {
+                            update_channel_config(ctx, &server_id, &channel_id, |config| {
+                                config.watch_lists.remove(&category);
+                            })
+                            .await;
+                            record_admin_action(ctx, msg, "content-report").await;
+                            format!("Removed the {} watch list", category)
+                        }
+                        Err(_) => String::from("Expected a category to stop watching"),
+                    },
+                    _ => String::from("Unknown action, try [help content-report]"),
+                },
+                _ => String::from("Expected a channel, try [help content-report]"),
+            },
+            false => not_in_allowed_roles_response(),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
@@ -0,0 +1,179 @@
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::word_rules::get_word_rules;
+use crate::language_parsing::{reading_ease, WordRules};
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+
+/// A message link like https://discord.com/channels/<server>/<channel>/<message>, or a bare
+/// message id in [channel_id]
+fn parse_message_ref(
+    arg: &str,
+    channel_id: ChannelId,
+) -> Option<(Option<GuildId>, ChannelId, MessageId)> {
+    if let Ok(message_id) = arg.parse::<u64>() {
+        return Some((None, channel_id, MessageId(message_id)));
+    }
+    let ids: Vec<u64> = arg
+        .trim_matches(|c| c == '<' || c == '>')
+        .split("/channels/")
+        .nth(1)?
+        .split('/')
+        .map(|id| id.parse::<u64>())
+        .collect::<Result<Vec<u64>, _>>()
+        .ok()?;
+    match ids.as_slice() {
+        [server_id, channel_id, message_id] => Some((
+            Some(GuildId(*server_id)),
+            ChannelId(*channel_id),
+            MessageId(*message_id),
+        )),
+        _ => None,
+    }
+}
+
+/// Enough to read a message in a channel, seeing it and its history
+fn can_read_history(granted: Permissions) -> bool {
+    granted.contains(Permissions::READ_MESSAGES | Permissions::READ_MESSAGE_HISTORY)
+}
+
+/// Whether [user_id] could read [channel_id] on [server_id] themselves, so messages in channels
+/// hidden from them can't be counted through the bot
+async fn user_can_read(
+    ctx: &Context,
+    server_id: &GuildId,
+    channel_id: &ChannelId,
+    user_id: &UserId,
+) -> bool {
+    match channel_id
+        .to_channel(ctx)
+        .await
+        .ok()
+        .and_then(|channel| channel.guild())
+    {
+        Some(channel) if channel.guild_id == *server_id => channel
+            .permissions_for_user(&ctx.cache, user_id)
+            .await
+            .is_ok_and(can_read_history),
+        _ => false,
+    }
+}
+
+fn describe_reading_ease(score: f64) -> &'static str {
+    match score {
+        s if s >= 90.0 => "very easy",
+        s if s >= 80.0 => "easy",
+        s if s >= 70.0 => "fairly easy",
+        s if s >= 60.0 => "plain",
+        s if s >= 50.0 => "fairly difficult",
+        s if s >= 30.0 => "difficult",
+        _ => "very difficult",
+    }
+}
+
+fn make_word_count(content: &str, rules: &WordRules) -> String {
+    let words = crate::stats::count_words(content, rules);
+    let counts = format!("{} words, {} characters", words, content.chars().count());
+    match reading_ease(content) {
+        Some(score) => format!(
+            "{}. Reading ease {:.0}, {}",
+            counts,
+            score,
+            describe_reading_ease(score)
+        ),
+        None => counts,
+    }
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[(
+        "[message link|message id]",
+        "The message to count, the one you're replying to if not given",
+    )],
+    permission: Permission::Anyone,
+};
+
+#[command("count-words")]
+#[usage("[message link|message id]")]
+#[description("Count the words and characters in a single message and score how easy it is to read (Flesch reading ease, 100 is very easy). Reply to the message with this command, or give its link or id. Works in any channel you can read, initialised or not, and nothing is recorded")]
+#[example("")]
+#[example("https://discord.com/channels/805918656622100500/805918656622100503/805918850104623136")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn count_words(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        let rules = get_word_rules(ctx, &server_id).await;
+        match (&msg.referenced_message, args.current()) {
+            (_, Some(arg)) => match parse_message_ref(arg, msg.channel_id) {
+                Some((Some(link_server_id), _, _)) if link_server_id != server_id => {
+                    String::from("That message is on another server")
+                }
+                Some((_, channel_id, message_id)) => {
+                    let message =
+                        match user_can_read(ctx, &server_id, &channel_id, &msg.author.id).await {
+                            true => channel_id.message(ctx, message_id).await.ok(),
+                            false => None,
+                        };
+                    match message {
+                        Some(message) => make_word_count(&message.content, &rules),
+                        None => String::from("Couldn't find that message, or can't read it"),
+                    }
+                }
+                None => String::from("Expected a message link or id, try [help count-words]"),
+            },
+            (Some(referenced), None) => make_word_count(&referenced.content, &rules),
+            (None, None) => String::from(
+                "Reply to a message with this command, or give a message link, try [help count-words]",
+            ),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::commands::count_words::{can_read_history, make_word_count, parse_message_ref};
+    use crate::language_parsing::WordRules;
+    use serenity::model::prelude::*;
+
+    #[test]
+    fn message_refs() {
+        assert_eq!(
+            parse_message_ref("12345", ChannelId(7)),
+            Some((None, ChannelId(7), MessageId(12345)))
+        );
+        assert_eq!(
+            parse_message_ref("<https://discord.com/channels/1/2/3>", ChannelId(7)),
+            Some((Some(GuildId(1)), ChannelId(2), MessageId(3)))
+        );
+        assert_eq!(
+            parse_message_ref("https://discord.com/channels/1/2", ChannelId(7)),
+            None
+        );
+        assert_eq!(parse_message_ref("rome", ChannelId(7)), None);
+    }
+
+    #[test]
+    fn word_counts() {
+        assert_eq!(
+            make_word_count("The cat sat on the mat.", &WordRules::Standard),
+            "6 words, 23 characters. Reading ease 116, very easy"
+        );
+        assert_eq!(
+            make_word_count("123", &WordRules::Standard),
+            "0 words, 3 characters"
+        );
+    }
+
+    #[test]
+    fn reading_needs_history() {
+        assert!(can_read_history(Permissions::all()));
+        assert!(can_read_history(
+            Permissions::READ_MESSAGES | Permissions::READ_MESSAGE_HISTORY
+        ));
+        assert!(!can_read_history(Permissions::READ_MESSAGES));
+        assert!(!can_read_history(Permissions::READ_MESSAGE_HISTORY));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::state::{StoreData, StoryKey, TRASH_RETENTION_DAYS};
+use crate::utils::confirmation::confirm_destructive_action;
+use chrono::Utc;
+use log::info;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+async fn actually_deinit_channel(story_key: &StoryKey, ctx: &Context) -> bool {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    info!(
+        "Moving story data for server_id {}, channel id {} to the trash",
+        story_key.0, story_key.1
+    );
+    store
+        .get_server_data_mut(&story_key.0)
+        .is_some_and(|server_data| server_data.trash_channel(&story_key.1, Utc::now()))
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[(
+        "<#channel name>",
+        "An initialised channel, its stats are moved to the trash",
+    )],
+    permission: Permission::AllowedRoles,
+};
+
+#[command("deinit-channel")]
+#[usage("<#channel name>")]
+#[description("Stop watching a channel, moving all of its stats (archived seasons included) to the trash. They can be restored with [undeinit] for 30 days, then they're deleted for good. Asks for confirmation first")]
+#[example("#the-fall-of-rome")]
+#[only_in("guilds")] // Reminder: guild = server
+#[bucket("destructive-bucket")]
+async fn deinit_channel(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+            true => {
+                if let Ok(channel_id) = args.single::<ChannelId>() {
+                    let story_key: StoryKey = (server_id, channel_id);
+                    let action = MessageBuilder::new()
+                        .push("stop watching ")
+                        .channel(channel_id)
+                        .push(format!(
+                            " and move its stats to the trash, they're deleted after {} days",
+                            TRASH_RETENTION_DAYS
+                        ))
+                        .build();
+                    if confirm_destructive_action(ctx, msg, &action).await? {
+                        match actually_deinit_channel(&story_key, ctx).await {
+                            true => {
+                                record_admin_action(ctx, msg, "deinit-channel").await;
+                                MessageBuilder::new()
+                                    .push("Stats for ")
+                                    .channel(channel_id)
+                                    .push(format!(
+                                        " moved to the trash, restore them with [undeinit] within {} days",
+                                        TRASH_RETENTION_DAYS
+                                    ))
+                                    .build()
+                            }
+                            false => String::from("Channel not initialised, nothing to delete"),
+                        }
+                    } else {
+                        return Ok(());
+                    }
+                } else {
+                    String::from("1 Arg expected: Channel")
+                }
+            }
+            false => not_in_allowed_roles_response(),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
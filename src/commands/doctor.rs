@@ -0,0 +1,45 @@
+use crate::config::GeneralAppConfigData;
+use crate::doctor::{config_checks, format_checklist, Check};
+use crate::intents::message_content_available;
+use crate::ADMINONLY_CHECK;
+use serenity::framework::standard::{macros::command, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+
+#[command("doctor")]
+#[description(
+    "Checks the config, the state file and the connection to discord, as a checklist of anything that needs fixing"
+)]
+#[checks("AdminOnly")]
+async fn doctor(ctx: &Context, msg: &Message) -> CommandResult {
+    let mut checks = {
+        let config_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<GeneralAppConfigData>()
+                .expect("Expected GeneralAppConfigData in TypeMap.")
+                .clone()
+        };
+        let config = config_lock.read().unwrap();
+        config_checks(&config)
+    };
+    checks.push(Check::new(
+        "Discord token",
+        ctx.http
+            .get_current_application_info()
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("Token was refused: {}", e)),
+    ));
+    checks.push(Check::new(
+        "Message content intent",
+        match message_content_available(ctx).await {
+            true => Ok(()),
+            false => Err(String::from(
+                "Messages are arriving without content, grant the intent in the developer portal",
+            )),
+        },
+    ));
+    msg.reply(ctx, format_checklist(&checks)).await?;
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use crate::rate_limit::EXPENSIVE_CHECK;
 use crate::state::StoreData;
 use crate::utils::trait_extensions::MessageBuilderExt;
 use crate::ADMINONLY_CHECK;
@@ -35,7 +36,7 @@ async fn actually_dump_messages(
 #[usage("<#channel name>")]
 #[description("Dumps the last up to 50 messages from a channel to a file, locally to the server. For debug reasons")]
 #[example("#the-fall-of-rome")]
-#[checks("AdminOnly")]
+#[checks("AdminOnly", "Expensive")]
 async fn dump_messages(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let reply = match args.len() {
         1 => match args.single::<ChannelId>() {
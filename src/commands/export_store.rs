@@ -0,0 +1,106 @@
+use crate::config::GeneralAppConfigData;
+use crate::export::{export_file, export_json};
+use crate::state::StoreData;
+use crate::ADMINONLY_CHECK;
+use chrono::Utc;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::http::AttachmentType;
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+
+struct ExportFile {
+    bytes: Vec<u8>,
+    encrypted: bool,
+}
+
+/// The export's JSON, encrypted as the state is if it's going [to_disk]
+async fn make_export(
+    ctx: &Context,
+    server_id: Option<GuildId>,
+    to_disk: bool,
+) -> std::result::Result<ExportFile, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    let export = export_json(&store, server_id)?;
+    match to_disk {
+        true => export_file(&store, export)
+            .map(|(bytes, encrypted)| ExportFile { bytes, encrypted })
+            .map_err(|e| format!("Failed encrypting export: {}", e)),
+        false => Ok(ExportFile {
+            bytes: export.into_bytes(),
+            encrypted: false,
+        }),
+    }
+}
+
+#[command("export-store")]
+#[usage("[server id]")]
+#[description("Export stats as JSON for offline analysis, for one server or all of them if not given. Written to the configured export path (encrypted if the state is), or uploaded here if there isn't one")]
+#[example("")]
+#[example("123456789012345678")]
+#[checks("AdminOnly")]
+async fn export_store(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let server_id = match args.single::<u64>() {
+        Ok(server_id) => Some(GuildId(server_id)),
+        Err(_) if args.is_empty() => None,
+        Err(_) => {
+            msg.reply(ctx, "Invalid arguments, try [help export-store]")
+                .await?;
+            return Ok(());
+        }
+    };
+    let export_path = {
+        let config_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<GeneralAppConfigData>()
+                .expect("Expected GeneralAppConfigData in TypeMap.")
+                .clone()
+        };
+        let config = config_lock.read().unwrap();
+        config.export_path.clone()
+    };
+    let export = match make_export(ctx, server_id, export_path.is_some()).await {
+        Ok(export) => export,
+        Err(e) => {
+            msg.reply(ctx, e).await?;
+            return Ok(());
+        }
+    };
+    let filename = format!(
+        "scrivener-export-{}.json",
+        Utc::now().format("%Y%m%dT%H%M%S")
+    );
+    match export_path {
+        Some(export_path) => {
+            // Written encrypted if the state is, read it back with `Scrivener decrypt-export`
+            let path = match export.encrypted {
+                true => export_path.join(format!("{}.enc", filename)),
+                false => export_path.join(filename),
+            };
+            let reply = match tokio::fs::write(&path, export.bytes).await {
+                Ok(()) => format!("Exported to {}", path.display()),
+                Err(e) => format!("Failed writing export to {}: {}", path.display(), e),
+            };
+            msg.reply(ctx, reply).await?;
+        }
+        None => {
+            let files = vec![AttachmentType::Bytes {
+                data: export.bytes.into(),
+                filename,
+            }];
+            msg.channel_id
+                .send_files(&ctx.http, files, |create_message| {
+                    create_message.content("Here's the export")
+                })
+                .await?;
+        }
+    }
+    Ok(())
+}
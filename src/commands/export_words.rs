@@ -0,0 +1,110 @@
+use crate::commands::help::{CommandHelp, Permission};
+use crate::export::word_frequencies_csv;
+use crate::state::StoreData;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::http::AttachmentType;
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+
+async fn make_word_export(
+    ctx: &Context,
+    server_id: &GuildId,
+    user_id: &UserId,
+    channel_id: Option<&ChannelId>,
+    author_id: &UserId,
+) -> std::result::Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    let server_data = store
+        .get_server_data(server_id)
+        .ok_or_else(|| String::from("No stats recorded on this server yet"))?;
+    if server_data.config.privacy_mode
+        && server_data.config.main_account(user_id) != server_data.config.main_account(author_id)
+    {
+        return Err(String::from(
+            "This server is in privacy mode, writers can only export their own words",
+        ));
+    }
+    if let Some(channel_id) = channel_id {
+        if store.get_channel_data(&(*server_id, *channel_id)).is_none() {
+            return Err(String::from(
+                "Channel not initialised, use [init-channel] to add it",
+            ));
+        }
+    }
+    let frequencies = server_data.word_frequencies_for_user(user_id, channel_id);
+    match frequencies.is_empty() {
+        true => Err(String::from("No words recorded for them yet")),
+        false => Ok(word_frequencies_csv(&frequencies)),
+    }
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        ("[@user]", "Whose words to export, yours if not given"),
+        (
+            "[#channel name]",
+            "Just this initialised channel, all of them if not given",
+        ),
+    ],
+    permission: Permission::Anyone,
+};
+
+#[command("export-words")]
+#[usage("[@user] [#channel name]")]
+#[description("Upload a CSV of how often a writer has used each word, the same counts word clouds are made from with common words like 'the' left out. Covers every initialised channel on the server unless one is given. Linked alt accounts are included")]
+#[example("")]
+#[example("@Caligula #the-fall-of-rome")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn export_words(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        let mut user_id = msg.author.id;
+        let mut channel_id = None;
+        let mut invalid = false;
+        for arg in args.iter::<String>().flatten() {
+            if let Ok(user) = arg.parse::<UserId>() {
+                user_id = user;
+            } else if let Ok(channel) = arg.parse::<ChannelId>() {
+                channel_id = Some(channel);
+            } else {
+                invalid = true;
+            }
+        }
+        match invalid {
+            true => String::from("Invalid arguments, try [help export-words]"),
+            false => match make_word_export(
+                ctx,
+                &server_id,
+                &user_id,
+                channel_id.as_ref(),
+                &msg.author.id,
+            )
+            .await
+            {
+                Ok(csv) => {
+                    let files = vec![AttachmentType::Bytes {
+                        data: csv.into_bytes().into(),
+                        filename: format!("scrivener-words-{}.csv", user_id),
+                    }];
+                    msg.channel_id
+                        .send_files(&ctx.http, files, |create_message| {
+                            create_message.content("Here are the word counts")
+                        })
+                        .await?;
+                    return Ok(());
+                }
+                Err(e) => e,
+            },
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use crate::commands::help::{CommandHelp, Permission};
 use serenity::prelude::Context;
 use serenity::model::prelude::Message;
 use serenity::framework::standard::{Args, CommandResult};
@@ -39,6 +40,14 @@ impl FromStr for FeedbackKind {
     }
 }
 
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        ("<kind>", "One of bug, feature or misc"),
+        ("<your feedback>", "Whatever you'd like to tell the bot admin"),
+    ],
+    permission: Permission::Anyone,
+};
+
 #[command("feedback")]
 #[usage("<kind> <your feedback>")]
 #[description("Give you feedback on the bot which will be given to the bot admin/developer. Kind can be one of: bug|feature|misc")]
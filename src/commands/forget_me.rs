@@ -0,0 +1,44 @@
+use crate::commands::help::{CommandHelp, Permission};
+use crate::state::StoreData;
+use crate::utils::confirmation::confirm_destructive_action;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[],
+    permission: Permission::Anyone,
+};
+
+#[command("forget-me")]
+#[description("Delete all of your stats from every initialised channel on this server. Messages you post afterwards will still be counted. Asks for confirmation first")]
+#[only_in("guilds")] // Reminder: guild = server
+#[bucket("destructive-bucket")]
+async fn forget_me(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        let action = "delete all of your stats from every channel on this server";
+        if confirm_destructive_action(ctx, msg, action).await? {
+            let store_lock = {
+                let data_read = ctx.data.read().await;
+                data_read
+                    .get::<StoreData>()
+                    .expect("Expected StoryData in TypeMap.")
+                    .clone()
+            };
+            let mut store = store_lock.write().unwrap();
+            let channels_forgotten = store
+                .get_server_data_mut(&server_id)
+                .map_or(0, |server_data| server_data.forget_author(&msg.author.id));
+            format!(
+                "Done, removed your stats from {} channel(s)",
+                channels_forgotten
+            )
+        } else {
+            return Ok(());
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
@@ -0,0 +1,305 @@
+use crate::commands::init_channel::ALLOWED_ROLES;
+use crate::commands::{
+    about, alias, audit_log, character, check_perms, config, content_report, count_words,
+    deinit_channel, export_words, feedback, forget_me, ignore_bot, init_channel, interactions,
+    merge_channels, nudge, personal_log, privacy_mode, quiet_hours, rebuild_stats, recap, remind,
+    reset_stats, response_times, server_summary, sessions, set_min_words, set_prefix, setup,
+    shoutout, show_channels, show_stats, timezone, turn_order, undeinit, word_cloud, word_rules,
+    wordcloud_schedule,
+};
+use crate::config::GeneralAppConfigData;
+use serenity::framework::standard::{
+    help_commands, macros::help, Args, Command, CommandGroup, CommandResult, HelpOptions,
+};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use std::collections::HashSet;
+
+/// Who can run a command, beyond what the framework's checks already enforce
+pub enum Permission {
+    Anyone,
+    AllowedRoles,
+    // Anyone can look, changing things needs an allowed role
+    AllowedRolesToChange,
+}
+
+impl Permission {
+    fn describe(&self) -> String {
+        let roles = ALLOWED_ROLES.join(", ");
+        match self {
+            Permission::Anyone => String::from("Anyone"),
+            Permission::AllowedRoles => format!("Needs one of the roles: {}", roles),
+            Permission::AllowedRolesToChange => format!(
+                "Anyone can view, changing it needs one of the roles: {}",
+                roles
+            ),
+        }
+    }
+}
+
+/// Help declared alongside each command, for what the framework's own attributes don't cover
+pub struct CommandHelp {
+    // (argument as written in the usage, what it's for)
+    pub args: &'static [(&'static str, &'static str)],
+    pub permission: Permission,
+}
+
+static COMMAND_HELP: [(&Command, &CommandHelp); 40] = [
+    (&setup::SETUP_COMMAND, &setup::HELP),
+    (&init_channel::INIT_CHANNEL_COMMAND, &init_channel::HELP),
+    (&check_perms::CHECK_PERMS_COMMAND, &check_perms::HELP),
+    (
+        &deinit_channel::DEINIT_CHANNEL_COMMAND,
+        &deinit_channel::HELP,
+    ),
+    (&undeinit::UNDEINIT_COMMAND, &undeinit::HELP),
+    (&rebuild_stats::REBUILD_STATS_COMMAND, &rebuild_stats::HELP),
+    (
+        &merge_channels::MERGE_CHANNELS_COMMAND,
+        &merge_channels::HELP,
+    ),
+    (&reset_stats::RESET_STATS_COMMAND, &reset_stats::HELP),
+    (&show_stats::SHOW_STATS_COMMAND, &show_stats::HELP),
+    (&show_channels::SHOW_CHANNELS_COMMAND, &show_channels::HELP),
+    (
+        &server_summary::SERVER_SUMMARY_COMMAND,
+        &server_summary::HELP,
+    ),
+    (&interactions::INTERACTIONS_COMMAND, &interactions::HELP),
+    (&sessions::SESSIONS_COMMAND, &sessions::HELP),
+    (
+        &response_times::RESPONSE_TIMES_COMMAND,
+        &response_times::HELP,
+    ),
+    (&recap::RECAP_COMMAND, &recap::HELP),
+    (&count_words::COUNT_WORDS_COMMAND, &count_words::HELP),
+    (&remind::REMIND_COMMAND, &remind::HELP),
+    (&nudge::NUDGE_COMMAND, &nudge::HELP),
+    (&shoutout::SHOUTOUT_COMMAND, &shoutout::HELP),
+    (&personal_log::PERSONAL_LOG_COMMAND, &personal_log::HELP),
+    (&alias::ALIAS_COMMAND, &alias::HELP),
+    (&character::CHARACTER_COMMAND, &character::HELP),
+    (&turn_order::TURN_ORDER_COMMAND, &turn_order::HELP),
+    (&turn_order::WHOS_NEXT_COMMAND, &turn_order::WHOS_NEXT_HELP),
+    (&forget_me::FORGET_ME_COMMAND, &forget_me::HELP),
+    (&timezone::TIMEZONE_COMMAND, &timezone::HELP),
+    (&word_rules::WORD_RULES_COMMAND, &word_rules::HELP),
+    (&quiet_hours::QUIET_HOURS_COMMAND, &quiet_hours::HELP),
+    (&set_min_words::SET_MIN_WORDS_COMMAND, &set_min_words::HELP),
+    (&set_prefix::SET_PREFIX_COMMAND, &set_prefix::HELP),
+    (&ignore_bot::IGNORE_BOT_COMMAND, &ignore_bot::HELP),
+    (&privacy_mode::PRIVACY_MODE_COMMAND, &privacy_mode::HELP),
+    (
+        &content_report::CONTENT_REPORT_COMMAND,
+        &content_report::HELP,
+    ),
+    (&export_words::EXPORT_WORDS_COMMAND, &export_words::HELP),
+    (&config::CONFIG_COMMAND, &config::HELP),
+    (&audit_log::AUDIT_LOG_COMMAND, &audit_log::HELP),
+    (&about::ABOUT_COMMAND, &about::HELP),
+    (&feedback::FEEDBACK_COMMAND, &feedback::HELP),
+    (&word_cloud::GEN_WORDCLOUD_COMMAND, &word_cloud::HELP),
+    (
+        &wordcloud_schedule::WORDCLOUD_SCHEDULE_COMMAND,
+        &wordcloud_schedule::HELP,
+    ),
+];
+
+fn command_help(command: &Command) -> Option<&'static CommandHelp> {
+    COMMAND_HELP
+        .iter()
+        .find(|(other, _)| other.options.names == command.options.names)
+        .map(|(_, help)| *help)
+}
+
+fn visible_groups<'a>(
+    groups: &'a [&'static CommandGroup],
+) -> impl Iterator<Item = &'static CommandGroup> + 'a {
+    groups
+        .iter()
+        .filter(|group| group.options.help_available)
+        .copied()
+}
+
+fn find_command(
+    groups: &[&'static CommandGroup],
+    name: &str,
+) -> Option<(&'static CommandGroup, &'static Command)> {
+    visible_groups(groups).find_map(|group| {
+        group
+            .options
+            .commands
+            .iter()
+            .find(|command| command.options.names.contains(&name))
+            .map(|command| (group, *command))
+    })
+}
+
+async fn get_prefix(ctx: &Context) -> String {
+    let config_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<GeneralAppConfigData>()
+            .expect("Expected GeneralAppConfigData in TypeMap.")
+            .clone()
+    };
+    let prefix = config_lock.read().unwrap().prefix.clone();
+    prefix
+}
+
+/// The help for one command, as (field name, field contents) for an embed
+fn command_fields(prefix: &str, group: &CommandGroup, command: &Command) -> Vec<(String, String)> {
+    let options = command.options;
+    let name = options.names[0];
+    let mut fields = vec![];
+    fields.push((
+        String::from("Usage"),
+        format!("`{}{} {}`", prefix, name, options.usage.unwrap_or_default()),
+    ));
+    let help = command_help(command);
+    if let Some(help) = help {
+        if !help.args.is_empty() {
+            let args: Vec<String> = help
+                .args
+                .iter()
+                .map(|(arg, description)| format!("`{}` {}", arg, description))
+                .collect();
+            fields.push((String::from("Arguments"), args.join("\n")));
+        }
+    }
+    if !options.examples.is_empty() {
+        let examples: Vec<String> = options
+            .examples
+            .iter()
+            .map(|example| format!("`{}{} {}`", prefix, name, example))
+            .collect();
+        fields.push((String::from("Examples"), examples.join("\n")));
+    }
+    fields.push((
+        String::from("Permission"),
+        help.map_or_else(
+            || Permission::Anyone.describe(),
+            |help| help.permission.describe(),
+        ),
+    ));
+    let rate_limited =
+        options.bucket.is_some() || options.checks.iter().any(|check| check.name == "Expensive");
+    if rate_limited {
+        fields.push((
+            String::from("Rate limited"),
+            String::from("Yes, there's a cooldown between uses"),
+        ));
+    }
+    fields.push((String::from("Category"), String::from(group.name)));
+    fields
+}
+
+async fn send_command_help(
+    ctx: &Context,
+    msg: &Message,
+    group: &CommandGroup,
+    command: &Command,
+) -> CommandResult {
+    let prefix = get_prefix(ctx).await;
+    let fields = command_fields(&prefix, group, command);
+    msg.channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title(format!("{}{}", prefix, command.options.names[0]));
+                if let Some(desc) = command.options.desc {
+                    e.description(desc);
+                }
+                for (name, value) in fields {
+                    e.field(name, value, false);
+                }
+                e
+            })
+        })
+        .await?;
+    Ok(())
+}
+
+async fn send_overview(
+    ctx: &Context,
+    msg: &Message,
+    groups: &[&'static CommandGroup],
+) -> CommandResult {
+    let prefix = get_prefix(ctx).await;
+    let categories: Vec<(&str, String)> = visible_groups(groups)
+        .map(|group| {
+            let commands: Vec<String> = group
+                .options
+                .commands
+                .iter()
+                .map(|command| format!("`{}`", command.options.names[0]))
+                .collect();
+            (group.name, commands.join(" "))
+        })
+        .collect();
+    msg.channel_id
+        .send_message(&ctx.http, |m| {
+            m.embed(|e| {
+                e.title("Commands");
+                e.description(format!(
+                    "Use `{}help <command>` for usage, arguments and examples of a command",
+                    prefix
+                ));
+                for (name, commands) in categories {
+                    e.field(name, commands, false);
+                }
+                e
+            })
+        })
+        .await?;
+    Ok(())
+}
+
+#[help]
+#[max_levenshtein_distance(3)]
+async fn help(
+    context: &Context,
+    msg: &Message,
+    args: Args,
+    help_options: &'static HelpOptions,
+    groups: &[&'static CommandGroup],
+    owners: HashSet<UserId>,
+) -> CommandResult {
+    if args.is_empty() {
+        return send_overview(context, msg, groups).await;
+    }
+    match find_command(groups, args.rest().trim()) {
+        Some((group, command)) => send_command_help(context, msg, group, command).await,
+        // Leave unknown commands to the framework's help, for its suggestions of similar ones
+        None => {
+            let _ =
+                help_commands::with_embeds(context, msg, args, help_options, groups, owners).await;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::commands::help::command_help;
+    use crate::{GENERAL_GROUP, WORDCLOUD_GROUP};
+
+    #[test]
+    fn every_command_has_help() {
+        for group in [&GENERAL_GROUP, &WORDCLOUD_GROUP].iter() {
+            for command in group.options.commands.iter() {
+                let name = command.options.names[0];
+                let help = command_help(command)
+                    .unwrap_or_else(|| panic!("{} has no help declared", name));
+                let usage = command.options.usage.unwrap_or_default();
+                for (arg, _) in help.args.iter() {
+                    assert!(
+                        usage.contains(arg),
+                        "{} documents {} which isn't in its usage",
+                        name,
+                        arg
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::guild_config::{update_channel_config, ChannelConfig};
+use crate::state::StoreData;
+use crate::utils::trait_extensions::MessageBuilderExt;
+use crate::utils::transient::transient_reply;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+async fn list_ignored_bots(ctx: &Context, server_id: &GuildId, channel_id: &ChannelId) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    let channel_config = store
+        .get_server_data(server_id)
+        .map_or(ChannelConfig::default(), |server_data| {
+            server_data.config.channel(channel_id)
+        });
+    let mut builder = MessageBuilder::new();
+    if channel_config.ignored_bots.is_empty() {
+        builder.push("No bots are ignored in ").channel(*channel_id);
+    } else {
+        builder
+            .push("Bots ignored in ")
+            .channel(*channel_id)
+            .push(":");
+        for (bot_id, prefix) in channel_config.ignored_bots.iter() {
+            builder.newline().user(*bot_id);
+            if let Some(prefix) = prefix {
+                builder
+                    .push(", and messages starting with ")
+                    .push_mono_safe(prefix);
+            }
+        }
+    }
+    builder.build()
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        (
+            "<#channel name>",
+            "An initialised channel, lists its ignored bots if nothing else is given",
+        ),
+        ("[add|remove]", "Whether to start or stop ignoring the bot"),
+        ("[@ bot mention]", "The bot to ignore"),
+        (
+            "[command prefix]",
+            "The bot's prefix, so messages invoking it are ignored too",
+        ),
+    ],
+    permission: Permission::AllowedRolesToChange,
+};
+
+#[command("ignore-bot")]
+#[usage("<#channel name> [add|remove] [@ bot mention] [command prefix]")]
+#[description("Leave a companion bot's messages (dice rollers, music bots) out of a channel's stats. Give the bot's command prefix to also leave out the messages invoking it. With no action, lists the ignored bots. Changing the list needs the same role as init-channel")]
+#[example("#the-fall-of-rome")]
+#[example("#the-fall-of-rome add @Avrae !r")]
+#[example("#the-fall-of-rome remove @Avrae")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn ignore_bot(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match (args.single::<ChannelId>(), args.single::<String>()) {
+            (Ok(channel_id), Err(_)) => list_ignored_bots(ctx, &server_id, &channel_id).await,
+            (Ok(channel_id), Ok(action)) => {
+                match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+                    true => match (action.as_str(), args.single::<UserId>()) {
+                        ("add", Ok(bot_id)) => match args.single_quoted::<String>().ok() {
+                            // Would match every message, leaving the whole channel out
+                            Some(prefix) if prefix.trim().is_empty() => String::from(
+                                "The command prefix can't be empty, leave it out to only ignore the bot's own messages",
+                            ),
+                            prefix => {
+                                update_channel_config(ctx, &server_id, &channel_id, |config| {
+                                    config.ignored_bots.insert(bot_id, prefix);
+                                })
+                                .await;
+                                record_admin_action(ctx, msg, "ignore-bot").await;
+                                MessageBuilder::new()
+                                    .user(bot_id)
+                                    .push(" will be left out of stats in ")
+                                    .channel(channel_id)
+                                    .build()
+                            }
+                        },
+                        ("remove", Ok(bot_id)) => {
+                            update_channel_config(ctx, &server_id, &channel_id, |config| {
+                                config.ignored_bots.remove(&bot_id);
+                            })
+                            .await;
+                            record_admin_action(ctx, msg, "ignore-bot").await;
+                            MessageBuilder::new()
+                                .user(bot_id)
+                                .push(" will count towards stats in ")
+                                .channel(channel_id)
+                                .build()
+                        }
+                        _ => String::from(
+                            "Expected add or remove, and a bot mention, try [help ignore-bot]",
+                        ),
+                    },
+                    false => not_in_allowed_roles_response(),
+                }
+            }
+            _ => String::from("Expected a channel, try [help ignore-bot]"),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    transient_reply(ctx, msg, reply).await?;
+    Ok(())
+}
@@ -1,11 +1,144 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::check_perms::preflight_permissions;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::guild_config::GuildConfig;
+use crate::rate_limit::EXPENSIVE_CHECK;
 use crate::state::{ChannelData, StoreData, StoryKey};
-use log::info;
-use serenity::framework::standard::{macros::command, Args, CommandResult};
+use crate::utils::discord::{message_id_at, DiscordApi};
+use crate::utils::replied::reply_and_fail;
+use chrono_tz::Tz;
+use log::{info, warn};
+use serenity::framework::standard::{
+    macros::{check, command},
+    Args, CommandOptions, CommandResult, Reason,
+};
 use serenity::model::prelude::*;
 use serenity::prelude::*;
 use serenity::utils::MessageBuilder;
 
-async fn actually_init_channel(
+/// Builds stats for a channel from its whole history, working back from its last message, counted
+/// with the server's settings in [config]. Messages up to [season_start] belong to archived seasons
+/// and are left out. Fails if the history can't be read, e.g. the bot lost access part way through
+pub async fn backfill_channel_data(
+    discord: &dyn DiscordApi,
+    text_channel: &GuildChannel,
+    config: &GuildConfig,
+    max_words: Option<usize>,
+    season_start: Option<MessageId>,
+) -> std::result::Result<ChannelData, String> {
+    let unreadable = |why: serenity::Error| {
+        warn!(
+            "Failed reading the history of {}: {:?}",
+            text_channel.name, why
+        );
+        format!(
+            "couldn't read the history of {}: {}",
+            text_channel.name, why
+        )
+    };
+    let in_season = |message: &Message| season_start.is_none_or(|start| message.id > start);
+    let timezone = &config.timezone();
+    let rules = &config.word_rules;
+    let channel_config = &config.channel(&text_channel.id);
+    let characters = &config.characters;
+    let mut channel_data = ChannelData::default();
+    info!(
+        "Creating new story data for server_id {}, channel id {}",
+        text_channel.guild_id, text_channel.id
+    );
+    if let Some(mut last_msg_id) = text_channel.last_message_id {
+        //Keep populating back in time until all messages are fetched
+        let oldest_message = chrono::Utc::now();
+        let mut fetched_messages = 0;
+        {
+            //Fetch the last_msg_id itself, or we miss it by just jumping in with [before(id)]
+            let last_msg = discord
+                .message(text_channel.id, last_msg_id)
+                .await
+                .map_err(unreadable)?;
+            if in_season(&last_msg) && channel_config.counts_message(&last_msg, rules) {
+                channel_data.update(&last_msg, timezone, rules);
+                if let Some(character) = characters.attribute(&last_msg, channel_config) {
+                    channel_data.update_character(character, &last_msg, timezone, rules);
+                }
+            }
+        }
+        loop {
+            let messages: Vec<Message> = discord
+                .messages_before(text_channel.id, last_msg_id, 100)
+                .await
+                .map_err(unreadable)?;
+            if messages.len() == 0 {
+                break;
+            } else {
+                fetched_messages += messages.len();
+                // I don't know if the older message will be first or last.
+                for message in messages {
+                    if message.timestamp < oldest_message {
+                        last_msg_id = message.id
+                    }
+                    if in_season(&message) && channel_config.counts_message(&message, rules) {
+                        channel_data.update(&message, timezone, rules);
+                        if let Some(character) = characters.attribute(&message, channel_config) {
+                            channel_data.update_character(character, &message, timezone, rules);
+                        }
+                    }
+                }
+                if let Some(max_words) = max_words {
+                    channel_data.prune_words(max_words);
+                }
+                info!(
+                    "Processed {} messages so far in {}...",
+                    fetched_messages, text_channel.name
+                );
+                if season_start.is_some_and(|start| last_msg_id <= start) {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(channel_data)
+}
+
+/// Counts a channel's history since its current season began with the server's current settings,
+/// leaving the store as it is. [timezone] is counted in instead of the server's if given, for one
+/// about to be set
+pub async fn count_channel_history(
+    text_channel: &GuildChannel,
+    ctx: &Context,
+    timezone: Option<Tz>,
+) -> std::result::Result<ChannelData, String> {
+    let story_key: StoryKey = (text_channel.guild_id, text_channel.id);
+    let (config, max_words, season_start) = {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let store = store_lock.read().unwrap();
+        let mut config = store
+            .get_server_data(&story_key.0)
+            .map_or(GuildConfig::default(), |server_data| {
+                server_data.config.clone()
+            });
+        if timezone.is_some() {
+            config.timezone = timezone;
+        }
+        (
+            config,
+            store.max_words_per_channel,
+            store
+                .get_server_data(&story_key.0)
+                .and_then(|server_data| server_data.season_started(&story_key.1))
+                .map(message_id_at),
+        )
+    };
+    backfill_channel_data(&ctx.http, text_channel, &config, max_words, season_start).await
+}
+
+pub async fn actually_init_channel(
     text_channel: GuildChannel,
     ctx: &Context,
 ) -> std::result::Result<(), String> {
@@ -50,45 +183,7 @@ async fn actually_init_channel(
         let mut store = store_lock.write().unwrap();
         store.initialising_channels.insert(story_key.clone());
     };
-    let mut channel_data = ChannelData::default();
-    info!(
-        "Creating new story data for server_id {}, channel id {}",
-        text_channel.guild_id, text_channel.id
-    );
-    if let Some(mut last_msg_id) = text_channel.last_message_id {
-        //Keep populating back in time until all messages are fetched
-        let oldest_message = chrono::Utc::now();
-        let mut fetched_messages = 0;
-        {
-            //Fetch the last_msg_id itself, or we miss it by just jumping in with [before(id)]
-            let last_msg = text_channel.message(&ctx.http, last_msg_id).await.unwrap();
-            channel_data.update(&last_msg);
-        }
-        loop {
-            let messages: Vec<Message> = text_channel
-                .messages(&ctx.http, |get_messages_builder| {
-                    get_messages_builder.before(last_msg_id).limit(100)
-                })
-                .await
-                .unwrap();
-            if messages.len() == 0 {
-                break;
-            } else {
-                fetched_messages += messages.len();
-                // I don't know if the older message will be first or last.
-                for message in messages {
-                    if message.timestamp < oldest_message {
-                        last_msg_id = message.id
-                    }
-                    channel_data.update(&message);
-                }
-                info!(
-                    "Processed {} messages so far in {}...",
-                    fetched_messages, text_channel.name
-                )
-            }
-        }
-    }
+    let channel_data = count_channel_history(&text_channel, ctx, None).await;
 
     //Insert story_data into store and unset it as being initialised
     {
@@ -100,8 +195,8 @@ async fn actually_init_channel(
                 .clone()
         };
         let mut store = store_lock.write().unwrap();
-        store.insert_channel_data_maybe_create_server_data(&story_key, channel_data);
         store.initialising_channels.remove(&story_key);
+        store.insert_channel_data_maybe_create_server_data(&story_key, channel_data?);
     };
 
     Ok(())
@@ -113,59 +208,78 @@ fn unicode_emoji(s: &str) -> ReactionType {
 async fn react_or_reply(msg: &Message, ctx: &Context) {
     match msg.react(ctx, unicode_emoji("🤖")).await {
         Ok(_) => {
-            let _ = msg.react(ctx, unicode_emoji("⌚")).await.unwrap();
+            let _ = msg.react(ctx, unicode_emoji("⌚")).await;
         }
         Err(_) => {
             let _ = msg
                 .reply(ctx, "Initialising channel, bear with me...")
-                .await
-                .unwrap();
+                .await;
         }
     }
 }
 
 // TODO: Load these from config
-const ALLOWED_ROLES: [&str; 3] = ["MasterScrivener", "ScrivMaster", "ScrivAdmin"];
+pub const ALLOWED_ROLES: [&str; 3] = ["MasterScrivener", "ScrivMaster", "ScrivAdmin"];
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[("<#channel name>", "The channel the story is written in")],
+    permission: Permission::AllowedRoles,
+};
 
 #[command("init-channel")]
 #[usage("<#channel name>")]
 #[description("Initialise a channel to generate stats for. Will backpopulate from existing messages and keep an eye out for future ones")]
 #[example("#the-fall-of-rome")]
 #[only_in("guilds")] // Reminder: guild = server
+#[checks(AllowedRoles, Expensive)]
 async fn init_channel(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let reply = if let Some(server_id) = msg.guild_id {
         match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
             true => {
                 if let Ok(channel_to_init) = args.single::<ChannelId>() {
-                    //Safely assuming we can convert to a guild channel considering the [only_in] constraint
                     let channel = channel_to_init
                         .to_channel(&ctx)
                         .await
-                        .unwrap()
-                        .guild()
-                        .unwrap();
-
-                    if channel.kind == ChannelType::Text {
-                        react_or_reply(msg, ctx).await;
-                        let okay_response = MessageBuilder::new()
-                            .push("Stats initialised for ")
-                            .channel(&channel)
-                            .build();
-                        match actually_init_channel(channel, ctx).await {
-                            Ok(()) => okay_response,
-                            Err(error_string) => format!("Not initialised: {}", error_string),
+                        .ok()
+                        .and_then(|channel| channel.guild());
+                    if let Some(channel) =
+                        channel.filter(|channel| channel.kind == ChannelType::Text)
+                    {
+                        match preflight_permissions(ctx, &channel).await {
+                            Ok(missing) => {
+                                react_or_reply(msg, ctx).await;
+                                let mut okay_response = MessageBuilder::new()
+                                    .push("Stats initialised for ")
+                                    .channel(&channel)
+                                    .build();
+                                if let Some(missing) = missing {
+                                    okay_response.push_str(&format!("\n{}", missing));
+                                }
+                                match actually_init_channel(channel, ctx).await {
+                                    Ok(()) => {
+                                        record_admin_action(ctx, msg, "init-channel").await;
+                                        okay_response
+                                    }
+                                    Err(error_string) => {
+                                        return reply_and_fail(
+                                            ctx,
+                                            msg,
+                                            format!("Not initialised: {}", error_string),
+                                        )
+                                        .await
+                                    }
+                                }
+                            }
+                            Err(missing) => format!("Not initialised: {}", missing),
                         }
                     } else {
-                        format!("Channel is not a text-channel, can only init normal text channels")
+                        format!("Channel is not a text-channel I can see, can only init normal text channels")
                     }
                 } else {
                     String::from("1 Arg expected: String: Channel name")
                 }
             }
-            false => format!(
-                "This command is only available to those with the role {}",
-                ALLOWED_ROLES[0]
-            ),
+            false => not_in_allowed_roles_response(),
         }
     } else {
         String::from("BUG: message had no server id, bot only supports server text channels")
@@ -174,13 +288,48 @@ async fn init_channel(ctx: &Context, msg: &Message, mut args: Args) -> CommandRe
     Ok(())
 }
 
+pub fn not_in_allowed_roles_response() -> String {
+    format!(
+        "This command is only available to those with the role {}",
+        ALLOWED_ROLES[0]
+    )
+}
+
 const BOSS: u64 = 190534649548767243;
 
-async fn author_is_in_allowed_roles(ctx: &Context, server_id: &GuildId, user: &User) -> bool {
+// For commands only those with an allowed role can run. Listed before [Expensive] so those
+// without the role don't use up the server's allowance
+#[check]
+#[name = "AllowedRoles"]
+#[check_in_help(false)]
+async fn allowed_roles_check(
+    ctx: &Context,
+    msg: &Message,
+    _: &mut Args,
+    _: &CommandOptions,
+) -> std::result::Result<(), Reason> {
+    match msg.guild_id {
+        Some(server_id) if !author_is_in_allowed_roles(ctx, &server_id, &msg.author).await => {
+            Err(Reason::User(not_in_allowed_roles_response()))
+        }
+        _ => Ok(()),
+    }
+}
+
+pub async fn author_is_in_allowed_roles(ctx: &Context, server_id: &GuildId, user: &User) -> bool {
     if user.id.0 == BOSS {
         return true;
     }
-    let partial_guild = server_id.to_partial_guild(ctx).await.unwrap();
+    let partial_guild = match server_id.to_partial_guild(ctx).await {
+        Ok(partial_guild) => partial_guild,
+        Err(why) => {
+            warn!(
+                "Failed fetching server {} to check roles: {:?}",
+                server_id, why
+            );
+            return false;
+        }
+    };
     for role_name in ALLOWED_ROLES.iter() {
         if let Some(role) = partial_guild.role_by_name(role_name) {
             if let Ok(true) = user.has_role(ctx, *server_id, role).await {
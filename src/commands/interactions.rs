@@ -0,0 +1,164 @@
+use crate::commands::help::{CommandHelp, Permission};
+use crate::state::{ChannelData, ServerData, StoreData, StoryKey};
+use crate::utils::discord::DiscordApi;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+const MAX_PAIRINGS: usize = 10;
+const GRAPH_SIZE: f64 = 600.0;
+
+/// Draws the pairings as a ring of writers, with thicker lines between those who interact more
+fn render_graph_svg(channel_data: &ChannelData, server_data: &ServerData) -> String {
+    let pairings = channel_data.strongest_pairings(MAX_PAIRINGS);
+    let mut writers = vec![];
+    for ((user_a, user_b), _) in pairings.iter() {
+        for user in [user_a, user_b].iter() {
+            if !writers.contains(*user) {
+                writers.push(**user);
+            }
+        }
+    }
+    let centre = GRAPH_SIZE / 2.0;
+    let radius = GRAPH_SIZE / 2.0 - 80.0;
+    let positions: HashMap<UserId, (f64, f64)> = writers
+        .iter()
+        .enumerate()
+        .map(|(i, user_id)| {
+            let angle = 2.0 * PI * i as f64 / writers.len() as f64;
+            (
+                *user_id,
+                (centre + radius * angle.cos(), centre + radius * angle.sin()),
+            )
+        })
+        .collect();
+    let max_count = pairings.first().map_or(1, |(_, count)| *count) as f64;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\">\n<rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n",
+        GRAPH_SIZE
+    );
+    for ((user_a, user_b), count) in pairings.iter() {
+        let (x1, y1) = positions[user_a];
+        let (x2, y2) = positions[user_b];
+        svg.push_str(&format!(
+            "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"steelblue\" stroke-width=\"{:.1}\"/>\n",
+            x1,
+            y1,
+            x2,
+            y2,
+            1.0 + 9.0 * *count as f64 / max_count
+        ));
+    }
+    for user_id in writers.iter() {
+        let (x, y) = positions[user_id];
+        svg.push_str(&format!(
+            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"8\" fill=\"darkorange\"/>\n<text x=\"{:.1}\" y=\"{:.1}\" font-family=\"sans-serif\" font-size=\"14\" text-anchor=\"middle\">{}</text>\n",
+            x,
+            y,
+            x,
+            y - 14.0,
+            escape_xml(&server_data.shown_name_by_id(user_id))
+        ));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+async fn get_interactions(
+    ctx: &Context,
+    story_key: &StoryKey,
+    text_channel: &GuildChannel,
+    with_graph: bool,
+) -> (String, Option<String>) {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    match (
+        store.get_server_data(&story_key.0),
+        store.get_channel_data(story_key),
+    ) {
+        (Some(server_data), Some(channel_data)) => {
+            let channel_data = server_data.aliased(channel_data);
+            let response =
+                channel_data.make_interactions_string(text_channel, server_data, MAX_PAIRINGS);
+            let graph = if with_graph && !channel_data.strongest_pairings(1).is_empty() {
+                Some(render_graph_svg(&channel_data, server_data))
+            } else {
+                None
+            };
+            (response, graph)
+        }
+        _ => (
+            String::from("Channel not initialised, use [init-channel] to add it"),
+            None,
+        ),
+    }
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        ("<#channel name>", "An initialised channel"),
+        ("-graph", "Also attach the pairings drawn as a graph"),
+    ],
+    permission: Permission::Anyone,
+};
+
+#[command("interactions")]
+#[usage("<#channel name> [-graph]")]
+#[description("Show which writers in an initialised channel mention or reply to each other the most. Provide -graph to also get them drawn as a graph")]
+#[example("#the-fall-of-rome")]
+#[example("#the-fall-of-rome -graph")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn interactions(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        if let Ok(channel_id) = args.single::<ChannelId>() {
+            let with_graph = args.iter::<String>().flatten().any(|arg| arg == "-graph");
+            match channel_id.to_channel(&ctx).await?.guild() {
+                Some(text_channel) => {
+                    let (response, graph) =
+                        get_interactions(ctx, &(server_id, channel_id), &text_channel, with_graph)
+                            .await;
+                    match graph {
+                        Some(svg) => {
+                            ctx.http
+                                .send_file(
+                                    msg.channel_id,
+                                    String::from("interactions.svg"),
+                                    svg.into_bytes(),
+                                    response,
+                                )
+                                .await?;
+                            None
+                        }
+                        None => Some(response),
+                    }
+                }
+                None => Some(String::from("Channel must be a server text channel")),
+            }
+        } else {
+            Some(String::from("1 Arg expected: Channel"))
+        }
+    } else {
+        Some(String::from(
+            "BUG: message had no server id, bot only supports server text channels",
+        ))
+    };
+    if let Some(simple_response) = reply {
+        msg.reply(ctx, simple_response).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,144 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::state::{StoreData, StoryKey};
+use crate::utils::confirmation::{confirm_destructive_action, DRY_RUN_FLAG};
+use crate::utils::trait_extensions::MessageBuilderExt;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+/// The channels to merge from and into, and whether it's a dry run
+fn parse_args(args: &mut Args) -> std::result::Result<(ChannelId, ChannelId, bool), String> {
+    match args.len() {
+        2 | 3 => match (
+            args.single::<ChannelId>(),
+            args.single::<ChannelId>(),
+            args.single::<String>().ok(),
+        ) {
+            (Ok(from), Ok(into), None) => Ok((from, into, false)),
+            (Ok(from), Ok(into), Some(flag)) if flag == DRY_RUN_FLAG => Ok((from, into, true)),
+            (Ok(_), Ok(_), Some(_)) => {
+                Err(String::from("Unknown option, try [help merge-channels]"))
+            }
+            (Err(e), _, _) | (_, Err(e), _) => Err(format!(
+                "Error with command arguments, try [help merge-channels]\nError:{}",
+                e,
+            )),
+        },
+        _ => Err(String::from(
+            "Invalid number of args, try [help merge-channels]",
+        )),
+    }
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        (
+            "<#channel to merge from>",
+            "Its stats are added to the other channel's, and it stops being watched",
+        ),
+        (
+            "<#channel to merge into>",
+            "Keeps being watched, with both channels' stats",
+        ),
+        (
+            "--dry-run",
+            "Show what the merged stats would be, without merging",
+        ),
+    ],
+    permission: Permission::AllowedRoles,
+};
+
+#[command("merge-channels")]
+#[usage("<#channel to merge from> <#channel to merge into> [--dry-run]")]
+#[description("Fold the stats of one initialised channel into another, for stories that have moved channel. The first channel stops being watched. Asks for confirmation first. With --dry-run the merged stats are reported, but nothing is changed")]
+#[example("#the-fall-of-rome #the-fall-of-rome-part-2")]
+#[example("#the-fall-of-rome #the-fall-of-rome-part-2 --dry-run")]
+#[only_in("guilds")] // Reminder: guild = server
+#[bucket("destructive-bucket")]
+async fn merge_channels(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+            true => match parse_args(&mut args) {
+                Ok((from, into, true)) => {
+                    let store_lock = {
+                        let data_read = ctx.data.read().await;
+                        data_read
+                            .get::<StoreData>()
+                            .expect("Expected StoryData in TypeMap.")
+                            .clone()
+                    };
+                    let store = store_lock.read().unwrap();
+                    let (from_key, into_key): (StoryKey, StoryKey) =
+                        ((server_id, from), (server_id, into));
+                    match store.preview_merge_channel_data(&from_key, &into_key) {
+                        Ok(merged) => MessageBuilder::new()
+                            .push("Dry run, nothing was changed. Merging ")
+                            .channel(from)
+                            .push(" into ")
+                            .channel(into)
+                            .push(" would stop watching ")
+                            .channel(from)
+                            .push(" and give ")
+                            .channel(into)
+                            .newline()
+                            .push(
+                                //Checked by the preview that this exists
+                                store
+                                    .get_channel_data(&into_key)
+                                    .unwrap()
+                                    .make_change_preview_string(&merged),
+                            )
+                            .build(),
+                        Err(error_string) => format!("Not merged: {}", error_string),
+                    }
+                }
+                Ok((from, into, false)) => {
+                    let action = MessageBuilder::new()
+                        .push("merge all stats from ")
+                        .channel(from)
+                        .push(" into ")
+                        .channel(into)
+                        .push(" and stop watching ")
+                        .channel(from)
+                        .build();
+                    if confirm_destructive_action(ctx, msg, &action).await? {
+                        let (from_key, into_key): (StoryKey, StoryKey) =
+                            ((server_id, from), (server_id, into));
+                        let store_lock = {
+                            let data_read = ctx.data.read().await;
+                            data_read
+                                .get::<StoreData>()
+                                .expect("Expected StoryData in TypeMap.")
+                                .clone()
+                        };
+                        let merge_result = store_lock
+                            .write()
+                            .unwrap()
+                            .merge_channel_data(&from_key, &into_key);
+                        match merge_result {
+                            Ok(()) => {
+                                record_admin_action(ctx, msg, "merge-channels").await;
+                                MessageBuilder::new()
+                                    .push("Stats merged into ")
+                                    .channel(into)
+                                    .build()
+                            }
+                            Err(error_string) => format!("Not merged: {}", error_string),
+                        }
+                    } else {
+                        return Ok(());
+                    }
+                }
+                Err(e) => e,
+            },
+            false => not_in_allowed_roles_response(),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
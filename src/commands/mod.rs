@@ -1,7 +1,47 @@
+pub mod about;
+pub mod alias;
+pub mod audit_log;
+pub mod bench_replay;
+pub mod character;
+pub mod check_perms;
+pub mod config;
+pub mod content_report;
+pub mod count_words;
+pub mod deinit_channel;
+pub mod doctor;
 pub mod dump_messages;
+pub mod export_store;
+pub mod export_words;
+pub mod forget_me;
+pub mod help;
+pub mod ignore_bot;
 pub mod init_channel;
+pub mod interactions;
+pub mod merge_channels;
+pub mod nudge;
+pub mod personal_log;
+pub mod privacy_mode;
+pub mod quiet_hours;
+pub mod rebuild_stats;
+pub mod recap;
+pub mod remind;
+pub mod reset_stats;
+pub mod response_times;
 pub mod server_summary;
+pub mod sessions;
+pub mod set_min_words;
+pub mod set_prefix;
+pub mod setup;
+pub mod shoutout;
 pub mod show_channels;
 pub mod show_stats;
+pub mod status;
+pub mod timezone;
+pub mod turn_order;
+pub mod undeinit;
+pub mod usage_report;
+pub mod verify_store;
 pub mod word_cloud;
+pub mod word_rules;
+pub mod wordcloud_schedule;
 pub mod feedback;
\ No newline at end of file
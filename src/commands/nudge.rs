@@ -0,0 +1,122 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::guild_config::{update_channel_config, NudgeConfig};
+use crate::state::StoreData;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+const MAX_NUDGE_DAYS: u32 = 365;
+
+fn describe_nudge(channel_id: &ChannelId, nudge: &Option<NudgeConfig>) -> String {
+    let mut builder = MessageBuilder::new();
+    match nudge {
+        Some(nudge) => builder
+            .channel(channel_id)
+            .push(format!(
+                " gets a nudge after {} days without a post, {}",
+                nudge.after_days,
+                match nudge.dm_last_poster {
+                    true => "sent by DM to whoever posted last",
+                    false => "posted in the channel",
+                }
+            ))
+            .build(),
+        None => builder
+            .push("Nudges are off for ")
+            .channel(channel_id)
+            .build(),
+    }
+}
+
+async fn get_nudge(ctx: &Context, server_id: &GuildId, channel_id: &ChannelId) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    let nudge = store
+        .get_server_data(server_id)
+        .and_then(|server_data| server_data.config.channel(channel_id).nudge);
+    describe_nudge(channel_id, &nudge)
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        (
+            "<#channel name>",
+            "An initialised channel, shows its setting if nothing else is given",
+        ),
+        (
+            "[days|off]",
+            "How many days without a post before a nudge, or off to stop them",
+        ),
+        (
+            "[-dm]",
+            "DM whoever posted last instead of posting in the channel",
+        ),
+    ],
+    permission: Permission::AllowedRolesToChange,
+};
+
+#[command("nudge")]
+#[usage("<#channel name> [days|off] [-dm]")]
+#[description("Have the bot give a stalled story a gentle nudge once nobody has posted for a number of days, either in the channel or by DM to the last poster. It nudges at most twice before waiting for the story to pick up again. Setting it needs the same role as init-channel")]
+#[example("#the-fall-of-rome")]
+#[example("#the-fall-of-rome 5")]
+#[example("#the-fall-of-rome 3 -dm")]
+#[example("#the-fall-of-rome off")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn nudge(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match (args.single::<ChannelId>(), args.single::<String>()) {
+            (Ok(channel_id), Err(_)) => get_nudge(ctx, &server_id, &channel_id).await,
+            (Ok(channel_id), Ok(setting)) => {
+                match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+                    true => {
+                        let dm_last_poster =
+                            args.iter::<String>().flatten().any(|arg| arg == "-dm");
+                        let nudge = match setting.as_str() {
+                            "off" => Ok(None),
+                            days => match days.parse::<u32>() {
+                                Ok(days) if (1..=MAX_NUDGE_DAYS).contains(&days) => {
+                                    Ok(Some(NudgeConfig {
+                                        after_days: days,
+                                        dm_last_poster,
+                                    }))
+                                }
+                                _ => Err(format!(
+                                    "Expected a number of days from 1 to {}, or off",
+                                    MAX_NUDGE_DAYS
+                                )),
+                            },
+                        };
+                        match nudge {
+                            Ok(nudge) => {
+                                let response = describe_nudge(&channel_id, &nudge);
+                                update_channel_config(ctx, &server_id, &channel_id, |config| {
+                                    config.nudge = nudge
+                                })
+                                .await;
+                                record_admin_action(ctx, msg, "nudge").await;
+                                response
+                            }
+                            Err(e) => e,
+                        }
+                    }
+                    false => not_in_allowed_roles_response(),
+                }
+            }
+            _ => String::from("Invalid arguments, try [help nudge]"),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
@@ -0,0 +1,203 @@
+use crate::commands::help::{CommandHelp, Permission};
+use crate::state::{PersonalLog, StoreData};
+use crate::utils::confirmation::confirm_destructive_action;
+use chrono_tz::Tz;
+use log::error;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+
+// Big enough for anyone's day, catches typos like a goal of 50000000
+const MAX_DAILY_GOAL: usize = 100_000;
+
+fn describe_personal_log(log: &PersonalLog) -> String {
+    let today = log.today();
+    let mut description = format!(
+        "{} words logged in {} entries. Today: {} words",
+        log.word_count,
+        log.entries,
+        log.words_on(&today)
+    );
+    if let Some(goal) = log.daily_goal {
+        description.push_str(&format!(" of your {} goal", goal));
+    }
+    description.push_str(&format!(
+        ". Streak: {} days. Days are counted in {}",
+        log.streak(today),
+        log.timezone.unwrap_or(Tz::UTC)
+    ));
+    if !log.logging {
+        description.push_str(". Logging is off, turn it back on with [personal-log on]");
+    }
+    description
+}
+
+/// [change] is given the user's log, made if they don't have one
+async fn update_personal_log<F: FnOnce(&mut PersonalLog) -> String>(
+    ctx: &Context,
+    user_id: &UserId,
+    change: F,
+) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    change(store.personal_logs.entry(*user_id).or_default())
+}
+
+async fn get_personal_log(ctx: &Context, user_id: &UserId) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    match store.personal_logs.get(user_id) {
+        Some(log) => describe_personal_log(log),
+        None => String::from("You don't have a personal log, start one with [personal-log on]"),
+    }
+}
+
+async fn forget_personal_log(ctx: &Context, user_id: &UserId) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    match store.personal_logs.remove(user_id) {
+        Some(_) => String::from("Deleted your personal log"),
+        None => String::from("You don't have a personal log"),
+    }
+}
+
+/// Counts a DM towards its author's personal log, if they've turned logging on
+pub async fn log_personal_writing(ctx: &Context, message: &Message) {
+    if message.author.bot {
+        return;
+    }
+    let logged = {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let mut store = store_lock.write().unwrap();
+        match store.personal_logs.get_mut(&message.author.id) {
+            Some(log) if log.logging => {
+                let today = log.today();
+                let words = log.log(&message.content, today);
+                Some(format!(
+                    "Logged {} words. {}",
+                    words,
+                    describe_personal_log(log)
+                ))
+            }
+            _ => None,
+        }
+    };
+    if let Some(reply) = logged {
+        if let Err(why) = message.reply(ctx, reply).await {
+            error!("Failed replying to personal log entry: {:?}", why);
+        }
+    }
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        (
+            "on",
+            "Start counting what you DM me, shows your log if nothing's given",
+        ),
+        ("off", "Stop counting DMs, your log is kept"),
+        (
+            "goal <words|off>",
+            "Words a day to keep your streak going, any writing counts if off",
+        ),
+        (
+            "timezone <timezone name>",
+            "A name like Europe/London for when your days start, UTC if not set",
+        ),
+        ("forget", "Delete your personal log"),
+    ],
+    permission: Permission::Anyone,
+};
+
+#[command("personal-log")]
+#[usage("[on | off | goal <words|off> | timezone <timezone name> | forget]")]
+#[description("Keep a personal writing log outside any server: once it's on, paste what you write into a DM with me and I'll count it, with a daily goal and a streak of days you met it. Your log is yours alone and never shows in a server's stats. Use it here or in DMs")]
+#[example("on")]
+#[example("goal 500")]
+#[example("timezone Europe/London")]
+#[example("")]
+async fn personal_log(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let user_id = msg.author.id;
+    let reply = match args.single::<String>().as_deref() {
+        Err(_) => get_personal_log(ctx, &user_id).await,
+        Ok("on") => {
+            update_personal_log(ctx, &user_id, |log| {
+                log.logging = true;
+                String::from("Personal log on, DM me what you write and I'll count it. See your log with [personal-log]")
+            })
+            .await
+        }
+        Ok("off") => {
+            update_personal_log(ctx, &user_id, |log| {
+                log.logging = false;
+                String::from("Personal log off, your DMs won't be counted. What you've logged is kept")
+            })
+            .await
+        }
+        Ok("goal") => match args.single::<String>().as_deref() {
+            Ok("off") => {
+                update_personal_log(ctx, &user_id, |log| {
+                    log.daily_goal = None;
+                    String::from("Goal removed, any writing keeps your streak going")
+                })
+                .await
+            }
+            Ok(words) => match words.parse::<usize>() {
+                Ok(goal) if (1..=MAX_DAILY_GOAL).contains(&goal) => {
+                    update_personal_log(ctx, &user_id, |log| {
+                        log.daily_goal = Some(goal);
+                        format!("Your goal is now {} words a day", goal)
+                    })
+                    .await
+                }
+                _ => format!("Goals should be from 1 to {} words", MAX_DAILY_GOAL),
+            },
+            Err(_) => String::from("Expected the words a day to aim for, or off"),
+        },
+        Ok("timezone") => match args.single::<String>().map(|s| s.parse::<Tz>()) {
+            Ok(Ok(timezone)) => {
+                update_personal_log(ctx, &user_id, |log| {
+                    log.timezone = Some(timezone);
+                    format!("Your days are now counted in {}", timezone)
+                })
+                .await
+            }
+            _ => String::from(
+                "Unknown timezone, expected a name like Europe/London or America/New_York",
+            ),
+        },
+        Ok("forget") => match confirm_destructive_action(ctx, msg, "delete your personal log")
+            .await?
+        {
+            true => forget_personal_log(ctx, &user_id).await,
+            false => return Ok(()),
+        },
+        Ok(_) => String::from("Invalid arguments, try [help personal-log]"),
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::state::StoreData;
+use crate::utils::transient::transient_reply;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+
+pub async fn in_privacy_mode(ctx: &Context, server_id: &GuildId) -> bool {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    store
+        .get_server_data(server_id)
+        .is_some_and(|server_data| server_data.config.privacy_mode)
+}
+
+fn describe_privacy_mode(privacy_mode: bool) -> String {
+    match privacy_mode {
+        true => String::from("Privacy mode is on, stats name writers as Writer A, Writer B and so on, and writers can only see their own summary, by DM"),
+        false => String::from("Privacy mode is off, stats name writers"),
+    }
+}
+
+async fn set_privacy_mode(ctx: &Context, server_id: &GuildId, privacy_mode: bool) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let server_data = store.get_server_data_mut_maybe_create(server_id);
+    server_data.config.privacy_mode = privacy_mode;
+    server_data.display_names.label_known_authors();
+    describe_privacy_mode(privacy_mode)
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[("[on|off]", "Shows whether it's on if not given")],
+    permission: Permission::AllowedRolesToChange,
+};
+
+#[command("privacy-mode")]
+#[usage("[on|off]")]
+#[description("For servers that would rather not name names: in privacy mode stats, sessions, interactions, response times, content reports and recaps label writers Writer A, Writer B and so on, and writers can only get their own server summary, which is sent by DM. Setting it needs the same role as init-channel")]
+#[example("")]
+#[example("on")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn privacy_mode(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        if args.is_empty() {
+            describe_privacy_mode(in_privacy_mode(ctx, &server_id).await)
+        } else {
+            match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+                true => match args.single::<String>().as_deref() {
+                    Ok(mode @ ("on" | "off")) => {
+                        let reply = set_privacy_mode(ctx, &server_id, mode == "on").await;
+                        record_admin_action(ctx, msg, "privacy-mode").await;
+                        reply
+                    }
+                    _ => String::from("Expected on or off"),
+                },
+                false => not_in_allowed_roles_response(),
+            }
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    transient_reply(ctx, msg, reply).await?;
+    Ok(())
+}
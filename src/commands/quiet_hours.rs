@@ -0,0 +1,104 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::guild_config::QuietHours;
+use crate::state::StoreData;
+use crate::utils::transient::transient_reply;
+use chrono::NaiveTime;
+use chrono_tz::Tz;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+
+/// Parses a window like 22:00-07:30
+fn parse_quiet_hours(s: &str) -> Option<QuietHours> {
+    let (start, end) = s.split_once('-')?;
+    let start = NaiveTime::parse_from_str(start, "%H:%M").ok()?;
+    let end = NaiveTime::parse_from_str(end, "%H:%M").ok()?;
+    match start == end {
+        true => None,
+        false => Some(QuietHours { start, end }),
+    }
+}
+
+fn describe_quiet_hours(quiet_hours: &Option<QuietHours>, timezone: &Tz) -> String {
+    match quiet_hours {
+        Some(quiet_hours) => format!(
+            "Automated posts are held back from {} to {} ({})",
+            quiet_hours.start.format("%H:%M"),
+            quiet_hours.end.format("%H:%M"),
+            timezone
+        ),
+        None => String::from("This server has no quiet hours"),
+    }
+}
+
+async fn update_quiet_hours(
+    ctx: &Context,
+    server_id: &GuildId,
+    quiet_hours: Option<Option<QuietHours>>,
+) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let config = &mut store.get_server_data_mut_maybe_create(server_id).config;
+    if let Some(quiet_hours) = quiet_hours {
+        config.quiet_hours = quiet_hours;
+    }
+    describe_quiet_hours(&config.quiet_hours, &config.timezone())
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[(
+        "[HH:MM-HH:MM|off]",
+        "The window to hold automated posts back in, in the server's timezone. Shows the current one if not given",
+    )],
+    permission: Permission::AllowedRolesToChange,
+};
+
+#[command("quiet-hours")]
+#[usage("[HH:MM-HH:MM|off]")]
+#[description("Show or set this server's quiet hours, during which the bot's automated posts are held back until the window ends. Times are in the server's timezone (see [timezone]) and can run over midnight. Setting them needs the same role as init-channel")]
+#[example("")]
+#[example("22:00-07:30")]
+#[example("off")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn quiet_hours(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        if args.is_empty() {
+            update_quiet_hours(ctx, &server_id, None).await
+        } else {
+            match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+                true => match args.single::<String>() {
+                    Ok(window) if window == "off" => {
+                        let reply = update_quiet_hours(ctx, &server_id, Some(None)).await;
+                        record_admin_action(ctx, msg, "quiet-hours").await;
+                        reply
+                    }
+                    Ok(window) => match parse_quiet_hours(&window) {
+                        Some(quiet_hours) => {
+                            let reply =
+                                update_quiet_hours(ctx, &server_id, Some(Some(quiet_hours))).await;
+                            record_admin_action(ctx, msg, "quiet-hours").await;
+                            reply
+                        }
+                        None => String::from(
+                            "Expected quiet hours like 22:00-07:30, with different start and end times",
+                        ),
+                    },
+                    Err(_) => String::from("Invalid arguments, try [help quiet-hours]"),
+                },
+                false => not_in_allowed_roles_response(),
+            }
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    transient_reply(ctx, msg, reply).await?;
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{
+    author_is_in_allowed_roles, count_channel_history, not_in_allowed_roles_response,
+    ALLOWEDROLES_CHECK,
+};
+use crate::rate_limit::EXPENSIVE_CHECK;
+use crate::state::{StoreData, StoryKey};
+use crate::utils::confirmation::{confirm_destructive_action, DRY_RUN_FLAG};
+use crate::utils::replied::reply_and_fail;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+/// Recounts the channel's history, and only then swaps the new stats in for the old, so a failed
+/// recount leaves them as they were. Messages posted while the history is read are counted after
+async fn rebuild_channel(ctx: &Context, story_key: &StoryKey) -> std::result::Result<(), String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    store_lock.write().unwrap().start_rebuild(story_key);
+    let recount = async {
+        let channel = match story_key
+            .1
+            .to_channel(&ctx)
+            .await
+            .map(|channel| channel.guild())
+        {
+            Ok(Some(channel)) => channel,
+            Ok(None) => return Err(String::from("not a server channel")),
+            Err(why) => return Err(format!("couldn't fetch the channel: {}", why)),
+        };
+        let rebuilt = count_channel_history(&channel, ctx, None).await?;
+        Ok((rebuilt, channel.last_message_id))
+    }
+    .await;
+    let mut store = store_lock.write().unwrap();
+    match recount {
+        Ok((rebuilt, counted_up_to)) => {
+            match store.finish_rebuild(story_key, Some(rebuilt), counted_up_to) {
+                true => Ok(()),
+                false => Err(String::from("the channel was deinitialised meanwhile")),
+            }
+        }
+        Err(e) => {
+            store.finish_rebuild(story_key, None, None);
+            Err(e)
+        }
+    }
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        ("<#channel name>", "An initialised channel to recount"),
+        (
+            "--dry-run",
+            "Show how the stats would change, without changing them",
+        ),
+    ],
+    permission: Permission::AllowedRoles,
+};
+
+#[command("rebuild-stats")]
+#[usage("<#channel name> [--dry-run]")]
+#[description("Delete the stats for an initialised channel and rebuild them from the channel's history. Asks for confirmation first. With --dry-run the history is recounted and the difference reported, but nothing is changed")]
+#[example("#the-fall-of-rome")]
+#[example("#the-fall-of-rome --dry-run")]
+#[only_in("guilds")] // Reminder: guild = server
+#[bucket("destructive-bucket")]
+#[checks(AllowedRoles, Expensive)]
+async fn rebuild_stats(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+            true => {
+                if let Ok(channel_id) = args.single::<ChannelId>() {
+                    let story_key: StoryKey = (server_id, channel_id);
+                    let dry_run = args
+                        .iter::<String>()
+                        .flatten()
+                        .any(|arg| arg == DRY_RUN_FLAG);
+                    let store_lock = {
+                        let data_read = ctx.data.read().await;
+                        data_read
+                            .get::<StoreData>()
+                            .expect("Expected StoryData in TypeMap.")
+                            .clone()
+                    };
+                    let is_initialised = store_lock.read().unwrap().channel_data_exists(&story_key);
+                    if is_initialised && dry_run {
+                        //Safely assuming we can convert to a guild channel considering the [only_in] constraint
+                        let channel = channel_id.to_channel(&ctx).await?.guild().unwrap();
+                        let rebuilt = count_channel_history(&channel, ctx, None).await;
+                        let current = store_lock
+                            .read()
+                            .unwrap()
+                            .get_channel_data(&story_key)
+                            .cloned();
+                        match (current, rebuilt) {
+                            (_, Err(e)) => format!("Dry run failed, {}", e),
+                            (Some(current), Ok(rebuilt)) => MessageBuilder::new()
+                                .push("Dry run, nothing was changed. Rebuilding ")
+                                .channel(channel_id)
+                                .push_line(" would give")
+                                .push(current.make_change_preview_string(&rebuilt))
+                                .build(),
+                            (None, Ok(_)) => String::from(
+                                "Channel not initialised, use [init-channel] to add it",
+                            ),
+                        }
+                    } else if is_initialised {
+                        let action = MessageBuilder::new()
+                            .push("delete all stats for ")
+                            .channel(channel_id)
+                            .push(" and rebuild them from the channel history")
+                            .build();
+                        if confirm_destructive_action(ctx, msg, &action).await? {
+                            match rebuild_channel(ctx, &story_key).await {
+                                Ok(()) => {
+                                    record_admin_action(ctx, msg, "rebuild-stats").await;
+                                    MessageBuilder::new()
+                                        .push("Stats rebuilt for ")
+                                        .channel(channel_id)
+                                        .build()
+                                }
+                                Err(error_string) => {
+                                    return reply_and_fail(
+                                        ctx,
+                                        msg,
+                                        format!(
+                                            "Not rebuilt, the stats are as they were: {}",
+                                            error_string
+                                        ),
+                                    )
+                                    .await
+                                }
+                            }
+                        } else {
+                            return Ok(());
+                        }
+                    } else {
+                        String::from("Channel not initialised, use [init-channel] to add it")
+                    }
+                } else {
+                    String::from("1 Arg expected: Channel")
+                }
+            }
+            false => not_in_allowed_roles_response(),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
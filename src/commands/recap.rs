@@ -0,0 +1,270 @@
+use crate::commands::help::{CommandHelp, Permission};
+use crate::language_parsing::WordRules;
+use crate::rate_limit::EXPENSIVE_CHECK;
+use crate::state::{StoreData, StoryKey};
+use crate::stats::count_words;
+use crate::utils::discord::fetch_messages_since;
+use chrono::{Duration, Utc};
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::collections::HashMap;
+
+const DEFAULT_RECAP_DAYS: i64 = 7;
+const MAX_RECAP_DAYS: i64 = 14;
+// Keeps a recap of a very busy channel from reading back forever
+const MAX_RECAP_MESSAGES: usize = 2000;
+const LONGEST_POSTS: usize = 3;
+const OPENING_LENGTH: usize = 150;
+
+/// The first sentence of a post, cut short if it runs on
+pub fn opening(content: &str) -> String {
+    let content = content.trim();
+    let sentence_end = content
+        .char_indices()
+        .find(|(_, c)| matches!(c, '.' | '!' | '?' | '\n'))
+        .map_or(content.len(), |(i, c)| i + c.len_utf8());
+    let sentence = content[..sentence_end].trim();
+    match sentence.char_indices().nth(OPENING_LENGTH) {
+        Some((i, _)) => format!("{}…", sentence[..i].trim_end()),
+        None => sentence.to_string(),
+    }
+}
+
+/// [names] are how authors are shown on the server, their usernames if not given
+fn make_recap(
+    (server_id, channel_id): &StoryKey,
+    messages: &[Message],
+    days: i64,
+    names: &HashMap<UserId, String>,
+    rules: &WordRules,
+) -> String {
+    let name = |author: &User| -> String {
+        names
+            .get(&author.id)
+            .cloned()
+            .unwrap_or_else(|| author.name.clone())
+    };
+    let mut builder = MessageBuilder::new();
+    builder.push_bold_line(format!(
+        "Recap of the last {} days in {}",
+        days,
+        channel_id.mention()
+    ));
+    if messages.is_empty() {
+        builder.push("Nothing has been written");
+        return builder.build();
+    }
+
+    let word_counts: Vec<usize> = messages
+        .iter()
+        .map(|message| count_words(&message.content, rules))
+        .collect();
+    let mut words_by_author: HashMap<&UserId, (String, usize)> = HashMap::new();
+    for (message, words) in messages.iter().zip(word_counts.iter()) {
+        words_by_author
+            .entry(&message.author.id)
+            .or_insert_with(|| (name(&message.author), 0))
+            .1 += words;
+    }
+    let mut authors: Vec<(String, usize)> = words_by_author.into_values().collect();
+    authors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let authors: Vec<String> = authors
+        .iter()
+        .map(|(name, words)| format!("{} ({})", name, words))
+        .collect();
+    builder.push_line(format!(
+        "{} words in {} posts by {}",
+        word_counts.iter().sum::<usize>(),
+        messages.len(),
+        authors.join(", ")
+    ));
+
+    let mut longest: Vec<(&Message, usize)> = messages
+        .iter()
+        .zip(word_counts.iter().copied())
+        .filter(|(_, words)| *words > 0)
+        .collect();
+    longest.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.id.cmp(&b.0.id)));
+    if !longest.is_empty() {
+        builder.push_bold_line("Longest posts");
+    }
+    for (message, words) in longest.into_iter().take(LONGEST_POSTS) {
+        builder
+            .push_quote_line_safe(opening(&message.content))
+            .push_line(format!(
+                // Fetched messages have no guild id, so [Message::link] would point at a DM
+                "— {}, {} words <https://discord.com/channels/{}/{}/{}>",
+                name(&message.author),
+                words,
+                server_id,
+                channel_id,
+                message.id
+            ));
+    }
+    builder.build()
+}
+
+fn parse_days(args: &mut Args) -> std::result::Result<i64, String> {
+    if args.is_empty() {
+        Ok(DEFAULT_RECAP_DAYS)
+    } else {
+        match args.single::<i64>() {
+            Ok(days) if (1..=MAX_RECAP_DAYS).contains(&days) => Ok(days),
+            _ => Err(format!(
+                "Days should be a whole number from 1 to {}, try [help recap]",
+                MAX_RECAP_DAYS
+            )),
+        }
+    }
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[(
+        "[days]",
+        "How many days back to cover, 7 if not given and at most 14",
+    )],
+    permission: Permission::Anyone,
+};
+
+#[command("recap")]
+#[usage("[days]")]
+#[description("Post a recap of the last few days in this channel, to help anyone returning catch up: total words, who wrote, and the openings of the longest posts with links to them. Messages the channel doesn't count (ignored bots, posts under the minimum words) and the bot's own posts are left out")]
+#[example("")]
+#[example("3")]
+#[only_in("guilds")] // Reminder: guild = server
+#[checks(Expensive)]
+async fn recap(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match parse_days(&mut args) {
+            Ok(days) => {
+                let (channel_config, rules) = {
+                    let store_lock = {
+                        let data_read = ctx.data.read().await;
+                        data_read
+                            .get::<StoreData>()
+                            .expect("Expected StoryData in TypeMap.")
+                            .clone()
+                    };
+                    let store = store_lock.read().unwrap();
+                    store
+                        .get_server_data(&server_id)
+                        .map(|server_data| {
+                            (
+                                server_data.config.channel(&msg.channel_id),
+                                server_data.config.word_rules,
+                            )
+                        })
+                        .unwrap_or_default()
+                };
+                let bot_id = ctx.cache.current_user_id().await;
+                let since = Utc::now() - Duration::days(days);
+                let messages: Vec<Message> = fetch_messages_since(
+                    &ctx.http,
+                    msg.channel_id,
+                    msg.id,
+                    since,
+                    MAX_RECAP_MESSAGES,
+                )
+                .await?
+                .into_iter()
+                .filter(|message| {
+                    message.author.id != bot_id && channel_config.counts_message(message, &rules)
+                })
+                .collect();
+                let names: HashMap<UserId, String> = {
+                    let store_lock = {
+                        let data_read = ctx.data.read().await;
+                        data_read
+                            .get::<StoreData>()
+                            .expect("Expected StoryData in TypeMap.")
+                            .clone()
+                    };
+                    let store = store_lock.read().unwrap();
+                    match store.get_server_data(&server_id) {
+                        Some(server_data) => messages
+                            .iter()
+                            .map(|message| {
+                                (message.author.id, server_data.shown_name(&message.author))
+                            })
+                            .collect(),
+                        None => HashMap::new(),
+                    }
+                };
+                make_recap(
+                    &(server_id, msg.channel_id),
+                    &messages,
+                    days,
+                    &names,
+                    &rules,
+                )
+            }
+            Err(e) => e,
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::bench_corpus::synthetic_messages;
+    use crate::commands::recap::{make_recap, opening};
+    use crate::language_parsing::WordRules;
+    use serenity::model::prelude::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn openings() {
+        assert_eq!(opening("  It begins. Then more."), "It begins.");
+        assert_eq!(opening("Who goes there?\nA friend"), "Who goes there?");
+        assert_eq!(opening("no ending"), "no ending");
+        let long = "word ".repeat(100);
+        assert!(opening(&long).chars().count() <= 151);
+        assert!(opening(&long).ends_with('…'));
+    }
+
+    #[test]
+    fn recap_lists_longest_posts() {
+        let messages = synthetic_messages(40, 3);
+        let recap = make_recap(
+            &(GuildId(1), ChannelId(7)),
+            &messages,
+            7,
+            &HashMap::new(),
+            &WordRules::default(),
+        );
+        assert!(recap.contains("in 40 posts by "));
+        assert_eq!(recap.matches("https://discord.com/channels/").count(), 3);
+
+        // As fetched over HTTP, without a guild id
+        let mut fetched = synthetic_messages(40, 3);
+        for message in fetched.iter_mut() {
+            message.guild_id = None;
+        }
+        let recap = make_recap(
+            &(GuildId(1), ChannelId(7)),
+            &fetched,
+            7,
+            &HashMap::new(),
+            &WordRules::default(),
+        );
+        assert_eq!(
+            recap.matches("https://discord.com/channels/1/7/").count(),
+            3
+        );
+        assert!(!recap.contains("@me"));
+
+        let empty = make_recap(
+            &(GuildId(1), ChannelId(7)),
+            &[],
+            7,
+            &HashMap::new(),
+            &WordRules::default(),
+        );
+        assert!(empty.contains("Nothing has been written"));
+    }
+}
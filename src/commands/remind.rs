@@ -0,0 +1,238 @@
+use crate::commands::help::{CommandHelp, Permission};
+use crate::scheduler::{Reminder, ReminderDelivery};
+use crate::state::StoreData;
+use chrono::{Duration, Utc};
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+
+const MAX_REMINDERS_PER_USER: usize = 20;
+const MAX_REMINDER_DAYS: i64 = 365;
+
+/// Parses durations like 2d, 90m or 1w2d12h
+fn parse_duration(s: &str) -> Option<Duration> {
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        // Anything longer overflows, and is well past [MAX_REMINDER_DAYS] anyway
+        if number.len() > 6 {
+            return None;
+        }
+        let amount: i64 = number.parse().ok()?;
+        number.clear();
+        total = total
+            + match c {
+                'w' => Duration::weeks(amount),
+                'd' => Duration::days(amount),
+                'h' => Duration::hours(amount),
+                'm' => Duration::minutes(amount),
+                _ => return None,
+            };
+    }
+    match number.is_empty() && total > Duration::zero() {
+        true => Some(total),
+        false => None,
+    }
+}
+
+/// The author's reminders on this server, soonest first, as numbered for [remind cancel]
+fn users_reminders(reminders: &[Reminder], user_id: &UserId) -> Vec<usize> {
+    let mut indices: Vec<usize> = reminders
+        .iter()
+        .enumerate()
+        .filter(|(_, reminder)| reminder.user_id == *user_id)
+        .map(|(i, _)| i)
+        .collect();
+    indices.sort_by_key(|i| reminders[*i].due);
+    indices
+}
+
+async fn add_reminder(ctx: &Context, server_id: &GuildId, reminder: Reminder) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let reminders = &mut store.get_server_data_mut_maybe_create(server_id).reminders;
+    if users_reminders(reminders, &reminder.user_id).len() >= MAX_REMINDERS_PER_USER {
+        return format!(
+            "You already have {} reminders set, cancel one with [remind cancel] first",
+            MAX_REMINDERS_PER_USER
+        );
+    }
+    let response = format!(
+        "I'll remind you {} on {} UTC",
+        match reminder.delivery {
+            ReminderDelivery::DirectMessage => "by DM",
+            ReminderDelivery::Channel => "here",
+        },
+        reminder.due.format("%Y-%m-%d %H:%M")
+    );
+    reminders.push(reminder);
+    response
+}
+
+async fn list_reminders(ctx: &Context, server_id: &GuildId, user_id: &UserId) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    let reminders = store
+        .get_server_data(server_id)
+        .map_or(&[][..], |server_data| &server_data.reminders);
+    let indices = users_reminders(reminders, user_id);
+    if indices.is_empty() {
+        return String::from("You have no reminders set on this server");
+    }
+    let lines: Vec<String> = indices
+        .iter()
+        .enumerate()
+        .map(|(number, i)| {
+            format!(
+                "{}. {} UTC: {}",
+                number + 1,
+                reminders[*i].due.format("%Y-%m-%d %H:%M"),
+                reminders[*i].text
+            )
+        })
+        .collect();
+    lines.join("\n")
+}
+
+async fn cancel_reminder(
+    ctx: &Context,
+    server_id: &GuildId,
+    user_id: &UserId,
+    number: usize,
+) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let reminders = &mut store.get_server_data_mut_maybe_create(server_id).reminders;
+    match users_reminders(reminders, user_id).get(number.wrapping_sub(1)) {
+        Some(i) => format!("Cancelled reminder: {}", reminders.remove(*i).text),
+        None => String::from("No reminder with that number, see [remind list]"),
+    }
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        (
+            "<me|here>",
+            "me to be reminded by DM, here to be pinged in this channel",
+        ),
+        (
+            "in <duration>",
+            "How long until the reminder, like 2d, 12h, 90m or 1w2d",
+        ),
+        ("[to] <message>", "What to remind you of"),
+        ("list", "Show your reminders on this server"),
+        (
+            "cancel <number>",
+            "Cancel one of them, numbered as in the list",
+        ),
+    ],
+    permission: Permission::Anyone,
+};
+
+#[command("remind")]
+#[usage("<me|here> in <duration> [to] <message> | list | cancel <number>")]
+#[description("Set yourself a reminder, delivered by DM (me) or with a ping in this channel (here). Durations can be given in weeks, days, hours and minutes")]
+#[example("me in 2d to post the next scene")]
+#[example("here in 1w2d check in on the others")]
+#[example("list")]
+#[example("cancel 1")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn remind(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match args.single::<String>().as_deref() {
+            Ok("list") => list_reminders(ctx, &server_id, &msg.author.id).await,
+            Ok("cancel") => match args.single::<usize>() {
+                Ok(number) => cancel_reminder(ctx, &server_id, &msg.author.id, number).await,
+                Err(_) => String::from("Expected the number of the reminder to cancel"),
+            },
+            Ok(target @ "me") | Ok(target @ "here") => {
+                let delivery = match target {
+                    "me" => ReminderDelivery::DirectMessage,
+                    _ => ReminderDelivery::Channel,
+                };
+                let duration = match (args.single::<String>(), args.single::<String>()) {
+                    (Ok(in_), Ok(duration)) if in_ == "in" => parse_duration(&duration),
+                    _ => None,
+                };
+                let text = args.rest();
+                let text = text.strip_prefix("to ").unwrap_or(text).trim();
+                match duration {
+                    Some(duration) if duration > Duration::days(MAX_REMINDER_DAYS) => {
+                        format!("Reminders can be at most {} days away", MAX_REMINDER_DAYS)
+                    }
+                    Some(_) if text.is_empty() => String::from("What should I remind you of?"),
+                    Some(duration) => {
+                        let reminder = Reminder {
+                            user_id: msg.author.id,
+                            channel_id: msg.channel_id,
+                            due: Utc::now() + duration,
+                            text: text.to_string(),
+                            delivery,
+                            failed_attempts: 0,
+                        };
+                        add_reminder(ctx, &server_id, reminder).await
+                    }
+                    None => String::from(
+                        "Expected a duration like \"in 2d\", \"in 12h\" or \"in 1w2d\"",
+                    ),
+                }
+            }
+            _ => String::from("Invalid arguments, try [help remind]"),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    // Replies can quote a reminder's text, which mustn't get to ping anyone
+    msg.channel_id
+        .send_message(&ctx.http, |create_message| {
+            create_message
+                .reference_message(msg)
+                .content(reply)
+                .allowed_mentions(|mentions| mentions.empty_parse().replied_user(true))
+        })
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::commands::remind::parse_duration;
+    use chrono::Duration;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("2d"), Some(Duration::days(2)));
+        assert_eq!(parse_duration("90m"), Some(Duration::minutes(90)));
+        assert_eq!(
+            parse_duration("1w2d12h"),
+            Some(Duration::days(9) + Duration::hours(12))
+        );
+        assert_eq!(parse_duration("12"), None);
+        assert_eq!(parse_duration("0d"), None);
+        assert_eq!(parse_duration("3x"), None);
+        assert_eq!(parse_duration("99999999999w"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+}
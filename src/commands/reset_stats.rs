@@ -0,0 +1,87 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::state::StoreData;
+use crate::utils::confirmation::confirm_destructive_action;
+use chrono::Utc;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+/// The number of the season archived, [None] if the channel isn't initialised
+async fn archive_season(
+    ctx: &Context,
+    server_id: &GuildId,
+    channel_id: &ChannelId,
+) -> Option<usize> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    store
+        .get_server_data_mut(server_id)?
+        .archive_season(channel_id, Utc::now())
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[(
+        "<#channel name>",
+        "An initialised channel, its stats are archived as a season",
+    )],
+    permission: Permission::AllowedRoles,
+};
+
+#[command("reset-stats")]
+#[usage("<#channel name>")]
+#[description("Start a new season in a channel, for stories run in arcs: its stats so far are archived as a season, viewable with [show-stats #channel --season 1], and counting starts again from zero. Rebuilding the channel afterwards only recounts the current season. Asks for confirmation first")]
+#[example("#the-fall-of-rome")]
+#[only_in("guilds")] // Reminder: guild = server
+#[bucket("destructive-bucket")]
+async fn reset_stats(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+            true => {
+                if let Ok(channel_id) = args.single::<ChannelId>() {
+                    let action = MessageBuilder::new()
+                        .push("archive the stats for ")
+                        .channel(channel_id)
+                        .push(" as a season and start counting again from zero")
+                        .build();
+                    if confirm_destructive_action(ctx, msg, &action).await? {
+                        match archive_season(ctx, &server_id, &channel_id).await {
+                            Some(season) => {
+                                record_admin_action(ctx, msg, "reset-stats").await;
+                                MessageBuilder::new()
+                                    .push(format!("Archived season {} of ", season))
+                                    .channel(channel_id)
+                                    .push(format!(
+                                        ", see it with [show-stats --season {}]. Season {} starts now",
+                                        season,
+                                        season + 1
+                                    ))
+                                    .build()
+                            }
+                            None => String::from(
+                                "Channel not initialised, use [init-channel] to add it",
+                            ),
+                        }
+                    } else {
+                        return Ok(());
+                    }
+                } else {
+                    String::from("1 Arg expected: Channel")
+                }
+            }
+            false => not_in_allowed_roles_response(),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
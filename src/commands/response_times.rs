@@ -0,0 +1,58 @@
+use crate::commands::help::{CommandHelp, Permission};
+use crate::state::{StoreData, StoryKey};
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+
+async fn get_response_times(
+    ctx: &Context,
+    story_key: &StoryKey,
+    text_channel: &GuildChannel,
+) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    match (
+        store.get_server_data(&story_key.0),
+        store.get_channel_data(story_key),
+    ) {
+        (Some(server_data), Some(channel_data)) => server_data
+            .aliased(channel_data)
+            .make_response_times_string(text_channel, server_data),
+        _ => String::from("Channel not initialised, use [init-channel] to add it"),
+    }
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[("<#channel name>", "An initialised channel")],
+    permission: Permission::Anyone,
+};
+
+#[command("response-times")]
+#[usage("<#channel name>")]
+#[description("Show each writer's median response time in an initialised channel, how long they take to post after someone else has. Only the first of several posts in a row counts, fastest first")]
+#[example("#the-fall-of-rome")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn response_times(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        if let Ok(channel_id) = args.single::<ChannelId>() {
+            match channel_id.to_channel(&ctx).await?.guild() {
+                Some(text_channel) => {
+                    get_response_times(ctx, &(server_id, channel_id), &text_channel).await
+                }
+                None => String::from("Channel must be a server text channel"),
+            }
+        } else {
+            String::from("1 Arg expected: Channel")
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
@@ -1,50 +1,99 @@
-use crate::state::{ServerData, Store, StoreData, StoryKey};
-use crate::stats::WordStats;
-use crate::utils::iterators::SortedHashMap;
-use crate::utils::trait_extensions::MessageBuilderExt;
-use chrono::{DateTime, NaiveDateTime, Utc};
-use serenity::framework::standard::help_commands::with_embeds;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::privacy_mode::in_privacy_mode;
+use crate::state::StoreData;
 use serenity::framework::standard::{macros::command, Args, CommandResult};
 use serenity::model::prelude::*;
 use serenity::prelude::*;
-use serenity::utils::MessageBuilder;
-use std::hash::Hash;
-use std::{cmp, collections::HashMap};
+
+const BY_AUTHOR_FLAG: &str = "--by-author";
+const LEADERBOARD_SIZE: usize = 10;
+
+enum Summary {
+    Writer(UserId),
+    Leaderboard,
+}
 
 async fn make_server_summary(ctx: &Context, user_id: &UserId, server_id: &GuildId) -> String {
-    let user = user_id.to_user(ctx).await.unwrap();
-    let mut channel_ids_with_counts = {
-        let store_lock = {
-            let data_read = ctx.data.read().await;
-            data_read
-                .get::<StoreData>()
-                .expect("Expected StoreData in TypeMap.")
-                .clone()
-        };
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    // Asking about an alt account summarises the main account it's linked to
+    let (user_id, channel_ids_with_counts) = {
         let store = store_lock.read().unwrap();
         let server = store.get_server_data(server_id);
         match server {
-            Some(server_data) => server_data.channel_ids_by_wordcount_for_user(&user),
+            Some(server_data) => {
+                let user_id = server_data.config.main_account(user_id);
+                let channel_ids = server_data.channel_ids_by_wordcount_for_user(&user_id);
+                (user_id, channel_ids)
+            }
             None => return format!("There are no initialised channels on this server"),
         }
     };
-    let mut channels_with_counts = vec![];
-    for (channel_id, wc) in channel_ids_with_counts.drain(..) {
-        let channel = channel_id.to_channel(ctx).await.unwrap();
-        channels_with_counts.push((channel, wc));
+    let store = store_lock.read().unwrap();
+    match store.get_server_data(server_id) {
+        Some(server_data) => server_data.make_user_stats_string(&user_id, channel_ids_with_counts),
+        None => format!("There are no initialised channels on this server"),
+    }
+}
+
+async fn make_leaderboard(ctx: &Context, server_id: &GuildId) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoreData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    match store.get_server_data(server_id) {
+        Some(server_data) => server_data.make_leaderboard_string(LEADERBOARD_SIZE),
+        None => String::from("There are no initialised channels on this server"),
     }
-    ServerData::make_user_stats_string(&user, channels_with_counts)
 }
 
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        ("<@ user mention>", "The writer to summarise"),
+        (
+            "--by-author",
+            "Instead, rank the server's top writers by words across every initialised channel",
+        ),
+    ],
+    permission: Permission::Anyone,
+};
+
 #[command("server-summary")]
-#[usage("<@ user mention>")]
-#[description("Display stats for a given user across all initialised channels on this server")]
+#[usage("<@ user mention> | --by-author")]
+#[description("Display stats for a given user across all initialised channels on this server, or with --by-author a leaderboard of the top writers by words across them all. On servers in privacy mode writers can only summarise themselves, and get it by DM, and the leaderboard names writers as Writer A, Writer B and so on")]
 #[example("@Caligula")]
+#[example("--by-author")]
 #[only_in("guilds")] // Reminder: guild = server
 async fn server_summary(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let reply = if let Some(server_id) = msg.guild_id {
         match parse_args(&mut args) {
-            Ok(user) => Some(make_server_summary(ctx, &user, &server_id).await),
+            Ok(Summary::Leaderboard) => Some(make_leaderboard(ctx, &server_id).await),
+            Ok(Summary::Writer(user)) if !in_privacy_mode(ctx, &server_id).await => {
+                Some(make_server_summary(ctx, &user, &server_id).await)
+            }
+            Ok(Summary::Writer(user)) if user == msg.author.id => {
+                let summary = make_server_summary(ctx, &user, &server_id).await;
+                match msg.author.direct_message(ctx, |m| m.content(summary)).await {
+                    Ok(_) => Some(String::from(
+                        "This server is in privacy mode, so I've sent your summary by DM",
+                    )),
+                    Err(_) => Some(String::from(
+                        "This server is in privacy mode and I couldn't DM you, check you accept DMs from server members",
+                    )),
+                }
+            }
+            Ok(_) => Some(String::from(
+                "This server is in privacy mode, writers can only see their own summary",
+            )),
             Err(e) => Some(e),
         }
     } else {
@@ -58,10 +107,11 @@ async fn server_summary(ctx: &Context, msg: &Message, mut args: Args) -> Command
     Ok(())
 }
 
-fn parse_args(args: &mut Args) -> std::result::Result<(UserId), String> {
+fn parse_args(args: &mut Args) -> std::result::Result<Summary, String> {
     match args.len() {
+        1 if args.current() == Some(BY_AUTHOR_FLAG) => Ok(Summary::Leaderboard),
         1 => match args.single::<UserId>() {
-            Ok(user_id) => Ok(user_id),
+            Ok(user_id) => Ok(Summary::Writer(user_id)),
             Err(e) => Err(format!(
                 "Error with command arguments, try [help server-summary]\nError:{}",
                 e,
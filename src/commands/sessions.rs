@@ -0,0 +1,86 @@
+use crate::commands::help::{CommandHelp, Permission};
+use crate::state::{StoreData, StoryKey};
+use chrono::Duration;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+
+const DEFAULT_GAP_MINUTES: i64 = 30;
+
+async fn get_sessions(
+    ctx: &Context,
+    story_key: &StoryKey,
+    text_channel: &GuildChannel,
+    max_gap: Duration,
+) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    match (
+        store.get_server_data(&story_key.0),
+        store.get_channel_data(story_key),
+    ) {
+        (Some(server_data), Some(channel_data)) => server_data
+            .aliased(channel_data)
+            .make_sessions_string(text_channel, server_data, max_gap),
+        _ => String::from("Channel not initialised, use [init-channel] to add it"),
+    }
+}
+
+fn parse_gap(args: &mut Args) -> std::result::Result<Duration, String> {
+    if args.is_empty() {
+        Ok(Duration::minutes(DEFAULT_GAP_MINUTES))
+    } else {
+        match args.single::<i64>() {
+            Ok(minutes) if minutes > 0 => Ok(Duration::minutes(minutes)),
+            _ => Err(String::from(
+                "Gap should be a whole number of minutes, try [help sessions]",
+            )),
+        }
+    }
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        ("<#channel name>", "An initialised channel"),
+        (
+            "[gap in minutes]",
+            "How long without posting ends a session, 30 if not given",
+        ),
+    ],
+    permission: Permission::Anyone,
+};
+
+#[command("sessions")]
+#[usage("<#channel name> [gap in minutes]")]
+#[description("Show how many writing sessions each writer has had in an initialised channel, and how long they last. A session ends once a writer goes longer than the gap without posting, 30 minutes if not given")]
+#[example("#the-fall-of-rome")]
+#[example("#the-fall-of-rome 60")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn sessions(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        if let Ok(channel_id) = args.single::<ChannelId>() {
+            match (
+                parse_gap(&mut args),
+                channel_id.to_channel(&ctx).await?.guild(),
+            ) {
+                (Ok(max_gap), Some(text_channel)) => {
+                    get_sessions(ctx, &(server_id, channel_id), &text_channel, max_gap).await
+                }
+                (Err(e), _) => e,
+                (_, None) => String::from("Channel must be a server text channel"),
+            }
+        } else {
+            String::from("1 Arg expected: Channel")
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
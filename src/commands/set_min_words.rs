@@ -0,0 +1,82 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::state::StoreData;
+use crate::utils::transient::transient_reply;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+async fn store_min_words(
+    ctx: &Context,
+    server_id: &GuildId,
+    channel_id: &ChannelId,
+    min_words: usize,
+) {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    store
+        .get_server_data_mut_maybe_create(server_id)
+        .config
+        .channels
+        .entry(*channel_id)
+        .or_default()
+        .min_words = min_words;
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        ("<#channel name>", "An initialised channel"),
+        (
+            "<number of words>",
+            "Messages shorter than this aren't counted, 0 counts all of them",
+        ),
+    ],
+    permission: Permission::AllowedRoles,
+};
+
+#[command("set-min-words")]
+#[usage("<#channel name> <number of words>")]
+#[description("Leave messages with fewer words than this out of a channel's stats, e.g. \"lol\" or dice bot rolls. 0 counts every message, which is the default. Needs the same role as init-channel. Messages already counted stay counted, use [rebuild-stats] to recount the channel")]
+#[example("#the-fall-of-rome 3")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn set_min_words(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+            true => match (args.single::<ChannelId>(), args.single::<usize>()) {
+                (Ok(channel_id), Ok(min_words)) => {
+                    store_min_words(ctx, &server_id, &channel_id, min_words).await;
+                    record_admin_action(ctx, msg, "set-min-words").await;
+                    match min_words {
+                        0 => MessageBuilder::new()
+                            .push("Every message in ")
+                            .channel(channel_id)
+                            .push(" will now count towards stats")
+                            .build(),
+                        _ => MessageBuilder::new()
+                            .push("Messages in ")
+                            .channel(channel_id)
+                            .push(format!(
+                                " need at least {} words to count towards stats",
+                                min_words
+                            ))
+                            .build(),
+                    }
+                }
+                _ => String::from("2 Args expected: Channel, Number of words"),
+            },
+            false => not_in_allowed_roles_response(),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    transient_reply(ctx, msg, reply).await?;
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::state::StoreData;
+use crate::utils::transient::transient_reply;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+
+pub const MIN_PREFIX_LENGTH: usize = 2;
+pub const MAX_PREFIX_LENGTH: usize = 10;
+
+/// Every message on the server is checked against the prefix, so it has to be something nobody
+/// starts an ordinary post with. Mixing punctuation with a letter or digit rules out words as well
+/// as "..." or "--"
+pub fn validate_prefix(prefix: &str) -> std::result::Result<String, String> {
+    let length = prefix.chars().count();
+    let valid = !prefix.contains(char::is_whitespace)
+        && (MIN_PREFIX_LENGTH..=MAX_PREFIX_LENGTH).contains(&length)
+        && prefix.contains(|c: char| c.is_ascii_punctuation())
+        && prefix.contains(char::is_alphanumeric);
+    match valid {
+        true => Ok(prefix.to_string()),
+        false => Err(format!(
+            "Prefix must be a single word of {} to {} characters, with both punctuation and a letter or digit, like ?scriv",
+            MIN_PREFIX_LENGTH, MAX_PREFIX_LENGTH
+        )),
+    }
+}
+
+async fn store_prefix(ctx: &Context, server_id: &GuildId, prefix: Option<String>) {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    store
+        .get_server_data_mut_maybe_create(server_id)
+        .config
+        .prefix = prefix;
+}
+
+pub async fn get_prefix(ctx: &Context, server_id: &GuildId) -> Option<String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    // One saved before prefixes were checked is left unused rather than catching every message
+    store
+        .get_server_data(server_id)
+        .and_then(|server_data| server_data.config.prefix.clone())
+        .filter(|prefix| validate_prefix(prefix).is_ok())
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[(
+        "[prefix|reset]",
+        "The new prefix, or reset to only use the bot's usual one. Shows the current prefix if not given",
+    )],
+    permission: Permission::AllowedRolesToChange,
+};
+
+#[command("set-prefix")]
+#[usage("[prefix|reset]")]
+#[description("Show or set an extra prefix for commands on this server, for when the usual one clashes with another bot. The usual prefix and mentioning the bot keep working. Setting it needs the same role as init-channel")]
+#[example("")]
+#[example("?scriv")]
+#[example("reset")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn set_prefix(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        if args.is_empty() {
+            match get_prefix(ctx, &server_id).await {
+                Some(prefix) => format!("Commands on this server can also start with {}", prefix),
+                None => String::from("This server has no extra prefix set"),
+            }
+        } else {
+            match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+                true => match args.single::<String>().map(|prefix| match prefix.as_str() {
+                    "reset" => Ok(None),
+                    _ => validate_prefix(&prefix).map(Some),
+                }) {
+                    Ok(Ok(None)) => {
+                        store_prefix(ctx, &server_id, None).await;
+                        record_admin_action(ctx, msg, "set-prefix").await;
+                        String::from("Removed this server's extra prefix")
+                    }
+                    Ok(Ok(Some(prefix))) => {
+                        store_prefix(ctx, &server_id, Some(prefix.clone())).await;
+                        record_admin_action(ctx, msg, "set-prefix").await;
+                        format!("Commands on this server can now also start with {}", prefix)
+                    }
+                    Ok(Err(e)) => e,
+                    Err(_) => String::from("Expected a prefix, or reset"),
+                },
+                false => not_in_allowed_roles_response(),
+            }
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    transient_reply(ctx, msg, reply).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::commands::set_prefix::validate_prefix;
+
+    #[test]
+    fn prefixes_ordinary_posts_cant_start_with() {
+        assert_eq!(validate_prefix("?scriv"), Ok(String::from("?scriv")));
+        assert!(validate_prefix("s!").is_ok());
+        for prefix in [
+            "",
+            "a",
+            ".",
+            "...",
+            "--",
+            "scriv",
+            "two words",
+            "muchtoolong?prefix",
+        ] {
+            assert!(
+                validate_prefix(prefix).is_err(),
+                "{:?} was accepted",
+                prefix
+            );
+        }
+    }
+}
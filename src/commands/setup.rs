@@ -0,0 +1,440 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::check_perms::preflight_permissions;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{
+    author_is_in_allowed_roles, count_channel_history, not_in_allowed_roles_response,
+    ALLOWEDROLES_CHECK,
+};
+use crate::commands::set_prefix::validate_prefix;
+use crate::commands::word_cloud::wordcloud_is_enabled;
+use crate::commands::wordcloud_schedule::parse_schedule;
+use crate::guild_config::{GuildConfig, WordCloudPeriod};
+use crate::rate_limit::EXPENSIVE_CHECK;
+use crate::state::{ChannelData, StoreData, StoryKey};
+use crate::utils::confirmation::confirm_destructive_action;
+use chrono_tz::Tz;
+use serenity::framework::standard::{macros::command, Args, CommandResult, Delimiter};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+use std::time::Duration;
+
+const STEP_TIMEOUT: Duration = Duration::from_secs(2 * 60);
+const SKIP: &str = "skip";
+const CANCEL: &str = "cancel";
+
+/// What was chosen at each step, [None] where it was skipped
+#[derive(Debug, Default)]
+struct SetupChoices {
+    channels: Vec<GuildChannel>,
+    timezone: Option<Tz>,
+    prefix: Option<String>,
+    // Some(None) turns scheduled word clouds off for the chosen channels
+    wordcloud_schedule: Option<Option<WordCloudPeriod>>,
+}
+
+impl SetupChoices {
+    fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+            && self.timezone.is_none()
+            && self.prefix.is_none()
+            && self.wordcloud_schedule.is_none()
+    }
+
+    fn describe(&self) -> String {
+        let mut builder = MessageBuilder::new();
+        if !self.channels.is_empty() {
+            builder.push("Track ");
+            for (i, channel) in self.channels.iter().enumerate() {
+                if i > 0 {
+                    builder.push(", ");
+                }
+                builder.channel(channel);
+            }
+            builder.push_line("");
+        }
+        if let Some(timezone) = self.timezone {
+            builder.push_line(format!("Count days in {}", timezone));
+        }
+        if let Some(prefix) = &self.prefix {
+            builder.push_line(format!("Also accept the prefix {}", prefix));
+        }
+        match &self.wordcloud_schedule {
+            Some(Some(period)) => {
+                builder.push_line(format!(
+                    "Post word clouds in those channels {}",
+                    period.describe()
+                ));
+            }
+            Some(None) => {
+                builder.push_line("Don't post word clouds in those channels");
+            }
+            None => {}
+        }
+        builder.build()
+    }
+
+    /// Everything but the channels, which need counting first
+    fn apply(&self, config: &mut GuildConfig) {
+        if let Some(timezone) = self.timezone {
+            config.timezone = Some(timezone);
+        }
+        if let Some(prefix) = &self.prefix {
+            config.prefix = Some(prefix.clone());
+        }
+        if let Some(schedule) = self.wordcloud_schedule {
+            for channel in self.channels.iter() {
+                config
+                    .channels
+                    .entry(channel.id)
+                    .or_default()
+                    .wordcloud_schedule = schedule;
+            }
+        }
+    }
+}
+
+enum Step<T> {
+    Answered(T),
+    Skipped,
+    // Cancelled or timed out, the author has already been told
+    Stopped,
+}
+
+fn answer_args(answer: &str) -> Args {
+    Args::new(answer, &[Delimiter::Single(' ')])
+}
+
+fn parse_channel_ids(answer: &str) -> std::result::Result<Vec<ChannelId>, String> {
+    let mut channel_ids = vec![];
+    for channel_id in answer_args(answer).iter::<ChannelId>() {
+        match channel_id {
+            Ok(channel_id) if !channel_ids.contains(&channel_id) => channel_ids.push(channel_id),
+            Ok(_) => {}
+            Err(_) => return Err(String::from("Expected only channel mentions")),
+        }
+    }
+    match channel_ids.is_empty() {
+        true => Err(String::from("Expected at least one channel mention")),
+        false => Ok(channel_ids),
+    }
+}
+
+fn parse_timezone(answer: &str) -> std::result::Result<Tz, String> {
+    answer.parse::<Tz>().map_err(|_| {
+        String::from("Unknown timezone, expected a name like Europe/London or America/New_York")
+    })
+}
+
+/// Asks the author of [msg] [question] until they give an answer [parse] accepts, skip or cancel
+async fn ask<T, F: Fn(&str) -> std::result::Result<T, String>>(
+    ctx: &Context,
+    msg: &Message,
+    question: &str,
+    parse: F,
+) -> serenity::Result<Step<T>> {
+    msg.channel_id
+        .say(
+            ctx,
+            format!(
+                "{}\n_Answer {} to leave it as it is, or {} to stop_",
+                question, SKIP, CANCEL
+            ),
+        )
+        .await?;
+    loop {
+        let answer = msg
+            .channel_id
+            .await_reply(ctx)
+            .author_id(msg.author.id)
+            .timeout(STEP_TIMEOUT)
+            .await;
+        let answer = match answer {
+            Some(answer) => answer,
+            None => {
+                msg.reply(
+                    ctx,
+                    format!(
+                        "No answer within {}s, setup stopped and nothing was changed",
+                        STEP_TIMEOUT.as_secs()
+                    ),
+                )
+                .await?;
+                return Ok(Step::Stopped);
+            }
+        };
+        let content = answer.content.trim();
+        if content.eq_ignore_ascii_case(CANCEL) {
+            answer
+                .reply(ctx, "Setup cancelled, nothing was changed")
+                .await?;
+            return Ok(Step::Stopped);
+        } else if content.eq_ignore_ascii_case(SKIP) {
+            return Ok(Step::Skipped);
+        }
+        match parse(content) {
+            Ok(parsed) => return Ok(Step::Answered(parsed)),
+            Err(e) => {
+                answer
+                    .reply(ctx, format!("{}. Answer again, or {}", e, SKIP))
+                    .await?;
+            }
+        }
+    }
+}
+
+/// The channels that can be counted, and why any others can't
+async fn usable_channels(
+    ctx: &Context,
+    server_id: &GuildId,
+    channel_ids: Vec<ChannelId>,
+) -> (Vec<GuildChannel>, Vec<String>) {
+    let mut usable = vec![];
+    let mut problems = vec![];
+    for channel_id in channel_ids {
+        let channel = channel_id
+            .to_channel(ctx)
+            .await
+            .ok()
+            .and_then(|c| c.guild());
+        match channel {
+            Some(channel)
+                if channel.guild_id == *server_id && channel.kind == ChannelType::Text =>
+            {
+                match preflight_permissions(ctx, &channel).await {
+                    Ok(_) => usable.push(channel),
+                    Err(missing) => problems.push(missing),
+                }
+            }
+            _ => problems.push(
+                MessageBuilder::new()
+                    .channel(channel_id)
+                    .push(" isn't a text channel on this server")
+                    .build(),
+            ),
+        }
+    }
+    (usable, problems)
+}
+
+/// Walks through each step in turn, [None] if the author stopped part way
+async fn run_steps(
+    ctx: &Context,
+    msg: &Message,
+    server_id: &GuildId,
+) -> serenity::Result<Option<SetupChoices>> {
+    let mut choices = SetupChoices::default();
+    match ask(
+        ctx,
+        msg,
+        "**1.** Which channels are stories written in? Mention them all in one message, like #the-fall-of-rome #the-rise-of-rome",
+        parse_channel_ids,
+    )
+    .await?
+    {
+        Step::Answered(channel_ids) => {
+            let (usable, problems) = usable_channels(ctx, server_id, channel_ids).await;
+            if !problems.is_empty() {
+                msg.channel_id
+                    .say(ctx, format!("Leaving out:\n{}", problems.join("\n")))
+                    .await?;
+            }
+            choices.channels = usable;
+        }
+        Step::Skipped => {}
+        Step::Stopped => return Ok(None),
+    }
+    match ask(
+        ctx,
+        msg,
+        "**2.** Which timezone should days be counted in, and scheduled posts go out in? A name like Europe/London",
+        parse_timezone,
+    )
+    .await?
+    {
+        Step::Answered(timezone) => choices.timezone = Some(timezone),
+        Step::Skipped => {}
+        Step::Stopped => return Ok(None),
+    }
+    match ask(
+        ctx,
+        msg,
+        "**3.** Should commands also start with a prefix of this server's own? Give it, like ?scriv",
+        validate_prefix,
+    )
+    .await?
+    {
+        Step::Answered(prefix) => choices.prefix = Some(prefix),
+        Step::Skipped => {}
+        Step::Stopped => return Ok(None),
+    }
+    if !choices.channels.is_empty() && wordcloud_is_enabled(ctx).await {
+        match ask(
+            ctx,
+            msg,
+            "**4.** Should I post word clouds of those channels? daily, weekly <day> or off",
+            |answer| parse_schedule(&mut answer_args(answer)),
+        )
+        .await?
+        {
+            Step::Answered(schedule) => choices.wordcloud_schedule = Some(schedule),
+            Step::Skipped => {}
+            Step::Stopped => return Ok(None),
+        }
+    }
+    Ok(Some(choices))
+}
+
+/// Counts the chosen channels that aren't yet initialised, then saves them alongside the settings
+/// in one go so a failed setup leaves nothing half done
+async fn apply_choices(
+    ctx: &Context,
+    server_id: &GuildId,
+    choices: &SetupChoices,
+) -> std::result::Result<(), String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let new_channels: Vec<&GuildChannel> = {
+        let mut store = store_lock.write().unwrap();
+        let new_channels: Vec<&GuildChannel> = choices
+            .channels
+            .iter()
+            .filter(|channel| {
+                let story_key: StoryKey = (*server_id, channel.id);
+                !store.channel_data_exists(&story_key)
+                    && !store.initialising_channels.contains(&story_key)
+            })
+            .collect();
+        for channel in new_channels.iter() {
+            store.initialising_channels.insert((*server_id, channel.id));
+        }
+        new_channels
+    };
+    let story_keys: Vec<StoryKey> = new_channels
+        .iter()
+        .map(|channel| (*server_id, channel.id))
+        .collect();
+    let mut counted: Vec<(StoryKey, ChannelData)> = vec![];
+    let mut failed = None;
+    for channel in new_channels {
+        match count_channel_history(channel, ctx, choices.timezone).await {
+            Ok(channel_data) => counted.push(((*server_id, channel.id), channel_data)),
+            Err(e) => {
+                failed = Some(e);
+                break;
+            }
+        }
+    }
+    let mut store = store_lock.write().unwrap();
+    for story_key in story_keys.iter() {
+        store.initialising_channels.remove(story_key);
+    }
+    if let Some(e) = failed {
+        return Err(e);
+    }
+    choices.apply(&mut store.get_server_data_mut_maybe_create(server_id).config);
+    for (story_key, channel_data) in counted {
+        store.insert_channel_data_maybe_create_server_data(&story_key, channel_data);
+    }
+    Ok(())
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[],
+    permission: Permission::AllowedRoles,
+};
+
+#[command("setup")]
+#[usage("")]
+#[description("Get set up step by step: I'll ask which channels to track, the server's timezone, an extra prefix and whether to post word clouds, then show what I'll do before doing it. Answer each question in this channel, skip any you don't need. Nothing is changed until the end")]
+#[example("")]
+#[only_in("guilds")] // Reminder: guild = server
+#[checks(AllowedRoles, Expensive)]
+async fn setup(ctx: &Context, msg: &Message) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+            true => {
+                let choices = match run_steps(ctx, msg, &server_id).await? {
+                    Some(choices) => choices,
+                    None => return Ok(()),
+                };
+                if choices.is_empty() {
+                    String::from("Every step was skipped, nothing was changed")
+                } else {
+                    let action = format!("make these changes:\n{}\n", choices.describe());
+                    if !confirm_destructive_action(ctx, msg, &action).await? {
+                        return Ok(());
+                    }
+                    if !choices.channels.is_empty() {
+                        msg.channel_id
+                            .say(ctx, "Reading back through the channels, bear with me...")
+                            .await?;
+                    }
+                    match apply_choices(ctx, &server_id, &choices).await {
+                        Ok(()) => {
+                            record_admin_action(ctx, msg, "setup").await;
+                            format!(
+                                "All set up:\n{}\nSee how a story's going with [show-stats #channel]",
+                                choices.describe()
+                            )
+                        }
+                        Err(e) => format!("Nothing was changed, {}", e),
+                    }
+                }
+            }
+            false => not_in_allowed_roles_response(),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::commands::set_prefix::validate_prefix;
+    use crate::commands::setup::{parse_channel_ids, SetupChoices};
+    use crate::guild_config::{GuildConfig, WordCloudPeriod};
+    use chrono_tz::Tz;
+    use serenity::model::prelude::*;
+
+    #[test]
+    fn parse_answers() {
+        assert_eq!(
+            parse_channel_ids("<#1> <#2> <#1>"),
+            Ok(vec![ChannelId(1), ChannelId(2)])
+        );
+        assert!(parse_channel_ids("<#1> rome").is_err());
+        assert!(parse_channel_ids("").is_err());
+        assert_eq!(validate_prefix("?scriv"), Ok(String::from("?scriv")));
+        assert!(validate_prefix("two words").is_err());
+        assert!(validate_prefix("muchtoolongaprefix").is_err());
+        // An answer that's only an attachment
+        assert!(validate_prefix("").is_err());
+    }
+
+    #[test]
+    fn apply_leaves_skipped_settings() {
+        let mut config = GuildConfig {
+            prefix: Some(String::from("?scriv")),
+            ..GuildConfig::default()
+        };
+        let choices = SetupChoices {
+            timezone: Some(Tz::Europe__London),
+            wordcloud_schedule: Some(Some(WordCloudPeriod::Daily)),
+            ..SetupChoices::default()
+        };
+        choices.apply(&mut config);
+        assert_eq!(config.timezone, Some(Tz::Europe__London));
+        assert_eq!(config.prefix, Some(String::from("?scriv")));
+        // No channels were chosen to schedule word clouds in
+        assert!(config.channels.is_empty());
+        assert!(SetupChoices::default().is_empty());
+    }
+}
@@ -0,0 +1,215 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::guild_config::{weekday_name, ShoutoutConfig};
+use crate::scheduler::DEFAULT_SHOUTOUT_MESSAGE;
+use crate::state::{ServerData, StoreData};
+use chrono::Weekday;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::{parse_role, MessageBuilder};
+
+const MAX_SHOUTOUT_MESSAGE_LENGTH: usize = 300;
+
+/// [role_name] is the shoutout role's, if it has one that still exists
+fn describe_shoutout(server_data: &ServerData, role_name: Option<&str>) -> String {
+    let config = match &server_data.config.shoutout {
+        Some(config) => config,
+        None => return String::from("Weekly shoutouts are off on this server"),
+    };
+    let mut builder = MessageBuilder::new();
+    builder
+        .push("The week's top writer gets a shoutout in ")
+        .channel(config.channel_id)
+        .push(format!(" every {}", weekday_name(&config.weekday)));
+    if let Some(role_name) = role_name {
+        // Named rather than mentioned, so showing the setting doesn't ping everyone with the role
+        builder.push(" and the role ").push_bold_safe(role_name);
+    }
+    builder.push_line("").push_line(format!(
+        "Message: {}",
+        config
+            .message
+            .as_deref()
+            .unwrap_or(DEFAULT_SHOUTOUT_MESSAGE)
+    ));
+    if let Some(record) = server_data.shoutouts.last() {
+        match record.winners.is_empty() {
+            true => builder.push_line(format!("Nobody wrote the week before {}", record.posted_on)),
+            false => builder.push_line(format!(
+                "Last top writer: {}, with {} words the week before {}",
+                record
+                    .winners
+                    .iter()
+                    .map(|winner| server_data.shown_name_by_id(winner))
+                    .collect::<Vec<String>>()
+                    .join(" and "),
+                record.words,
+                record.posted_on
+            )),
+        };
+    }
+    if server_data.config.privacy_mode {
+        builder.push_line("Privacy mode is on, so no shoutouts are posted until it's turned off");
+    }
+    builder.build()
+}
+
+fn parse_shoutout(args: &mut Args) -> std::result::Result<Option<ShoutoutConfig>, String> {
+    if args.current() == Some("off") {
+        return Ok(None);
+    }
+    let channel_id = args
+        .single::<ChannelId>()
+        .map_err(|_| String::from("Invalid arguments, try [help shoutout]"))?;
+    let weekday = args
+        .single::<Weekday>()
+        .map_err(|_| String::from("Expected the day of the week to post on, like monday"))?;
+    let role = match args.current().and_then(parse_role) {
+        Some(role_id) => {
+            args.advance();
+            Some(RoleId(role_id))
+        }
+        None => None,
+    };
+    let message = match args.rest().trim() {
+        "" => None,
+        message if !message.contains("{writer}") => {
+            return Err(String::from(
+                "The message should say where the writer goes with {writer}",
+            ))
+        }
+        message if message.chars().count() > MAX_SHOUTOUT_MESSAGE_LENGTH => {
+            return Err(format!(
+                "The message can be at most {} characters",
+                MAX_SHOUTOUT_MESSAGE_LENGTH
+            ))
+        }
+        message => Some(message.to_string()),
+    };
+    Ok(Some(ShoutoutConfig {
+        channel_id,
+        weekday,
+        message,
+        role,
+    }))
+}
+
+/// Sets the shoutout if [config] is given, and describes it either way
+async fn update_shoutout(
+    ctx: &Context,
+    server_id: &GuildId,
+    config: Option<Option<ShoutoutConfig>>,
+) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let role_id = {
+        let mut store = store_lock.write().unwrap();
+        if let Some(config) = config {
+            store
+                .get_server_data_mut_maybe_create(server_id)
+                .config
+                .shoutout = config;
+        }
+        store
+            .get_server_data(server_id)
+            .and_then(|server_data| server_data.config.shoutout.as_ref())
+            .and_then(|config| config.role)
+    };
+    let role_name = match role_id {
+        Some(role_id) => ctx
+            .cache
+            .role(*server_id, role_id)
+            .await
+            .map(|role| role.name),
+        None => None,
+    };
+    let store = store_lock.read().unwrap();
+    match store.get_server_data(server_id) {
+        Some(server_data) => describe_shoutout(server_data, role_name.as_deref()),
+        None => String::from("Weekly shoutouts are off on this server"),
+    }
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        (
+            "<#channel name> <day>",
+            "Where and on which day of the week to post, shows the setting if nothing's given",
+        ),
+        (
+            "[@role]",
+            "Given to the week's top writer and taken from the last, I need Manage Roles and to be above it",
+        ),
+        (
+            "[message]",
+            "Your own wording, {writer} and {words} are filled in",
+        ),
+        ("off", "Stop posting shoutouts"),
+    ],
+    permission: Permission::AllowedRolesToChange,
+};
+
+#[command("shoutout")]
+#[usage("[<#channel name> <day> [@role] [message] | off]")]
+#[description("Post a weekly shoutout to whoever wrote the most words across the server's initialised channels over the past 7 days, optionally passing a Writer of the Week role on to them. Ties share the shoutout, and a writer on a run of weeks is cheered on. Days follow the server's timezone, posts wait for quiet hours and none are made in privacy mode. Setting it needs the same role as init-channel")]
+#[example("")]
+#[example("#announcements monday")]
+#[example("#announcements monday @Writer of the Week")]
+#[example("#announcements friday Hats off to {writer}, {words} words this week!")]
+#[example("off")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn shoutout(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        if args.is_empty() {
+            update_shoutout(ctx, &server_id, None).await
+        } else {
+            match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+                true => match parse_shoutout(&mut args) {
+                    Ok(config) => {
+                        let response = update_shoutout(ctx, &server_id, Some(config)).await;
+                        record_admin_action(ctx, msg, "shoutout").await;
+                        response
+                    }
+                    Err(e) => e,
+                },
+                false => not_in_allowed_roles_response(),
+            }
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::commands::shoutout::parse_shoutout;
+    use chrono::Weekday;
+    use serenity::framework::standard::{Args, Delimiter};
+    use serenity::model::prelude::*;
+
+    #[test]
+    fn parse_settings() {
+        let parse = |s: &str| parse_shoutout(&mut Args::new(s, &[Delimiter::Single(' ')]));
+        assert_eq!(parse("off"), Ok(None));
+        let config = parse("<#5> monday <@&9> Hats off to {writer}!")
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.channel_id, ChannelId(5));
+        assert_eq!(config.weekday, Weekday::Mon);
+        assert_eq!(config.role, Some(RoleId(9)));
+        assert_eq!(config.message.as_deref(), Some("Hats off to {writer}!"));
+        let config = parse("<#5> friday").unwrap().unwrap();
+        assert_eq!((config.role, config.message), (None, None));
+        assert!(parse("<#5> friday Well done").is_err());
+        assert!(parse("<#5> someday").is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::commands::help::{CommandHelp, Permission};
 use crate::state::StoreData;
 use crate::utils::trait_extensions::MessageBuilderExt;
 use log::error;
@@ -7,7 +8,7 @@ use serenity::prelude::*;
 use serenity::utils::MessageBuilder;
 
 async fn get_channels(server_id: &GuildId, ctx: &Context) -> String {
-    let channel_ids = {
+    let (channel_ids, inaccessible_channels) = {
         let store_lock = {
             let data_read = ctx.data.read().await;
             data_read
@@ -16,7 +17,14 @@ async fn get_channels(server_id: &GuildId, ctx: &Context) -> String {
                 .clone()
         };
         let store = store_lock.read().unwrap();
-        store.get_all_channels_in_server(server_id)
+        let inaccessible_channels = store
+            .get_server_data(server_id)
+            .map(|server_data| server_data.inaccessible_channels.clone())
+            .unwrap_or_default();
+        (
+            store.get_all_channels_in_server(server_id),
+            inaccessible_channels,
+        )
     };
     let mut builder = MessageBuilder::new();
     if channel_ids.len() == 0 {
@@ -27,12 +35,21 @@ async fn get_channels(server_id: &GuildId, ctx: &Context) -> String {
     } else {
         let mut builder = builder.push_bold_line("Channels being watched:");
         for channel_id in channel_ids {
-            builder = builder.channel(channel_id).newline();
+            builder = builder.channel(channel_id);
+            if inaccessible_channels.contains(&channel_id) {
+                builder = builder.push(" (inaccessible, I can't read it any more)");
+            }
+            builder = builder.newline();
         }
         builder.build()
     }
 }
 
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[],
+    permission: Permission::Anyone,
+};
+
 #[command("show-channels")]
 async fn show_channels(ctx: &Context, msg: &Message, mut _args: Args) -> CommandResult {
     let reply = if let Some(server_id) = msg.guild_id {
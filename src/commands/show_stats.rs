@@ -1,5 +1,8 @@
-use crate::state::{StoreData, StoryKey};
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::recap::opening;
+use crate::state::{ServerData, StatsDisplayOptions, Store, StoreData, StoryKey};
 use crate::stats::WordStats;
+use crate::utils::discord::DiscordApi;
 use crate::utils::iterators::SortedHashMap;
 use crate::utils::trait_extensions::MessageBuilderExt;
 use chrono::{DateTime, NaiveDateTime, Utc};
@@ -11,59 +14,347 @@ use serenity::utils::MessageBuilder;
 use std::hash::Hash;
 use std::{cmp, collections::HashMap};
 
-async fn get_stats(channel_id: ChannelId, ctx: &Context, truncate_limit: Option<usize>) -> String {
-    let text_channel = channel_id.to_channel(&ctx).await.unwrap().guild().unwrap();
-    let story_key: StoryKey = (text_channel.guild_id, channel_id);
-    let store_lock = {
-        let data_read = ctx.data.read().await;
-        data_read
-            .get::<StoreData>()
-            .expect("Expected StoryData in TypeMap.")
-            .clone()
+pub fn stats_response(
+    store: &Store,
+    text_channel: &GuildChannel,
+    options: &StatsDisplayOptions,
+) -> String {
+    let story_key: StoryKey = (text_channel.guild_id, text_channel.id);
+    let server_data = store.get_server_data(&story_key.0);
+    let (channel_data, heading) = match (options.season, server_data) {
+        (Some(number), Some(server_data)) if store.channel_data_exists(&story_key) => {
+            match server_data.season(&story_key.1, number) {
+                Some(season) => (
+                    Some(&season.stats),
+                    format!(
+                        "Season {}, archived {} UTC\n",
+                        number,
+                        season.ended.format("%Y-%m-%d")
+                    ),
+                ),
+                None => {
+                    return MessageBuilder::new()
+                        .push(format!("No season {} in ", number))
+                        .channel(text_channel)
+                        .push(format!(
+                            ", it has {} archived season(s)",
+                            server_data.season_count(&story_key.1)
+                        ))
+                        .build()
+                }
+            }
+        }
+        _ => (store.get_channel_data(&story_key), String::new()),
     };
-    let store = store_lock.read().unwrap();
-    match store.get_channel_data(&story_key) {
-        Some(channel_data) => channel_data.make_stats_string(&text_channel, truncate_limit),
-        None => format!("Channel not initialised, use [init-channel] to add it"),
+    match (server_data, channel_data) {
+        (Some(server_data), Some(channel_data)) if options.by_character => with_counting_rules(
+            heading + &channel_data.make_character_stats_string(text_channel, server_data, options),
+            server_data,
+        ),
+        (Some(server_data), Some(channel_data)) => with_counting_rules(
+            heading
+                + &server_data.aliased(channel_data).make_stats_string(
+                    text_channel,
+                    server_data,
+                    options,
+                ),
+            server_data,
+        ),
+        _ => match store.get_all_channels_in_server(&story_key.0).as_slice() {
+            [] => String::from(
+                "Channel not initialised, use [init-channel] to add it. No channels here are initialised yet",
+            ),
+            initialised => format!(
+                "Channel not initialised, use [init-channel] to add it. Initialised here: {}",
+                mention_channels(initialised)
+            ),
+        },
+    }
+}
+
+/// So counts that differ from a word processor's, or another server's, can be explained
+fn with_counting_rules(stats: String, server_data: &ServerData) -> String {
+    format!(
+        "{}\nCounting rules: {}",
+        stats.trim_end(),
+        server_data.config.word_rules.name()
+    )
+}
+
+/// Who wrote the shown stats' longest post, and its words. Left out where the stats don't name
+/// writers
+fn longest_post(
+    store: &Store,
+    story_key: &StoryKey,
+    options: &StatsDisplayOptions,
+) -> Option<(String, MessageId, usize)> {
+    let server_data = store.get_server_data(&story_key.0)?;
+    if options.by_character || server_data.config.privacy_mode {
+        return None;
+    }
+    let channel_data = match options.season {
+        Some(number) => &server_data.season(&story_key.1, number)?.stats,
+        None => store.get_channel_data(story_key)?,
+    };
+    server_data
+        .aliased(channel_data)
+        .longest_message()
+        .map(|(author, message_id, words)| (server_data.shown_name(author), message_id, words))
+}
+
+/// [excerpt] is [None] if the post couldn't be fetched, it may have been deleted
+fn make_longest_post_string(
+    story_key: &StoryKey,
+    (name, message_id, words): &(String, MessageId, usize),
+    excerpt: Option<&str>,
+) -> String {
+    let mut builder = MessageBuilder::new();
+    builder
+        .push_bold("Longest post: ")
+        .push(format!("{} words by ", words))
+        .push_bold_safe(name)
+        .push_line(format!(
+            " <https://discord.com/channels/{}/{}/{}>",
+            story_key.0, story_key.1, message_id
+        ));
+    if let Some(excerpt) = excerpt.filter(|excerpt| !excerpt.is_empty()) {
+        builder.push_quote_line_safe(excerpt);
+    }
+    builder.build()
+}
+
+const SEASON_FLAG: &str = "--season";
+
+// Keeps a suggestion list readable on servers with many initialised channels
+const MAX_SUGGESTIONS: usize = 10;
+
+fn mention_channels(channel_ids: &[ChannelId]) -> String {
+    let mut mentions: Vec<String> = channel_ids
+        .iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|channel_id| channel_id.mention().to_string())
+        .collect();
+    if channel_ids.len() > MAX_SUGGESTIONS {
+        mentions.push(format!("and {} more", channel_ids.len() - MAX_SUGGESTIONS));
     }
+    mentions.join(", ")
+}
+
+/// Initialised channels whose name contains [query], an exact name match wins outright
+fn matching_channels(initialised: &[(ChannelId, String)], query: &str) -> Vec<ChannelId> {
+    let query = query.trim_start_matches('#').to_lowercase();
+    if let Some((channel_id, _)) = initialised.iter().find(|(_, name)| *name == query) {
+        return vec![*channel_id];
+    }
+    let mut matches: Vec<&(ChannelId, String)> = initialised
+        .iter()
+        .filter(|(_, name)| name.contains(&query))
+        .collect();
+    matches.sort_by(|a, b| a.1.cmp(&b.1));
+    matches
+        .into_iter()
+        .map(|(channel_id, _)| *channel_id)
+        .collect()
+}
+
+/// Resolves part of a channel name to one of the server's initialised channels, or explains why
+/// it couldn't
+async fn find_initialised_channel(
+    ctx: &Context,
+    server_id: &GuildId,
+    query: &str,
+) -> Result<ChannelId, String> {
+    let initialised_ids = {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let store = store_lock.read().unwrap();
+        store.get_all_channels_in_server(server_id)
+    };
+    let channels = server_id
+        .channels(ctx)
+        .await
+        .map_err(|_| String::from("Couldn't look up this server's channels, try mentioning one"))?;
+    let initialised: Vec<(ChannelId, String)> = initialised_ids
+        .iter()
+        .filter_map(|channel_id| {
+            channels
+                .get(channel_id)
+                .map(|channel| (*channel_id, channel.name.to_lowercase()))
+        })
+        .collect();
+    match matching_channels(&initialised, query).as_slice() {
+        [channel_id] => Ok(*channel_id),
+        [] if initialised.is_empty() => Err(String::from(
+            "No channels here are initialised yet, use [init-channel] to add one",
+        )),
+        [] => Err(format!(
+            "No initialised channel matches that, initialised here: {}",
+            mention_channels(&initialised_ids)
+        )),
+        several => Err(format!("Did you mean: {}", mention_channels(several))),
+    }
+}
+
+/// The stats for [text_channel] with its longest post quoted, [None] if the bot can't see it
+async fn get_stats(
+    discord: &dyn DiscordApi,
+    store_lock: &std::sync::RwLock<Store>,
+    text_channel: Option<&GuildChannel>,
+    options: &StatsDisplayOptions,
+) -> String {
+    let text_channel = match text_channel {
+        Some(text_channel) => text_channel,
+        None => {
+            return String::from(
+                "Can't see that channel, it may have been deleted or I can't read it any more",
+            )
+        }
+    };
+    let story_key: StoryKey = (text_channel.guild_id, text_channel.id);
+    let (response, longest) = {
+        let store = store_lock.read().unwrap();
+        (
+            stats_response(&store, text_channel, options),
+            longest_post(&store, &story_key, options),
+        )
+    };
+    match longest {
+        Some(longest) => {
+            let excerpt = discord
+                .message(text_channel.id, longest.1)
+                .await
+                .ok()
+                .map(|message| opening(&message.content));
+            format!(
+                "{}\n{}",
+                response.trim_end(),
+                make_longest_post_string(&story_key, &longest, excerpt.as_deref())
+            )
+        }
+        None => response,
+    }
+}
+
+/// Everything show-stats does once it has found the channel, apart from [Context] so it can be
+/// run against a mock discord
+async fn send_stats(
+    discord: &dyn DiscordApi,
+    store_lock: &std::sync::RwLock<Store>,
+    text_channel: Option<&GuildChannel>,
+    options: &StatsDisplayOptions,
+    reply_channel: ChannelId,
+) -> serenity::Result<()> {
+    let response = get_stats(discord, store_lock, text_channel, options).await;
+    discord.say(reply_channel, response).await
 }
 
-fn get_truncate_limit(args: &mut Args) -> Option<usize> {
+fn get_display_options(args: &mut Args) -> Result<StatsDisplayOptions, String> {
     // TODO: This default should be somewhere central, pluck it out of Context when needed?
-    let default = Some(5);
-    if args.len() > 0 {
-        match args.single::<String>() {
-            Ok(s) => {
-                if &s == "-full" {
-                    None
-                } else {
-                    default
+    let mut options = StatsDisplayOptions {
+        truncate_limit: Some(5),
+        ..StatsDisplayOptions::default()
+    };
+    let mut args = args.iter::<String>().flatten();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-full" => options.truncate_limit = None,
+            "-fold-departed" => options.fold_departed = true,
+            "-by-character" => options.by_character = true,
+            SEASON_FLAG => match args.next().and_then(|number| number.parse::<usize>().ok()) {
+                Some(number) if number > 0 => options.season = Some(number),
+                _ => {
+                    return Err(String::from(
+                        "Expected a season number after --season, starting from 1",
+                    ))
                 }
-            }
-            Err(_) => default,
+            },
+            _ => (),
         }
-    } else {
-        default
     }
+    Ok(options)
 }
 
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        (
+            "<#channel name>",
+            "An initialised channel, or part of its name",
+        ),
+        ("-full", "Show every writer, rather than just the top ones"),
+        (
+            "-fold-departed",
+            "Count writers who have left the server as one",
+        ),
+        (
+            "-by-character",
+            "Show registered characters instead of writers",
+        ),
+        (
+            "--season <number>",
+            "Show a season archived by [reset-stats], counting from 1",
+        ),
+    ],
+    permission: Permission::Anyone,
+};
+
 #[command("show-stats")]
-#[usage("<#channel name> [-full]")]
-#[description("Display stats for an initialised channel by name. Returns an error if channel hasn't been initialised. If there are lots of users the results will be truncated, provide -full to show all")]
+#[usage("<#channel name> [-full] [-fold-departed] [-by-character] [--season <number>]")]
+#[description("Display stats for an initialised channel by name. Part of the name works too, e.g. rome for #the-fall-of-rome, matching only initialised channels and suggesting them if it isn't clear which. Returns an error listing the initialised channels if the channel hasn't been initialised. If there are lots of users the results will be truncated, provide -full to show all. Writers who have left the server are marked (departed), provide -fold-departed to show them all as one entry. Provide -by-character to show the characters registered with [character] instead. Provide --season and a number to show a season archived by [reset-stats]. The longest post is quoted with a link to it, unless the server is in privacy mode, and the server's [word-rules] are named at the end")]
 #[example("#the-fall-of-rome")]
+#[example("#the-fall-of-rome -full -fold-departed")]
+#[example("#the-fall-of-rome -by-character")]
+#[example("#the-fall-of-rome --season 1")]
+#[example("rome")]
 #[only_in("guilds")] // Reminder: guild = server
 async fn show_stats(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-    let reply = if let Some(_server_id) = msg.guild_id {
-        if let Ok(channel_id) = args.single::<ChannelId>() {
-            let truncate_limit = get_truncate_limit(&mut args);
-            let response = get_stats(channel_id, ctx, truncate_limit).await;
-            //send it
-            if let Err(why) = msg.channel_id.say(&ctx.http, &response).await {
-                println!("Error sending message: {:?}", why);
-            }
-            None
-        } else {
-            Some(String::from("1 Arg expected: Channel"))
+    let reply = if let Some(server_id) = msg.guild_id {
+        let channel_id = match args.single::<ChannelId>() {
+            Ok(channel_id) => Ok(channel_id),
+            Err(_) => match args.current().filter(|arg| !arg.starts_with('-')) {
+                Some(query) => {
+                    let query = query.to_string();
+                    args.advance();
+                    find_initialised_channel(ctx, &server_id, &query).await
+                }
+                None => Err(String::from("1 Arg expected: Channel")),
+            },
+        };
+        match channel_id {
+            Ok(channel_id) => match get_display_options(&mut args) {
+                Ok(options) => {
+                    let text_channel = channel_id
+                        .to_channel(&ctx)
+                        .await
+                        .ok()
+                        .and_then(|c| c.guild());
+                    let store_lock = {
+                        let data_read = ctx.data.read().await;
+                        data_read
+                            .get::<StoreData>()
+                            .expect("Expected StoryData in TypeMap.")
+                            .clone()
+                    };
+                    let sent = send_stats(
+                        &ctx.http,
+                        &store_lock,
+                        text_channel.as_ref(),
+                        &options,
+                        msg.channel_id,
+                    )
+                    .await;
+                    if let Err(why) = sent {
+                        println!("Error sending message: {:?}", why);
+                    }
+                    None
+                }
+                Err(e) => Some(e),
+            },
+            Err(e) => Some(e),
         }
     } else {
         Some(String::from(
@@ -71,7 +362,213 @@ async fn show_stats(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
         ))
     };
     if let Some(simple_response) = reply {
-        msg.reply(ctx, simple_response).await?;
+        ctx.http.reply(msg, simple_response).await?;
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod testing {
+    use crate::bench_corpus::synthetic_messages;
+    use crate::commands::init_channel::backfill_channel_data;
+    use crate::commands::show_stats::{
+        get_display_options, longest_post, make_longest_post_string, matching_channels, send_stats,
+        stats_response,
+    };
+    use crate::guild_config::GuildConfig;
+    use crate::language_parsing::WordRules;
+    use crate::state::{ChannelData, StatsDisplayOptions, Store};
+    use crate::utils::discord::mock::MockDiscord;
+    use chrono::Utc;
+    use chrono_tz::Tz;
+    use serenity::framework::standard::{Args, Delimiter};
+    use serenity::model::channel::GuildChannel;
+    use serenity::model::id::ChannelId;
+    use std::sync::RwLock;
+
+    fn text_channel(last_message: &serenity::model::channel::Message) -> GuildChannel {
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "bitrate": null,
+            "parent_id": null,
+            "guild_id": "1",
+            "type": 0,
+            "last_message_id": last_message.id.to_string(),
+            "last_pin_timestamp": null,
+            "name": "the-fall-of-rome",
+            "permission_overwrites": [],
+            "position": 0,
+            "topic": null,
+            "user_limit": null,
+        }))
+        .unwrap()
+    }
+
+    /// init-channel backfilling, then a new message arriving, then show-stats
+    #[tokio::test]
+    async fn init_process_show() {
+        let mut messages = synthetic_messages(250, 3);
+        let new_message = messages.pop().unwrap();
+        let text_channel = text_channel(messages.last().unwrap());
+        let discord = MockDiscord::with_history(messages.clone());
+        let channel_data =
+            backfill_channel_data(&discord, &text_channel, &GuildConfig::default(), None, None)
+                .await
+                .unwrap();
+        let backfilled_words = channel_data.general_stats.word_count;
+        let expected_words: usize = messages
+            .iter()
+            .map(|message| crate::stats::count_words(&message.content, &WordRules::default()))
+            .sum();
+        assert_eq!(backfilled_words, expected_words);
+
+        let mut store = Store::default();
+        let story_key = (text_channel.guild_id, text_channel.id);
+        store.insert_channel_data_maybe_create_server_data(&story_key, channel_data);
+        store.process_message(&story_key, &new_message);
+
+        let store_lock = RwLock::new(store);
+        let mut args = Args::new("-full", &[Delimiter::Single(' ')]);
+        let options = get_display_options(&mut args).unwrap();
+        let reply_channel = ChannelId(9);
+        send_stats(
+            &discord,
+            &store_lock,
+            Some(&text_channel),
+            &options,
+            reply_channel,
+        )
+        .await
+        .unwrap();
+        let sent = discord.sent.lock().unwrap().clone();
+        assert_eq!(sent.len(), 1);
+        let (sent_to, sent) = &sent[0];
+        assert_eq!(*sent_to, reply_channel);
+        let total_words =
+            expected_words + crate::stats::count_words(&new_message.content, &WordRules::default());
+        assert!(sent.contains(&format!("Word count: {}", total_words)));
+        for writer in ["**writer1**", "**writer2**", "**writer3**"] {
+            assert!(sent.contains(writer));
+        }
+        assert!(sent.contains("Counting rules: standard"));
+        // Quoted from the history, as fetched through discord
+        assert!(sent.contains("**Longest post: **"));
+        assert!(sent.contains("<https://discord.com/channels/1/1/"));
+        assert!(sent.contains("\n> "));
+
+        send_stats(&discord, &store_lock, None, &options, reply_channel)
+            .await
+            .unwrap();
+        assert!(discord
+            .last_sent()
+            .unwrap()
+            .starts_with("Can't see that channel"));
+    }
+
+    /// Losing access part way through is an error rather than a panic, or stats with a gap
+    #[tokio::test]
+    async fn unreadable_history() {
+        let messages = synthetic_messages(5, 1);
+        let text_channel = text_channel(messages.last().unwrap());
+        let discord = MockDiscord::with_history(vec![]);
+        let backfilled =
+            backfill_channel_data(&discord, &text_channel, &GuildConfig::default(), None, None)
+                .await;
+        assert!(backfilled
+            .unwrap_err()
+            .starts_with("couldn't read the history of the-fall-of-rome"));
+    }
+
+    /// A reset archives the season, and a rebuild only recounts the new season's messages
+    #[tokio::test]
+    async fn seasons() {
+        let messages = synthetic_messages(40, 3);
+        let (old_season, new_season) = messages.split_at(30);
+        let text_channel = text_channel(messages.last().unwrap());
+        let story_key = (text_channel.guild_id, text_channel.id);
+        let mut store = Store::default();
+        let mut channel_data = ChannelData::default();
+        for message in old_season {
+            channel_data.update(message, &Tz::UTC, &WordRules::default());
+        }
+        let old_words = channel_data.general_stats.word_count;
+        store.insert_channel_data_maybe_create_server_data(&story_key, channel_data);
+        store
+            .get_server_data_mut(&story_key.0)
+            .unwrap()
+            .archive_season(&story_key.1, Utc::now());
+
+        let discord = MockDiscord::with_history(messages.clone());
+        let rebuilt = backfill_channel_data(
+            &discord,
+            &text_channel,
+            &GuildConfig::default(),
+            None,
+            Some(old_season.last().unwrap().id),
+        )
+        .await
+        .unwrap();
+        let new_words: usize = new_season
+            .iter()
+            .map(|message| crate::stats::count_words(&message.content, &WordRules::default()))
+            .sum();
+        assert_eq!(rebuilt.general_stats.word_count, new_words);
+
+        let season_options = |season| StatsDisplayOptions {
+            season: Some(season),
+            ..StatsDisplayOptions::default()
+        };
+        let archived = stats_response(&store, &text_channel, &season_options(1));
+        assert!(archived.starts_with("Season 1, archived "));
+        assert!(archived.contains(&format!("Word count: {}", old_words)));
+        let missing = stats_response(&store, &text_channel, &season_options(2));
+        assert!(missing.contains("it has 1 archived season(s)"));
+    }
+
+    #[test]
+    fn longest_post_quoted() {
+        let messages = synthetic_messages(40, 3);
+        let story_key = (serenity::model::id::GuildId(1), ChannelId(1));
+        let mut channel_data = ChannelData::default();
+        for message in messages.iter() {
+            channel_data.update(message, &Tz::UTC, &WordRules::default());
+        }
+        let mut store = Store::default();
+        store.insert_channel_data_maybe_create_server_data(&story_key, channel_data);
+        let longest_words = messages
+            .iter()
+            .map(|message| crate::stats::count_words(&message.content, &WordRules::default()))
+            .max()
+            .unwrap();
+        let longest = longest_post(&store, &story_key, &StatsDisplayOptions::default()).unwrap();
+        assert_eq!(longest.2, longest_words);
+        let shown = make_longest_post_string(&story_key, &longest, Some("It begins."));
+        assert!(shown.contains(&format!("{} words by", longest_words)));
+        assert!(shown.contains(&format!("<https://discord.com/channels/1/1/{}>", longest.1)));
+        assert!(shown.contains("> It begins."));
+
+        store
+            .get_server_data_mut(&story_key.0)
+            .unwrap()
+            .config
+            .privacy_mode = true;
+        assert!(longest_post(&store, &story_key, &StatsDisplayOptions::default()).is_none());
+    }
+
+    #[test]
+    fn channel_name_matching() {
+        let initialised = vec![
+            (ChannelId(1), String::from("the-fall-of-rome")),
+            (ChannelId(2), String::from("rome-ooc")),
+            (ChannelId(3), String::from("rome")),
+        ];
+        assert_eq!(matching_channels(&initialised, "#ROME"), vec![ChannelId(3)]);
+        assert_eq!(matching_channels(&initialised, "fall"), vec![ChannelId(1)]);
+        assert_eq!(matching_channels(&initialised, "rome-"), vec![ChannelId(2)]);
+        assert_eq!(
+            matching_channels(&initialised, "ro"),
+            vec![ChannelId(3), ChannelId(2), ChannelId(1)]
+        );
+        assert!(matching_channels(&initialised, "carthage").is_empty());
+    }
+}
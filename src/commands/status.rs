@@ -0,0 +1,69 @@
+use crate::intents::message_content_available;
+use crate::state::StoreData;
+use crate::ADMINONLY_CHECK;
+use serenity::framework::standard::{macros::command, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use sysinfo::{get_current_pid, ProcessExt, System, SystemExt};
+
+// Just the biggest, to keep the message under discord's length limit
+const MAX_SERVERS: usize = 10;
+
+fn process_memory_kb() -> Option<u64> {
+    let pid = get_current_pid().ok()?;
+    let mut system = System::new();
+    system.refresh_process(pid);
+    system.get_process(pid).map(|process| process.memory())
+}
+
+#[command("status")]
+#[description(
+    "Reports the bot's memory use, and roughly how much of it each server's stats take up"
+)]
+#[checks("AdminOnly")]
+async fn status(ctx: &Context, msg: &Message) -> CommandResult {
+    let (mut server_sizes, max_words) = {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let store = store_lock.read().unwrap();
+        let server_sizes: Vec<(GuildId, usize)> = store
+            .servers()
+            .map(|(server_id, server_data)| (*server_id, server_data.approx_memory_bytes()))
+            .collect();
+        (server_sizes, store.max_words_per_channel)
+    };
+    server_sizes.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+    let mut lines = vec![];
+    match process_memory_kb() {
+        Some(memory_kb) => lines.push(format!("Process memory: {} MB", memory_kb / 1024)),
+        None => lines.push(String::from("Process memory: unknown")),
+    }
+    if !message_content_available(ctx).await {
+        lines.push(String::from(
+            "Message content is unavailable, messages are not being counted",
+        ));
+    }
+    match max_words {
+        Some(max_words) => lines.push(format!("Words tracked per channel capped at {}", max_words)),
+        None => lines.push(String::from("Words tracked per channel are not capped")),
+    }
+    lines.push(format!(
+        "Stats across {} servers take roughly {} KB:",
+        server_sizes.len(),
+        server_sizes.iter().map(|(_, size)| size).sum::<usize>() / 1024
+    ));
+    for (server_id, size) in server_sizes.iter().take(MAX_SERVERS) {
+        let server_name = server_id
+            .name(ctx)
+            .await
+            .unwrap_or_else(|| server_id.to_string());
+        lines.push(format!("{}: {} KB", server_name, size / 1024));
+    }
+    msg.reply(ctx, lines.join("\n")).await?;
+    Ok(())
+}
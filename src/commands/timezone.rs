@@ -0,0 +1,81 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::state::StoreData;
+use crate::utils::transient::transient_reply;
+use chrono_tz::Tz;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+
+async fn set_timezone(ctx: &Context, server_id: &GuildId, timezone: Tz) {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    store
+        .get_server_data_mut_maybe_create(server_id)
+        .config
+        .timezone = Some(timezone);
+}
+
+async fn get_timezone(ctx: &Context, server_id: &GuildId) -> Tz {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    store
+        .get_server_data(server_id)
+        .map_or(Tz::UTC, |server_data| server_data.config.timezone())
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[(
+        "[timezone name]",
+        "A name like Europe/London, shows the current timezone if not given",
+    )],
+    permission: Permission::AllowedRolesToChange,
+};
+
+#[command("timezone")]
+#[usage("[timezone name]")]
+#[description("Show or set the timezone this server's daily stats are counted in, as a name like Europe/London. Setting it needs the same role as init-channel. Messages already counted keep the day they were given, use [rebuild-stats] to recount a channel")]
+#[example("")]
+#[example("America/New_York")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn timezone(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        if args.is_empty() {
+            format!(
+                "Daily stats on this server are counted in {}",
+                get_timezone(ctx, &server_id).await
+            )
+        } else {
+            match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+                true => match args.single::<String>().map(|s| s.parse::<Tz>()) {
+                    Ok(Ok(timezone)) => {
+                        set_timezone(ctx, &server_id, timezone).await;
+                        record_admin_action(ctx, msg, "timezone").await;
+                        format!("Daily stats will now be counted in {}", timezone)
+                    }
+                    _ => String::from(
+                        "Unknown timezone, expected a name like Europe/London or America/New_York",
+                    ),
+                },
+                false => not_in_allowed_roles_response(),
+            }
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    transient_reply(ctx, msg, reply).await?;
+    Ok(())
+}
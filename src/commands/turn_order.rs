@@ -0,0 +1,282 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::guild_config::TurnOrder;
+use crate::scheduler::send_automated_post;
+use crate::state::{ServerData, StoreData};
+use crate::utils::trait_extensions::MessageBuilderExt;
+use log::error;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+// Long enough for any table, short enough that a typo'd mention list is noticed
+const MAX_WRITERS: usize = 25;
+
+/// Moves the turn on in the message's channel, pinging the next writer if the channel wants that
+pub async fn track_turn(ctx: &Context, server_id: &GuildId, message: &Message) {
+    let to_ping = {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let mut store = store_lock.write().unwrap();
+        store
+            .get_server_data_mut(server_id)
+            .and_then(|server_data| {
+                let ping_next = server_data
+                    .config
+                    .channel(&message.channel_id)
+                    .turn_order
+                    .is_some_and(|turn_order| turn_order.ping_next);
+                server_data
+                    .take_turn(&message.channel_id, message)
+                    .filter(|_| ping_next)
+            })
+    };
+    if let Some(next) = to_ping {
+        let content = MessageBuilder::new()
+            .mention(&next)
+            .push(", you're up next")
+            .build();
+        if let Err(why) = send_automated_post(ctx, server_id, message.channel_id, content).await {
+            error!(
+                "Failed to ping the next writer in {}: {:?}",
+                message.channel_id, why
+            );
+        }
+    }
+}
+
+fn describe_turn_order(server_data: &ServerData, channel_id: &ChannelId) -> String {
+    let mut builder = MessageBuilder::new();
+    let turn_order = match server_data.config.channel(channel_id).turn_order {
+        Some(turn_order) => turn_order,
+        None => {
+            return builder
+                .push("There's no turn order in ")
+                .channel(channel_id)
+                .push(", set one with [turn-order]")
+                .build()
+        }
+    };
+    let next = server_data.whose_turn(channel_id);
+    if let Some(next) = next {
+        builder
+            .push_bold_safe(server_data.shown_name_by_id(&next))
+            .push(" is up next in ")
+            .channel(channel_id)
+            .newline();
+    }
+    let writers: Vec<String> = turn_order
+        .writers
+        .iter()
+        .map(|writer| server_data.shown_name_by_id(writer))
+        .collect();
+    builder.push_safe(format!("Order: {}", writers.join(" -> ")));
+    if turn_order.ping_next {
+        builder.push(", pinging each writer when it's their turn");
+    }
+    builder.build()
+}
+
+/// [change] is given the channel's turn order and the position of whoever is up next
+async fn update_turn_order<F: FnOnce(&mut Option<TurnOrder>, &mut usize)>(
+    ctx: &Context,
+    server_id: &GuildId,
+    channel_id: &ChannelId,
+    change: F,
+) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let server_data = store.get_server_data_mut_maybe_create(server_id);
+    let mut turn = server_data.turns.get(channel_id).copied().unwrap_or(0);
+    let channel_config = server_data.config.channels.entry(*channel_id).or_default();
+    change(&mut channel_config.turn_order, &mut turn);
+    match channel_config.turn_order.is_some() {
+        true => server_data.turns.insert(*channel_id, turn),
+        false => server_data.turns.remove(channel_id),
+    };
+    describe_turn_order(server_data, channel_id)
+}
+
+async fn get_turn_order(ctx: &Context, server_id: &GuildId, channel_id: &ChannelId) -> String {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    match store.get_server_data(server_id) {
+        Some(server_data) => describe_turn_order(server_data, channel_id),
+        None => describe_turn_order(&ServerData::new(), channel_id),
+    }
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        ("<#channel name>", "The channel to keep the turn order for"),
+        (
+            "[@writer...|skip|off]",
+            "The writers in the order they post, skip to pass the current writer's turn, or off to stop tracking turns. Shows the order if not given",
+        ),
+        ("[-ping]", "Ping each writer when it's their turn"),
+    ],
+    permission: Permission::AllowedRolesToChange,
+};
+
+#[command("turn-order")]
+#[usage("<#channel name> [@writer...|skip|off] [-ping]")]
+#[description("Keep track of whose turn it is in a channel where writers post in a fixed rotation. The turn passes to the next writer once the current one posts, and whoever posts out of turn passes it on from themselves. Setting it needs the same role as init-channel")]
+#[example("#the-fall-of-rome @Caligula @Nero @Claudius -ping")]
+#[example("#the-fall-of-rome skip")]
+#[example("#the-fall-of-rome off")]
+#[example("#the-fall-of-rome")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn turn_order(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match args.single::<ChannelId>() {
+            Ok(channel_id) if args.is_empty() => get_turn_order(ctx, &server_id, &channel_id).await,
+            Ok(channel_id) => {
+                match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+                    true => {
+                        let rest: Vec<String> = args.iter::<String>().flatten().collect();
+                        let ping_next = rest.iter().any(|arg| arg == "-ping");
+                        let writers: Vec<UserId> = rest
+                            .iter()
+                            .filter_map(|arg| arg.parse::<UserId>().ok())
+                            .fold(vec![], |mut writers, writer| {
+                                if !writers.contains(&writer) {
+                                    writers.push(writer);
+                                }
+                                writers
+                            });
+                        match rest.first().map(|arg| arg.as_str()) {
+                            Some("off") => {
+                                let reply = update_turn_order(
+                                    ctx,
+                                    &server_id,
+                                    &channel_id,
+                                    |turn_order, _| *turn_order = None,
+                                )
+                                .await;
+                                record_admin_action(ctx, msg, "turn-order").await;
+                                reply
+                            }
+                            Some("skip") => {
+                                let reply = update_turn_order(
+                                    ctx,
+                                    &server_id,
+                                    &channel_id,
+                                    |turn_order, turn| {
+                                        if let Some(turn_order) = turn_order {
+                                            *turn = (*turn + 1) % turn_order.writers.len();
+                                        }
+                                    },
+                                )
+                                .await;
+                                record_admin_action(ctx, msg, "turn-order").await;
+                                reply
+                            }
+                            _ if writers.len() > MAX_WRITERS => {
+                                format!("A turn order can have at most {} writers", MAX_WRITERS)
+                            }
+                            _ if writers.len() < 2 => {
+                                String::from("Mention at least two writers, in the order they post")
+                            }
+                            _ => {
+                                let reply = update_turn_order(
+                                    ctx,
+                                    &server_id,
+                                    &channel_id,
+                                    |turn_order, turn| {
+                                        *turn_order = Some(TurnOrder { writers, ping_next });
+                                        *turn = 0;
+                                    },
+                                )
+                                .await;
+                                record_admin_action(ctx, msg, "turn-order").await;
+                                reply
+                            }
+                        }
+                    }
+                    false => not_in_allowed_roles_response(),
+                }
+            }
+            Err(_) => String::from("Invalid arguments, try [help turn-order]"),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+pub const WHOS_NEXT_HELP: CommandHelp = CommandHelp {
+    args: &[(
+        "[#channel name]",
+        "A channel with a turn order, this one if not given",
+    )],
+    permission: Permission::Anyone,
+};
+
+#[command("whos-next")]
+#[usage("[#channel name]")]
+#[description("Show whose turn it is in a channel with a turn order, see [turn-order]")]
+#[example("")]
+#[example("#the-fall-of-rome")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn whos_next(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match args.single::<ChannelId>() {
+            Ok(channel_id) => get_turn_order(ctx, &server_id, &channel_id).await,
+            Err(_) if args.is_empty() => get_turn_order(ctx, &server_id, &msg.channel_id).await,
+            Err(_) => String::from("Invalid arguments, try [help whos-next]"),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::commands::turn_order::describe_turn_order;
+    use crate::guild_config::TurnOrder;
+    use crate::state::ServerData;
+    use serenity::model::prelude::*;
+
+    #[test]
+    fn private_turn_order() {
+        let mut server_data = ServerData::new();
+        server_data.display_names.update(&UserId(1), "Caligula");
+        server_data.display_names.update(&UserId(2), "Nero");
+        server_data
+            .config
+            .channels
+            .entry(ChannelId(5))
+            .or_default()
+            .turn_order = Some(TurnOrder {
+            writers: vec![UserId(1), UserId(2)],
+            ping_next: false,
+        });
+        assert!(describe_turn_order(&server_data, &ChannelId(5)).contains("Caligula -> Nero"));
+        server_data.config.privacy_mode = true;
+        let described = describe_turn_order(&server_data, &ChannelId(5));
+        assert!(described.contains("Writer A -> Writer B"));
+        assert!(!described.contains("Caligula"));
+    }
+}
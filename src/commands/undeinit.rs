@@ -0,0 +1,117 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::state::{ServerData, StoreData};
+use chrono::{DateTime, Utc};
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+fn describe_trash(server_data: &ServerData, now: &DateTime<Utc>) -> String {
+    if server_data.trash.is_empty() {
+        return String::from("There are no deinitialised channels in the trash");
+    }
+    let mut trashed = server_data.trash.iter().collect::<Vec<_>>();
+    trashed.sort_by_key(|(_, trashed)| trashed.trashed);
+    let mut builder = MessageBuilder::new();
+    builder.push_line("Deinitialised channels in the trash:");
+    for (channel_id, trashed) in trashed {
+        builder.push("- ").channel(channel_id).push_line(format!(
+            ", deinitialised {}, deleted for good in {} days",
+            trashed.trashed.format("%Y-%m-%d"),
+            (trashed.expires() - *now).num_days().max(0)
+        ));
+    }
+    builder.build()
+}
+
+async fn list_or_restore(
+    ctx: &Context,
+    server_id: &GuildId,
+    channel_id: Option<ChannelId>,
+) -> std::result::Result<String, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    let server_data = store.get_server_data_mut_maybe_create(server_id);
+    match channel_id {
+        None => Ok(describe_trash(server_data, &Utc::now())),
+        Some(channel_id) => server_data.restore_channel(&channel_id).map(|()| {
+            MessageBuilder::new()
+                .push("Restored the stats for ")
+                .channel(channel_id)
+                .push(", messages posted while it was deinitialised aren't counted, [rebuild-stats] counts them")
+                .build()
+        }),
+    }
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[(
+        "[#channel name]",
+        "A deinitialised channel to restore, lists the trash if not given",
+    )],
+    permission: Permission::AllowedRoles,
+};
+
+#[command("undeinit")]
+#[usage("[#channel name]")]
+#[description("Restore the stats of a channel that was deinitialised or deleted in the last 30 days, archived seasons included, and start watching it again. Given no channel, lists what's in the trash and when each is deleted for good")]
+#[example("")]
+#[example("#the-fall-of-rome")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn undeinit(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+            true => {
+                let channel_id = match args.is_empty() {
+                    true => Ok(None),
+                    false => args.single::<ChannelId>().map(Some),
+                };
+                match channel_id {
+                    Ok(channel_id) => match list_or_restore(ctx, &server_id, channel_id).await {
+                        Ok(response) => {
+                            if channel_id.is_some() {
+                                record_admin_action(ctx, msg, "undeinit").await;
+                            }
+                            response
+                        }
+                        Err(e) => e,
+                    },
+                    Err(_) => String::from("Invalid arguments, try [help undeinit]"),
+                }
+            }
+            false => not_in_allowed_roles_response(),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    msg.reply(ctx, reply).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::commands::undeinit::describe_trash;
+    use crate::state::{ChannelData, ServerData};
+    use chrono::{Duration, Utc};
+    use serenity::model::prelude::*;
+
+    #[test]
+    fn lists_trash_with_expiry() {
+        let now = Utc::now();
+        let mut server_data = ServerData::new();
+        assert!(describe_trash(&server_data, &now).contains("no deinitialised channels"));
+        server_data.insert(&ChannelId(7), ChannelData::default());
+        server_data.trash_channel(&ChannelId(7), now - Duration::days(10));
+        let described = describe_trash(&server_data, &now);
+        assert!(described.contains("<#7>"));
+        assert!(described.contains("in 20 days"));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::state::StoreData;
+use crate::ADMINONLY_CHECK;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+
+async fn make_usage_report(ctx: &Context, server_ids: Vec<GuildId>) -> Vec<String> {
+    let mut server_names = vec![];
+    for server_id in server_ids.iter() {
+        let server_name = server_id
+            .name(ctx)
+            .await
+            .unwrap_or_else(|| server_id.to_string());
+        server_names.push(server_name);
+    }
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    server_ids
+        .iter()
+        .zip(server_names.iter())
+        .filter_map(|(server_id, server_name)| {
+            store
+                .get_server_data(server_id)
+                .map(|server_data| server_data.make_usage_report_string(server_name))
+        })
+        .collect()
+}
+
+#[command("usage-report")]
+#[usage("[all]")]
+#[description("Reports how often each command has been used on this server, and how often it failed. Give [all] to report on every server")]
+#[example("all")]
+#[checks("AdminOnly")]
+async fn usage_report(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let server_ids = match (args.single::<String>().ok().as_deref(), msg.guild_id) {
+        (Some("all"), _) => {
+            let store_lock = {
+                let data_read = ctx.data.read().await;
+                data_read
+                    .get::<StoreData>()
+                    .expect("Expected StoryData in TypeMap.")
+                    .clone()
+            };
+            let store = store_lock.read().unwrap();
+            store.get_unique_server_ids()
+        }
+        (None, Some(server_id)) => vec![server_id],
+        (None, None) => {
+            msg.reply(ctx, "Not in a server, use [all] to report on every server")
+                .await?;
+            return Ok(());
+        }
+        (Some(_), _) => {
+            msg.reply(ctx, "Invalid arguments, try [help usage-report]")
+                .await?;
+            return Ok(());
+        }
+    };
+    let reports = make_usage_report(ctx, server_ids).await;
+    if reports.is_empty() {
+        msg.reply(ctx, "No command usage recorded").await?;
+    }
+    for report in reports {
+        msg.channel_id.say(&ctx.http, report).await?;
+    }
+    Ok(())
+}
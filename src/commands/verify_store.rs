@@ -0,0 +1,14 @@
+use crate::integrity::verify_store as check_store;
+use crate::ADMINONLY_CHECK;
+use serenity::framework::standard::{macros::command, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+
+#[command("verify-store")]
+#[description("Runs the nightly integrity check now: checks every server's stats for impossible values, repairs what it can and reports what it found")]
+#[checks("AdminOnly")]
+async fn verify_store(ctx: &Context, msg: &Message) -> CommandResult {
+    let (report, _) = check_store(ctx).await;
+    msg.reply(ctx, report).await?;
+    Ok(())
+}
@@ -1,7 +1,17 @@
+use crate::commands::help::{CommandHelp, Permission};
 use crate::config::GeneralAppConfigData;
-use crate::state::{StoreData, StoryKey};
+use crate::guild_config::WordCloudPeriod;
+use crate::rate_limit::EXPENSIVE_CHECK;
+use crate::scheduler::{take_due_wordclouds, ScheduledWordCloud, TICK};
+use crate::state::{ServerData, StoreData, StoryKey};
+use crate::stats::{distinctive_frequencies, WordStats};
+use crate::utils::discord::{fetch_messages_since, message_id_at};
+use crate::utils::render_options::{extract_render_options, RenderOptions};
 use crate::utils::trait_extensions::MessageBuilderExt;
-use log::error;
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use log::{error, info};
+use serde::Serialize;
 use serenity::framework::standard::{macros::command, Args, CommandResult};
 use serenity::http::AttachmentType;
 use serenity::model::prelude::*;
@@ -14,12 +24,16 @@ use std::iter::FromIterator;
 use std::ops::Add;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Duration;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 use tokio::io::ErrorKind;
 use uuid::Uuid;
 
+// Same cap as a recap, a scheduled wordcloud of a very busy channel covers the latest of these
+const MAX_SCHEDULED_WORDCLOUD_MESSAGES: usize = 2000;
+
 #[derive(Debug, EnumIter)]
 enum MaskName {
     Bunny,
@@ -54,6 +68,94 @@ impl FromStr for MaskName {
     }
 }
 
+const COMPARE_FLAG: &str = "--compare";
+// Higher than a scheduled wordcloud's, as a month of a busy channel is a lot of messages
+const MAX_COMPARE_MESSAGES: usize = 5000;
+
+/// A stretch of the server's calendar a comparison wordcloud can cover
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter)]
+enum ComparePeriod {
+    ThisWeek,
+    LastWeek,
+    ThisMonth,
+    LastMonth,
+}
+impl ComparePeriod {
+    fn to_str(self) -> &'static str {
+        match self {
+            Self::ThisWeek => "thisweek",
+            Self::LastWeek => "lastweek",
+            Self::ThisMonth => "thismonth",
+            Self::LastMonth => "lastmonth",
+        }
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            Self::ThisWeek => "this week",
+            Self::LastWeek => "last week",
+            Self::ThisMonth => "this month",
+            Self::LastMonth => "last month",
+        }
+    }
+
+    /// Start and end of the period, in days of [timezone]. Weeks start on Monday, and the current
+    /// week or month ends [now]
+    fn range(&self, now: &DateTime<Utc>, timezone: &Tz) -> (DateTime<Utc>, DateTime<Utc>) {
+        let today = now.with_timezone(timezone).date().naive_local();
+        let week_start =
+            today - chrono::Duration::days(today.weekday().num_days_from_monday() as i64);
+        let month_start = NaiveDate::from_ymd(today.year(), today.month(), 1);
+        let start_of = |date: NaiveDate| -> DateTime<Utc> {
+            timezone
+                .from_local_datetime(&date.and_hms(0, 0, 0))
+                .earliest()
+                .map_or_else(
+                    || Utc.from_utc_datetime(&date.and_hms(0, 0, 0)),
+                    |start| start.with_timezone(&Utc),
+                )
+        };
+        match self {
+            Self::ThisWeek => (start_of(week_start), *now),
+            Self::LastWeek => (
+                start_of(week_start - chrono::Duration::days(7)),
+                start_of(week_start),
+            ),
+            Self::ThisMonth => (start_of(month_start), *now),
+            Self::LastMonth => {
+                let last_month = month_start.pred();
+                let last_month_start =
+                    NaiveDate::from_ymd(last_month.year(), last_month.month(), 1);
+                (start_of(last_month_start), start_of(month_start))
+            }
+        }
+    }
+}
+impl FromStr for ComparePeriod {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        ComparePeriod::iter()
+            .find(|period| period.to_str() == s)
+            .ok_or_else(|| format!("Invalid period {}", s))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Comparison {
+    Authors(UserId, UserId),
+    Periods(ComparePeriod, ComparePeriod),
+}
+
+/// Body of the request file picked up by the python worker
+#[derive(Serialize)]
+struct WordCloudRequest<'a> {
+    frequencies: &'a HashMap<String, usize>,
+    width: Option<u32>,
+    height: Option<u32>,
+    format: &'static str,
+}
+
 fn error_help_text(error: &impl Display) -> String {
     format!("ERROR: Invalid Arguments: {}", error)
 }
@@ -100,7 +202,48 @@ fn parse_args(
     }
 }
 
-async fn wordcloud_is_enabled(ctx: &Context) -> bool {
+fn parse_compare_args(
+    args: &mut Args,
+) -> std::result::Result<(ChannelId, Comparison, Option<MaskName>), String> {
+    let channel_id = args
+        .single::<ChannelId>()
+        .map_err(|e| error_help_text(&e))?;
+    if args.single::<String>().ok().as_deref() != Some(COMPARE_FLAG) {
+        return Err(error_help_text(&format!(
+            "Expected {} after the channel",
+            COMPARE_FLAG
+        )));
+    }
+    let comparison = match (args.single::<String>(), args.single::<String>()) {
+        (Ok(left), Ok(right)) => match (left.parse::<UserId>(), right.parse::<UserId>()) {
+            (Ok(left), Ok(right)) => Comparison::Authors(left, right),
+            _ => match (
+                left.parse::<ComparePeriod>(),
+                right.parse::<ComparePeriod>(),
+            ) {
+                (Ok(left), Ok(right)) => Comparison::Periods(left, right),
+                _ => return Err(error_help_text(&String::from(
+                    "Compare two user mentions, or two of thisweek|lastweek|thismonth|lastmonth",
+                ))),
+            },
+        },
+        _ => return Err(error_help_text(&String::from("Comparing needs two sides"))),
+    };
+    let mask_name = match args.single::<String>() {
+        Ok(mask_name) => Some(
+            mask_name
+                .parse::<MaskName>()
+                .map_err(|e| error_help_text(&e))?,
+        ),
+        Err(_) => None,
+    };
+    match args.is_empty() {
+        true => Ok((channel_id, comparison, mask_name)),
+        false => Err(String::from("Invalid number of args")),
+    }
+}
+
+pub async fn wordcloud_is_enabled(ctx: &Context) -> bool {
     let config_lock = {
         let data_read = ctx.data.read().await;
         data_read
@@ -112,44 +255,77 @@ async fn wordcloud_is_enabled(ctx: &Context) -> bool {
     is_enabled
 }
 
-fn unicode_emoji(s: &str) -> ReactionType {
-    ReactionType::Unicode(String::from(s))
-}
-async fn react_or_reply(msg: &Message, ctx: &Context) {
-    match msg.react(ctx, unicode_emoji("🤖")).await {
-        Ok(_) => {
-            let _ = msg.react(ctx, unicode_emoji("⌚")).await.unwrap();
-        }
-        Err(_) => {
-            let _ = msg.reply(ctx, "Making wordcloud...").await.unwrap();
-        }
-    }
-}
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        ("<#channel name>", "An initialised channel"),
+        ("[<@user mention>]", "Just this writer's words, everyone's if not given"),
+        ("[mask]", "A shape for the wordcloud, one of bunny, d20, shield, wolf or horse"),
+        (
+            "--compare",
+            "Instead compare two @user mentions, or two of thisweek, lastweek, thismonth and lastmonth",
+        ),
+    ],
+    permission: Permission::Anyone,
+};
+
 #[command("gen-wordcloud")]
-#[usage("<#channel name> [<@user mention>] [mask]")]
+#[usage("<#channel name> [<@user mention>] [mask] | <#channel name> --compare <a> <b> [mask]")]
 #[description(
-    "Generate a wordcloud from the given channel's general stats. If a user is given (via @mention) the wordcloud if for just that user's stats. Available masks: bunny|d20|shield|wolf|horse"
+    "Generate a wordcloud from the given channel's general stats. If a user is given (via @mention) the wordcloud if for just that user's stats. Image size and format can be set with --size WIDTHxHEIGHT and --format png|webp|svg (with a mask, the mask is scaled to fit the size). With --compare two @users or two periods (thisweek|lastweek|thismonth|lastmonth), you get a pair of wordclouds of the words each side uses more than the other. Available masks: bunny|d20|shield|wolf|horse"
 )]
 #[example("#war-and-peace")]
 #[example("#the-fall-of-rome @Caligula wolf")]
 #[example("#the-fall-of-rome wolf")]
+#[example("#the-fall-of-rome --size 1920x1080 --format webp")]
+#[example("#the-fall-of-rome --compare @Caligula @Nero")]
+#[example("#the-fall-of-rome --compare lastmonth thismonth")]
 #[bucket("global-wordcloud-bucket")]
+#[checks(Expensive)]
 #[only_in("guilds")] // Reminder: guild = server
-async fn gen_wordcloud(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+async fn gen_wordcloud(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    if args.raw().any(|arg| arg == COMPARE_FLAG) && wordcloud_is_enabled(ctx).await {
+        return compare_wordclouds(ctx, msg, &args).await;
+    }
     let reply = if wordcloud_is_enabled(ctx).await {
-        match parse_args(&mut args) {
-            Ok((channel_id, user_id, mask_name)) => {
+        let parsed_args =
+            extract_render_options(&args).and_then(|(mut remaining_args, options)| {
+                parse_args(&mut remaining_args).map(|parsed| (parsed, options))
+            });
+        match parsed_args {
+            Ok(((channel_id, user_id, mask_name), render_options)) => {
                 if let Some(server_id) = msg.guild_id {
-                    react_or_reply(msg, ctx).await;
+                    if let Some(refusal) =
+                        privacy_refusal(ctx, &server_id, &msg.author.id, user_id.as_slice()).await
+                    {
+                        msg.reply(ctx, refusal).await?;
+                        return Ok(());
+                    }
+                    // Keep hold of this reply so we can edit it with the outcome, rather than
+                    // leaving the user staring at a "making" message forever
+                    let mut pending_reply = msg.reply(ctx, "Making wordcloud...").await?;
                     let story_key = (server_id, channel_id);
-                    request_and_fetch_wordcloud(
+                    let failure = request_and_fetch_wordcloud(
                         &story_key,
                         ctx,
                         &msg.channel_id,
                         &user_id,
                         &mask_name,
+                        &render_options,
                     )
-                    .await
+                    .await;
+                    match failure {
+                        Some(failure_reason) => {
+                            pending_reply
+                                .edit(ctx, |m| m.content(failure_reason))
+                                .await?
+                        }
+                        None => {
+                            if let Err(why) = pending_reply.delete(ctx).await {
+                                error!("Failed deleting pending wordcloud reply: {:?}", why);
+                            }
+                        }
+                    }
+                    None
                 } else {
                     Some(String::from(
                         "BUG: message had no server id, bot only supports server text channels",
@@ -167,12 +343,50 @@ async fn gen_wordcloud(ctx: &Context, msg: &Message, mut args: Args) -> CommandR
     Ok(())
 }
 
+/// In privacy mode writers only get word clouds of their own words, as with [export-words]
+fn private_words_refusal(
+    server_data: &ServerData,
+    author: &UserId,
+    users: &[UserId],
+) -> Option<String> {
+    let own_account = server_data.config.main_account(author);
+    let others_words = users
+        .iter()
+        .any(|user| server_data.config.main_account(user) != own_account);
+    match server_data.config.privacy_mode && others_words {
+        true => Some(String::from(
+            "This server is in privacy mode, writers can only make word clouds of their own words",
+        )),
+        false => None,
+    }
+}
+
+async fn privacy_refusal(
+    ctx: &Context,
+    server_id: &GuildId,
+    author: &UserId,
+    users: &[UserId],
+) -> Option<String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    store
+        .get_server_data(server_id)
+        .and_then(|server_data| private_words_refusal(server_data, author, users))
+}
+
 async fn request_and_fetch_wordcloud(
     story_key: &StoryKey,
     ctx: &Context,
     send_to_channel: &ChannelId,
     user: &Option<UserId>,
     mask_name: &Option<MaskName>,
+    render_options: &RenderOptions,
 ) -> Option<String> {
     //Look up a specific user's frequencies in WordStats, dump to specific file, watch for response from the worker
     let response_content = MessageBuilder::new()
@@ -180,7 +394,120 @@ async fn request_and_fetch_wordcloud(
         .channel(story_key.1)
         .apply_if(user.is_some(), |b| b.push(" for user ").user(user.unwrap()))
         .build();
-    let users_stats = {
+    match stored_word_frequencies(ctx, story_key, user).await {
+        Ok(word_freqs) => {
+            render_and_send_wordclouds(
+                ctx,
+                std::slice::from_ref(&word_freqs),
+                send_to_channel,
+                response_content,
+                mask_name,
+                render_options,
+            )
+            .await
+        }
+        Err(e) => Some(e),
+    }
+}
+
+async fn compare_wordclouds(ctx: &Context, msg: &Message, args: &Args) -> CommandResult {
+    let server_id = match msg.guild_id {
+        Some(server_id) => server_id,
+        None => {
+            msg.reply(
+                ctx,
+                "BUG: message had no server id, bot only supports server text channels",
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let parsed_args = extract_render_options(args).and_then(|(mut remaining_args, options)| {
+        parse_compare_args(&mut remaining_args).map(|parsed| (parsed, options))
+    });
+    let ((channel_id, comparison, mask_name), render_options) = match parsed_args {
+        Ok(parsed_args) => parsed_args,
+        Err(parse_command_error) => {
+            msg.reply(ctx, parse_command_error).await?;
+            return Ok(());
+        }
+    };
+    if let Comparison::Authors(left_user, right_user) = comparison {
+        if let Some(refusal) =
+            privacy_refusal(ctx, &server_id, &msg.author.id, &[left_user, right_user]).await
+        {
+            msg.reply(ctx, refusal).await?;
+            return Ok(());
+        }
+    }
+    let mut pending_reply = msg.reply(ctx, "Making wordclouds...").await?;
+    let story_key = (server_id, channel_id);
+    let failure = match comparison_frequencies(ctx, &story_key, &comparison).await {
+        Ok((left, right)) => {
+            let (left, right) = distinctive_frequencies(&left, &right);
+            if left.is_empty() || right.is_empty() {
+                Some(String::from(
+                    "Not enough distinct words on both sides to compare",
+                ))
+            } else {
+                let mut builder = MessageBuilder::new();
+                builder.push("Words distinctive to ");
+                match comparison {
+                    Comparison::Authors(left_user, right_user) => builder
+                        .user(left_user)
+                        .push(" (first) and ")
+                        .user(right_user),
+                    Comparison::Periods(left_period, right_period) => builder
+                        .push(left_period.describe())
+                        .push(" (first) and ")
+                        .push(right_period.describe()),
+                };
+                let response_content = builder.push(" (second) in ").channel(channel_id).build();
+                render_and_send_wordclouds(
+                    ctx,
+                    &[left, right],
+                    &msg.channel_id,
+                    response_content,
+                    &mask_name,
+                    &render_options,
+                )
+                .await
+            }
+        }
+        Err(e) => Some(e),
+    };
+    match failure {
+        Some(failure_reason) => {
+            pending_reply
+                .edit(ctx, |m| m.content(failure_reason))
+                .await?
+        }
+        None => {
+            if let Err(why) = pending_reply.delete(ctx).await {
+                error!("Failed deleting pending wordcloud reply: {:?}", why);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Both sides' word frequencies, from the channel's stats for authors or read back from the
+/// channel for periods
+async fn comparison_frequencies(
+    ctx: &Context,
+    story_key: &StoryKey,
+    comparison: &Comparison,
+) -> std::result::Result<(HashMap<String, usize>, HashMap<String, usize>), String> {
+    let (left_period, right_period) = match comparison {
+        Comparison::Authors(left, right) => {
+            return Ok((
+                stored_word_frequencies(ctx, story_key, &Some(*left)).await?,
+                stored_word_frequencies(ctx, story_key, &Some(*right)).await?,
+            ))
+        }
+        Comparison::Periods(left, right) => (left, right),
+    };
+    let (channel_config, timezone, rules) = {
         let store_lock = {
             let data_read = ctx.data.read().await;
             data_read
@@ -189,72 +516,303 @@ async fn request_and_fetch_wordcloud(
                 .clone()
         };
         let store = store_lock.read().unwrap();
-        if let Some(story_data) = store.get_channel_data(story_key) {
-            let mut res: Option<HashMap<String, usize>> = None;
-            match user {
-                Some(user_id) => {
-                    for (author, stats) in story_data.author_stats.iter() {
-                        if &author.id == user_id {
-                            res = Some(stats.filtered_word_frequencies())
-                        }
-                    }
-                }
-                None => res = Some(story_data.general_stats.filtered_word_frequencies()),
+        match (
+            store.get_server_data(&story_key.0),
+            store.get_channel_data(story_key),
+        ) {
+            (Some(server_data), Some(_)) => (
+                server_data.config.channel(&story_key.1),
+                server_data.config.timezone(),
+                server_data.config.word_rules,
+            ),
+            _ => return Err(String::from("Channel not initialised")),
+        }
+    };
+    let now = Utc::now();
+    let left_range = left_period.range(&now, &timezone);
+    let right_range = right_period.range(&now, &timezone);
+    let bot_id = ctx.cache.current_user_id().await;
+    let messages = fetch_messages_since(
+        &ctx.http,
+        story_key.1,
+        message_id_at(left_range.1.max(right_range.1)),
+        left_range.0.min(right_range.0),
+        MAX_COMPARE_MESSAGES,
+    )
+    .await
+    .map_err(|e| format!("Failed reading back the channel: {}", e))?;
+    let mut left_stats = WordStats::default();
+    let mut right_stats = WordStats::default();
+    for message in messages
+        .iter()
+        .filter(|message| {
+            message.author.id != bot_id && channel_config.counts_message(message, &rules)
+        })
+    {
+        for ((start, end), stats) in [
+            (left_range, &mut left_stats),
+            (right_range, &mut right_stats),
+        ] {
+            if start <= message.timestamp && message.timestamp < end {
+                stats.update(message, &timezone, &rules);
             }
-            res
-        } else {
-            return Some(format!("Channel not initialised"));
         }
+    }
+    Ok((
+        left_stats.filtered_word_frequencies(),
+        right_stats.filtered_word_frequencies(),
+    ))
+}
+
+/// The channel's word frequencies from its stats, just [user]'s if given
+async fn stored_word_frequencies(
+    ctx: &Context,
+    story_key: &StoryKey,
+    user: &Option<UserId>,
+) -> std::result::Result<HashMap<String, usize>, String> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    if let (Some(server_data), Some(story_data)) = (
+        store.get_server_data(&story_key.0),
+        store.get_channel_data(story_key),
+    ) {
+        let story_data = server_data.aliased(story_data);
+        match user {
+            Some(user_id) => {
+                let user_id = server_data.config.main_account(user_id);
+                story_data
+                    .author_stats
+                    .iter()
+                    .find(|(author, _)| author.id == user_id)
+                    .map(|(_, stats)| stats.filtered_word_frequencies())
+                    .ok_or_else(|| format!("User not found in channel"))
+            }
+            None => Ok(story_data.general_stats.filtered_word_frequencies()),
+        }
+    } else {
+        Err(format!("Channel not initialised"))
+    }
+}
+
+/// Hands [word_freqs] to the python worker and waits for the image it makes
+async fn render_wordcloud(
+    ctx: &Context,
+    word_freqs: &HashMap<String, usize>,
+    mask_name: &Option<MaskName>,
+    render_options: &RenderOptions,
+) -> std::result::Result<PathBuf, String> {
+    let request_uuid = Uuid::new_v4();
+    let mask_name_str = match mask_name {
+        Some(mask_name) => format!("{}.", mask_name.to_str()),
+        None => String::from(""),
     };
-    if let Some(word_freqs) = users_stats {
-        let request_uuid = Uuid::new_v4();
-        let mask_name_str = match mask_name {
-            Some(mask_name) => format!("{}.", mask_name.to_str()),
-            None => String::from(""),
+    let request_filename =
+        PathBuf::from(format!("{}.{}generate.json", request_uuid, mask_name_str));
+    let image_extension = render_options.format.to_str();
+    let expect_image_filename =
+        PathBuf::from(format!("{}.generated.{}", request_uuid, image_extension));
+    let (generated_image_path, request_path, timeout) = {
+        let config_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<GeneralAppConfigData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
         };
-        let request_filename =
-            PathBuf::from(format!("{}.{}generate.json", request_uuid, mask_name_str));
-        let expect_image_filename = PathBuf::from(format!("{}.generated.png", request_uuid));
-        let (generated_image_path, request_path, timeout) = {
-            let config_lock = {
-                let data_read = ctx.data.read().await;
-                data_read
-                    .get::<GeneralAppConfigData>()
-                    .expect("Expected StoryData in TypeMap.")
-                    .clone()
-            };
-            let config = config_lock.read().unwrap();
-            let wordcloud_config = config.wordcloud_config.as_ref().unwrap();
-            let generated_image_path = wordcloud_config
-                .generated_image_path
-                .join(expect_image_filename);
-            let request_path = wordcloud_config.request_path.join(request_filename);
-            (generated_image_path, request_path, wordcloud_config.timeout)
+        let config = config_lock.read().unwrap();
+        let wordcloud_config = config.wordcloud_config.as_ref().unwrap();
+        let generated_image_path = wordcloud_config
+            .generated_image_path
+            .join(expect_image_filename);
+        let request_path = wordcloud_config.request_path.join(request_filename);
+        (generated_image_path, request_path, wordcloud_config.timeout)
+    };
+    {
+        let request = WordCloudRequest {
+            frequencies: word_freqs,
+            width: render_options.size.map(|(width, _)| width),
+            height: render_options.size.map(|(_, height)| height),
+            format: image_extension,
         };
-        {
-            let outfile = File::create(request_path).unwrap();
-            serde_json::to_writer(&outfile, &word_freqs).unwrap();
+        let written = File::create(&request_path)
+            .map_err(|e| e.to_string())
+            .and_then(|outfile| {
+                serde_json::to_writer(&outfile, &request).map_err(|e| e.to_string())
+            });
+        if let Err(e) = written {
+            error!("Failed writing wordcloud request {:?}: {}", request_path, e);
+            return Err(String::from(
+                "Failed creating image - Speak to your bot admin",
+            ));
+        }
+    }
+    let image_arrived = wait_for_image(&generated_image_path, &timeout).await;
+    if image_arrived.is_err() {
+        remove_dead_request(&request_path);
+    }
+    match image_arrived {
+        Ok(()) => Ok(generated_image_path),
+        Err(e) => Err(format!("Failed creating image: {}", e)),
+    }
+}
+
+/// Renders a wordcloud of each of [word_freqs] and posts them together, in order. Returns why
+/// not, if it couldn't
+async fn render_and_send_wordclouds(
+    ctx: &Context,
+    word_freqs: &[HashMap<String, usize>],
+    send_to_channel: &ChannelId,
+    response_content: String,
+    mask_name: &Option<MaskName>,
+    render_options: &RenderOptions,
+) -> Option<String> {
+    let mut image_paths = vec![];
+    for freqs in word_freqs {
+        match render_wordcloud(ctx, freqs, mask_name, render_options).await {
+            Ok(image_path) => image_paths.push(image_path),
+            Err(e) => return Some(e),
         }
-        let image_arrived = wait_for_image(&generated_image_path, &timeout).await;
-        match image_arrived {
-            Ok(()) => {
-                let file = tokio::fs::File::from_std(File::open(&generated_image_path).unwrap());
-                let files = vec![AttachmentType::File {
-                    file: &file,
-                    filename: String::from("wordcloud.png"),
-                }];
-                send_to_channel
-                    .send_files(&ctx.http, files, |create_message| {
-                        create_message.content(response_content)
-                    })
-                    .await
-                    .unwrap();
-                None
+    }
+    let image_extension = render_options.format.to_str();
+    let image_files: Vec<tokio::fs::File> = image_paths
+        .iter()
+        .map(|image_path| tokio::fs::File::from_std(File::open(image_path).unwrap()))
+        .collect();
+    let files: Vec<AttachmentType> = image_files
+        .iter()
+        .enumerate()
+        .map(|(i, file)| AttachmentType::File {
+            file,
+            filename: match image_files.len() {
+                1 => format!("wordcloud.{}", image_extension),
+                _ => format!("wordcloud-{}.{}", i + 1, image_extension),
+            },
+        })
+        .collect();
+    send_to_channel
+        .send_files(&ctx.http, files, |create_message| {
+            create_message.content(response_content)
+        })
+        .await
+        .unwrap();
+    None
+}
+
+async fn post_scheduled_wordcloud(
+    ctx: &Context,
+    wordcloud: ScheduledWordCloud,
+) -> serenity::Result<()> {
+    let (channel_config, timezone, rules) = {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let store = store_lock.read().unwrap();
+        match store.get_server_data(&wordcloud.server_id) {
+            Some(server_data) => (
+                server_data.config.channel(&wordcloud.channel_id),
+                server_data.config.timezone(),
+                server_data.config.word_rules,
+            ),
+            None => return Ok(()),
+        }
+    };
+    let bot_id = ctx.cache.current_user_id().await;
+    let messages = fetch_messages_since(
+        &ctx.http,
+        wordcloud.channel_id,
+        message_id_at(Utc::now()),
+        wordcloud.since,
+        MAX_SCHEDULED_WORDCLOUD_MESSAGES,
+    )
+    .await?;
+    let mut word_stats = WordStats::default();
+    for message in messages
+        .iter()
+        .filter(|message| {
+            message.author.id != bot_id && channel_config.counts_message(message, &rules)
+        })
+    {
+        word_stats.update(message, &timezone, &rules);
+    }
+    let word_freqs = word_stats.filtered_word_frequencies();
+    if word_freqs.is_empty() {
+        info!(
+            "Nothing written in {} for its scheduled wordcloud",
+            wordcloud.channel_id
+        );
+        return Ok(());
+    }
+    let period = match wordcloud.period {
+        WordCloudPeriod::Daily => "day",
+        WordCloudPeriod::Weekly(_) => "week",
+    };
+    let response_content = MessageBuilder::new()
+        .push(format!("Word cloud of the past {} in ", period))
+        .channel(wordcloud.channel_id)
+        .build();
+    let failure = render_and_send_wordclouds(
+        ctx,
+        std::slice::from_ref(&word_freqs),
+        &wordcloud.channel_id,
+        response_content,
+        &None,
+        &RenderOptions::default(),
+    )
+    .await;
+    if let Some(failure_reason) = failure {
+        error!(
+            "Scheduled wordcloud for {} failed: {}",
+            wordcloud.channel_id, failure_reason
+        );
+    }
+    Ok(())
+}
+
+/// Background task posting the wordclouds channels have scheduled with [wordcloud-schedule]
+pub async fn scheduled_wordcloud_worker(ctx: Arc<Context>) {
+    loop {
+        if wordcloud_is_enabled(&ctx).await {
+            let due_wordclouds = {
+                let store_lock = {
+                    let data_read = ctx.data.read().await;
+                    data_read
+                        .get::<StoreData>()
+                        .expect("Expected StoryData in TypeMap.")
+                        .clone()
+                };
+                let mut store = store_lock.write().unwrap();
+                take_due_wordclouds(&mut store, &Utc::now())
+            };
+            for wordcloud in due_wordclouds {
+                if let Err(why) = post_scheduled_wordcloud(&ctx, wordcloud).await {
+                    error!("Error posting scheduled wordcloud: {:?}", why);
+                }
             }
-            Err(e) => Some(format!("Failed creating image: {}", e)),
         }
-    } else {
-        Some(format!("User not found in channel"))
+        tokio::time::sleep(TICK).await;
+    }
+}
+
+/// Remove a request file the worker never answered, so it can't be picked up later and generate
+/// an image nobody is waiting for
+fn remove_dead_request(request_path: &Path) {
+    match std::fs::remove_file(request_path) {
+        Ok(()) => info!("Removed dead wordcloud request {:?}", request_path),
+        Err(e) if e.kind() == ErrorKind::NotFound => (),
+        Err(e) => error!(
+            "Failed removing dead wordcloud request {:?}: {}",
+            request_path, e
+        ),
     }
 }
 
@@ -292,3 +850,76 @@ fn usage_matches_all_masks() {
         .join("|");
     assert_eq!(all_masks_from_enum_iter, masks_from_desc);
 }
+
+#[test]
+fn compare_args() {
+    use serenity::framework::standard::Delimiter;
+    let mut args = Args::new("<#7> --compare <@1> <@2> wolf", &[Delimiter::Single(' ')]);
+    let (channel_id, comparison, mask_name) = parse_compare_args(&mut args).unwrap();
+    assert_eq!(channel_id, ChannelId(7));
+    assert_eq!(comparison, Comparison::Authors(UserId(1), UserId(2)));
+    assert!(matches!(mask_name, Some(MaskName::Wolf)));
+
+    let mut args = Args::new(
+        "<#7> --compare lastmonth thismonth",
+        &[Delimiter::Single(' ')],
+    );
+    let (_, comparison, mask_name) = parse_compare_args(&mut args).unwrap();
+    assert_eq!(
+        comparison,
+        Comparison::Periods(ComparePeriod::LastMonth, ComparePeriod::ThisMonth)
+    );
+    assert!(mask_name.is_none());
+
+    for invalid in [
+        "<#7> --compare <@1>",
+        "<#7> --compare <@1> lastweek",
+        "<#7> <@1> <@2>",
+    ]
+    .iter()
+    {
+        assert!(parse_compare_args(&mut Args::new(invalid, &[Delimiter::Single(' ')])).is_err());
+    }
+}
+
+#[test]
+fn compare_periods() {
+    // A Wednesday afternoon, early enough that it's still Wednesday in London (UTC+1 then)
+    let now = Utc.ymd(2021, 6, 16).and_hms(15, 0, 0);
+    let london: Tz = "Europe/London".parse().unwrap();
+    assert_eq!(
+        ComparePeriod::ThisWeek.range(&now, &london),
+        (Utc.ymd(2021, 6, 13).and_hms(23, 0, 0), now)
+    );
+    assert_eq!(
+        ComparePeriod::LastWeek.range(&now, &Tz::UTC),
+        (
+            Utc.ymd(2021, 6, 7).and_hms(0, 0, 0),
+            Utc.ymd(2021, 6, 14).and_hms(0, 0, 0)
+        )
+    );
+    assert_eq!(
+        ComparePeriod::LastMonth.range(&now, &Tz::UTC),
+        (
+            Utc.ymd(2021, 5, 1).and_hms(0, 0, 0),
+            Utc.ymd(2021, 6, 1).and_hms(0, 0, 0)
+        )
+    );
+    assert_eq!(
+        ComparePeriod::LastMonth
+            .range(&Utc.ymd(2021, 1, 10).and_hms(0, 0, 0), &Tz::UTC)
+            .0,
+        Utc.ymd(2020, 12, 1).and_hms(0, 0, 0)
+    );
+}
+
+#[test]
+fn private_word_clouds() {
+    let mut server_data = ServerData::new();
+    assert!(private_words_refusal(&server_data, &UserId(1), &[UserId(2)]).is_none());
+    server_data.config.privacy_mode = true;
+    assert!(private_words_refusal(&server_data, &UserId(1), &[]).is_none());
+    assert!(private_words_refusal(&server_data, &UserId(1), &[UserId(1)]).is_none());
+    assert!(private_words_refusal(&server_data, &UserId(1), &[UserId(2)]).is_some());
+    assert!(private_words_refusal(&server_data, &UserId(1), &[UserId(1), UserId(2)]).is_some());
+}
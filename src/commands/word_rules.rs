@@ -0,0 +1,98 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::language_parsing::WordRules;
+use crate::state::StoreData;
+use crate::utils::transient::transient_reply;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+
+pub async fn get_word_rules(ctx: &Context, server_id: &GuildId) -> WordRules {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    store
+        .get_server_data(server_id)
+        .map_or(WordRules::default(), |server_data| {
+            server_data.config.word_rules
+        })
+}
+
+async fn set_word_rules(ctx: &Context, server_id: &GuildId, rules: WordRules) {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    store
+        .get_server_data_mut_maybe_create(server_id)
+        .config
+        .word_rules = rules;
+}
+
+fn describe_word_rules(rules: &WordRules) -> String {
+    let others = WordRules::PRESETS
+        .iter()
+        .filter(|preset| *preset != rules)
+        .map(|preset| format!("{}: {}", preset.name(), preset.describe()))
+        .collect::<Vec<String>>()
+        .join("\n");
+    format!(
+        "Words on this server are counted with the {} rules, {}\nThe others are\n{}",
+        rules.name(),
+        rules.describe(),
+        others
+    )
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[(
+        "[standard|nanowrimo|strict|loose]",
+        "The rules to count with, shows the current ones if not given",
+    )],
+    permission: Permission::AllowedRolesToChange,
+};
+
+#[command("word-rules")]
+#[usage("[standard|nanowrimo|strict|loose]")]
+#[description("Show or set how this server's words are counted, for communities that count hyphenated words, numbers, contractions or em-dash joins differently. nanowrimo comes closest to a word processor's count. Setting it needs the same role as init-channel. Messages already counted keep their counts, use [rebuild-stats] to recount a channel")]
+#[example("")]
+#[example("nanowrimo")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn word_rules(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if let Some(server_id) = msg.guild_id {
+        if args.is_empty() {
+            describe_word_rules(&get_word_rules(ctx, &server_id).await)
+        } else {
+            match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+                true => match args.single::<String>().map(|s| s.parse::<WordRules>()) {
+                    Ok(Ok(rules)) => {
+                        set_word_rules(ctx, &server_id, rules).await;
+                        record_admin_action(ctx, msg, "word-rules").await;
+                        format!(
+                            "New messages will be counted with the {} rules, {}. Use [rebuild-stats] to recount a channel's older ones",
+                            rules.name(),
+                            rules.describe()
+                        )
+                    }
+                    Ok(Err(e)) => e,
+                    Err(_) => String::from("Invalid arguments, try [help word-rules]"),
+                },
+                false => not_in_allowed_roles_response(),
+            }
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    transient_reply(ctx, msg, reply).await?;
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use crate::commands::audit_log::record_admin_action;
+use crate::commands::help::{CommandHelp, Permission};
+use crate::commands::init_channel::{author_is_in_allowed_roles, not_in_allowed_roles_response};
+use crate::commands::word_cloud::wordcloud_is_enabled;
+use crate::guild_config::{update_channel_config, WordCloudPeriod};
+use crate::state::StoreData;
+use crate::utils::transient::transient_reply;
+use chrono::Weekday;
+use serenity::framework::standard::{macros::command, Args, CommandResult};
+use serenity::model::prelude::*;
+use serenity::prelude::*;
+use serenity::utils::MessageBuilder;
+
+fn describe_schedule(channel_id: &ChannelId, schedule: &Option<WordCloudPeriod>) -> String {
+    let mut builder = MessageBuilder::new();
+    match schedule {
+        Some(period) => builder
+            .push("A word cloud of ")
+            .channel(channel_id)
+            .push(format!(
+                " is posted there {}, covering the {} before",
+                period.describe(),
+                match period {
+                    WordCloudPeriod::Daily => "day",
+                    WordCloudPeriod::Weekly(_) => "week",
+                }
+            ))
+            .build(),
+        None => builder
+            .push("No word clouds are scheduled for ")
+            .channel(channel_id)
+            .build(),
+    }
+}
+
+pub fn parse_schedule(args: &mut Args) -> std::result::Result<Option<WordCloudPeriod>, String> {
+    match args.single::<String>().as_deref() {
+        Ok("off") => Ok(None),
+        Ok("daily") => Ok(Some(WordCloudPeriod::Daily)),
+        Ok("weekly") => match args.single::<Weekday>() {
+            Ok(weekday) => Ok(Some(WordCloudPeriod::Weekly(weekday))),
+            Err(_) => Err(String::from(
+                "Expected the day of the week to post on, like sunday",
+            )),
+        },
+        _ => Err(String::from(
+            "Expected daily, weekly <day> or off, try [help wordcloud-schedule]",
+        )),
+    }
+}
+
+/// The channel's schedule, or [None] if it isn't initialised
+async fn get_schedule(
+    ctx: &Context,
+    server_id: &GuildId,
+    channel_id: &ChannelId,
+) -> Option<Option<WordCloudPeriod>> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    store.get_channel_data(&(*server_id, *channel_id))?;
+    store
+        .get_server_data(server_id)
+        .map(|server_data| server_data.config.channel(channel_id).wordcloud_schedule)
+}
+
+pub const HELP: CommandHelp = CommandHelp {
+    args: &[
+        (
+            "<#channel name>",
+            "An initialised channel, shows its schedule if nothing else is given",
+        ),
+        (
+            "[daily|weekly <day>|off]",
+            "How often to post, weekly ones go out on the given day",
+        ),
+    ],
+    permission: Permission::AllowedRolesToChange,
+};
+
+#[command("wordcloud-schedule")]
+#[usage("<#channel name> [daily|weekly <day>|off]")]
+#[description("Have the bot post a word cloud of a channel's messages from the past day or week into that channel, every day or on a day of the week. Days follow the server's timezone, and posts wait for quiet hours to end. Setting it needs the same role as init-channel")]
+#[example("#the-fall-of-rome")]
+#[example("#the-fall-of-rome weekly sunday")]
+#[example("#the-fall-of-rome daily")]
+#[example("#the-fall-of-rome off")]
+#[only_in("guilds")] // Reminder: guild = server
+async fn wordcloud_schedule(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reply = if !wordcloud_is_enabled(ctx).await {
+        String::from("Wordclouds are not enabled, sorry - Speak to your bot admin")
+    } else if let Some(server_id) = msg.guild_id {
+        match args.single::<ChannelId>() {
+            Ok(channel_id) => match get_schedule(ctx, &server_id, &channel_id).await {
+                None => String::from("Channel not initialised, use [init-channel] to add it"),
+                Some(schedule) if args.is_empty() => describe_schedule(&channel_id, &schedule),
+                Some(_) => match author_is_in_allowed_roles(ctx, &server_id, &msg.author).await {
+                    true => match parse_schedule(&mut args) {
+                        Ok(schedule) => {
+                            let response = describe_schedule(&channel_id, &schedule);
+                            update_channel_config(ctx, &server_id, &channel_id, |config| {
+                                config.wordcloud_schedule = schedule
+                            })
+                            .await;
+                            record_admin_action(ctx, msg, "wordcloud-schedule").await;
+                            response
+                        }
+                        Err(e) => e,
+                    },
+                    false => not_in_allowed_roles_response(),
+                },
+            },
+            Err(_) => String::from("Invalid arguments, try [help wordcloud-schedule]"),
+        }
+    } else {
+        String::from("BUG: message had no server id, bot only supports server text channels")
+    };
+    transient_reply(ctx, msg, reply).await?;
+    Ok(())
+}
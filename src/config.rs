@@ -2,14 +2,15 @@ use crate::WordCloud;
 use ron::de::from_reader;
 use ron::ser::{to_writer_pretty, PrettyConfig};
 use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, GuildId, UserId};
 use serenity::prelude::TypeMapKey;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::RwLock;
 use std::time::Duration;
-use serenity::model::id::UserId;
 
 pub struct GeneralAppConfigData;
 
@@ -22,6 +23,72 @@ pub struct GeneralAppConfig {
     pub prefix: String,
     pub wordcloud_config: Option<WordCloudConfig>,
     pub bot_admin: Option<UserId>,
+    // Where the nightly integrity check reports problems, [bot_admin] is DMed instead if unset
+    #[serde(default)]
+    pub error_channel: Option<ChannelId>,
+    #[serde(default)]
+    pub rate_limits: RateLimitConfig,
+    // Where [export-store] writes exports, they're uploaded to discord instead if unset
+    #[serde(default)]
+    pub export_path: Option<PathBuf>,
+    // Distinct words tracked per stats in a channel before the least frequent are dropped
+    #[serde(default = "default_max_words_per_channel")]
+    pub max_words_per_channel: usize,
+    #[serde(default)]
+    pub intents: IntentsConfig,
+    // Invite link shown by [about]
+    #[serde(default)]
+    pub support_server: Option<String>,
+    #[serde(default)]
+    pub activity: ActivityConfig,
+    // Environment variable the bot's token is read from
+    #[serde(default = "default_token_env")]
+    pub token_env: String,
+    // Where the state is saved, relative to where the bot is run
+    #[serde(default = "default_state_path")]
+    pub state_path: PathBuf,
+    #[serde(default = "default_wordcloud_worker_script")]
+    pub wordcloud_worker_script: PathBuf,
+    // Named sets of overrides for the settings above, chosen with --profile so e.g. a dev bot can
+    // run from the same checkout and config as prod
+    #[serde(default)]
+    pub profiles: HashMap<String, BotProfile>,
+    // The profile applied, if any
+    #[serde(skip)]
+    pub profile: Option<String>,
+}
+
+fn default_max_words_per_channel() -> usize {
+    100_000
+}
+
+fn default_token_env() -> String {
+    String::from("BOT_TOKEN")
+}
+
+fn default_state_path() -> PathBuf {
+    PathBuf::from("state.sexp")
+}
+
+fn default_wordcloud_worker_script() -> PathBuf {
+    PathBuf::from("wordcloud/word_cloud_worker.py")
+}
+
+/// Settings a profile replaces, those it leaves unset are shared with the defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BotProfile {
+    #[serde(default)]
+    pub token_env: Option<String>,
+    #[serde(default)]
+    pub state_path: Option<PathBuf>,
+    #[serde(default)]
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub wordcloud_config: Option<WordCloudConfig>,
+    #[serde(default)]
+    pub wordcloud_worker_script: Option<PathBuf>,
+    #[serde(default)]
+    pub export_path: Option<PathBuf>,
 }
 
 impl Default for GeneralAppConfig {
@@ -30,6 +97,18 @@ impl Default for GeneralAppConfig {
             prefix: String::from("!"),
             wordcloud_config: Some(WordCloudConfig::default()),
             bot_admin: None,
+            error_channel: None,
+            rate_limits: RateLimitConfig::default(),
+            export_path: None,
+            max_words_per_channel: default_max_words_per_channel(),
+            intents: IntentsConfig::default(),
+            support_server: None,
+            activity: ActivityConfig::default(),
+            token_env: default_token_env(),
+            state_path: default_state_path(),
+            wordcloud_worker_script: default_wordcloud_worker_script(),
+            profiles: HashMap::new(),
+            profile: None,
         }
     }
 }
@@ -50,9 +129,51 @@ impl GeneralAppConfig {
             Err(other) => panic!("Failed opening file: {}", other),
         }
     }
+
+    pub fn apply_profile(&mut self, name: &str) -> Result<(), String> {
+        let profile = match self.profiles.get(name) {
+            Some(profile) => profile.clone(),
+            None => {
+                let mut names: Vec<&String> = self.profiles.keys().collect();
+                names.sort();
+                return Err(match names.is_empty() {
+                    true => format!("No profile {}, config.ron has no profiles", name),
+                    false => format!(
+                        "No profile {}, config.ron has {}",
+                        name,
+                        names
+                            .iter()
+                            .map(|name| name.as_str())
+                            .collect::<Vec<&str>>()
+                            .join(", ")
+                    ),
+                });
+            }
+        };
+        if let Some(token_env) = profile.token_env {
+            self.token_env = token_env;
+        }
+        if let Some(state_path) = profile.state_path {
+            self.state_path = state_path;
+        }
+        if let Some(prefix) = profile.prefix {
+            self.prefix = prefix;
+        }
+        if let Some(wordcloud_config) = profile.wordcloud_config {
+            self.wordcloud_config = Some(wordcloud_config);
+        }
+        if let Some(wordcloud_worker_script) = profile.wordcloud_worker_script {
+            self.wordcloud_worker_script = wordcloud_worker_script;
+        }
+        if let Some(export_path) = profile.export_path {
+            self.export_path = Some(export_path);
+        }
+        self.profile = Some(name.to_string());
+        Ok(())
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WordCloudConfig {
     pub python_path: PathBuf,
     pub venv_path: Option<PathBuf>,
@@ -72,3 +193,130 @@ impl Default for WordCloudConfig {
         }
     }
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimit {
+    pub limit: u32,
+    pub time_span: Duration,
+}
+
+/// Limits for expensive commands (wordclouds, backfills, exports), checked per guild and per user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    pub per_guild: RateLimit,
+    pub per_user: RateLimit,
+    // Replaces [per_guild] for specific servers, for those who need more (or less). A limit of 0
+    // turns the expensive commands off for the server
+    #[serde(default)]
+    pub guild_overrides: HashMap<GuildId, RateLimit>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_guild: RateLimit {
+                limit: 10,
+                time_span: Duration::from_secs(60),
+            },
+            per_user: RateLimit {
+                limit: 3,
+                time_span: Duration::from_secs(60),
+            },
+            guild_overrides: HashMap::new(),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    pub fn limit_for_guild(&self, guild_id: &GuildId) -> &RateLimit {
+        self.guild_overrides
+            .get(guild_id)
+            .unwrap_or(&self.per_guild)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum ActivityKind {
+    Listening,
+    Playing,
+    Competing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusTemplate {
+    pub kind: ActivityKind,
+    // {prefix}, {words}, {stories} and {servers} are filled in when it's shown
+    pub text: String,
+}
+
+/// Statuses the bot cycles through, one every [interval]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityConfig {
+    pub statuses: Vec<StatusTemplate>,
+    pub interval: Duration,
+}
+
+impl Default for ActivityConfig {
+    fn default() -> Self {
+        Self {
+            statuses: vec![
+                StatusTemplate {
+                    kind: ActivityKind::Listening,
+                    text: String::from("{prefix}"),
+                },
+                StatusTemplate {
+                    kind: ActivityKind::Listening,
+                    text: String::from("{words} words across {stories} stories"),
+                },
+            ],
+            interval: Duration::from_secs(300),
+        }
+    }
+}
+
+/// Which privileged intents the bot has been granted in the developer portal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntentsConfig {
+    // Off unless granted, asking for a privileged intent the bot doesn't have stops it connecting
+    #[serde(default)]
+    pub guild_members: bool,
+    // Without this message bodies arrive empty, so the bot starts without counting them
+    pub message_content: bool,
+}
+
+impl Default for IntentsConfig {
+    fn default() -> Self {
+        Self {
+            guild_members: false,
+            message_content: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::config::{BotProfile, GeneralAppConfig};
+    use std::path::PathBuf;
+
+    #[test]
+    fn profile_overrides_defaults() {
+        let mut config = GeneralAppConfig::default();
+        config.profiles.insert(
+            String::from("dev"),
+            BotProfile {
+                token_env: Some(String::from("DEV_BOT_TOKEN")),
+                state_path: Some(PathBuf::from("dev-state.sexp")),
+                ..BotProfile::default()
+            },
+        );
+        assert!(config
+            .apply_profile("staging")
+            .unwrap_err()
+            .contains("has dev"));
+        config.apply_profile("dev").unwrap();
+        assert_eq!(config.token_env, "DEV_BOT_TOKEN");
+        assert_eq!(config.state_path, PathBuf::from("dev-state.sexp"));
+        assert_eq!(config.prefix, GeneralAppConfig::default().prefix);
+        assert_eq!(config.profile.as_deref(), Some("dev"));
+    }
+}
@@ -0,0 +1,193 @@
+use crate::config::GeneralAppConfig;
+use crate::encryption::{is_encrypted, StateKey, STATE_KEY_ENV};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// One line of the checklist, fine unless it has a [problem]
+#[derive(Debug)]
+pub struct Check {
+    pub name: String,
+    pub problem: Option<String>,
+}
+
+impl Check {
+    pub fn new(name: &str, result: Result<(), String>) -> Self {
+        Check {
+            name: name.to_string(),
+            problem: result.err(),
+        }
+    }
+}
+
+pub fn format_checklist(checks: &[Check]) -> String {
+    checks
+        .iter()
+        .map(|check| match &check.problem {
+            None => format!("✅ {}", check.name),
+            Some(problem) => format!("❌ {}: {}", check.name, problem),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn has_problems(checks: &[Check]) -> bool {
+    checks.iter().any(|check| check.problem.is_some())
+}
+
+/// Writes and removes a file in [dir], as permissions alone don't account for read-only mounts
+fn check_writable_dir(dir: &Path) -> Result<(), String> {
+    if !dir.is_dir() {
+        return Err(format!("{:?} isn't a directory", dir));
+    }
+    let probe = dir.join(format!(".scrivener-doctor-{}", Uuid::new_v4()));
+    std::fs::write(&probe, b"")
+        .and_then(|()| std::fs::remove_file(&probe))
+        .map_err(|e| format!("Can't write to {:?}: {}", dir, e))
+}
+
+/// A bare name like python3 is looked up on PATH, as it is when the worker is started
+fn find_executable(program: &Path) -> Option<PathBuf> {
+    let candidates: Vec<PathBuf> = match program.components().count() {
+        1 if !program.is_absolute() => std::env::var_os("PATH")
+            .map(|path| {
+                std::env::split_paths(&path)
+                    .map(|dir| dir.join(program))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        _ => vec![program.to_path_buf()],
+    };
+    candidates.into_iter().find(|candidate| candidate.is_file())
+}
+
+fn check_state_file(state_path: &Path) -> Result<(), String> {
+    match state_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => check_writable_dir(dir)?,
+        _ => check_writable_dir(Path::new("."))?,
+    }
+    let bytes = match std::fs::read(state_path) {
+        Ok(bytes) => bytes,
+        // Made on the first save
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("Can't read {:?}: {}", state_path, e)),
+    };
+    let state_key = StateKey::from_env()?;
+    match (is_encrypted(&bytes), state_key) {
+        (true, None) => Err(format!(
+            "{:?} is encrypted but {} isn't set",
+            state_path, STATE_KEY_ENV
+        )),
+        (true, Some(state_key)) => state_key
+            .decrypt(&bytes)
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+        (false, _) => Ok(()),
+    }
+}
+
+/// Everything that can be checked without connecting to discord
+pub fn config_checks(config: &GeneralAppConfig) -> Vec<Check> {
+    let mut checks = vec![
+        Check::new("State file", check_state_file(&config.state_path)),
+        Check::new(
+            "Bot token",
+            match std::env::var(&config.token_env) {
+                Ok(token) if !token.trim().is_empty() => Ok(()),
+                _ => Err(format!("{} isn't set", config.token_env)),
+            },
+        ),
+        Check::new(
+            "Bot admin",
+            match config.bot_admin {
+                Some(_) => Ok(()),
+                None => Err(String::from(
+                    "bot_admin isn't set, nobody is told if message content stops arriving",
+                )),
+            },
+        ),
+    ];
+    if let Some(wordcloud_config) = &config.wordcloud_config {
+        checks.push(Check::new(
+            "Wordcloud python",
+            match find_executable(&wordcloud_config.python_path) {
+                Some(_) => Ok(()),
+                None => Err(format!(
+                    "python_path {:?} isn't an executable file",
+                    wordcloud_config.python_path
+                )),
+            },
+        ));
+        if let Some(venv_path) = &wordcloud_config.venv_path {
+            checks.push(Check::new(
+                "Wordcloud venv",
+                match venv_path.is_dir() {
+                    true => Ok(()),
+                    false => Err(format!("venv_path {:?} isn't a directory", venv_path)),
+                },
+            ));
+        }
+        checks.push(Check::new(
+            "Wordcloud worker script",
+            match config.wordcloud_worker_script.is_file() {
+                true => Ok(()),
+                false => Err(format!("{:?} is missing", config.wordcloud_worker_script)),
+            },
+        ));
+        checks.push(Check::new(
+            "Wordcloud request directory",
+            check_writable_dir(&wordcloud_config.request_path),
+        ));
+        checks.push(Check::new(
+            "Wordcloud image directory",
+            check_writable_dir(&wordcloud_config.generated_image_path),
+        ));
+    }
+    if let Some(export_path) = &config.export_path {
+        checks.push(Check::new(
+            "Export directory",
+            check_writable_dir(export_path),
+        ));
+    }
+    checks
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::config::{GeneralAppConfig, WordCloudConfig};
+    use crate::doctor::{check_writable_dir, config_checks, find_executable, format_checklist};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn writable_dirs() {
+        let dir = std::env::temp_dir();
+        assert!(check_writable_dir(&dir).is_ok());
+        assert!(check_writable_dir(&dir.join("scrivener-doctor-missing")).is_err());
+    }
+
+    #[test]
+    fn executables() {
+        assert!(find_executable(Path::new("sh")).is_some());
+        // The old default, a directory rather than a python
+        assert!(find_executable(Path::new(".")).is_none());
+        assert!(find_executable(Path::new("/no/such/python")).is_none());
+    }
+
+    #[test]
+    fn problems_listed() {
+        let config = GeneralAppConfig {
+            wordcloud_config: Some(WordCloudConfig {
+                python_path: PathBuf::from("/no/such/python"),
+                venv_path: None,
+                request_path: std::env::temp_dir(),
+                generated_image_path: PathBuf::from("/no/such/dir"),
+                timeout: std::time::Duration::from_secs(2),
+            }),
+            ..GeneralAppConfig::default()
+        };
+        let checklist = format_checklist(&config_checks(&config));
+        assert!(checklist.contains("✅ Wordcloud request directory"));
+        assert!(checklist.contains("❌ Wordcloud python: python_path"));
+        assert!(checklist.contains("❌ Wordcloud image directory"));
+        assert!(checklist.contains("❌ Bot admin"));
+    }
+}
@@ -0,0 +1,128 @@
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use std::io::{Error, ErrorKind, Result};
+
+/// Environment variable holding the key state is encrypted with, as 64 hex characters
+pub const STATE_KEY_ENV: &str = "STATE_KEY";
+// Also authenticated, so a file can't be passed off as another format's
+const ENCRYPTED_MAGIC: &[u8; 8] = b"SCRVENC1";
+
+/// AES-256-GCM key for the state file. Each write gets a fresh random nonce, stored ahead of the
+/// ciphertext
+pub struct StateKey(LessSafeKey);
+
+impl std::fmt::Debug for StateKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("StateKey(..)")
+    }
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+impl StateKey {
+    pub fn from_hex(hex: &str) -> std::result::Result<Self, String> {
+        let hex = hex.trim();
+        // Checked up front, as parsing each pair alone would take a sign like "+f"
+        if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!(
+                "{} should be 64 hex characters (32 bytes)",
+                STATE_KEY_ENV
+            ));
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<std::result::Result<Vec<u8>, _>>()
+            .map_err(|_| format!("{} should be 64 hex characters (32 bytes)", STATE_KEY_ENV))?;
+        let key = UnboundKey::new(&AES_256_GCM, &bytes)
+            .map_err(|_| format!("{} isn't a valid AES-256 key", STATE_KEY_ENV))?;
+        Ok(StateKey(LessSafeKey::new(key)))
+    }
+
+    /// [None] if the variable isn't set, state is then kept unencrypted
+    pub fn from_env() -> std::result::Result<Option<Self>, String> {
+        match std::env::var(STATE_KEY_ENV) {
+            Ok(hex) => StateKey::from_hex(&hex).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+
+    pub fn encrypt(&self, mut data: Vec<u8>) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| Error::other("No randomness for a nonce"))?;
+        self.0
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(ENCRYPTED_MAGIC),
+                &mut data,
+            )
+            .map_err(|_| invalid_data("State too large to encrypt"))?;
+        let mut encrypted = Vec::with_capacity(ENCRYPTED_MAGIC.len() + NONCE_LEN + data.len());
+        encrypted.extend_from_slice(ENCRYPTED_MAGIC);
+        encrypted.extend_from_slice(&nonce);
+        encrypted.extend(data);
+        Ok(encrypted)
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let (nonce, ciphertext) = data
+            .strip_prefix(ENCRYPTED_MAGIC.as_ref())
+            .filter(|rest| rest.len() >= NONCE_LEN)
+            .map(|rest| rest.split_at(NONCE_LEN))
+            .ok_or_else(|| invalid_data("State file isn't encrypted"))?;
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| invalid_data("Bad nonce in state file"))?;
+        let mut ciphertext = ciphertext.to_vec();
+        let plaintext_len = self
+            .0
+            .open_in_place(nonce, Aad::from(ENCRYPTED_MAGIC), &mut ciphertext)
+            .map_err(|_| {
+                invalid_data(
+                    "Failed decrypting state file, is STATE_KEY the key it was written with?",
+                )
+            })?
+            .len();
+        ciphertext.truncate(plaintext_len);
+        Ok(ciphertext)
+    }
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(ENCRYPTED_MAGIC)
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::encryption::{is_encrypted, StateKey};
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+    #[test]
+    fn round_trip() {
+        let key = StateKey::from_hex(KEY).unwrap();
+        let encrypted = key.encrypt(b"word counts".to_vec()).unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(!is_encrypted(b"word counts"));
+        assert_eq!(key.decrypt(&encrypted).unwrap(), b"word counts");
+        // Nonces are fresh per write
+        assert_ne!(encrypted, key.encrypt(b"word counts".to_vec()).unwrap());
+    }
+
+    #[test]
+    fn wrong_or_tampered() {
+        let key = StateKey::from_hex(KEY).unwrap();
+        let other_key = StateKey::from_hex(&KEY.replace("1f", "ff")).unwrap();
+        let mut encrypted = key.encrypt(b"word counts".to_vec()).unwrap();
+        assert!(other_key.decrypt(&encrypted).is_err());
+        let last = encrypted.len() - 1;
+        encrypted[last] ^= 1;
+        assert!(key.decrypt(&encrypted).is_err());
+        assert!(StateKey::from_hex("abcd").is_err());
+        assert!(StateKey::from_hex(&KEY.replace("00", "zz")).is_err());
+        assert!(StateKey::from_hex(&KEY.replace("01", "+1")).is_err());
+    }
+}
@@ -0,0 +1,203 @@
+use crate::state::{ChannelData, ServerData, Store};
+use crate::stats::WordStats;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use serenity::model::id::{ChannelId, GuildId};
+use std::collections::{BTreeMap, HashMap};
+
+// Bump when a field is removed or changes meaning, adding fields is fine
+const EXPORT_VERSION: u32 = 1;
+
+/// The shape stats are exported in for offline analysis. This is kept apart from [ServerData]
+/// and friends on purpose, so that the internal state can change without breaking anyone's
+/// scripts
+#[derive(Debug, Serialize)]
+pub struct StoreExport {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub servers: Vec<ServerExport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServerExport {
+    pub server_id: u64,
+    pub timezone: String,
+    pub channels: Vec<ChannelExport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChannelExport {
+    pub channel_id: u64,
+    pub general: StatsExport,
+    pub authors: Vec<AuthorExport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuthorExport {
+    pub user_id: u64,
+    pub name: String,
+    pub departed: bool,
+    pub stats: StatsExport,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatsExport {
+    pub word_count: usize,
+    pub word_frequencies: BTreeMap<String, usize>,
+    pub daily_word_counts: BTreeMap<NaiveDate, usize>,
+    pub images_shared: usize,
+    pub files_shared: usize,
+    pub embeds_shared: usize,
+    pub last_message_at: Option<DateTime<Utc>>,
+}
+
+impl StoreExport {
+    pub fn new<'a, I: Iterator<Item = (&'a GuildId, &'a ServerData)>>(servers: I) -> Self {
+        let mut servers: Vec<ServerExport> = servers
+            .map(|(server_id, server_data)| ServerExport::new(server_id, server_data))
+            .collect();
+        servers.sort_by_key(|server| server.server_id);
+        Self {
+            version: EXPORT_VERSION,
+            exported_at: Utc::now(),
+            servers,
+        }
+    }
+}
+
+impl ServerExport {
+    fn new(server_id: &GuildId, server_data: &ServerData) -> Self {
+        let mut channels: Vec<ChannelExport> = server_data
+            .channels()
+            .map(|(channel_id, channel_data)| {
+                ChannelExport::new(channel_id, channel_data, server_data)
+            })
+            .collect();
+        channels.sort_by_key(|channel| channel.channel_id);
+        Self {
+            server_id: server_id.0,
+            timezone: server_data.config.timezone().to_string(),
+            channels,
+        }
+    }
+}
+
+impl ChannelExport {
+    fn new(channel_id: &ChannelId, channel_data: &ChannelData, server_data: &ServerData) -> Self {
+        let channel_data = server_data.aliased(channel_data);
+        let mut authors: Vec<AuthorExport> = channel_data
+            .author_stats
+            .iter()
+            .map(|(author, stats)| AuthorExport {
+                user_id: author.id.0,
+                name: server_data.display_names.get(author).to_string(),
+                departed: server_data.departed_authors.contains(&author.id),
+                stats: StatsExport::new(stats),
+            })
+            .collect();
+        authors.sort_by_key(|author| author.user_id);
+        Self {
+            channel_id: channel_id.0,
+            general: StatsExport::new(&channel_data.general_stats),
+            authors,
+        }
+    }
+}
+
+impl StatsExport {
+    fn new(stats: &WordStats) -> Self {
+        Self {
+            word_count: stats.word_count,
+            word_frequencies: stats
+                .word_frequencies
+                .iter()
+                .map(|(word, count)| (word.clone(), *count))
+                .collect(),
+            daily_word_counts: stats.daily_word_counts().clone(),
+            images_shared: stats.media.images,
+            files_shared: stats.media.files,
+            embeds_shared: stats.media.embeds,
+            last_message_at: stats.last_message_time().cloned(),
+        }
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+/// One `word,count` row per word, most used first
+pub fn word_frequencies_csv(frequencies: &HashMap<String, usize>) -> String {
+    let mut rows: Vec<(&String, &usize)> = frequencies.iter().collect();
+    rows.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    let mut csv = String::from("word,count\n");
+    for (word, count) in rows {
+        csv.push_str(&format!("{},{}\n", csv_field(word), count));
+    }
+    csv
+}
+
+/// Pretty JSON of [StoreExport] for [server_id], or every server if not given
+pub fn export_json(store: &Store, server_id: Option<GuildId>) -> Result<String, String> {
+    let export = match server_id {
+        Some(server_id) => match store.get_server_data(&server_id) {
+            Some(server_data) => StoreExport::new(std::iter::once((&server_id, server_data))),
+            None => return Err(String::from("No stats recorded for that server")),
+        },
+        None => StoreExport::new(store.servers()),
+    };
+    serde_json::to_string_pretty(&export).map_err(|e| format!("Failed serialising export: {}", e))
+}
+
+/// [export] as written to a file, encrypted with the state's key if it has one, so the stats don't
+/// sit on disk in plain text beside an encrypted state file. Also returns whether it was encrypted
+pub fn export_file(store: &Store, export: String) -> std::io::Result<(Vec<u8>, bool)> {
+    match &store.state_key {
+        Some(state_key) => state_key
+            .encrypt(export.into_bytes())
+            .map(|bytes| (bytes, true)),
+        None => Ok((export.into_bytes(), false)),
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::encryption::{is_encrypted, StateKey};
+    use crate::export::{export_file, export_json, word_frequencies_csv};
+    use crate::state::Store;
+    use std::collections::HashMap;
+
+    #[test]
+    fn csv_rows() {
+        let frequencies: HashMap<String, usize> = vec![
+            (String::from("rome"), 3),
+            (String::from("fall"), 3),
+            (String::from("say \"hi\", then"), 1),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            word_frequencies_csv(&frequencies),
+            "word,count\nfall,3\nrome,3\n\"say \"\"hi\"\", then\",1\n"
+        );
+    }
+
+    #[test]
+    fn export_files_encrypted_with_the_state() {
+        let mut store = Store::default();
+        let export = export_json(&store, None).unwrap();
+        let (plain, encrypted) = export_file(&store, export.clone()).unwrap();
+        assert!(!encrypted);
+        assert_eq!(plain, export.as_bytes());
+
+        let key = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+        store.state_key = Some(StateKey::from_hex(key).unwrap());
+        let (bytes, encrypted) = export_file(&store, export.clone()).unwrap();
+        assert!(encrypted && is_encrypted(&bytes));
+        let state_key = StateKey::from_hex(key).unwrap();
+        assert_eq!(state_key.decrypt(&bytes).unwrap(), export.as_bytes());
+    }
+}
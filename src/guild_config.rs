@@ -0,0 +1,504 @@
+use crate::language_parsing::WordRules;
+use crate::state::StoreData;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serenity::model::channel::Message;
+use serenity::model::id::{ChannelId, GuildId, RoleId, UserId};
+use serenity::prelude::Context;
+use std::collections::{HashMap, HashSet};
+
+/// Per-server settings, set by that server's admins (as opposed to [GeneralAppConfig] which
+/// belongs to whoever runs the bot)
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct GuildConfig {
+    // Decides which day a message counts towards, unset is UTC
+    #[serde(default)]
+    pub timezone: Option<Tz>,
+    // Kept here rather than on the channel's stats so settings survive deinit and rebuilds, and
+    // can be set before a channel is initialised
+    #[serde(default)]
+    pub channels: HashMap<ChannelId, ChannelConfig>,
+    // Accepted alongside the bot's global prefix, for servers where that clashes with another bot
+    #[serde(default)]
+    pub prefix: Option<String>,
+    // Automated posts are held back until these end, in the server's timezone
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+    // Alt account -> the main account its stats are shown under
+    #[serde(default)]
+    pub aliases: HashMap<UserId, UserId>,
+    // Named characters for roleplay servers, words are attributed to them as well as to players
+    #[serde(default)]
+    pub characters: CharacterRoster,
+    // Stats posted on the server name writers anonymously, only writers themselves see theirs
+    #[serde(default)]
+    pub privacy_mode: bool,
+    #[serde(default)]
+    pub shoutout: Option<ShoutoutConfig>,
+    // Which preset splits and counts words in this server's messages
+    #[serde(default)]
+    pub word_rules: WordRules,
+}
+
+impl GuildConfig {
+    pub fn timezone(&self) -> Tz {
+        self.timezone.unwrap_or(Tz::UTC)
+    }
+
+    pub fn channel(&self, channel_id: &ChannelId) -> ChannelConfig {
+        self.channels.get(channel_id).cloned().unwrap_or_default()
+    }
+
+    pub fn local_date(&self, time: &DateTime<Utc>) -> NaiveDate {
+        time.with_timezone(&self.timezone()).date().naive_local()
+    }
+
+    /// The account stats are shown under, the user themselves unless they're a linked alt
+    pub fn main_account(&self, user_id: &UserId) -> UserId {
+        *self.aliases.get(user_id).unwrap_or(user_id)
+    }
+
+    /// Links [alt] to [main], or to whoever [main] is itself an alt of so links never chain.
+    /// Returns the main account it ended up linked to
+    pub fn link_alias(&mut self, alt: UserId, main: UserId) -> Result<UserId, String> {
+        let main = self.main_account(&main);
+        if main == alt {
+            return Err(String::from("An account can't be an alias of itself"));
+        }
+        for linked_main in self.aliases.values_mut() {
+            if *linked_main == alt {
+                *linked_main = main;
+            }
+        }
+        self.aliases.insert(alt, main);
+        Ok(main)
+    }
+
+    pub fn is_quiet_at(&self, time: &DateTime<Utc>) -> bool {
+        self.quiet_hours.as_ref().is_some_and(|quiet_hours| {
+            quiet_hours.contains(&time.with_timezone(&self.timezone()).time())
+        })
+    }
+}
+
+/// A daily window, which runs over midnight if it ends earlier than it starts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: &NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= *time && *time < self.end
+        } else {
+            self.start <= *time || *time < self.end
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Character {
+    pub name: String,
+    pub player: Option<UserId>,
+}
+
+/// Characters on a server, looked up by name regardless of case
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CharacterRoster {
+    characters: HashMap<String, Character>,
+}
+
+impl CharacterRoster {
+    pub fn get(&self, name: &str) -> Option<&Character> {
+        self.characters.get(&name.to_lowercase())
+    }
+
+    pub fn insert(&mut self, character: Character) {
+        self.characters
+            .insert(character.name.to_lowercase(), character);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Character> {
+        self.characters.remove(&name.to_lowercase())
+    }
+
+    pub fn len(&self) -> usize {
+        self.characters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.characters.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Character> {
+        self.characters.values()
+    }
+
+    /// The character a message was written as: a leading tag like [Kira], then a proxy bot
+    /// posting under the character's name, then whoever the channel assigns to the author
+    pub fn attribute(
+        &self,
+        message: &Message,
+        channel_config: &ChannelConfig,
+    ) -> Option<&Character> {
+        let tagged = message
+            .content
+            .trim_start()
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once(']'))
+            .and_then(|(name, _)| self.get(name.trim()));
+        let proxied = || match message.webhook_id.is_some() || message.author.bot {
+            true => self.get(&message.author.name),
+            false => None,
+        };
+        let assigned = || {
+            channel_config
+                .characters
+                .get(&message.author.id)
+                .and_then(|name| self.get(name))
+        };
+        tagged.or_else(proxied).or_else(assigned)
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ChannelConfig {
+    // Messages with fewer words than this are left out of stats, e.g. "lol" or dice bot triggers
+    #[serde(default)]
+    pub min_words: usize,
+    // Companion bots (dice rollers, music bots) left out of stats, along with the messages
+    // invoking them, which are recognised by the bot's command prefix if it was given one
+    #[serde(default)]
+    pub ignored_bots: HashMap<UserId, Option<String>>,
+    // Content reports are opt-in, servers that don't want them never see them
+    #[serde(default)]
+    pub content_reports: bool,
+    // Custom word categories for content reports, by category name
+    #[serde(default)]
+    pub watch_lists: HashMap<String, HashSet<String>>,
+    // Off unless set, nudges are posted once the channel has been quiet for this long
+    #[serde(default)]
+    pub nudge: Option<NudgeConfig>,
+    // Player -> the character their untagged posts in this channel are written as
+    #[serde(default)]
+    pub characters: HashMap<UserId, String>,
+    // Off unless set, for roleplay channels where writers post in a fixed rotation
+    #[serde(default)]
+    pub turn_order: Option<TurnOrder>,
+    // Off unless set, a word cloud of the period's messages is posted on this schedule
+    #[serde(default)]
+    pub wordcloud_schedule: Option<WordCloudPeriod>,
+}
+
+/// A weekly post naming whoever wrote the most across the server's channels over the week before
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShoutoutConfig {
+    pub channel_id: ChannelId,
+    pub weekday: Weekday,
+    // With {writer} and {words} filled in, the default wording if not set
+    #[serde(default)]
+    pub message: Option<String>,
+    // Given to the week's top writers, and taken back from the last week's
+    #[serde(default)]
+    pub role: Option<RoleId>,
+}
+
+/// How often a scheduled word cloud is posted, each covers the messages since the last
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum WordCloudPeriod {
+    Daily,
+    Weekly(Weekday),
+}
+
+impl WordCloudPeriod {
+    pub fn days(&self) -> i64 {
+        match self {
+            Self::Daily => 1,
+            Self::Weekly(_) => 7,
+        }
+    }
+
+    pub fn is_due_on(&self, date: &NaiveDate) -> bool {
+        match self {
+            Self::Daily => true,
+            Self::Weekly(weekday) => date.weekday() == *weekday,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Daily => String::from("every day"),
+            Self::Weekly(weekday) => format!("every {}", weekday_name(weekday)),
+        }
+    }
+}
+
+pub fn weekday_name(weekday: &Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnOrder {
+    pub writers: Vec<UserId>,
+    // Ping whoever is next once the current writer has posted
+    pub ping_next: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NudgeConfig {
+    pub after_days: u32,
+    // DM whoever posted last rather than posting in the channel
+    pub dm_last_poster: bool,
+}
+
+// Built in watch list for content reports, servers can add their own categories on top
+const PROFANITY: [&str; 12] = [
+    "arse", "ass", "bastard", "bitch", "bollocks", "crap", "cunt", "damn", "dick", "fuck", "piss",
+    "shit",
+];
+
+impl ChannelConfig {
+    pub fn counts_message(&self, message: &Message, rules: &WordRules) -> bool {
+        let from_ignored_bot = self.ignored_bots.contains_key(&message.author.id);
+        let invokes_ignored_bot = self
+            .ignored_bots
+            .values()
+            .flatten()
+            // An empty prefix would match every message, leaving the whole channel out
+            .filter(|prefix| !prefix.trim().is_empty())
+            .any(|prefix| message.content.starts_with(prefix.as_str()));
+        let long_enough = self.min_words == 0
+            || crate::stats::count_words(&message.content, rules) >= self.min_words;
+        !from_ignored_bot && !invokes_ignored_bot && long_enough
+    }
+
+    /// The custom watch lists, along with the built in profanity list
+    pub fn content_categories(&self) -> HashMap<String, HashSet<String>> {
+        let mut categories = self.watch_lists.clone();
+        categories
+            .entry(String::from("profanity"))
+            .or_default()
+            .extend(PROFANITY.iter().map(|word| word.to_string()));
+        categories
+    }
+}
+
+/// Applies [f] to the channel's config, creating it if needed
+pub async fn update_channel_config<F: FnOnce(&mut ChannelConfig)>(
+    ctx: &Context,
+    server_id: &GuildId,
+    channel_id: &ChannelId,
+    f: F,
+) {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let mut store = store_lock.write().unwrap();
+    f(store
+        .get_server_data_mut_maybe_create(server_id)
+        .config
+        .channels
+        .entry(*channel_id)
+        .or_default());
+}
+
+// Bump when a field is removed or changes meaning, so old exports can be told apart
+pub const GUILD_CONFIG_EXPORT_VERSION: u32 = 1;
+
+/// Portable copy of a server's settings, to keep as a backup or to clone a setup onto another
+/// server. Channels are matched up by id and then by name when imported
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GuildConfigExport {
+    pub version: u32,
+    #[serde(default)]
+    pub timezone: Option<Tz>,
+    #[serde(default)]
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+    #[serde(default)]
+    pub word_rules: WordRules,
+    #[serde(default)]
+    pub characters: Vec<Character>,
+    #[serde(default)]
+    pub channels: Vec<ChannelConfigExport>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChannelConfigExport {
+    pub channel_id: ChannelId,
+    pub channel_name: String,
+    // Whether the channel was initialised, initialising is too expensive to do on import
+    #[serde(default)]
+    pub tracked: bool,
+    #[serde(default)]
+    pub config: ChannelConfig,
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::bench_corpus::synthetic_messages;
+    use crate::guild_config::{
+        ChannelConfig, ChannelConfigExport, Character, CharacterRoster, GuildConfig,
+        GuildConfigExport, QuietHours,
+    };
+    use crate::language_parsing::WordRules;
+    use chrono::{NaiveTime, TimeZone, Utc};
+    use serenity::model::channel::Message;
+    use serenity::model::id::{ChannelId, UserId};
+
+    #[test]
+    fn export_round_trip() {
+        let mut config = ChannelConfig {
+            min_words: 3,
+            ..ChannelConfig::default()
+        };
+        config
+            .ignored_bots
+            .insert(UserId(7), Some(String::from("!r")));
+        let export = GuildConfigExport {
+            version: 1,
+            timezone: Some(chrono_tz::Europe::London),
+            prefix: Some(String::from("?")),
+            quiet_hours: None,
+            word_rules: WordRules::NaNoWriMo,
+            characters: vec![],
+            channels: vec![ChannelConfigExport {
+                channel_id: ChannelId(5),
+                channel_name: String::from("the-fall-of-rome"),
+                tracked: true,
+                config,
+            }],
+        };
+        let json = serde_json::to_string(&export).unwrap();
+        let imported: GuildConfigExport = serde_json::from_str(&json).unwrap();
+        assert_eq!(imported.timezone, Some(chrono_tz::Europe::London));
+        assert_eq!(imported.word_rules, WordRules::NaNoWriMo);
+        assert_eq!(imported.prefix, Some(String::from("?")));
+        assert_eq!(imported.channels[0].config.min_words, 3);
+        assert_eq!(
+            imported.channels[0].config.ignored_bots.get(&UserId(7)),
+            Some(&Some(String::from("!r")))
+        );
+    }
+
+    #[test]
+    fn aliases_never_chain() {
+        let mut config = GuildConfig::default();
+        assert_eq!(config.link_alias(UserId(2), UserId(1)), Ok(UserId(1)));
+        // Linking to an alt links to its main instead
+        assert_eq!(config.link_alias(UserId(3), UserId(2)), Ok(UserId(1)));
+        // And an account with alts can become an alt itself, taking them along
+        assert_eq!(config.link_alias(UserId(1), UserId(4)), Ok(UserId(4)));
+        assert_eq!(config.main_account(&UserId(2)), UserId(4));
+        assert_eq!(config.main_account(&UserId(3)), UserId(4));
+        assert!(config.link_alias(UserId(4), UserId(3)).is_err());
+        assert_eq!(config.main_account(&UserId(5)), UserId(5));
+    }
+
+    #[test]
+    fn ignored_bot_prefixes() {
+        let message = synthetic_messages(1, 1).pop().unwrap();
+        let mut config = ChannelConfig::default();
+        config
+            .ignored_bots
+            .insert(UserId(7), Some(String::from(" ")));
+        config.ignored_bots.insert(UserId(8), Some(String::new()));
+        assert!(config.counts_message(&message, &WordRules::default()));
+        let prefix: String = message.content.chars().take(2).collect();
+        config.ignored_bots.insert(UserId(9), Some(prefix));
+        assert!(!config.counts_message(&message, &WordRules::default()));
+    }
+
+    #[test]
+    fn short_messages_left_out() {
+        let mut message = synthetic_messages(1, 1).pop().unwrap();
+        let mut config = ChannelConfig::default();
+        message.content = String::from("Yes!");
+        assert!(config.counts_message(&message, &WordRules::default()));
+        config.min_words = 3;
+        assert!(!config.counts_message(&message, &WordRules::default()));
+        // Numbers and emoji aren't words
+        message.content = String::from("Roll 20 😀");
+        assert!(!config.counts_message(&message, &WordRules::default()));
+        message.content = String::from("The cat sat.");
+        assert!(config.counts_message(&message, &WordRules::default()));
+    }
+
+    #[test]
+    fn character_attribution() {
+        let mut characters = CharacterRoster::default();
+        for name in ["Kira", "Old Tom"] {
+            characters.insert(Character {
+                name: String::from(name),
+                player: Some(UserId(1)),
+            });
+        }
+        let mut channel_config = ChannelConfig::default();
+        let mut message = synthetic_messages(1, 1).pop().unwrap();
+        let attributed = |message: &Message, channel_config: &ChannelConfig| {
+            characters
+                .attribute(message, channel_config)
+                .map(|character| character.name.clone())
+        };
+        assert_eq!(attributed(&message, &channel_config), None);
+
+        channel_config
+            .characters
+            .insert(message.author.id, String::from("Old Tom"));
+        assert_eq!(
+            attributed(&message, &channel_config).as_deref(),
+            Some("Old Tom")
+        );
+
+        // A tag wins over the channel's assignment
+        message.content = String::from("[kira] She drew her sword.");
+        assert_eq!(
+            attributed(&message, &channel_config).as_deref(),
+            Some("Kira")
+        );
+
+        message.content = String::from("Untagged");
+        message.author.bot = true;
+        message.author.name = String::from("Kira");
+        assert_eq!(
+            attributed(&message, &ChannelConfig::default()).as_deref(),
+            Some("Kira")
+        );
+    }
+
+    #[test]
+    fn quiet_hours() {
+        let overnight = QuietHours {
+            start: NaiveTime::from_hms(22, 0, 0),
+            end: NaiveTime::from_hms(7, 30, 0),
+        };
+        assert!(overnight.contains(&NaiveTime::from_hms(23, 0, 0)));
+        assert!(overnight.contains(&NaiveTime::from_hms(3, 0, 0)));
+        assert!(!overnight.contains(&NaiveTime::from_hms(7, 30, 0)));
+        assert!(!overnight.contains(&NaiveTime::from_hms(12, 0, 0)));
+        let config = GuildConfig {
+            timezone: Some(chrono_tz::America::New_York),
+            quiet_hours: Some(overnight),
+            ..GuildConfig::default()
+        };
+        // 03:00 UTC is 23:00 the evening before in New York
+        assert!(config.is_quiet_at(&Utc.ymd(2021, 6, 1).and_hms(3, 0, 0)));
+        assert!(!config.is_quiet_at(&Utc.ymd(2021, 6, 1).and_hms(14, 0, 0)));
+    }
+}
@@ -0,0 +1,203 @@
+use crate::state::{Store, StoreData, StoryKey};
+use log::warn;
+use serenity::futures::FutureExt;
+use serenity::model::channel::Message;
+use serenity::model::id::UserId;
+use serenity::prelude::{Context, TypeMapKey};
+use std::sync::{Arc, RwLock};
+use tokio::sync::mpsc;
+
+// Past this many messages waiting to be counted, new ones wait for room rather than the queue
+// growing without bound while the store is busy
+const QUEUE_CAPACITY: usize = 10_000;
+// Caps how long each batch holds the store's write lock, so commands reading stats aren't held up
+// behind a burst. Kept under tokio's budget of 128 polls a turn, past which the queue reads as
+// empty with messages still waiting
+const MAX_BATCH: usize = 100;
+
+pub type QueuedMessage = (StoryKey, Message);
+
+/// Live messages go through here to be counted in batches, so a busy server takes the store's
+/// write lock once per batch rather than once for every message
+pub struct MessageQueueData;
+
+impl TypeMapKey for MessageQueueData {
+    type Value = mpsc::Sender<QueuedMessage>;
+}
+
+pub fn message_queue() -> (mpsc::Sender<QueuedMessage>, mpsc::Receiver<QueuedMessage>) {
+    mpsc::channel(QUEUE_CAPACITY)
+}
+
+/// Whether [message] came from another bot or a webhook. The framework passes these on so proxy
+/// bots' posts are counted for their characters, but they never run commands
+pub fn from_bot_or_webhook(message: &Message) -> bool {
+    message.author.bot || message.webhook_id.is_some()
+}
+
+/// Whether a live [message] should be counted, anything but the bot's own posts
+pub fn counted_live(message: &Message, own_id: &UserId) -> bool {
+    message.author.id != *own_id
+}
+
+/// Queues [message] to be counted by [ingest_worker], counting it straight away if the worker has
+/// stopped
+pub async fn queue_message(ctx: &Context, story_key: StoryKey, message: &Message) {
+    let sender = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<MessageQueueData>()
+            .expect("Expected MessageQueueData in TypeMap.")
+            .clone()
+    };
+    if let Err(mpsc::error::SendError((story_key, message))) =
+        sender.send((story_key, message.clone())).await
+    {
+        warn!("Message queue closed, counting {} directly", message.id);
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let mut store = store_lock.write().unwrap();
+        store.process_message(&story_key, &message);
+    }
+}
+
+/// Everything already queued, up to [MAX_BATCH], without waiting for more
+fn drain_ready(receiver: &mut mpsc::Receiver<QueuedMessage>, batch: &mut Vec<QueuedMessage>) {
+    while batch.len() < MAX_BATCH {
+        match receiver.recv().now_or_never() {
+            Some(Some(queued)) => batch.push(queued),
+            Some(None) | None => break,
+        }
+    }
+}
+
+/// Waits for a message, then counts it along with any others queued up meanwhile. Runs until every
+/// sender is dropped
+pub async fn ingest_worker(
+    store_lock: Arc<RwLock<Store>>,
+    mut receiver: mpsc::Receiver<QueuedMessage>,
+) {
+    let mut batch = Vec::with_capacity(MAX_BATCH);
+    while let Some(queued) = receiver.recv().await {
+        batch.push(queued);
+        drain_ready(&mut receiver, &mut batch);
+        {
+            let mut store = store_lock.write().unwrap();
+            for (story_key, message) in batch.drain(..) {
+                store.process_message(&story_key, &message);
+            }
+        }
+        // Starts the next batch with a fresh budget, and gives other tasks a turn between them
+        let _ = tokio::task::yield_now().await;
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::bench_corpus::synthetic_messages;
+    use crate::guild_config::Character;
+    use crate::ingest::{
+        counted_live, drain_ready, from_bot_or_webhook, ingest_worker, message_queue, MAX_BATCH,
+    };
+    use crate::language_parsing::WordRules;
+    use crate::state::{ChannelData, Store};
+    use chrono_tz::Tz;
+    use serenity::model::id::{ChannelId, GuildId, UserId, WebhookId};
+    use std::sync::{Arc, RwLock};
+
+    #[tokio::test]
+    async fn queued_messages_are_counted() {
+        let messages = synthetic_messages(MAX_BATCH + 20, 3);
+        let story_key = (GuildId(1), ChannelId(1));
+        let mut store = Store::default();
+        store.insert_channel_data_maybe_create_server_data(&story_key, ChannelData::default());
+        store.finish_replay();
+        let store_lock = Arc::new(RwLock::new(store));
+
+        let (sender, mut receiver) = message_queue();
+        for message in messages.iter() {
+            sender.send((story_key, message.clone())).await.unwrap();
+        }
+        let _ = tokio::task::yield_now().await;
+        let mut batch = vec![];
+        drain_ready(&mut receiver, &mut batch);
+        assert_eq!(batch.len(), MAX_BATCH);
+        for queued in batch {
+            sender.send(queued).await.unwrap();
+        }
+        drop(sender);
+        ingest_worker(Arc::clone(&store_lock), receiver).await;
+
+        let mut expected = ChannelData::default();
+        for message in messages.iter() {
+            expected.update(message, &Tz::UTC, &WordRules::default());
+        }
+        let store = store_lock.read().unwrap();
+        let channel_data = store.get_channel_data(&story_key).unwrap();
+        assert_eq!(
+            channel_data.general_stats.word_count,
+            expected.general_stats.word_count
+        );
+        for message in messages.iter().take(3) {
+            assert_eq!(
+                channel_data
+                    .get_user_by_id(&message.author.id)
+                    .unwrap()
+                    .word_count,
+                expected
+                    .get_user_by_id(&message.author.id)
+                    .unwrap()
+                    .word_count
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn proxied_messages_are_counted_for_their_character() {
+        let story_key = (GuildId(1), ChannelId(1));
+        let mut store = Store::default();
+        store.insert_channel_data_maybe_create_server_data(&story_key, ChannelData::default());
+        store.finish_replay();
+        store
+            .get_server_data_mut_maybe_create(&story_key.0)
+            .config
+            .characters
+            .insert(Character {
+                name: String::from("Kira"),
+                player: Some(UserId(1)),
+            });
+        let store_lock = Arc::new(RwLock::new(store));
+
+        let mut messages = synthetic_messages(2, 1);
+        let own_id = UserId(99);
+        messages[0].webhook_id = Some(WebhookId(5));
+        messages[0].author.bot = true;
+        messages[0].author.id = UserId(50);
+        messages[0].author.name = String::from("Kira");
+        messages[1].author.id = own_id;
+        assert!(from_bot_or_webhook(&messages[0]));
+        assert!(!counted_live(&messages[1], &own_id));
+
+        let (sender, receiver) = message_queue();
+        for message in messages
+            .iter()
+            .filter(|message| counted_live(message, &own_id))
+        {
+            sender.send((story_key, message.clone())).await.unwrap();
+        }
+        drop(sender);
+        ingest_worker(Arc::clone(&store_lock), receiver).await;
+
+        let store = store_lock.read().unwrap();
+        let channel_data = store.get_channel_data(&story_key).unwrap();
+        let kira = channel_data.character_stats.get("kira").unwrap();
+        assert!(kira.word_count > 0);
+        assert_eq!(channel_data.general_stats.word_count, kira.word_count);
+        assert!(channel_data.get_user_by_id(&own_id).is_none());
+    }
+}
@@ -0,0 +1,153 @@
+use crate::config::GeneralAppConfigData;
+use crate::state::StoreData;
+use crate::stats::Anomaly;
+use chrono::{Duration as ChronoDuration, Utc};
+use log::{error, info};
+use serenity::model::prelude::*;
+use serenity::prelude::Context;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+const INTEGRITY_CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+// Keeps the report to one discord message, the rest are just counted
+const MAX_LISTED_ANOMALIES: usize = 15;
+
+/// [anomalies] are paired with the name of the server they were found on
+fn format_report(anomalies: &[(String, Anomaly)]) -> String {
+    if anomalies.is_empty() {
+        return String::from("Integrity check found no problems");
+    }
+    let repaired = anomalies
+        .iter()
+        .filter(|(_, anomaly)| anomaly.repaired)
+        .count();
+    let mut lines = vec![format!(
+        "Integrity check found {} problem(s), {} repaired",
+        anomalies.len(),
+        repaired
+    )];
+    for (server_name, anomaly) in anomalies.iter().take(MAX_LISTED_ANOMALIES) {
+        lines.push(format!(
+            "{} {}: {}",
+            match anomaly.repaired {
+                true => "🔧",
+                false => "❌",
+            },
+            server_name,
+            anomaly.description
+        ));
+    }
+    if anomalies.len() > MAX_LISTED_ANOMALIES {
+        lines.push(format!(
+            "and {} more",
+            anomalies.len() - MAX_LISTED_ANOMALIES
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Checks the whole store, repairing what it can, and returns a report. Also returns whether
+/// anything was found
+pub async fn verify_store(ctx: &Context) -> (String, bool) {
+    // A day of slack, the servers furthest ahead of UTC are already on tomorrow
+    let latest_day = (Utc::now() + ChronoDuration::days(1)).naive_utc().date();
+    let anomalies = {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let mut store = store_lock.write().unwrap();
+        store.check_integrity(latest_day)
+    };
+    let mut server_names: HashMap<GuildId, String> = HashMap::new();
+    for (server_id, _) in anomalies.iter() {
+        if !server_names.contains_key(server_id) {
+            let server_name = server_id
+                .name(ctx)
+                .await
+                .unwrap_or_else(|| server_id.to_string());
+            server_names.insert(*server_id, server_name);
+        }
+    }
+    let named: Vec<(String, Anomaly)> = anomalies
+        .into_iter()
+        .map(|(server_id, anomaly)| (server_names[&server_id].clone(), anomaly))
+        .collect();
+    (format_report(&named), !named.is_empty())
+}
+
+/// Posts to the configured error channel, or DMs the bot admin if there isn't one
+async fn report_problems(ctx: &Context, report: &str) {
+    let (error_channel, bot_admin) = {
+        let config_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<GeneralAppConfigData>()
+                .expect("Expected GeneralAppConfigData in TypeMap.")
+                .clone()
+        };
+        let config = config_lock.read().unwrap();
+        (config.error_channel, config.bot_admin)
+    };
+    let sent = match (error_channel, bot_admin) {
+        (Some(channel_id), _) => channel_id.say(&ctx.http, report).await.map(|_| ()),
+        (None, Some(user_id)) => match user_id.to_user(ctx).await {
+            Ok(user) => user
+                .direct_message(ctx, |m| m.content(report))
+                .await
+                .map(|_| ()),
+            Err(e) => Err(e),
+        },
+        (None, None) => {
+            error!("Nowhere to report integrity problems to, set error_channel or bot_admin");
+            Ok(())
+        }
+    };
+    if let Err(why) = sent {
+        error!("Failed reporting integrity problems: {:?}", why);
+    }
+}
+
+pub async fn integrity_worker(ctx: Arc<Context>) {
+    loop {
+        tokio::time::sleep(INTEGRITY_CHECK_INTERVAL).await;
+        let (report, found_problems) = verify_store(&ctx).await;
+        info!("{}", report);
+        if found_problems {
+            report_problems(&ctx, &report).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::integrity::{format_report, MAX_LISTED_ANOMALIES};
+    use crate::stats::Anomaly;
+
+    #[test]
+    fn report_lists_and_counts() {
+        assert_eq!(format_report(&[]), "Integrity check found no problems");
+        let anomalies: Vec<(String, Anomaly)> = (0..MAX_LISTED_ANOMALIES + 2)
+            .map(|i| match i {
+                0 => (
+                    String::from("Rome"),
+                    Anomaly::unrepaired(String::from("daily counts too high")),
+                ),
+                _ => (
+                    String::from("Rome"),
+                    Anomaly::repaired(String::from("removed 1 counts of zero")),
+                ),
+            })
+            .collect();
+        let report = format_report(&anomalies);
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[0], "Integrity check found 17 problem(s), 16 repaired");
+        assert_eq!(lines[1], "❌ Rome: daily counts too high");
+        assert_eq!(lines.len(), MAX_LISTED_ANOMALIES + 2);
+        assert_eq!(lines.last(), Some(&"and 2 more"));
+    }
+}
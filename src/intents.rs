@@ -0,0 +1,178 @@
+use crate::config::{GeneralAppConfigData, IntentsConfig};
+use log::{info, warn};
+use serenity::client::bridge::gateway::GatewayIntents;
+use serenity::model::channel::{Message, MessageType};
+use serenity::prelude::{Context, TypeMapKey};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Forwarded messages and polls arrive looking empty too, so it takes this many in a row before
+// content is taken to be missing
+const MISSING_CONTENT_RUN: usize = 5;
+
+/// Whether message bodies are reaching the bot. Starts as `message_content` in [IntentsConfig] says,
+/// cleared after [MISSING_CONTENT_RUN] empty messages in a row and set again once one with content
+/// arrives. Messages aren't counted while it's cleared
+pub struct MessageContentData;
+
+impl TypeMapKey for MessageContentData {
+    type Value = Arc<MessageContent>;
+}
+
+pub struct MessageContent {
+    available: AtomicBool,
+    missing_in_a_row: AtomicUsize,
+}
+
+impl MessageContent {
+    pub fn new(available: bool) -> Self {
+        Self {
+            available: AtomicBool::new(available),
+            missing_in_a_row: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn available(&self) -> bool {
+        self.available.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether content has just gone missing or come back with [message], if either
+    fn observe(&self, message: &Message) -> Option<bool> {
+        if is_missing_content(message) {
+            let missing = self.missing_in_a_row.fetch_add(1, Ordering::Relaxed) + 1;
+            match missing >= MISSING_CONTENT_RUN && self.available.swap(false, Ordering::Relaxed) {
+                true => Some(false),
+                false => None,
+            }
+        } else {
+            self.missing_in_a_row.store(0, Ordering::Relaxed);
+            match self.available.swap(true, Ordering::Relaxed) {
+                true => None,
+                false => Some(true),
+            }
+        }
+    }
+}
+
+pub fn gateway_intents(config: &IntentsConfig) -> GatewayIntents {
+    let mut intents = GatewayIntents::GUILDS
+        | GatewayIntents::GUILD_MESSAGES
+        | GatewayIntents::GUILD_MESSAGE_REACTIONS
+        | GatewayIntents::DIRECT_MESSAGES;
+    // Privileged, needed to keep departures and display names up to date
+    if config.guild_members {
+        intents |= GatewayIntents::GUILD_MEMBERS;
+    }
+    intents
+}
+
+/// A regular message with nothing in it at all can't have been sent by a person, so its content
+/// has been withheld from us
+pub fn is_missing_content(message: &Message) -> bool {
+    matches!(
+        message.kind,
+        MessageType::Regular | MessageType::InlineReply
+    ) && message.content.is_empty()
+        && message.attachments.is_empty()
+        && message.embeds.is_empty()
+        && message.stickers.is_empty()
+}
+
+pub async fn message_content_available(ctx: &Context) -> bool {
+    let data_read = ctx.data.read().await;
+    data_read
+        .get::<MessageContentData>()
+        .expect("Expected MessageContentData in TypeMap.")
+        .available()
+}
+
+/// Stops counting messages once they keep turning up without their content, and lets the bot
+/// admin know why. Counting starts again with the next message that has content
+pub async fn observe_message_content(ctx: &Context, message: &Message) {
+    let changed = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<MessageContentData>()
+            .expect("Expected MessageContentData in TypeMap.")
+            .observe(message)
+    };
+    match changed {
+        Some(true) => {
+            info!("Message content is arriving again, counting messages");
+            return;
+        }
+        Some(false) => (),
+        None => return,
+    }
+    warn!("Message content looks to be unavailable, no longer counting messages");
+    let bot_admin = {
+        let config_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<GeneralAppConfigData>()
+                .expect("Expected GeneralAppConfigData in TypeMap.")
+                .clone()
+        };
+        let bot_admin = config_lock.read().unwrap().bot_admin;
+        bot_admin
+    };
+    if let Some(user_id) = bot_admin {
+        let warning =
+            "Messages are arriving without their content, so I've stopped counting them. \
+            Check the message content intent is enabled for the bot in the developer portal, I'll \
+            start counting again once content arrives. Commands still work by mentioning me";
+        let sent = match user_id.to_user(ctx).await {
+            Ok(user) => user
+                .direct_message(ctx, |m| m.content(warning))
+                .await
+                .map(|_| ()),
+            Err(e) => Err(e),
+        };
+        if let Err(why) = sent {
+            println!(
+                "Error warning bot admin about missing message content: {:?}",
+                why
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::bench_corpus::synthetic_messages;
+    use crate::intents::{is_missing_content, MessageContent, MISSING_CONTENT_RUN};
+    use serenity::model::channel::MessageType;
+
+    #[test]
+    fn missing_content() {
+        let mut message = synthetic_messages(1, 1).pop().unwrap();
+        assert!(!is_missing_content(&message));
+        message.content.clear();
+        assert!(is_missing_content(&message));
+        // System messages are empty anyway
+        message.kind = MessageType::PinsAdd;
+        assert!(!is_missing_content(&message));
+    }
+
+    #[test]
+    fn degrades_after_a_run_and_recovers() {
+        let message = synthetic_messages(1, 1).pop().unwrap();
+        let mut empty = message.clone();
+        empty.content.clear();
+        let content = MessageContent::new(true);
+        for _ in 1..MISSING_CONTENT_RUN {
+            assert_eq!(content.observe(&empty), None);
+        }
+        // A message with content breaks the run
+        assert_eq!(content.observe(&message), None);
+        for _ in 1..MISSING_CONTENT_RUN {
+            assert_eq!(content.observe(&empty), None);
+        }
+        assert!(content.available());
+        assert_eq!(content.observe(&empty), Some(false));
+        assert_eq!(content.observe(&empty), None);
+        assert!(!content.available());
+        assert_eq!(content.observe(&message), Some(true));
+        assert!(content.available());
+    }
+}
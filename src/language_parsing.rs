@@ -1,11 +1,136 @@
-//This tokenising is mostly taken from https://github.com/christophertrml/rs-natural
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// How a server wants words counted, as one of a few named presets
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WordRules {
+    /// How the bot has always counted: hyphens split words, contractions and em-dash joins don't,
+    /// and numbers aren't words
+    #[default]
+    Standard,
+    /// Closer to a word processor's count, which is what NaNoWriMo's word count validator sees
+    NaNoWriMo,
+    Strict,
+    Loose,
+}
+
+impl WordRules {
+    pub const PRESETS: [WordRules; 4] = [
+        WordRules::Standard,
+        WordRules::NaNoWriMo,
+        WordRules::Strict,
+        WordRules::Loose,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WordRules::Standard => "standard",
+            WordRules::NaNoWriMo => "nanowrimo",
+            WordRules::Strict => "strict",
+            WordRules::Loose => "loose",
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            WordRules::Standard => "hyphenated words count as one per part, numbers aren't counted",
+            WordRules::NaNoWriMo => {
+                "like a word processor, hyphenated words and em-dash joins are one word and numbers count"
+            }
+            WordRules::Strict => {
+                "hyphenated words are one word, em-dash joins are split and numbers aren't counted"
+            }
+            WordRules::Loose => {
+                "hyphenated words, em-dash joins and contractions are all split up and numbers count"
+            }
+        }
+    }
+
+    fn splits_on(&self, c: char) -> bool {
+        match c {
+            '-' => matches!(self, WordRules::Standard | WordRules::Loose),
+            '—' | '–' => matches!(self, WordRules::Strict | WordRules::Loose),
+            '\'' => matches!(self, WordRules::Loose),
+            c => Splitter::is_match(c),
+        }
+    }
+
+    pub fn counts_numbers(&self) -> bool {
+        matches!(self, WordRules::NaNoWriMo | WordRules::Loose)
+    }
+}
+
+impl FromStr for WordRules {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WordRules::PRESETS
+            .iter()
+            .find(|rules| rules.name().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| {
+                format!(
+                    "Unknown word counting rules, expected one of {}",
+                    WordRules::PRESETS
+                        .iter()
+                        .map(WordRules::name)
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
 pub fn tokenise(text: &str) -> Vec<&str> {
-    text.split(Splitter::is_match)
-        .filter(|s| !s.is_empty())
+    tokenise_with_rules(text, &WordRules::Standard)
+}
+
+//This tokenising is mostly taken from https://github.com/christophertrml/rs-natural
+pub fn tokenise_with_rules<'a>(text: &'a str, rules: &WordRules) -> Vec<&'a str> {
+    text.split(|c| rules.splits_on(c))
         .map(Splitter::strip_leading_trailing_apostrophes)
+        // After stripping, so lone apostrophes don't leave empty tokens
+        .filter(|s| !s.is_empty())
         .collect()
 }
 
+/// Estimated from groups of vowels, less a silent trailing e. Not exact, but good enough to average
+/// over a whole post
+pub fn count_syllables(word: &str) -> usize {
+    let word = word.to_lowercase();
+    let is_vowel = |c: char| matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+    let chars: Vec<char> = word.chars().filter(|c| c.is_alphabetic()).collect();
+    let mut syllables = chars
+        .iter()
+        .enumerate()
+        .filter(|(i, c)| is_vowel(**c) && (*i == 0 || !is_vowel(chars[i - 1])))
+        .count();
+    if syllables > 1 && chars.ends_with(&['e']) && !chars.ends_with(&['l', 'e']) {
+        syllables -= 1;
+    }
+    syllables.max(1)
+}
+
+/// Flesch reading ease, roughly 100 for very easy text down to 0 and below for very hard. [None]
+/// for text without any words
+pub fn reading_ease(text: &str) -> Option<f64> {
+    let words: Vec<&str> = tokenise(text)
+        .into_iter()
+        .filter(|word| word.chars().any(char::is_alphabetic))
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+    let sentences = text
+        .split(['.', '!', '?', '\n'])
+        .filter(|sentence| sentence.chars().any(char::is_alphabetic))
+        .count()
+        .max(1);
+    let syllables: usize = words.iter().map(|word| count_syllables(word)).sum();
+    let words = words.len() as f64;
+    Some(206.835 - 1.015 * (words / sentences as f64) - 84.6 * (syllables as f64 / words))
+}
+
 struct Splitter;
 
 impl Splitter {
@@ -33,7 +158,9 @@ impl Splitter {
 
 #[cfg(test)]
 mod testing {
-    use crate::language_parsing::tokenise;
+    use crate::language_parsing::{
+        count_syllables, reading_ease, tokenise, tokenise_with_rules, WordRules,
+    };
 
     #[test]
     fn basic_tokenising() {
@@ -61,4 +188,126 @@ mod testing {
             vec!["the", "cats", "they're", "sat", "on", "the", "mat"]
         );
     }
+
+    #[test]
+    fn tokenising_with_rules() {
+        let input = "a well-known em—dash, they're";
+        let tokens = |rules| tokenise_with_rules(input, &rules);
+        assert_eq!(tokens(WordRules::Standard), tokenise(input));
+        assert_eq!(
+            tokens(WordRules::Standard),
+            vec!["a", "well", "known", "em—dash", "they're"]
+        );
+        assert_eq!(
+            tokens(WordRules::NaNoWriMo),
+            vec!["a", "well-known", "em—dash", "they're"]
+        );
+        assert_eq!(
+            tokens(WordRules::Strict),
+            vec!["a", "well-known", "em", "dash", "they're"]
+        );
+        assert_eq!(
+            tokens(WordRules::Loose),
+            vec!["a", "well", "known", "em", "dash", "they", "re"]
+        );
+        assert_eq!("NaNoWriMo".parse(), Ok(WordRules::NaNoWriMo));
+        assert!("lenient".parse::<WordRules>().is_err());
+    }
+
+    #[test]
+    fn syllables() {
+        assert_eq!(count_syllables("cat"), 1);
+        assert_eq!(count_syllables("table"), 2);
+        assert_eq!(count_syllables("stone"), 1);
+        assert_eq!(count_syllables("Beautiful"), 3);
+        assert_eq!(count_syllables("the"), 1);
+        assert_eq!(count_syllables("hmm"), 1);
+    }
+
+    #[test]
+    fn readability() {
+        assert_eq!(reading_ease("*** 123"), None);
+        let easy = reading_ease("The cat sat on the mat. It was a good cat.").unwrap();
+        let hard = reading_ease(
+        "Notwithstanding considerable institutional opposition, the administration implemented comprehensive regulatory modifications",
+    )
+    .unwrap();
+        assert!(easy > 90.0);
+        assert!(hard < 0.0);
+    }
+}
+
+#[cfg(test)]
+mod test_messy_messages {
+    use crate::language_parsing::{tokenise, Splitter, WordRules};
+    use proptest::prelude::*;
+
+    /// Bits of text discord messages are made of, beyond plain words
+    fn fragment() -> impl Strategy<Value = String> {
+        prop_oneof![
+            "[a-zA-Z']{1,12}",
+            "[0-9]{1,4}",
+            Just(String::from("**")),
+            Just(String::from("__")),
+            Just(String::from("~~")),
+            Just(String::from("||")),
+            Just(String::from("`")),
+            Just(String::from("> ")),
+            Just(String::from("\u{200b}")),
+            Just(String::from("\u{200d}")),
+            Just(String::from("😀")),
+            Just(String::from("👩‍💻")),
+            "<@!?[0-9]{17,18}>",
+            "<#[0-9]{17,18}>",
+            "<:[a-z]{2,8}:[0-9]{17,18}>",
+            "[ ,.!?;:\"\t\n()*-]{1,3}",
+        ]
+    }
+
+    fn messy_message() -> impl Strategy<Value = String> {
+        prop::collection::vec(fragment(), 0..40).prop_map(|fragments| fragments.concat())
+    }
+
+    proptest! {
+        #[test]
+        fn tokens_are_non_empty_substrings_without_separators(message in messy_message()) {
+            for token in tokenise(&message) {
+                prop_assert!(!token.is_empty());
+                prop_assert!(message.contains(token));
+                prop_assert!(!token.contains(Splitter::is_match));
+            }
+        }
+
+        #[test]
+        fn tokenising_splits_cleanly_on_whitespace(a in messy_message(), b in messy_message()) {
+            let mut separately = tokenise(&a);
+            separately.extend(tokenise(&b));
+            let joined = format!("{} {}", a, b);
+            prop_assert_eq!(tokenise(&joined), separately);
+        }
+
+        #[test]
+        fn word_count_never_exceeds_tokens(message in messy_message()) {
+            prop_assert!(crate::stats::count_words(&message, &WordRules::Standard) <= tokenise(&message).len());
+        }
+    }
+
+    // Changes to these snapshots change how historical messages are counted, so they need to be
+    // deliberate
+    #[test]
+    fn snapshot_messy_messages() {
+        let messages = [
+            "**Chapter 1**: the *cat* sat on the ~~mat~~ rug...",
+            "<@!190534649548767243> look at this 😀 isn't it grand?",
+            "||spoiler|| and `code` and > a quote",
+            "zero\u{200b}width joiner👩‍💻 and 'quoted' words''",
+            "<:pepe:805918656622100500> -- well-known em—dash",
+            "  \n\t  ",
+        ];
+        let tokens: Vec<(&str, Vec<&str>)> = messages
+            .iter()
+            .map(|message| (*message, tokenise(message)))
+            .collect();
+        insta::assert_debug_snapshot!(tokens);
+    }
 }
@@ -1,4 +1,3 @@
-#![macro_use]
 macro_rules! some_string {
     ($s:literal) => {
         Some(String::from($s))
@@ -1,15 +1,16 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::env;
 use std::sync::atomic::Ordering;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
-use log::{debug, info, LevelFilter};
+use chrono::Utc;
+use log::{debug, info, warn, LevelFilter};
 use serenity::async_trait;
 use serenity::client::{Client, Context, EventHandler};
 use serenity::framework::standard::{
-    help_commands,
-    macros::{check, command, group, help, hook},
-    Args, CommandGroup, CommandOptions, CommandResult, HelpOptions, Reason, StandardFramework,
+    buckets::LimitedFor,
+    macros::{check, command, group, hook},
+    Args, CommandOptions, CommandResult, DispatchError, Reason, StandardFramework,
 };
 use serenity::http::Http;
 use serenity::model::channel::Message;
@@ -20,16 +21,73 @@ use simplelog::SimpleLogger;
 use sysinfo::get_current_pid;
 use tokio::time::Duration;
 
+use commands::about::{StartTimeData, ABOUT_COMMAND};
+use commands::alias::ALIAS_COMMAND;
+use commands::audit_log::AUDIT_LOG_COMMAND;
+use commands::bench_replay::BENCH_REPLAY_COMMAND;
+use commands::character::CHARACTER_COMMAND;
+use commands::check_perms::CHECK_PERMS_COMMAND;
+use commands::config::CONFIG_COMMAND;
+use commands::content_report::CONTENT_REPORT_COMMAND;
+use commands::count_words::COUNT_WORDS_COMMAND;
+use commands::deinit_channel::DEINIT_CHANNEL_COMMAND;
+use commands::doctor::DOCTOR_COMMAND;
 use commands::dump_messages::DUMP_MESSAGES_COMMAND;
+use commands::export_store::EXPORT_STORE_COMMAND;
+use commands::export_words::EXPORT_WORDS_COMMAND;
+use commands::forget_me::FORGET_ME_COMMAND;
+use commands::help::HELP;
+use commands::ignore_bot::IGNORE_BOT_COMMAND;
 use commands::init_channel::INIT_CHANNEL_COMMAND;
+use commands::interactions::INTERACTIONS_COMMAND;
+use commands::merge_channels::MERGE_CHANNELS_COMMAND;
+use commands::nudge::NUDGE_COMMAND;
+use commands::personal_log::{log_personal_writing, PERSONAL_LOG_COMMAND};
+use commands::privacy_mode::PRIVACY_MODE_COMMAND;
+use commands::quiet_hours::QUIET_HOURS_COMMAND;
+use commands::rebuild_stats::REBUILD_STATS_COMMAND;
+use commands::recap::RECAP_COMMAND;
+use commands::remind::REMIND_COMMAND;
+use commands::reset_stats::RESET_STATS_COMMAND;
+use commands::response_times::RESPONSE_TIMES_COMMAND;
 use commands::server_summary::SERVER_SUMMARY_COMMAND;
+use commands::sessions::SESSIONS_COMMAND;
+use commands::set_min_words::SET_MIN_WORDS_COMMAND;
+use commands::set_prefix::{get_prefix, SET_PREFIX_COMMAND};
+use commands::setup::SETUP_COMMAND;
+use commands::shoutout::SHOUTOUT_COMMAND;
 use commands::show_channels::SHOW_CHANNELS_COMMAND;
 use commands::show_stats::SHOW_STATS_COMMAND;
-use commands::word_cloud::GEN_WORDCLOUD_COMMAND;
+use commands::status::STATUS_COMMAND;
+use commands::timezone::TIMEZONE_COMMAND;
+use commands::turn_order::{track_turn, TURN_ORDER_COMMAND, WHOS_NEXT_COMMAND};
+use commands::undeinit::UNDEINIT_COMMAND;
+use commands::usage_report::USAGE_REPORT_COMMAND;
+use commands::verify_store::VERIFY_STORE_COMMAND;
+use commands::word_cloud::{scheduled_wordcloud_worker, GEN_WORDCLOUD_COMMAND};
+use commands::word_rules::WORD_RULES_COMMAND;
+use commands::wordcloud_schedule::WORDCLOUD_SCHEDULE_COMMAND;
 use commands::feedback::FEEDBACK_COMMAND;
 
+use crate::activity::activity_worker;
 use crate::config::{GeneralAppConfig, GeneralAppConfigData};
+use crate::doctor::{config_checks, format_checklist, has_problems};
+use crate::encryption::{StateKey, STATE_KEY_ENV};
+use crate::ingest::{
+    counted_live, from_bot_or_webhook, ingest_worker, message_queue, queue_message,
+    MessageQueueData,
+};
+use crate::integrity::integrity_worker;
+use crate::intents::{
+    gateway_intents, message_content_available, observe_message_content, MessageContent,
+    MessageContentData,
+};
+use crate::onboarding::welcome_new_guild;
+use crate::rate_limit::{RateLimiter, RateLimiterData};
+use crate::scheduler::scheduler_worker;
 use crate::state::{Store, StoreData, StoryKey};
+use crate::utils::discord::channel_name;
+use crate::utils::replied::already_replied;
 use serenity::futures::StreamExt;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
@@ -37,23 +95,85 @@ use tokio::io::{AsyncBufReadExt, BufReader};
 
 #[macro_use]
 mod macros;
+mod activity;
+mod bench_corpus;
 mod commands;
 mod config;
+mod doctor;
+mod encryption;
+mod export;
+mod guild_config;
+mod ingest;
+mod integrity;
+mod intents;
 mod language_parsing;
+mod offline;
+mod onboarding;
+mod rate_limit;
+mod scheduler;
 mod state;
 mod stats;
 mod utils;
 
 #[group]
-#[commands(init_channel, show_stats, show_channels, server_summary, feedback)]
+#[commands(
+    setup,
+    init_channel,
+    check_perms,
+    deinit_channel,
+    undeinit,
+    rebuild_stats,
+    merge_channels,
+    reset_stats,
+    show_stats,
+    show_channels,
+    server_summary,
+    interactions,
+    sessions,
+    response_times,
+    recap,
+    count_words,
+    remind,
+    nudge,
+    shoutout,
+    personal_log,
+    alias,
+    character,
+    turn_order,
+    whos_next,
+    forget_me,
+    timezone,
+    word_rules,
+    quiet_hours,
+    set_min_words,
+    set_prefix,
+    ignore_bot,
+    privacy_mode,
+    content_report,
+    export_words,
+    config,
+    audit_log,
+    about,
+    feedback
+)]
 struct General;
 
 #[group]
-#[commands(gen_wordcloud)]
+#[commands(gen_wordcloud, wordcloud_schedule)]
 struct WordCloud;
 
 #[group]
-#[commands(ping, ping_me, dump_messages)]
+#[commands(
+    ping,
+    ping_me,
+    dump_messages,
+    usage_report,
+    export_store,
+    status,
+    doctor,
+    bench_replay,
+    verify_store
+)]
 #[help_available(false)]
 struct Debug;
 
@@ -65,9 +185,11 @@ struct Handler {
 impl EventHandler for Handler {
     async fn cache_ready(&self, ctx: Context, _guilds: Vec<GuildId>) {
         println!("Cache built successfully!");
-        set_bot_activity(&ctx).await;
         if !self.tasks_running.load(Ordering::Relaxed) {
-            store_replay(&ctx).await;
+            // Replaying would only count empty messages
+            if message_content_available(&ctx).await {
+                store_replay(&ctx).await;
+            }
             let ctx = Arc::new(ctx);
             let ctx1 = Arc::clone(&ctx);
             let _ = tokio::spawn(async move {
@@ -77,6 +199,22 @@ impl EventHandler for Handler {
             let _ = tokio::spawn(async move {
                 dump_state(ctx2).await;
             });
+            let ctx3 = Arc::clone(&ctx);
+            tokio::spawn(async move {
+                scheduler_worker(ctx3).await;
+            });
+            let ctx4 = Arc::clone(&ctx);
+            tokio::spawn(async move {
+                scheduled_wordcloud_worker(ctx4).await;
+            });
+            let ctx5 = Arc::clone(&ctx);
+            tokio::spawn(async move {
+                activity_worker(ctx5).await;
+            });
+            let ctx6 = Arc::clone(&ctx);
+            tokio::spawn(async move {
+                integrity_worker(ctx6).await;
+            });
             self.tasks_running.swap(true, Ordering::Relaxed);
         }
     }
@@ -84,21 +222,76 @@ impl EventHandler for Handler {
     async fn ready(&self, _ctx: Context, ready: Ready) {
         println!("{} is connected!", ready.user.name);
     }
-}
 
-async fn set_bot_activity(ctx: &Context) {
-    let prefix = {
-        let config_lock = {
+    async fn guild_create(&self, ctx: Context, guild: Guild, is_new: bool) {
+        if is_new {
+            if let Err(why) = welcome_new_guild(&ctx, &guild).await {
+                println!("Error welcoming new server {}: {:?}", guild.name, why);
+            }
+        }
+    }
+
+    async fn guild_member_addition(&self, ctx: Context, guild_id: GuildId, new_member: Member) {
+        let store_lock = {
             let data_read = ctx.data.read().await;
             data_read
-                .get::<GeneralAppConfigData>()
+                .get::<StoreData>()
                 .expect("Expected StoryData in TypeMap.")
                 .clone()
         };
-        let config = config_lock.read().unwrap();
-        config.prefix.clone()
-    };
-    ctx.set_activity(Activity::listening(&prefix)).await;
+        let mut store = store_lock.write().unwrap();
+        store.set_author_departed(&guild_id, &new_member.user.id, false);
+    }
+
+    async fn guild_member_removal(
+        &self,
+        ctx: Context,
+        guild_id: GuildId,
+        user: User,
+        _member: Option<Member>,
+    ) {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let mut store = store_lock.write().unwrap();
+        store.set_author_departed(&guild_id, &user.id, true);
+    }
+
+    async fn guild_member_update(&self, ctx: Context, _old: Option<Member>, new: Member) {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let mut store = store_lock.write().unwrap();
+        store.update_display_name(&new.guild_id, &new.user.id, &new.display_name());
+    }
+
+    async fn channel_delete(&self, ctx: Context, channel: &GuildChannel) {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let mut store = store_lock.write().unwrap();
+        let trashed = store
+            .get_server_data_mut(&channel.guild_id)
+            .is_some_and(|server_data| server_data.trash_channel(&channel.id, Utc::now()));
+        if trashed {
+            info!(
+                "Initialised channel {} ({}) was deleted, its stats are in the trash",
+                channel.name, channel.id
+            );
+        }
+    }
 }
 
 async fn store_replay(ctx: &Context) {
@@ -115,16 +308,28 @@ async fn store_replay(ctx: &Context) {
     };
     info!("initialising store!");
     let mut new_messages = HashMap::<StoryKey, Vec<Message>>::new();
+    let mut accessible = HashMap::<StoryKey, bool>::new();
     for (story_key, last_message_id) in story_keys_with_last_message.into_iter() {
         let (_, channel_id) = story_key;
-        let channel_name = channel_id.name(&ctx.cache).await.unwrap();
+        let channel_name = channel_name(ctx, channel_id)
+            .await
+            .unwrap_or_else(|| channel_id.to_string());
         info!("Checking for missed messages in {}", channel_name);
         let msgs = channel_id
             .messages(&ctx.http, |get_messages_builder| {
                 get_messages_builder.after(last_message_id).limit(50)
             })
-            .await
-            .unwrap();
+            .await;
+        // A lost permission or deleted channel shouldn't stop the rest catching up
+        let msgs = match msgs {
+            Ok(msgs) => msgs,
+            Err(why) => {
+                warn!("Skipping replay of {}, it can't be read: {:?}", channel_name, why);
+                accessible.insert(story_key, false);
+                continue;
+            }
+        };
+        accessible.insert(story_key, true);
         info!("Got {} messages", msgs.len());
         if msgs.len() > 0 {
             new_messages.insert(story_key, msgs);
@@ -142,11 +347,12 @@ async fn store_replay(ctx: &Context) {
             .clone()
     };
     let mut store = store_lock.write().unwrap();
+    for (story_key, accessible) in accessible {
+        store.set_channel_accessible(&story_key, accessible);
+    }
     for (story_key, messages) in new_messages {
-        //Since we got these messages from the store, we can expect the key to exist
-        let channel_data = store.get_channel_data_mut(&story_key).unwrap();
         for message in messages {
-            channel_data.update(&message);
+            store.update_channel_data(&story_key, &message);
         }
     }
     store.finish_replay();
@@ -188,14 +394,17 @@ fn maybe_start_python_wordcloud_worker(config: &GeneralAppConfig) {
         let python_wordcloud_worker =
             tokio::process::Command::new(word_cloud_config.python_path.as_os_str())
                 .env("PYTHONPATH", python_path)
-                .arg("wordcloud/word_cloud_worker.py")
+                .arg(&config.wordcloud_worker_script)
                 .arg(word_cloud_config.request_path.as_os_str())
                 .arg(word_cloud_config.generated_image_path.as_os_str())
                 .stdout(Stdio::piped())
                 .stderr(Stdio::piped())
-                .spawn()
-                .unwrap();
-        wordcloud_worker_chaperone(python_wordcloud_worker);
+                .spawn();
+        match python_wordcloud_worker {
+            Ok(python_wordcloud_worker) => wordcloud_worker_chaperone(python_wordcloud_worker),
+            // Wordcloud commands then time out waiting for images, rather than the bot not starting
+            Err(e) => println!("Failed starting the wordcloud worker: {}", e),
+        }
     }
 }
 fn wordcloud_worker_chaperone(mut child: tokio::process::Child) {
@@ -217,27 +426,191 @@ fn wordcloud_worker_chaperone(mut child: tokio::process::Child) {
     });
 }
 
+/// Without a subcommand the bot runs, the others work on the state file offline
+fn cli() -> clap::Command {
+    let guild = || {
+        clap::Arg::new("guild")
+            .long("guild")
+            .value_name("ID")
+            .value_parser(clap::value_parser!(u64))
+    };
+    clap::Command::new("Scrivener")
+        .about("Story stats discord bot")
+        .arg(
+            clap::Arg::new("profile")
+                .long("profile")
+                .value_name("NAME")
+                .global(true)
+                .help("Apply this profile from config.ron over its defaults"),
+        )
+        .subcommand(clap::Command::new("run").about("Start the bot, the default"))
+        .subcommand(
+            clap::Command::new("inspect-state")
+                .about("Summarise the state file, with each channel of --guild")
+                .arg(guild().help("The server to list the channels of")),
+        )
+        .subcommand(
+            clap::Command::new("migrate-state")
+                .about(format!(
+                    "Rewrite the state file encrypted with {}, or unencrypted",
+                    STATE_KEY_ENV
+                ))
+                .arg(
+                    clap::Arg::new("target")
+                        .required(true)
+                        .value_parser(["encrypt", "decrypt"]),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("export")
+                .about("Export stats as JSON, as the export-store command does")
+                .arg(guild().help("The server to export, every server if not given"))
+                .arg(
+                    clap::Arg::new("out")
+                        .long("out")
+                        .value_name("PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("Where to write the export, printed if not given"),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("decrypt-export")
+                .about(format!(
+                    "Print an export written encrypted with {}",
+                    STATE_KEY_ENV
+                ))
+                .arg(
+                    clap::Arg::new("path")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            clap::Command::new("merge-dumps")
+                .about("Merge two state files into a new one, the channel counted furthest wins")
+                .arg(
+                    clap::Arg::new("base")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("State file whose settings are kept"),
+                )
+                .arg(
+                    clap::Arg::new("other")
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("State file to take servers and newer channels from"),
+                )
+                .arg(
+                    clap::Arg::new("out")
+                        .long("out")
+                        .required(true)
+                        .value_name("PATH")
+                        .value_parser(clap::value_parser!(PathBuf))
+                        .help("Where to write the merged state, mustn't exist yet"),
+                ),
+        )
+}
+
+/// config.ron, with the profile picked by `--profile` applied over it
+fn load_config(profile: Option<&String>) -> Result<GeneralAppConfig, String> {
+    let mut config = GeneralAppConfig::load(Path::new("config.ron"))
+        .map_err(|e| format!("Failed loading config.ron: {}", e))?;
+    if let Some(profile) = profile {
+        config.apply_profile(profile)?;
+    }
+    Ok(config)
+}
+
+/// Runs an offline subcommand, exiting once it's done
+fn run_offline(result: Result<String, String>) -> ! {
+    match result {
+        Ok(done) => {
+            println!("{}", done);
+            std::process::exit(0)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1)
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() {
-    let config = GeneralAppConfig::load(Path::new("config.ron")).unwrap();
+    let matches = cli().get_matches();
+    let config = load_config(matches.get_one::<String>("profile")).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let guild = |args: &clap::ArgMatches| args.get_one::<u64>("guild").map(|id| GuildId(*id));
+    match matches.subcommand() {
+        Some(("inspect-state", args)) => run_offline(offline::inspect_state(&config, guild(args))),
+        Some(("migrate-state", args)) => run_offline(offline::migrate_state(
+            &config,
+            args.get_one::<String>("target").unwrap(),
+        )),
+        Some(("export", args)) => run_offline(offline::export_state(
+            &config,
+            guild(args),
+            args.get_one::<PathBuf>("out").map(|out| out.as_path()),
+        )),
+        Some(("decrypt-export", args)) => run_offline(offline::decrypt_export(
+            args.get_one::<PathBuf>("path").unwrap(),
+        )),
+        Some(("merge-dumps", args)) => run_offline(offline::merge_dumps(
+            args.get_one::<PathBuf>("base").unwrap(),
+            args.get_one::<PathBuf>("other").unwrap(),
+            args.get_one::<PathBuf>("out").unwrap(),
+        )),
+        _ => (),
+    }
+    let started_at = std::time::Instant::now();
+    if let Some(profile) = &config.profile {
+        println!("Running with the {} profile", profile);
+    }
+    let checks = config_checks(&config);
+    if has_problems(&checks) {
+        println!(
+            "Startup checks found problems, [doctor] checks again once running:\n{}",
+            format_checklist(&checks)
+        );
+    }
     //Start python wordcloud worker
     maybe_start_python_wordcloud_worker(&config);
     let _ = SimpleLogger::init(LevelFilter::Info, simplelog::Config::default());
-    let token = env::var("BOT_TOKEN").expect("Need bot token");
+    let token = env::var(&config.token_env)
+        .unwrap_or_else(|_| panic!("Need bot token in {}", config.token_env));
     let http = Http::new_with_token(&token);
     let app_info = http.get_current_application_info().await.unwrap();
     println!("{:#?}", app_info);
     let framework = StandardFramework::new()
-        .configure(|c| c.prefix(&config.prefix).on_mention(Some(app_info.id)))
+        .configure(|c| {
+            c.prefix(&config.prefix)
+                .dynamic_prefix(server_prefix)
+                .on_mention(Some(app_info.id))
+                // Proxy bots post characters' lines through webhooks, which need counting. Other
+                // bots and webhooks still can't run commands, see [from_bot_or_webhook]
+                .ignore_bots(false)
+                .ignore_webhooks(false)
+        })
         .normal_message(on_regular_message)
         .unrecognised_command(on_unrecognised_command)
+        .before(before_command)
+        .after(after_command)
+        .on_dispatch_error(on_dispatch_error)
         .bucket("global-wordcloud-bucket", |b| b.limit(20).time_span(30))
         .await
+        // Per-user cooldown on the commands which wipe stats, on top of their confirmation prompt
+        .bucket("destructive-bucket", |b| {
+            b.delay(10).limit_for(LimitedFor::User)
+        })
+        .await
         .help(&HELP)
         .group(&GENERAL_GROUP)
         .group(&DEBUG_GROUP)
         .group(&WORDCLOUD_GROUP);
     let mut client = Client::builder(&token)
+        .intents(gateway_intents(&config.intents))
         .event_handler(Handler {
             tasks_running: AtomicBool::new(false),
         })
@@ -248,14 +621,27 @@ async fn main() {
     // Insert the global data:
     {
         let mut data = client.data.write().await;
-        let store = match Store::load() {
+        let state_key = StateKey::from_env().unwrap_or_else(|e| panic!("{}", e));
+        let mut store = match Store::load(&config.state_path, state_key) {
             Ok(store) => store,
             Err(e) => {
                 panic!("Parse failed: {:#?}", e);
             }
         };
-        data.insert::<StoreData>(Arc::new(RwLock::new(store)));
+        store.max_words_per_channel = Some(config.max_words_per_channel);
+        let store_lock = Arc::new(RwLock::new(store));
+        let (message_sender, message_receiver) = message_queue();
+        tokio::spawn(ingest_worker(Arc::clone(&store_lock), message_receiver));
+        data.insert::<StoreData>(store_lock);
+        data.insert::<MessageQueueData>(message_sender);
+        data.insert::<RateLimiterData>(Arc::new(Mutex::new(RateLimiter::new(
+            config.rate_limits.clone(),
+        ))));
+        data.insert::<MessageContentData>(Arc::new(MessageContent::new(
+            config.intents.message_content,
+        )));
         data.insert::<GeneralAppConfigData>(Arc::new(RwLock::new(config)));
+        data.insert::<StartTimeData>(Arc::new(started_at));
     }
 
     // start listening for events by starting a single shard
@@ -266,6 +652,9 @@ async fn main() {
 
 #[hook]
 async fn on_unrecognised_command(ctx: &Context, msg: &Message, unrecognised_command_name: &str) {
+    if from_bot_or_webhook(msg) {
+        return;
+    }
     msg.reply(
         ctx,
         format!(
@@ -277,38 +666,100 @@ async fn on_unrecognised_command(ctx: &Context, msg: &Message, unrecognised_comm
     .unwrap();
 }
 
-#[help]
-#[max_levenshtein_distance(3)]
-async fn help(
-    context: &Context,
+#[hook]
+async fn server_prefix(ctx: &Context, msg: &Message) -> Option<String> {
+    match msg.guild_id {
+        Some(server_id) => get_prefix(ctx, &server_id).await,
+        None => None,
+    }
+}
+
+#[hook]
+async fn before_command(ctx: &Context, msg: &Message, command_name: &str) -> bool {
+    if from_bot_or_webhook(msg) {
+        return false;
+    }
+    if let Some(server_id) = msg.guild_id {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let mut store = store_lock.write().unwrap();
+        store.record_command_use(&server_id, command_name);
+    }
+    true
+}
+
+#[hook]
+async fn after_command(
+    ctx: &Context,
     msg: &Message,
-    args: Args,
-    help_options: &'static HelpOptions,
-    groups: &[&'static CommandGroup],
-    owners: HashSet<UserId>,
-) -> CommandResult {
-    let _ = help_commands::with_embeds(context, msg, args, help_options, groups, owners).await;
-    Ok(())
+    command_name: &str,
+    command_result: CommandResult,
+) {
+    if let Err(why) = command_result {
+        info!("Command {} returned error: {:?}", command_name, why);
+        if !already_replied(&why) {
+            let reply = format!(
+                "Something went wrong running [{}], try again or report it with [feedback]",
+                command_name
+            );
+            if let Err(why) = msg.reply(ctx, reply).await {
+                println!("Error sending command error reply: {:?}", why);
+            }
+        }
+        if let Some(server_id) = msg.guild_id {
+            let store_lock = {
+                let data_read = ctx.data.read().await;
+                data_read
+                    .get::<StoreData>()
+                    .expect("Expected StoryData in TypeMap.")
+                    .clone()
+            };
+            let mut store = store_lock.write().unwrap();
+            store.record_command_error(&server_id, command_name);
+        }
+    }
 }
 
-async fn update_stats_if_exist(story_key: StoryKey, ctx: &Context, message: &Message) {
-    let store_lock = {
-        let data_read = ctx.data.read().await;
-        data_read
-            .get::<StoreData>()
-            .expect("Expected StoryData in TypeMap.")
-            .clone()
+#[hook]
+async fn on_dispatch_error(ctx: &Context, msg: &Message, error: DispatchError) {
+    if from_bot_or_webhook(msg) {
+        return;
+    }
+    let reply = match error {
+        DispatchError::CheckFailed(_, Reason::User(reason))
+        | DispatchError::CheckFailed(_, Reason::UserAndLog { user: reason, .. }) => Some(reason),
+        DispatchError::Ratelimited(info) if info.is_first_try => {
+            Some(format!("Slow down, try again in {}s", info.as_secs() + 1))
+        }
+        _ => None,
     };
-    let mut store = store_lock.write().unwrap();
-    store.process_message(&story_key, message);
+    if let Some(reply) = reply {
+        if let Err(why) = msg.reply(ctx, reply).await {
+            println!("Error sending dispatch error reply: {:?}", why);
+        }
+    }
 }
 
 #[hook]
 async fn on_regular_message(ctx: &Context, message: &Message) {
+    if !counted_live(message, &ctx.cache.current_user_id().await) {
+        return;
+    }
     //Update a stats if this channel is initialised
     if let Some(server_id) = message.guild_id {
-        let story_key = (server_id, message.channel_id);
-        update_stats_if_exist(story_key, ctx, message).await;
+        observe_message_content(ctx, message).await;
+        if message_content_available(ctx).await {
+            let story_key = (server_id, message.channel_id);
+            queue_message(ctx, story_key, message).await;
+            track_turn(ctx, &server_id, message).await;
+        }
+    } else {
+        log_personal_writing(ctx, message).await;
     }
 }
 
@@ -0,0 +1,247 @@
+use crate::config::GeneralAppConfig;
+use crate::encryption::{is_encrypted, StateKey, STATE_KEY_ENV};
+use crate::export::{export_file, export_json};
+use crate::state::Store;
+use serenity::model::id::GuildId;
+use std::path::Path;
+
+// Work on the state file from the command line, without starting the discord client. Stop the bot
+// first, it overwrites the state file with what it has in memory on its next save
+
+fn load_store(state_path: &Path) -> Result<Store, String> {
+    let state_key = StateKey::from_env()?;
+    Store::load(state_path, state_key)
+        .map_err(|e| format!("Failed loading {}: {}", state_path.display(), e))
+}
+
+fn describe_store(store: &Store, server_id: Option<GuildId>) -> Result<String, String> {
+    let mut servers = store
+        .servers()
+        .filter(|(id, _)| server_id.is_none_or(|server_id| **id == server_id))
+        .collect::<Vec<_>>();
+    if servers.is_empty() && server_id.is_some() {
+        return Err(String::from("No stats recorded for that server"));
+    }
+    servers.sort_by_key(|(id, _)| **id);
+    let mut lines = vec![format!(
+        "{} servers, {} personal logs",
+        store.servers().count(),
+        store.personal_logs.len()
+    )];
+    for (id, server_data) in servers {
+        lines.push(format!(
+            "Server {}: {} channels, {} words, {} archived seasons, {} in the trash, {} audit entries",
+            id,
+            server_data.channels().count(),
+            server_data
+                .channels()
+                .map(|(_, channel_data)| channel_data.general_stats.word_count)
+                .sum::<usize>(),
+            server_data.seasons.values().map(|seasons| seasons.len()).sum::<usize>(),
+            server_data.trash.len(),
+            server_data.audit_log.len()
+        ));
+        if server_id.is_none() {
+            continue;
+        }
+        let mut channels = server_data.channels().collect::<Vec<_>>();
+        channels.sort_by_key(|(channel_id, _)| **channel_id);
+        for (channel_id, channel_data) in channels {
+            lines.push(format!(
+                "  Channel {}: {} words by {} writers, last message {}",
+                channel_id,
+                channel_data.general_stats.word_count,
+                channel_data.author_stats.len(),
+                channel_data
+                    .general_stats
+                    .last_message_time()
+                    .map_or(String::from("never"), |at| at
+                        .format("%Y-%m-%d %H:%M UTC")
+                        .to_string())
+            ));
+        }
+        let mut trashed = server_data.trash.iter().collect::<Vec<_>>();
+        trashed.sort_by_key(|(channel_id, _)| **channel_id);
+        for (channel_id, trashed) in trashed {
+            lines.push(format!(
+                "  Trashed channel {}: deleted for good {}",
+                channel_id,
+                trashed.expires().format("%Y-%m-%d")
+            ));
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+/// A summary of each server in the state file, and each channel's for [server_id] if given
+pub fn inspect_state(
+    config: &GeneralAppConfig,
+    server_id: Option<GuildId>,
+) -> Result<String, String> {
+    let store = load_store(&config.state_path)?;
+    let encryption = match store.state_is_encrypted() {
+        Ok(true) => "encrypted",
+        Ok(false) => "unencrypted",
+        Err(_) => "not saved yet",
+    };
+    Ok(format!(
+        "{} ({})\n{}",
+        config.state_path.display(),
+        encryption,
+        describe_store(&store, server_id)?
+    ))
+}
+
+/// `migrate-state encrypt` rewrites the state file encrypted with the key in [STATE_KEY_ENV],
+/// `migrate-state decrypt` rewrites it unencrypted, e.g. to rotate the key
+pub fn migrate_state(config: &GeneralAppConfig, target: &str) -> Result<String, String> {
+    let mut store = load_store(&config.state_path)?;
+    match target {
+        "encrypt" if store.state_key.is_none() => {
+            return Err(format!("Set {} to encrypt with", STATE_KEY_ENV))
+        }
+        "encrypt" => (),
+        "decrypt" => store.state_key = None,
+        _ => return Err(String::from("Usage: migrate-state encrypt|decrypt")),
+    }
+    store
+        .dump()
+        .map_err(|e| format!("Failed writing state: {}", e))?;
+    match store.state_is_encrypted() {
+        Ok(true) => Ok(String::from("State file is now encrypted")),
+        Ok(false) => Ok(String::from("State file is now unencrypted")),
+        Err(e) => Err(format!("Failed reading back state: {}", e)),
+    }
+}
+
+/// The same JSON as the [export-store] command, written to [out] or returned to be printed
+pub fn export_state(
+    config: &GeneralAppConfig,
+    server_id: Option<GuildId>,
+    out: Option<&Path>,
+) -> Result<String, String> {
+    let store = load_store(&config.state_path)?;
+    let export = export_json(&store, server_id)?;
+    match out {
+        Some(out) => {
+            let (bytes, encrypted) = export_file(&store, export)
+                .map_err(|e| format!("Failed encrypting export: {}", e))?;
+            std::fs::write(out, bytes)
+                .map_err(|e| format!("Failed writing export to {}: {}", out.display(), e))?;
+            Ok(match encrypted {
+                true => format!(
+                    "Exported to {}, encrypted with {}. Read it with decrypt-export",
+                    out.display(),
+                    STATE_KEY_ENV
+                ),
+                false => format!("Exported to {}", out.display()),
+            })
+        }
+        None => Ok(export),
+    }
+}
+
+/// Prints an export written encrypted by [export_state] or the [export-store] command
+pub fn decrypt_export(path: &Path) -> Result<String, String> {
+    let state_key = StateKey::from_env()?
+        .ok_or_else(|| format!("Set {} to the key it was encrypted with", STATE_KEY_ENV))?;
+    let bytes =
+        std::fs::read(path).map_err(|e| format!("Failed reading {}: {}", path.display(), e))?;
+    if !is_encrypted(&bytes) {
+        return Err(format!("{} isn't encrypted", path.display()));
+    }
+    let plaintext = state_key
+        .decrypt(&bytes)
+        .map_err(|e| format!("Failed decrypting {}: {}", path.display(), e))?;
+    String::from_utf8(plaintext).map_err(|_| format!("{} isn't an export", path.display()))
+}
+
+/// Folds the state file [other] into [base] and writes the result to [out], encrypted if
+/// [STATE_KEY_ENV] is set. Neither input is changed, and [out] isn't overwritten
+pub fn merge_dumps(base: &Path, other: &Path, out: &Path) -> Result<String, String> {
+    if out.exists() {
+        return Err(format!(
+            "{} already exists, pick somewhere new to write to",
+            out.display()
+        ));
+    }
+    for path in [base, other].iter() {
+        if !path.is_file() {
+            return Err(format!("No state file at {}", path.display()));
+        }
+    }
+    let mut store = load_store(base)?;
+    let merge = store.merge_dump(load_store(other)?);
+    store.state_path = out.to_path_buf();
+    store
+        .dump()
+        .map_err(|e| format!("Failed writing {}: {}", out.display(), e))?;
+    Ok(format!(
+        "Wrote {}, taking {} servers, {} newer channels and {} personal logs from {}\n{}",
+        out.display(),
+        merge.servers_added,
+        merge.channels_taken,
+        merge.personal_logs_added,
+        other.display(),
+        describe_store(&store, None)?
+    ))
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::bench_corpus::synthetic_messages;
+    use crate::language_parsing::WordRules;
+    use crate::offline::{describe_store, merge_dumps};
+    use crate::state::{ChannelData, Store};
+    use chrono_tz::Tz;
+    use serenity::model::id::{ChannelId, GuildId};
+    use std::path::Path;
+
+    fn store_at(path: &Path, channels: &[u64]) -> Store {
+        let mut store = Store::default();
+        store.state_path = path.to_path_buf();
+        for channel in channels {
+            let mut channel_data = ChannelData::default();
+            for message in synthetic_messages(5, 2).iter() {
+                channel_data.update(message, &Tz::UTC, &WordRules::default());
+            }
+            store.insert_channel_data_maybe_create_server_data(
+                &(GuildId(1), ChannelId(*channel)),
+                channel_data,
+            );
+        }
+        store
+    }
+
+    #[test]
+    fn describes_servers() {
+        let store = store_at(Path::new("unused"), &[1, 2]);
+        let overview = describe_store(&store, None).unwrap();
+        assert!(overview.starts_with("1 servers, 0 personal logs"));
+        assert!(overview.contains("Server 1: 2 channels"));
+        assert!(!overview.contains("Channel 1"));
+        let detail = describe_store(&store, Some(GuildId(1))).unwrap();
+        assert!(detail.contains("  Channel 1: "));
+        assert!(detail.contains("  Channel 2: "));
+        assert!(describe_store(&store, Some(GuildId(9))).is_err());
+    }
+
+    #[test]
+    fn merges_state_files() {
+        let dir = std::env::temp_dir().join(format!("scrivener-merge-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (base, other, out) = (dir.join("base"), dir.join("other"), dir.join("out"));
+        store_at(&base, &[1]).dump().unwrap();
+        store_at(&other, &[2]).dump().unwrap();
+        assert!(merge_dumps(&base, &dir.join("missing"), &out).is_err());
+
+        let merged = merge_dumps(&base, &other, &out).unwrap();
+        assert!(merged.contains("0 servers, 1 newer channels"));
+        let store = Store::load(&out, None).unwrap();
+        assert_eq!(store.get_all_channels_in_server(&GuildId(1)).len(), 2);
+        assert!(merge_dumps(&base, &other, &out)
+            .unwrap_err()
+            .contains("already exists"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+use crate::commands::audit_log::record_audit_entry;
+use crate::commands::check_perms::preflight_permissions;
+use crate::commands::init_channel::{
+    actually_init_channel, author_is_in_allowed_roles, not_in_allowed_roles_response, ALLOWED_ROLES,
+};
+use crate::config::GeneralAppConfigData;
+use crate::rate_limit::take_expensive_use;
+use crate::scheduler::{is_quiet_now, send_automated_post};
+use log::info;
+use serenity::model::guild::ActionMember;
+use serenity::model::prelude::*;
+use serenity::prelude::Context;
+use serenity::utils::MessageBuilder;
+use std::time::Duration;
+
+const INIT_EMOJI: &str = "📌";
+// Long enough for someone to notice the message and give themselves the role
+const INIT_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+fn init_reaction() -> ReactionType {
+    ReactionType::Unicode(String::from(INIT_EMOJI))
+}
+
+/// Whoever added the bot, from the audit log. Needs the View Audit Log permission, without it (or
+/// if the entry can't be found) there's no one to DM
+async fn find_inviter(ctx: &Context, guild_id: GuildId) -> Option<User> {
+    let bot_id = ctx.cache.current_user_id().await;
+    let audit_logs = guild_id
+        .audit_logs(
+            &ctx.http,
+            Some(ActionMember::BotAdd.num()),
+            None,
+            None,
+            Some(10),
+        )
+        .await
+        .ok()?;
+    let inviter_id = audit_logs
+        .entries
+        .values()
+        .filter(|entry| entry.target_id == Some(bot_id.0))
+        .max_by_key(|entry| entry.id)?
+        .user_id;
+    audit_logs
+        .users
+        .into_iter()
+        .find(|user| user.id == inviter_id)
+}
+
+fn setup_guide(guild_name: &str, prefix: &str, offer_reaction: bool) -> String {
+    let mut builder = MessageBuilder::new();
+    builder
+        .push_bold_line(format!("Thanks for adding me to {}!", guild_name))
+        .push_line("I keep stats on the stories written in your channels. To get going:")
+        .push_line(format!(
+            "1. Give yourself one of the roles {} to manage me",
+            ALLOWED_ROLES.join(", ")
+        ))
+        .push_line(format!(
+            "2. Run {}init-channel #channel for each story channel, I'll read back through its history",
+            prefix
+        ))
+        .push_line(format!(
+            "3. If {} clashes with another bot, run {}set-prefix <prefix> to add one for your server",
+            prefix, prefix
+        ))
+        .push_line(format!(
+            "4. {}show-stats #channel once it's initialised, and {}help for everything else",
+            prefix, prefix
+        ))
+        .push_line(format!(
+            "Or once you have the role, {}setup walks you through it all",
+            prefix
+        ));
+    if offer_reaction {
+        builder.push(format!(
+            "Or react {} to start with this channel",
+            INIT_EMOJI
+        ));
+    }
+    builder.build()
+}
+
+/// Waits for someone allowed to react to the guide, and initialises the channel it was posted in
+async fn init_on_reaction(ctx: &Context, guide: &Message) -> serenity::Result<()> {
+    guide.react(ctx, init_reaction()).await?;
+    let bot_id = ctx.cache.current_user_id().await;
+    while let Some(reaction) = guide
+        .await_reaction(ctx)
+        .timeout(INIT_TIMEOUT)
+        .filter(move |reaction| {
+            reaction.emoji == init_reaction() && reaction.user_id != Some(bot_id)
+        })
+        .await
+    {
+        let reaction = reaction.as_inner_ref();
+        let user = reaction.user(ctx).await?;
+        let guild_id = match reaction.guild_id {
+            Some(guild_id) => guild_id,
+            None => break,
+        };
+        if !author_is_in_allowed_roles(ctx, &guild_id, &user).await {
+            guide
+                .channel_id
+                .say(&ctx.http, not_in_allowed_roles_response())
+                .await?;
+            continue;
+        }
+        // Same as [init-channel] goes through, as this backfills just as much
+        if let Err(wait) = take_expensive_use(ctx, Some(guild_id), user.id).await {
+            guide.channel_id.say(&ctx.http, wait).await?;
+            continue;
+        }
+        let channel = match guide.channel_id.to_channel(ctx).await?.guild() {
+            Some(channel) => channel,
+            None => break,
+        };
+        let response = match preflight_permissions(ctx, &channel).await {
+            Ok(missing) => {
+                let mut okay_response = MessageBuilder::new()
+                    .push("Stats initialised for ")
+                    .channel(guide.channel_id)
+                    .build();
+                if let Some(missing) = missing {
+                    okay_response.push_str(&format!("\n{}", missing));
+                }
+                match actually_init_channel(channel, ctx).await {
+                    Ok(()) => {
+                        let invocation = format!("{} on the setup guide", INIT_EMOJI);
+                        record_audit_entry(ctx, &guild_id, user.id, "init-channel", invocation)
+                            .await;
+                        okay_response
+                    }
+                    Err(error_string) => format!("Not initialised: {}", error_string),
+                }
+            }
+            Err(missing) => format!("Not initialised: {}", missing),
+        };
+        guide.channel_id.say(&ctx.http, response).await?;
+        break;
+    }
+    Ok(())
+}
+
+/// Sends a setup guide when the bot joins a server, to whoever invited it if they can be found,
+/// otherwise to the server's system channel
+pub async fn welcome_new_guild(ctx: &Context, guild: &Guild) -> serenity::Result<()> {
+    info!("Joined new server {}", guild.name);
+    let prefix = {
+        let config_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<GeneralAppConfigData>()
+                .expect("Expected GeneralAppConfigData in TypeMap.")
+                .clone()
+        };
+        let prefix = config_lock.read().unwrap().prefix.clone();
+        prefix
+    };
+    if let Some(inviter) = find_inviter(ctx, guild.id).await {
+        let guide = setup_guide(&guild.name, &prefix, false);
+        if inviter
+            .direct_message(ctx, |m| m.content(guide))
+            .await
+            .is_ok()
+        {
+            return Ok(());
+        }
+    }
+    if let Some(system_channel_id) = guild.system_channel_id {
+        // Nothing waits on the reaction to a guide held back for quiet hours, so that one doesn't
+        // offer it
+        let offer_reaction = !is_quiet_now(ctx, &guild.id).await;
+        let guide = setup_guide(&guild.name, &prefix, offer_reaction);
+        if let Some(guide) = send_automated_post(ctx, &guild.id, system_channel_id, guide).await? {
+            if offer_reaction {
+                init_on_reaction(ctx, &guide).await?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,254 @@
+use crate::config::{RateLimit, RateLimitConfig};
+use crate::ingest::from_bot_or_webhook;
+use serenity::framework::standard::{macros::check, Args, CommandOptions, Reason};
+use serenity::model::channel::Message;
+use serenity::model::id::{GuildId, UserId};
+use serenity::prelude::{Context, TypeMapKey};
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub struct RateLimiterData;
+
+impl TypeMapKey for RateLimiterData {
+    type Value = Arc<Mutex<RateLimiter>>;
+}
+
+/// Sliding window limiter for expensive commands, both per-guild and per-user, so one busy server
+/// (or one eager user) can't starve everyone else
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    guild_uses: HashMap<GuildId, VecDeque<Instant>>,
+    user_uses: HashMap<UserId, VecDeque<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            guild_uses: HashMap::new(),
+            user_uses: HashMap::new(),
+        }
+    }
+
+    /// Takes a use from the guild and user windows if both have room, otherwise returns how long
+    /// until the limiting window has room again. Nothing is taken from either window on failure.
+    pub fn try_take(
+        &mut self,
+        guild_id: Option<GuildId>,
+        user_id: UserId,
+        now: Instant,
+    ) -> std::result::Result<(), Duration> {
+        let Self {
+            config,
+            guild_uses,
+            user_uses,
+        } = self;
+        let guild_wait = match &guild_id {
+            Some(guild_id) => {
+                wait_time(guild_uses, guild_id, config.limit_for_guild(guild_id), now)
+            }
+            None => None,
+        };
+        let user_wait = wait_time(user_uses, &user_id, &config.per_user, now);
+        match (guild_wait, user_wait) {
+            (None, None) => {
+                if let Some(guild_id) = guild_id {
+                    guild_uses.entry(guild_id).or_default().push_back(now);
+                }
+                user_uses.entry(user_id).or_default().push_back(now);
+                Ok(())
+            }
+            (guild_wait, user_wait) => Err(std::cmp::max(
+                guild_wait.unwrap_or_default(),
+                user_wait.unwrap_or_default(),
+            )),
+        }
+    }
+}
+
+/// Drops uses which have fallen out of the window, then reports how long until there's room for
+/// another, if there isn't room now. A limit of 0 is never room, to turn the commands off
+fn wait_time<K: Eq + Hash>(
+    uses: &mut HashMap<K, VecDeque<Instant>>,
+    key: &K,
+    limit: &RateLimit,
+    now: Instant,
+) -> Option<Duration> {
+    if limit.limit == 0 {
+        return Some(limit.time_span);
+    }
+    let window = uses.get_mut(key)?;
+    while let Some(oldest) = window.front() {
+        if now.duration_since(*oldest) >= limit.time_span {
+            window.pop_front();
+        } else {
+            break;
+        }
+    }
+    if window.len() < limit.limit as usize {
+        None
+    } else {
+        window
+            .front()
+            .map(|oldest| limit.time_span - now.duration_since(*oldest))
+    }
+}
+
+/// Takes an expensive use for [user_id] in [guild_id], for work started other than by a command
+/// with the [Expensive] check. Explains how long to wait if there's no room
+pub async fn take_expensive_use(
+    ctx: &Context,
+    guild_id: Option<GuildId>,
+    user_id: UserId,
+) -> std::result::Result<(), String> {
+    let rate_limiter_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<RateLimiterData>()
+            .expect("Expected RateLimiterData in TypeMap.")
+            .clone()
+    };
+    let mut rate_limiter = rate_limiter_lock.lock().unwrap();
+    let turned_off = guild_id
+        .is_some_and(|guild_id| rate_limiter.config.limit_for_guild(&guild_id).limit == 0)
+        || rate_limiter.config.per_user.limit == 0;
+    if turned_off {
+        return Err(String::from("This command is turned off here"));
+    }
+    rate_limiter
+        .try_take(guild_id, user_id, Instant::now())
+        .map_err(|wait| {
+            format!(
+                "This command is popular right now, try again in {}s",
+                wait.as_secs() + 1
+            )
+        })
+}
+
+#[check]
+#[name = "Expensive"]
+#[check_in_help(false)]
+async fn expensive_check(
+    ctx: &Context,
+    msg: &Message,
+    _: &mut Args,
+    _: &CommandOptions,
+) -> std::result::Result<(), Reason> {
+    // Leaves the allowance to people, bots never get past [before_command] anyway
+    if from_bot_or_webhook(msg) {
+        return Err(Reason::Log(String::from("Bots can't run commands")));
+    }
+    take_expensive_use(ctx, msg.guild_id, msg.author.id)
+        .await
+        .map_err(Reason::User)
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::config::{RateLimit, RateLimitConfig};
+    use crate::rate_limit::RateLimiter;
+    use serenity::model::id::{GuildId, UserId};
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+
+    fn limiter() -> RateLimiter {
+        let mut guild_overrides = HashMap::new();
+        guild_overrides.insert(
+            GuildId(3),
+            RateLimit {
+                limit: 1,
+                time_span: Duration::from_secs(10),
+            },
+        );
+        RateLimiter::new(RateLimitConfig {
+            per_guild: RateLimit {
+                limit: 3,
+                time_span: Duration::from_secs(10),
+            },
+            per_user: RateLimit {
+                limit: 2,
+                time_span: Duration::from_secs(10),
+            },
+            guild_overrides,
+        })
+    }
+
+    #[test]
+    fn per_user_limit() {
+        let mut limiter = limiter();
+        let now = Instant::now();
+        assert!(limiter.try_take(Some(GuildId(1)), UserId(1), now).is_ok());
+        assert!(limiter.try_take(Some(GuildId(1)), UserId(1), now).is_ok());
+        assert_eq!(
+            limiter.try_take(Some(GuildId(1)), UserId(1), now),
+            Err(Duration::from_secs(10))
+        );
+        // Another user in the same guild is fine
+        assert!(limiter.try_take(Some(GuildId(1)), UserId(2), now).is_ok());
+        // And the window frees up again
+        let later = now + Duration::from_secs(10);
+        assert!(limiter.try_take(Some(GuildId(1)), UserId(1), later).is_ok());
+    }
+
+    #[test]
+    fn zero_limit_disables() {
+        let mut limiter = limiter();
+        limiter.config.guild_overrides.insert(
+            GuildId(4),
+            RateLimit {
+                limit: 0,
+                time_span: Duration::from_secs(10),
+            },
+        );
+        let now = Instant::now();
+        assert_eq!(
+            limiter.try_take(Some(GuildId(4)), UserId(1), now),
+            Err(Duration::from_secs(10))
+        );
+        // Not even once the window's passed, and nobody else's allowance is touched
+        let later = now + Duration::from_secs(60);
+        assert!(limiter
+            .try_take(Some(GuildId(4)), UserId(1), later)
+            .is_err());
+        assert!(limiter.try_take(Some(GuildId(1)), UserId(1), later).is_ok());
+    }
+
+    #[test]
+    fn per_guild_limit_does_not_starve_other_guilds() {
+        let mut limiter = limiter();
+        let now = Instant::now();
+        for user in 1..=3 {
+            assert!(limiter
+                .try_take(Some(GuildId(1)), UserId(user), now)
+                .is_ok());
+        }
+        assert!(limiter.try_take(Some(GuildId(1)), UserId(4), now).is_err());
+        assert!(limiter.try_take(Some(GuildId(2)), UserId(4), now).is_ok());
+    }
+
+    #[test]
+    fn failed_take_uses_nothing() {
+        let mut limiter = limiter();
+        let now = Instant::now();
+        assert!(limiter.try_take(Some(GuildId(3)), UserId(1), now).is_ok());
+        // Guild is limited, so user 2 shouldn't lose any of their allowance for trying
+        assert!(limiter.try_take(Some(GuildId(3)), UserId(2), now).is_err());
+        assert!(limiter.try_take(Some(GuildId(1)), UserId(2), now).is_ok());
+        assert!(limiter.try_take(Some(GuildId(1)), UserId(2), now).is_ok());
+    }
+
+    #[test]
+    fn guild_override() {
+        let mut limiter = limiter();
+        let now = Instant::now();
+        let soon = now + Duration::from_secs(4);
+        assert!(limiter.try_take(Some(GuildId(3)), UserId(1), now).is_ok());
+        assert_eq!(
+            limiter.try_take(Some(GuildId(3)), UserId(2), soon),
+            Err(Duration::from_secs(6))
+        );
+    }
+}
@@ -0,0 +1,817 @@
+use crate::guild_config::{ShoutoutConfig, WordCloudPeriod};
+use crate::state::{Store, StoreData};
+use chrono::{DateTime, Datelike, Utc};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use serenity::model::prelude::*;
+use serenity::prelude::Context;
+use serenity::utils::MessageBuilder;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const TICK: Duration = Duration::from_secs(60);
+
+/// An automated post held back until the server's quiet hours end
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeferredPost {
+    pub channel_id: ChannelId,
+    pub content: String,
+    pub deferred_at: DateTime<Utc>,
+    // Posts carrying someone else's text, like reminders, may only ping this user
+    #[serde(default)]
+    pub only_ping: Option<UserId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ReminderDelivery {
+    DirectMessage,
+    // Pings the writer in the channel the reminder was set in
+    Channel,
+}
+
+/// A writer's note to themselves, set with [remind]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reminder {
+    pub user_id: UserId,
+    pub channel_id: ChannelId,
+    pub due: DateTime<Utc>,
+    pub text: String,
+    pub delivery: ReminderDelivery,
+    // Failed deliveries so far, it's retried until MAX_REMINDER_ATTEMPTS
+    #[serde(default)]
+    pub failed_attempts: u32,
+}
+
+const MAX_REMINDER_ATTEMPTS: u32 = 5;
+const REMINDER_RETRY_MINUTES: i64 = 10;
+
+/// A word cloud a channel's schedule says is due, covering the messages [since]
+#[derive(Debug)]
+pub struct ScheduledWordCloud {
+    pub server_id: GuildId,
+    pub channel_id: ChannelId,
+    pub period: WordCloudPeriod,
+    pub since: DateTime<Utc>,
+}
+
+pub const DEFAULT_SHOUTOUT_MESSAGE: &str =
+    "Writer of the week: {writer}, with {words} words over the last 7 days!";
+
+/// A week's top writers, due to be announced. [previous_winners] lose the shoutout role
+#[derive(Debug)]
+pub struct Shoutout {
+    pub server_id: GuildId,
+    pub config: ShoutoutConfig,
+    pub winners: Vec<UserId>,
+    pub words: usize,
+    // Weeks running a lone winner has been top, ties always count as 1
+    pub streak: usize,
+    pub previous_winners: Vec<UserId>,
+}
+
+// Per stall, so a story that's been abandoned isn't nudged forever
+const MAX_NUDGES_PER_STALL: u32 = 2;
+
+/// Nudges sent since the channel last had a message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NudgeState {
+    pub last_message: MessageId,
+    pub nudges_sent: u32,
+    pub last_nudge: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+struct Nudge {
+    channel_id: ChannelId,
+    idle_days: i64,
+    // Who to DM, if the channel is set to nudge its last poster
+    dm: Option<UserId>,
+}
+
+/// Whether an automated post to the server would be held back right now
+pub async fn is_quiet_now(ctx: &Context, server_id: &GuildId) -> bool {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let store = store_lock.read().unwrap();
+    store
+        .get_server_data(server_id)
+        .is_some_and(|server_data| server_data.config.is_quiet_at(&Utc::now()))
+}
+
+/// Posts the bot makes on its own (rather than in reply to a command) should go through here, so
+/// they respect quiet hours. Returns the message if it was sent straight away
+pub async fn send_automated_post(
+    ctx: &Context,
+    server_id: &GuildId,
+    channel_id: ChannelId,
+    content: String,
+) -> serenity::Result<Option<Message>> {
+    send_or_defer(ctx, server_id, channel_id, content, None).await
+}
+
+/// As [send_automated_post], for content written by [user_id] that may only ping them, so it can't
+/// carry an @everyone or a role ping
+pub async fn send_automated_ping(
+    ctx: &Context,
+    server_id: &GuildId,
+    channel_id: ChannelId,
+    content: String,
+    user_id: UserId,
+) -> serenity::Result<Option<Message>> {
+    send_or_defer(ctx, server_id, channel_id, content, Some(user_id)).await
+}
+
+async fn send_or_defer(
+    ctx: &Context,
+    server_id: &GuildId,
+    channel_id: ChannelId,
+    content: String,
+    only_ping: Option<UserId>,
+) -> serenity::Result<Option<Message>> {
+    let store_lock = {
+        let data_read = ctx.data.read().await;
+        data_read
+            .get::<StoreData>()
+            .expect("Expected StoryData in TypeMap.")
+            .clone()
+    };
+    let deferred = {
+        let mut store = store_lock.write().unwrap();
+        let now = Utc::now();
+        match store.get_server_data(server_id) {
+            Some(server_data) if server_data.config.is_quiet_at(&now) => {
+                store
+                    .get_server_data_mut_maybe_create(server_id)
+                    .deferred_posts
+                    .push(DeferredPost {
+                        channel_id,
+                        content: content.clone(),
+                        deferred_at: now,
+                        only_ping,
+                    });
+                true
+            }
+            _ => false,
+        }
+    };
+    match deferred {
+        true => Ok(None),
+        false => say(ctx, channel_id, &content, only_ping).await.map(Some),
+    }
+}
+
+async fn say(
+    ctx: &Context,
+    channel_id: ChannelId,
+    content: &str,
+    only_ping: Option<UserId>,
+) -> serenity::Result<Message> {
+    channel_id
+        .send_message(&ctx.http, |create_message| {
+            create_message.content(content);
+            if let Some(user_id) = only_ping {
+                create_message
+                    .allowed_mentions(|mentions| mentions.empty_parse().users(vec![user_id]));
+            }
+            create_message
+        })
+        .await
+}
+
+/// Takes the deferred posts of every server whose quiet hours are over
+fn take_due_posts(store: &mut Store, now: &DateTime<Utc>) -> Vec<DeferredPost> {
+    store
+        .data
+        .values_mut()
+        .filter(|server_data| !server_data.config.is_quiet_at(now))
+        .flat_map(|server_data| server_data.deferred_posts.drain(..))
+        .collect()
+}
+
+fn take_due_reminders(store: &mut Store, now: &DateTime<Utc>) -> Vec<(GuildId, Reminder)> {
+    let mut due_reminders = vec![];
+    for (server_id, server_data) in store.data.iter_mut() {
+        let (due, pending): (Vec<Reminder>, Vec<Reminder>) = server_data
+            .reminders
+            .drain(..)
+            .partition(|reminder| reminder.due <= *now);
+        server_data.reminders = pending;
+        due_reminders.extend(due.into_iter().map(|reminder| (*server_id, reminder)));
+    }
+    due_reminders
+}
+
+async fn deliver_reminder(
+    ctx: &Context,
+    server_id: &GuildId,
+    reminder: Reminder,
+) -> serenity::Result<()> {
+    let content = format!("Reminder: {}", reminder.text);
+    if reminder.delivery == ReminderDelivery::DirectMessage {
+        let dm = reminder.user_id.create_dm_channel(ctx).await;
+        match dm {
+            Ok(dm) if dm.say(&ctx.http, &content).await.is_ok() => return Ok(()),
+            // DMs closed, so ping them in the channel instead
+            _ => (),
+        }
+    }
+    let content = format!("{} {}", reminder.user_id.mention(), content);
+    send_automated_ping(
+        ctx,
+        server_id,
+        reminder.channel_id,
+        content,
+        reminder.user_id,
+    )
+    .await
+    .map(|_| ())
+}
+
+/// Puts a reminder that failed to send back to be tried again later, unless it's failed too often
+/// already. Returns whether it was put back
+fn return_failed_reminder(
+    store: &mut Store,
+    server_id: &GuildId,
+    mut reminder: Reminder,
+    now: &DateTime<Utc>,
+) -> bool {
+    reminder.failed_attempts += 1;
+    match store.data.get_mut(server_id) {
+        Some(server_data) if reminder.failed_attempts < MAX_REMINDER_ATTEMPTS => {
+            reminder.due = *now + chrono::Duration::minutes(REMINDER_RETRY_MINUTES);
+            server_data.reminders.push(reminder);
+            true
+        }
+        _ => false,
+    }
+}
+
+async fn deliver_due_reminders(ctx: &Context) {
+    let due_reminders = {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let mut store = store_lock.write().unwrap();
+        take_due_reminders(&mut store, &Utc::now())
+    };
+    for (server_id, reminder) in due_reminders {
+        if let Err(why) = deliver_reminder(ctx, &server_id, reminder.clone()).await {
+            error!("Error delivering reminder: {:?}", why);
+            let store_lock = {
+                let data_read = ctx.data.read().await;
+                data_read
+                    .get::<StoreData>()
+                    .expect("Expected StoryData in TypeMap.")
+                    .clone()
+            };
+            let mut store = store_lock.write().unwrap();
+            if !return_failed_reminder(&mut store, &server_id, reminder, &Utc::now()) {
+                error!("Gave up on a reminder for {}", server_id);
+            }
+        }
+    }
+}
+
+/// Channels which have been quiet for longer than their nudge setting, and haven't been nudged
+/// too much already this stall. Records the nudge as sent
+fn take_due_nudges(store: &mut Store, now: &DateTime<Utc>) -> Vec<(GuildId, Nudge)> {
+    let mut due_nudges = vec![];
+    for (server_id, server_data) in store.data.iter_mut() {
+        let mut server_nudges = vec![];
+        for (channel_id, channel_data) in server_data.channels() {
+            let nudge_config = match server_data.config.channel(channel_id).nudge {
+                Some(nudge_config) => nudge_config,
+                None => continue,
+            };
+            let after = chrono::Duration::days(nudge_config.after_days as i64);
+            let (last_message, last_message_time) = match (
+                channel_data.general_stats.last_message(),
+                channel_data.general_stats.last_message_time(),
+            ) {
+                (Some(last_message), Some(last_message_time)) => (last_message, *last_message_time),
+                _ => continue,
+            };
+            if *now - last_message_time < after {
+                continue;
+            }
+            let state = server_data.nudges.get(channel_id);
+            let nudges_sent = match state {
+                Some(state) if state.last_message == last_message => {
+                    if state.nudges_sent >= MAX_NUDGES_PER_STALL || *now - state.last_nudge < after
+                    {
+                        continue;
+                    }
+                    state.nudges_sent
+                }
+                _ => 0,
+            };
+            let dm = match nudge_config.dm_last_poster {
+                true => channel_data.last_poster().map(|user| user.id),
+                false => None,
+            };
+            server_nudges.push((
+                *channel_id,
+                NudgeState {
+                    last_message,
+                    nudges_sent: nudges_sent + 1,
+                    last_nudge: *now,
+                },
+                Nudge {
+                    channel_id: *channel_id,
+                    idle_days: (*now - last_message_time).num_days(),
+                    dm,
+                },
+            ));
+        }
+        for (channel_id, state, nudge) in server_nudges {
+            server_data.nudges.insert(channel_id, state);
+            due_nudges.push((*server_id, nudge));
+        }
+    }
+    due_nudges
+}
+
+/// Channels with a word cloud schedule due on the server's local day, at most once a day and not in
+/// quiet hours. Records the day as posted
+pub fn take_due_wordclouds(store: &mut Store, now: &DateTime<Utc>) -> Vec<ScheduledWordCloud> {
+    let mut due_wordclouds = vec![];
+    for (server_id, server_data) in store.data.iter_mut() {
+        if server_data.config.is_quiet_at(now) {
+            continue;
+        }
+        let today = server_data.config.local_date(now);
+        for (channel_id, channel_config) in server_data.config.channels.iter() {
+            let period = match channel_config.wordcloud_schedule {
+                Some(period) if period.is_due_on(&today) => period,
+                _ => continue,
+            };
+            if server_data.wordcloud_posts.get(channel_id) == Some(&today) {
+                continue;
+            }
+            server_data.wordcloud_posts.insert(*channel_id, today);
+            due_wordclouds.push(ScheduledWordCloud {
+                server_id: *server_id,
+                channel_id: *channel_id,
+                period,
+                since: *now - chrono::Duration::days(period.days()),
+            });
+        }
+    }
+    due_wordclouds
+}
+
+/// Servers whose shoutout is due on their local day, once that day and not in quiet hours or
+/// privacy mode. Records the week's top writers, even if nobody wrote so the role still moves on
+fn take_due_shoutouts(store: &mut Store, now: &DateTime<Utc>) -> Vec<Shoutout> {
+    let mut due_shoutouts = vec![];
+    for (server_id, server_data) in store.data.iter_mut() {
+        let config = match &server_data.config.shoutout {
+            Some(config) => config.clone(),
+            None => continue,
+        };
+        if server_data.config.is_quiet_at(now) || server_data.config.privacy_mode {
+            continue;
+        }
+        let today = server_data.config.local_date(now);
+        if today.weekday() != config.weekday
+            || server_data.shoutouts.last().map(|record| record.posted_on) == Some(today)
+        {
+            continue;
+        }
+        let previous_winners = server_data
+            .shoutouts
+            .last()
+            .map_or(vec![], |record| record.winners.clone());
+        let record = server_data.record_shoutout(today).clone();
+        let streak = match record.winners.as_slice() {
+            [winner] => server_data.shoutout_streak(winner),
+            _ => 1,
+        };
+        due_shoutouts.push(Shoutout {
+            server_id: *server_id,
+            config,
+            winners: record.winners,
+            words: record.words,
+            streak,
+            previous_winners,
+        });
+    }
+    due_shoutouts
+}
+
+/// [template] with {writer} and {words} filled in, noting a tie or a winning streak
+pub fn shoutout_message(
+    template: Option<&str>,
+    winners: &[UserId],
+    words: usize,
+    streak: usize,
+) -> String {
+    let writer = winners
+        .iter()
+        .map(|winner| winner.mention().to_string())
+        .collect::<Vec<String>>()
+        .join(" and ");
+    let mut message = template
+        .unwrap_or(DEFAULT_SHOUTOUT_MESSAGE)
+        .replace("{writer}", &writer)
+        .replace("{words}", &words.to_string());
+    if winners.len() > 1 {
+        message.push_str(" A tie at the top!");
+    } else if streak > 1 {
+        message.push_str(&format!(" That's {} weeks running!", streak));
+    }
+    message
+}
+
+async fn deliver_shoutout(ctx: &Context, shoutout: Shoutout) -> serenity::Result<()> {
+    if let Some(role_id) = shoutout.config.role {
+        // A failed role change (missing Manage Roles, someone who left) shouldn't stop the post
+        for user_id in shoutout.previous_winners.iter() {
+            if !shoutout.winners.contains(user_id) {
+                if let Err(why) = ctx
+                    .http
+                    .remove_member_role(shoutout.server_id.0, user_id.0, role_id.0)
+                    .await
+                {
+                    error!("Error taking back shoutout role: {:?}", why);
+                }
+            }
+        }
+        for user_id in shoutout.winners.iter() {
+            if !shoutout.previous_winners.contains(user_id) {
+                if let Err(why) = ctx
+                    .http
+                    .add_member_role(shoutout.server_id.0, user_id.0, role_id.0)
+                    .await
+                {
+                    error!("Error giving shoutout role: {:?}", why);
+                }
+            }
+        }
+    }
+    if shoutout.winners.is_empty() {
+        return Ok(());
+    }
+    let content = shoutout_message(
+        shoutout.config.message.as_deref(),
+        &shoutout.winners,
+        shoutout.words,
+        shoutout.streak,
+    );
+    send_automated_post(
+        ctx,
+        &shoutout.server_id,
+        shoutout.config.channel_id,
+        content,
+    )
+    .await
+    .map(|_| ())
+}
+
+async fn deliver_due_shoutouts(ctx: &Context) {
+    let due_shoutouts = {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let mut store = store_lock.write().unwrap();
+        take_due_shoutouts(&mut store, &Utc::now())
+    };
+    for shoutout in due_shoutouts {
+        if let Err(why) = deliver_shoutout(ctx, shoutout).await {
+            error!("Error posting shoutout: {:?}", why);
+        }
+    }
+}
+
+async fn deliver_nudge(ctx: &Context, server_id: &GuildId, nudge: Nudge) -> serenity::Result<()> {
+    if let Some(user_id) = nudge.dm {
+        let content = MessageBuilder::new()
+            .push(format!(
+                "It's been {} days since anyone posted in ",
+                nudge.idle_days
+            ))
+            .channel(nudge.channel_id)
+            .push(", and you were the last to. Is the story waiting on someone?")
+            .build();
+        if let Ok(dm) = user_id.create_dm_channel(ctx).await {
+            if dm.say(&ctx.http, &content).await.is_ok() {
+                return Ok(());
+            }
+        }
+    }
+    let content = format!(
+        "This story has been quiet for {} days, who's up next?",
+        nudge.idle_days
+    );
+    send_automated_post(ctx, server_id, nudge.channel_id, content)
+        .await
+        .map(|_| ())
+}
+
+async fn deliver_due_nudges(ctx: &Context) {
+    let due_nudges = {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let mut store = store_lock.write().unwrap();
+        take_due_nudges(&mut store, &Utc::now())
+    };
+    for (server_id, nudge) in due_nudges {
+        if let Err(why) = deliver_nudge(ctx, &server_id, nudge).await {
+            error!("Error delivering nudge: {:?}", why);
+        }
+    }
+}
+
+async fn deliver_due_posts(ctx: &Context) {
+    let due_posts = {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let mut store = store_lock.write().unwrap();
+        take_due_posts(&mut store, &Utc::now())
+    };
+    if !due_posts.is_empty() {
+        info!(
+            "Delivering {} posts deferred for quiet hours",
+            due_posts.len()
+        );
+    }
+    for post in due_posts {
+        // The channel may have gone in the meantime, nothing to do but drop the post
+        if let Err(why) = say(ctx, post.channel_id, &post.content, post.only_ping).await {
+            error!("Error delivering deferred post: {:?}", why);
+        }
+    }
+}
+
+async fn empty_expired_trash(ctx: &Context) {
+    let emptied: usize = {
+        let store_lock = {
+            let data_read = ctx.data.read().await;
+            data_read
+                .get::<StoreData>()
+                .expect("Expected StoryData in TypeMap.")
+                .clone()
+        };
+        let mut store = store_lock.write().unwrap();
+        let now = Utc::now();
+        store
+            .data
+            .values_mut()
+            .map(|server_data| server_data.empty_expired_trash(&now))
+            .sum()
+    };
+    if emptied > 0 {
+        info!("Deleted the stats of {} channels from the trash", emptied);
+    }
+}
+
+/// Background task for everything the bot does on a timer
+pub async fn scheduler_worker(ctx: Arc<Context>) {
+    loop {
+        deliver_due_reminders(&ctx).await;
+        deliver_due_nudges(&ctx).await;
+        deliver_due_shoutouts(&ctx).await;
+        deliver_due_posts(&ctx).await;
+        empty_expired_trash(&ctx).await;
+        tokio::time::sleep(TICK).await;
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::bench_corpus::synthetic_messages;
+    use crate::guild_config::NudgeConfig;
+    use crate::guild_config::QuietHours;
+    use crate::guild_config::ShoutoutConfig;
+    use crate::guild_config::WordCloudPeriod;
+    use crate::language_parsing::WordRules;
+    use crate::scheduler::{
+        return_failed_reminder, shoutout_message, take_due_nudges, take_due_posts,
+        take_due_reminders, take_due_shoutouts, take_due_wordclouds, DeferredPost, Reminder,
+        ReminderDelivery, MAX_REMINDER_ATTEMPTS, REMINDER_RETRY_MINUTES,
+    };
+    use crate::state::ChannelData;
+    use crate::state::Store;
+    use chrono::{Datelike, NaiveTime, TimeZone, Utc, Weekday};
+    use chrono_tz::Tz;
+    use serenity::model::id::{ChannelId, GuildId, UserId};
+
+    #[test]
+    fn deferred_until_quiet_hours_end() {
+        let night = Utc.ymd(2021, 6, 1).and_hms(23, 0, 0);
+        let morning = Utc.ymd(2021, 6, 2).and_hms(9, 0, 0);
+        let mut store = Store::default();
+        let server_data = store.get_server_data_mut_maybe_create(&GuildId(1));
+        server_data.config.quiet_hours = Some(QuietHours {
+            start: NaiveTime::from_hms(22, 0, 0),
+            end: NaiveTime::from_hms(8, 0, 0),
+        });
+        server_data.deferred_posts.push(DeferredPost {
+            channel_id: ChannelId(2),
+            content: String::from("Digest"),
+            deferred_at: night,
+            only_ping: None,
+        });
+        assert!(take_due_posts(&mut store, &night).is_empty());
+        let due = take_due_posts(&mut store, &morning);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].content, "Digest");
+        assert!(take_due_posts(&mut store, &morning).is_empty());
+    }
+
+    #[test]
+    fn reminders_taken_once_due() {
+        let now = Utc.ymd(2021, 6, 1).and_hms(12, 0, 0);
+        let mut store = Store::default();
+        let server_data = store.get_server_data_mut_maybe_create(&GuildId(1));
+        for hours in [1, 3].iter() {
+            server_data.reminders.push(Reminder {
+                user_id: UserId(4),
+                channel_id: ChannelId(2),
+                due: now + chrono::Duration::hours(*hours),
+                text: format!("In {}h", hours),
+                delivery: ReminderDelivery::DirectMessage,
+                failed_attempts: 0,
+            });
+        }
+        assert!(take_due_reminders(&mut store, &now).is_empty());
+        let due = take_due_reminders(&mut store, &(now + chrono::Duration::hours(2)));
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].1.text, "In 1h");
+        assert_eq!(
+            store.get_server_data(&GuildId(1)).unwrap().reminders.len(),
+            1
+        );
+    }
+
+    #[test]
+    fn failed_reminders_are_retried() {
+        let now = Utc.ymd(2021, 6, 1).and_hms(12, 0, 0);
+        let mut store = Store::default();
+        store.get_server_data_mut_maybe_create(&GuildId(1));
+        let mut reminder = Reminder {
+            user_id: UserId(4),
+            channel_id: ChannelId(2),
+            due: now,
+            text: String::from("Post the next scene"),
+            delivery: ReminderDelivery::Channel,
+            failed_attempts: 0,
+        };
+        for attempt in 1..MAX_REMINDER_ATTEMPTS {
+            assert!(return_failed_reminder(
+                &mut store,
+                &GuildId(1),
+                reminder,
+                &now
+            ));
+            assert!(take_due_reminders(&mut store, &now).is_empty());
+            let mut due = take_due_reminders(
+                &mut store,
+                &(now + chrono::Duration::minutes(REMINDER_RETRY_MINUTES)),
+            );
+            assert_eq!(due.len(), 1);
+            reminder = due.pop().unwrap().1;
+            assert_eq!(reminder.failed_attempts, attempt);
+        }
+        assert!(!return_failed_reminder(
+            &mut store,
+            &GuildId(1),
+            reminder,
+            &now
+        ));
+        assert!(store
+            .get_server_data(&GuildId(1))
+            .unwrap()
+            .reminders
+            .is_empty());
+    }
+
+    #[test]
+    fn nudges_are_limited_per_stall() {
+        let story_key = (GuildId(1), ChannelId(2));
+        let mut store = Store::default();
+        let mut channel_data = ChannelData::default();
+        let messages = synthetic_messages(3, 2);
+        for message in messages.iter() {
+            channel_data.update(message, &Tz::UTC, &WordRules::default());
+        }
+        store.insert_channel_data_maybe_create_server_data(&story_key, channel_data);
+        let server_data = store.get_server_data_mut_maybe_create(&story_key.0);
+        server_data
+            .config
+            .channels
+            .entry(story_key.1)
+            .or_default()
+            .nudge = Some(NudgeConfig {
+            after_days: 2,
+            dm_last_poster: true,
+        });
+        let last_post = messages.last().unwrap().timestamp;
+        let day = chrono::Duration::days(1);
+        assert!(take_due_nudges(&mut store, &(last_post + day)).is_empty());
+        let nudges = take_due_nudges(&mut store, &(last_post + day * 3));
+        assert_eq!(nudges.len(), 1);
+        assert_eq!(nudges[0].1.idle_days, 3);
+        assert_eq!(nudges[0].1.dm, Some(messages.last().unwrap().author.id));
+        // Not again until another stretch of quiet, and then not at all
+        assert!(take_due_nudges(&mut store, &(last_post + day * 4)).is_empty());
+        assert_eq!(take_due_nudges(&mut store, &(last_post + day * 5)).len(), 1);
+        assert!(take_due_nudges(&mut store, &(last_post + day * 30)).is_empty());
+    }
+
+    #[test]
+    fn wordclouds_posted_once_on_their_day() {
+        // 2021-06-06 was a Sunday
+        let sunday = Utc.ymd(2021, 6, 6).and_hms(12, 0, 0);
+        let mut store = Store::default();
+        let server_data = store.get_server_data_mut_maybe_create(&GuildId(1));
+        server_data
+            .config
+            .channels
+            .entry(ChannelId(2))
+            .or_default()
+            .wordcloud_schedule = Some(WordCloudPeriod::Weekly(Weekday::Sun));
+        server_data
+            .config
+            .channels
+            .entry(ChannelId(3))
+            .or_default()
+            .wordcloud_schedule = Some(WordCloudPeriod::Daily);
+        let saturday = sunday - chrono::Duration::days(1);
+        let due = take_due_wordclouds(&mut store, &saturday);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].channel_id, ChannelId(3));
+
+        let mut due = take_due_wordclouds(&mut store, &sunday);
+        due.sort_by_key(|wordcloud| wordcloud.channel_id);
+        assert_eq!(due.len(), 2);
+        assert_eq!(due[0].since, sunday - chrono::Duration::days(7));
+        let later = sunday + chrono::Duration::hours(6);
+        assert!(take_due_wordclouds(&mut store, &later).is_empty());
+    }
+
+    #[test]
+    fn shoutouts_weekly_with_streaks() {
+        let story_key = (GuildId(1), ChannelId(2));
+        let mut store = Store::default();
+        let mut channel_data = ChannelData::default();
+        let messages = synthetic_messages(20, 1);
+        for message in messages.iter() {
+            channel_data.update(message, &Tz::UTC, &WordRules::default());
+        }
+        let writer = messages[0].author.id;
+        store.insert_channel_data_maybe_create_server_data(&story_key, channel_data);
+        let day_after = messages.last().unwrap().timestamp + chrono::Duration::days(1);
+        let server_data = store.get_server_data_mut_maybe_create(&story_key.0);
+        server_data.config.shoutout = Some(ShoutoutConfig {
+            channel_id: story_key.1,
+            weekday: day_after.weekday(),
+            message: None,
+            role: None,
+        });
+        assert!(
+            take_due_shoutouts(&mut store, &(day_after - chrono::Duration::days(1))).is_empty()
+        );
+        let due = take_due_shoutouts(&mut store, &day_after);
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].winners, vec![writer]);
+        assert!(due[0].words > 0);
+        assert!(
+            take_due_shoutouts(&mut store, &(day_after + chrono::Duration::hours(1))).is_empty()
+        );
+
+        // Nobody wrote the week after, so last week's role goes
+        let due = take_due_shoutouts(&mut store, &(day_after + chrono::Duration::days(7)));
+        assert!(due[0].winners.is_empty());
+        assert_eq!(due[0].previous_winners, vec![writer]);
+
+        let tied = shoutout_message(None, &[UserId(3), UserId(4)], 100, 1);
+        assert!(tied.contains("<@3> and <@4>, with 100 words"));
+        assert!(tied.ends_with("A tie at the top!"));
+        let repeat = shoutout_message(Some("Well done {writer}"), &[UserId(3)], 100, 3);
+        assert_eq!(repeat, "Well done <@3> That's 3 weeks running!");
+    }
+}
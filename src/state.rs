@@ -1,17 +1,30 @@
-use crate::stats::WordStats;
+use crate::encryption::{is_encrypted, StateKey, STATE_KEY_ENV};
+use crate::guild_config::{Character, GuildConfig};
+use crate::language_parsing::WordRules;
+use crate::scheduler::{DeferredPost, NudgeState, Reminder};
+use crate::stats::{
+    co_writers, count_words, counted_words, without_co_writing_tag, Anomaly, ResponseTimeStats,
+    SessionStats, WordStats,
+};
 use crate::utils::iterators::helpers::sort_by_last_message_and_maybe_truncate;
 use crate::utils::trait_extensions::MessageBuilderExt;
-use log::debug;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::{debug, info};
 use serde::{Deserialize, Serialize};
 use serenity::model::channel::{GuildChannel, Message};
 use serenity::model::id::{ChannelId, GuildId, MessageId, UserId};
-use serenity::model::prelude::Channel;
 use serenity::model::user::User;
-use serenity::prelude::TypeMapKey;
+use serenity::prelude::{Mentionable, TypeMapKey};
 use serenity::utils::MessageBuilder;
-use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::{ErrorKind, Read, Write};
+use std::borrow::Cow;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 
 pub struct StoreData;
@@ -25,12 +38,48 @@ pub struct Store {
     replay_needed: bool,
     queued_messages_until_replay: Vec<(StoryKey, Message)>,
     pub initialising_channels: HashSet<StoryKey>,
+    // Live messages for channels whose history is being recounted, see [start_rebuild]
+    #[serde(skip)]
+    rebuilding_channels: HashMap<StoryKey, Vec<Message>>,
     pub data: StoreInnerData,
+    // Writing users have logged by DMing the bot, see [PersonalLog]
+    pub personal_logs: HashMap<UserId, PersonalLog>,
+    // Cap on distinct words tracked per stats in a channel, from [GeneralAppConfig]
+    #[serde(skip)]
+    pub max_words_per_channel: Option<usize>,
+    // State is written unencrypted if not set
+    #[serde(skip)]
+    pub state_key: Option<StateKey>,
+    // Where [dump] writes to, the path [load] read from
+    #[serde(skip)]
+    pub state_path: PathBuf,
 }
 
 type StoreInnerData = HashMap<GuildId, ServerData>;
 
-const STATE_FILENAME: &str = "state.sexp";
+/// What [Store::merge_dump] took from the other state file
+#[derive(Debug, Default, PartialEq)]
+pub struct DumpMerge {
+    pub servers_added: usize,
+    pub channels_taken: usize,
+    pub personal_logs_added: usize,
+}
+
+/// What's written to the state file. Files from before personal logs are just [StoreInnerData]
+#[derive(Serialize)]
+struct PersistedStoreRef<'a> {
+    servers: &'a StoreInnerData,
+    personal_logs: &'a HashMap<UserId, PersonalLog>,
+}
+
+#[derive(Deserialize)]
+struct PersistedStore {
+    servers: StoreInnerData,
+    #[serde(default)]
+    personal_logs: HashMap<UserId, PersonalLog>,
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
 
 impl Store {
     fn new(data: HashMap<GuildId, ServerData>) -> Self {
@@ -38,34 +87,90 @@ impl Store {
             replay_needed: true,
             queued_messages_until_replay: Vec::new(),
             initialising_channels: HashSet::new(),
+            rebuilding_channels: HashMap::new(),
             data,
+            personal_logs: HashMap::new(),
+            max_words_per_channel: None,
+            state_key: None,
+            state_path: PathBuf::new(),
         }
     }
+    /// State is gzipped as per-word and per-day counts make it large, then encrypted if there's a
+    /// [state_key]. [load] still reads the uncompressed files from before this
     pub fn dump(&self) -> serde_pickle::error::Result<()> {
-        let tmp_file = "state.pickle.tmp";
-        let mut f = File::create(tmp_file).unwrap();
-        let serialise_result = serde_pickle::to_writer(&mut f, &self.data, true);
+        let tmp_file = self.state_path.with_extension("pickle.tmp");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        let persisted = PersistedStoreRef {
+            servers: &self.data,
+            personal_logs: &self.personal_logs,
+        };
+        let serialise_result = serde_pickle::to_writer(&mut encoder, &persisted, true);
         if serialise_result.is_ok() {
-            std::fs::rename(tmp_file, STATE_FILENAME)?;
+            let mut bytes = encoder.finish()?;
+            if let Some(state_key) = &self.state_key {
+                bytes = state_key.encrypt(bytes)?;
+            }
+            std::fs::write(&tmp_file, bytes)?;
+            std::fs::rename(&tmp_file, &self.state_path)?;
         }
         serialise_result
     }
 
-    pub fn load() -> serde_pickle::error::Result<Self> {
-        match File::open(STATE_FILENAME) {
-            Ok(mut f) =>
+    /// Reads unencrypted state as well as encrypted, so setting a key for the first time takes
+    /// effect on the next [dump]
+    pub fn load(
+        state_path: &Path,
+        state_key: Option<StateKey>,
+    ) -> serde_pickle::error::Result<Self> {
+        let mut store = match std::fs::read(state_path) {
+            Ok(bytes) =>
             //ron::de::from_reader::<_, StoreInnerData>(f).map(|data| Store::new(data)),
             {
                 //bincode::deserialize_from(f).map(|data| Store::new(data))
-                serde_pickle::from_reader(f).map(|data| Store::new(data))
-            }
-            Err(e) if e.kind() == ErrorKind::NotFound => {
-                serde_pickle::error::Result::Ok(Store::default())
+                let bytes = match (is_encrypted(&bytes), &state_key) {
+                    (true, Some(state_key)) => state_key.decrypt(&bytes)?,
+                    (true, None) => {
+                        return Err(std::io::Error::new(
+                            ErrorKind::InvalidData,
+                            format!("State file is encrypted, set {} to load it", STATE_KEY_ENV),
+                        )
+                        .into())
+                    }
+                    (false, _) => bytes,
+                };
+                let bytes = match bytes.starts_with(&GZIP_MAGIC) {
+                    true => {
+                        let mut decompressed = vec![];
+                        GzDecoder::new(bytes.as_slice()).read_to_end(&mut decompressed)?;
+                        decompressed
+                    }
+                    false => bytes,
+                };
+                Store::from_pickle(&bytes)?
             }
+            Err(e) if e.kind() == ErrorKind::NotFound => Store::default(),
             Err(other) => panic!("Failed opening state file: {}", other),
+        };
+        store.state_key = state_key;
+        store.state_path = state_path.to_path_buf();
+        Ok(store)
+    }
+
+    fn from_pickle(bytes: &[u8]) -> serde_pickle::error::Result<Self> {
+        match serde_pickle::from_slice::<PersistedStore>(bytes) {
+            Ok(persisted) => {
+                let mut store = Store::new(persisted.servers);
+                store.personal_logs = persisted.personal_logs;
+                Ok(store)
+            }
+            Err(_) => serde_pickle::from_slice(bytes).map(Store::new),
         }
     }
 
+    pub fn state_is_encrypted(&self) -> std::io::Result<bool> {
+        std::fs::read(&self.state_path).map(|bytes| is_encrypted(&bytes))
+    }
+
     pub fn story_keys_with_last_message(&self) -> Vec<(StoryKey, MessageId)> {
         self.data
             .iter()
@@ -85,14 +190,20 @@ impl Store {
             .map_or(vec![], |server| server.get_all_channel_ids())
     }
 
+    pub fn set_channel_accessible(&mut self, (server_id, channel_id): &StoryKey, accessible: bool) {
+        if let Some(server_data) = self.data.get_mut(server_id) {
+            match accessible {
+                true => server_data.inaccessible_channels.remove(channel_id),
+                false => server_data.inaccessible_channels.insert(*channel_id),
+            };
+        }
+    }
+
     pub fn finish_replay(&mut self) {
         let replay_queue: Vec<(StoryKey, Message)> =
             self.queued_messages_until_replay.drain(..).collect();
         for (key, message) in replay_queue {
-            match self.get_channel_data_mut(&key) {
-                Some(story_data) => story_data.update(&message),
-                None => debug!("Message not in a channel that's been initialised"),
-            }
+            self.update_channel_data(&key, &message);
         }
         //self.queued_messages_until_replay.clear();
         self.replay_needed = false;
@@ -102,16 +213,99 @@ impl Store {
         match self.replay_needed {
             true => self
                 .queued_messages_until_replay
-                .push((story_key.clone(), message.clone())),
-            false => match self.get_channel_data_mut(&story_key) {
-                Some(story_data) => story_data.update(message),
-                None => debug!("Message not in a channel that's been initialised"),
-            },
+                .push((*story_key, message.clone())),
+            false => self.update_channel_data(story_key, message),
+        }
+    }
+
+    /// Keeps the channel's live messages from now until [finish_rebuild], counting them as usual
+    /// meanwhile, so those posted while its history is fetched aren't lost from the rebuilt stats
+    pub fn start_rebuild(&mut self, story_key: &StoryKey) {
+        self.rebuilding_channels.insert(*story_key, Vec::new());
+    }
+
+    /// Swaps [rebuilt] in for the channel's stats, then counts the live messages kept since
+    /// [start_rebuild] which came after [counted_up_to], the newest message its history was read
+    /// from. False, leaving the stats as they were, if there's no [rebuilt] or the channel was
+    /// deinitialised meanwhile
+    pub fn finish_rebuild(
+        &mut self,
+        story_key: &StoryKey,
+        rebuilt: Option<ChannelData>,
+        counted_up_to: Option<MessageId>,
+    ) -> bool {
+        let kept = self
+            .rebuilding_channels
+            .remove(story_key)
+            .unwrap_or_default();
+        let rebuilt = match rebuilt {
+            Some(rebuilt) if self.channel_data_exists(story_key) => rebuilt,
+            _ => return false,
+        };
+        self.insert_channel_data_maybe_create_server_data(story_key, rebuilt);
+        for message in kept
+            .iter()
+            .filter(|message| counted_up_to.is_none_or(|counted_up_to| message.id > counted_up_to))
+        {
+            self.update_channel_data(story_key, message);
+        }
+        true
+    }
+
+    /// Updates the channel's stats with [message], and the author's display name along with it
+    pub fn update_channel_data(&mut self, (server_id, channel_id): &StoryKey, message: &Message) {
+        if let Some(kept) = self.rebuilding_channels.get_mut(&(*server_id, *channel_id)) {
+            kept.push(message.clone());
+        }
+        let max_words = self.max_words_per_channel;
+        match self.data.get_mut(server_id) {
+            Some(server_data) => {
+                server_data.update_channel_data(channel_id, message);
+                if let Some(max_words) = max_words {
+                    server_data.prune_channel_words(channel_id, max_words);
+                }
+            }
+            None => debug!("Message not in a server with initialised channels"),
+        }
+    }
+
+    pub fn set_author_departed(&mut self, server_id: &GuildId, user_id: &UserId, departed: bool) {
+        if let Some(server_data) = self.data.get_mut(server_id) {
+            // Only authors are worth remembering, not everyone who passes through the server
+            let is_author = server_data.display_names.get_by_id(user_id).is_some();
+            match departed {
+                true if is_author => server_data.departed_authors.insert(*user_id),
+                _ => server_data.departed_authors.remove(user_id),
+            };
+        }
+    }
+
+    pub fn update_display_name(&mut self, server_id: &GuildId, user_id: &UserId, name: &str) {
+        if let Some(server_data) = self.data.get_mut(server_id) {
+            server_data.display_names.update_if_known(user_id, name);
+        }
+    }
+
+    pub fn servers(&self) -> impl Iterator<Item = (&GuildId, &ServerData)> {
+        self.data.iter()
+    }
+
+    pub fn check_integrity(&mut self, latest_day: NaiveDate) -> Vec<(GuildId, Anomaly)> {
+        let mut anomalies = vec![];
+        for (server_id, server_data) in self.data.iter_mut() {
+            anomalies.extend(
+                server_data
+                    .check_integrity(latest_day)
+                    .into_iter()
+                    .map(|anomaly| (*server_id, anomaly)),
+            );
         }
+        anomalies.sort_by_key(|(server_id, _)| *server_id);
+        anomalies
     }
 
     pub fn get_unique_server_ids(&self) -> Vec<GuildId> {
-        let mut guild_ids: Vec<GuildId> = self.data.keys().map(|id| id.clone()).collect();
+        let mut guild_ids: Vec<GuildId> = self.data.keys().copied().collect();
         guild_ids.sort();
         guild_ids.dedup();
         guild_ids
@@ -165,39 +359,387 @@ impl Store {
         };
         server_data.insert(channel_id, channel_data);
     }
+
+    pub fn remove_channel_data(
+        &mut self,
+        (server_id, channel_id): &StoryKey,
+    ) -> Option<ChannelData> {
+        self.data
+            .get_mut(server_id)
+            .and_then(|server_data| server_data.channels.remove(channel_id))
+    }
+
+    /// Folds the stats of [from] into [into], and stops tracking [from]
+    pub fn merge_channel_data(
+        &mut self,
+        from: &StoryKey,
+        into: &StoryKey,
+    ) -> std::result::Result<(), String> {
+        self.check_mergeable(from, into)?;
+        match self.remove_channel_data(from) {
+            Some(from_channel_data) => {
+                //Checked above that this exists
+                let into_channel_data = self.get_channel_data_mut(into).unwrap();
+                into_channel_data.merge(from_channel_data);
+                Ok(())
+            }
+            None => Err(String::from("Channel to merge from is not initialised")),
+        }
+    }
+
+    fn check_mergeable(&self, from: &StoryKey, into: &StoryKey) -> std::result::Result<(), String> {
+        if from == into {
+            Err(String::from("Can't merge a channel into itself"))
+        } else if !self.channel_data_exists(into) {
+            Err(String::from("Channel to merge into is not initialised"))
+        } else if !self.channel_data_exists(from) {
+            Err(String::from("Channel to merge from is not initialised"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The stats [into] would have after [merge_channel_data], leaving the store as it is
+    pub fn preview_merge_channel_data(
+        &self,
+        from: &StoryKey,
+        into: &StoryKey,
+    ) -> std::result::Result<ChannelData, String> {
+        self.check_mergeable(from, into)?;
+        //Checked above that both exist
+        let mut merged = self.get_channel_data(into).unwrap().clone();
+        merged.merge(self.get_channel_data(from).unwrap().clone());
+        Ok(merged)
+    }
+
+    pub fn get_server_data_mut_maybe_create(&mut self, server_id: &GuildId) -> &mut ServerData {
+        self.data.entry(*server_id).or_default()
+    }
+
+    /// Folds another state file's servers into this one, e.g. one left by a bot run from a backup.
+    /// Both count from the same channel histories, so where both have a channel the one counted
+    /// up to the later message is kept rather than adding them up
+    pub fn merge_dump(&mut self, other: Store) -> DumpMerge {
+        let mut merge = DumpMerge::default();
+        for (server_id, other_server_data) in other.data {
+            match self.data.get_mut(&server_id) {
+                Some(server_data) => {
+                    merge.channels_taken += server_data.merge_dump(other_server_data)
+                }
+                None => {
+                    merge.servers_added += 1;
+                    self.data.insert(server_id, other_server_data);
+                }
+            }
+        }
+        for (user_id, personal_log) in other.personal_logs {
+            if let Entry::Vacant(entry) = self.personal_logs.entry(user_id) {
+                merge.personal_logs_added += 1;
+                entry.insert(personal_log);
+            }
+        }
+        merge
+    }
+
+    /// Usage is only kept for servers the bot has stats for, rather than creating server data for
+    /// every server a command is tried in
+    pub fn record_command_use(&mut self, server_id: &GuildId, command_name: &str) {
+        if let Some(server_data) = self.data.get_mut(server_id) {
+            server_data
+                .command_usage
+                .entry(command_name.to_string())
+                .or_default()
+                .uses += 1;
+        }
+    }
+
+    pub fn record_command_error(&mut self, server_id: &GuildId, command_name: &str) {
+        if let Some(server_data) = self.data.get_mut(server_id) {
+            server_data
+                .command_usage
+                .entry(command_name.to_string())
+                .or_default()
+                .errors += 1;
+        }
+    }
 }
 
 pub type StoryKey = (GuildId, ChannelId);
 
+#[derive(Debug, Default)]
+pub struct StatsDisplayOptions {
+    pub truncate_limit: Option<usize>,
+    // Show authors who have left the server as one "(departed)" entry, rather than individually
+    pub fold_departed: bool,
+    // Show characters rather than players, for roleplay servers
+    pub by_character: bool,
+    // Show an archived season rather than the current one, numbered from 1
+    pub season: Option<usize>,
+}
+
+/// Writing a user logs by DMing the bot once they've opted in, kept apart from any server's stats
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PersonalLog {
+    pub logging: bool,
+    pub word_count: usize,
+    pub entries: usize,
+    daily_word_counts: BTreeMap<NaiveDate, usize>,
+    // Words a day that keep a streak going, any writing at all does if unset
+    pub daily_goal: Option<usize>,
+    // Days are in UTC if unset
+    pub timezone: Option<Tz>,
+}
+
+impl PersonalLog {
+    pub fn today(&self) -> NaiveDate {
+        Utc::now()
+            .with_timezone(&self.timezone.unwrap_or(Tz::UTC))
+            .naive_local()
+            .date()
+    }
+
+    /// Returns the words counted from [text]
+    pub fn log(&mut self, text: &str, day: NaiveDate) -> usize {
+        let words = count_words(text, &WordRules::Standard);
+        self.word_count += words;
+        self.entries += 1;
+        *self.daily_word_counts.entry(day).or_insert(0) += words;
+        words
+    }
+
+    pub fn words_on(&self, day: &NaiveDate) -> usize {
+        self.daily_word_counts.get(day).copied().unwrap_or(0)
+    }
+
+    fn met_goal(&self, day: &NaiveDate) -> bool {
+        let words = self.words_on(day);
+        words > 0 && words >= self.daily_goal.unwrap_or(0)
+    }
+
+    /// Days in a row the goal was met, up to [today]. Today not being done yet doesn't break it
+    pub fn streak(&self, today: NaiveDate) -> usize {
+        let mut day = match self.met_goal(&today) {
+            true => today,
+            false => today.pred_opt().unwrap_or(today),
+        };
+        let mut streak = 0;
+        while self.met_goal(&day) {
+            streak += 1;
+            day = match day.pred_opt() {
+                Some(previous) => previous,
+                None => break,
+            };
+        }
+        streak
+    }
+}
+
+/// Last seen display name (server nickname, or username) of authors, so stats can be rendered
+/// without looking users up, and still show a name for users who have since left
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DisplayNameCache {
+    names: HashMap<UserId, String>,
+    // Order authors were first seen in, for anonymous labels that don't change as writers join
+    #[serde(default)]
+    labels: HashMap<UserId, usize>,
+}
+
+impl DisplayNameCache {
+    pub fn update(&mut self, user_id: &UserId, name: &str) {
+        if self.names.get(user_id).map(|n| n.as_str()) != Some(name) {
+            self.names.insert(*user_id, name.to_string());
+        }
+        let next_label = self.labels.len();
+        self.labels.entry(*user_id).or_insert(next_label);
+    }
+
+    /// Labels authors known from before labels were kept, in id order
+    pub fn label_known_authors(&mut self) {
+        let mut unlabelled: Vec<UserId> = self
+            .names
+            .keys()
+            .filter(|user_id| !self.labels.contains_key(user_id))
+            .copied()
+            .collect();
+        unlabelled.sort();
+        for user_id in unlabelled {
+            let next_label = self.labels.len();
+            self.labels.insert(user_id, next_label);
+        }
+    }
+
+    /// "Writer A" to "Writer Z", then "Writer AA" and so on
+    pub fn anonymous_label(&self, user_id: &UserId) -> String {
+        match self.labels.get(user_id) {
+            Some(label) => {
+                let mut letters = vec![];
+                let mut n = label + 1;
+                while n > 0 {
+                    n -= 1;
+                    letters.push((b'A' + (n % 26) as u8) as char);
+                    n /= 26;
+                }
+                format!("Writer {}", letters.iter().rev().collect::<String>())
+            }
+            None => String::from("Anonymous writer"),
+        }
+    }
+
+    /// Only tracks names of users we already know of, i.e. authors in initialised channels
+    pub fn update_if_known(&mut self, user_id: &UserId, name: &str) {
+        if self.names.contains_key(user_id) {
+            self.update(user_id, name);
+        }
+    }
+
+    pub fn update_from_message(&mut self, message: &Message) {
+        let name = message
+            .member
+            .as_ref()
+            .and_then(|member| member.nick.as_deref())
+            .unwrap_or(&message.author.name);
+        self.update(&message.author.id, name);
+    }
+
+    pub fn get<'a>(&'a self, user: &'a User) -> &'a str {
+        self.names.get(&user.id).unwrap_or(&user.name)
+    }
+
+    pub fn get_by_id(&self, user_id: &UserId) -> Option<&str> {
+        self.names.get(user_id).map(|name| name.as_str())
+    }
+}
+
 // this could be a stable type since i intend to serialise this for disk storage.
 // this doesn't seem to be an obvious rust pattern but we could do ocaml/sexp style
 // and use an enum of v0,v1,...
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct ChannelData {
     pub author_stats: HashMap<User, WordStats>,
     pub general_stats: WordStats,
+    // By character name, only for posts that could be attributed to a registered character
+    #[serde(default)]
+    pub character_stats: HashMap<String, WordStats>,
+    // Who actually posted each co-written post, which every writer credited has in their stats
+    #[serde(default)]
+    pub co_written_posts: HashMap<MessageId, UserId>,
 }
 
 impl ChannelData {
-    pub fn update(&mut self, message: &Message) {
-        self.general_stats.update(message);
-        if let Some(word_stats) = self.author_stats.get_mut(&message.author) {
-            debug!("Updating word stats for existing author");
-            word_stats.update(message);
-        } else {
-            debug!("Inserting new word stats for new author");
-            let word_stats = WordStats::new_from_message(&message);
-            self.author_stats.insert(message.author.clone(), word_stats);
+    pub fn update(&mut self, message: &Message, timezone: &Tz, rules: &WordRules) {
+        let untagged = without_co_writing_tag(message);
+        let counted = untagged.as_ref().unwrap_or(message);
+        // Tokenised once for the channel and every author credited, it's most of the work
+        let words = counted_words(&counted.content, rules);
+        self.general_stats.update_counted(counted, &words, timezone);
+        let co_writers = co_writers(message);
+        if !co_writers.is_empty() {
+            self.co_written_posts.insert(message.id, message.author.id);
+        }
+        for author in std::iter::once(&message.author).chain(co_writers) {
+            if let Some(word_stats) = self.author_stats.get_mut(author) {
+                debug!("Updating word stats for existing author");
+                word_stats.update_counted(counted, &words, timezone);
+            } else {
+                debug!("Inserting new word stats for new author");
+                let mut word_stats = WordStats::default();
+                word_stats.update_counted(counted, &words, timezone);
+                self.author_stats.insert(author.clone(), word_stats);
+            }
+        }
+    }
+
+    pub fn update_character(
+        &mut self,
+        character: &Character,
+        message: &Message,
+        timezone: &Tz,
+        rules: &WordRules,
+    ) {
+        let untagged = without_co_writing_tag(message);
+        let message = untagged.as_ref().unwrap_or(message);
+        let key = character.name.to_lowercase();
+        match self.character_stats.get_mut(&key) {
+            Some(word_stats) => word_stats.update(message, timezone, rules),
+            None => {
+                let word_stats = WordStats::new_from_message(message, timezone, rules);
+                self.character_stats.insert(key, word_stats);
+            }
         }
     }
 
+    /// What a change would do to these stats, for a dry run before making it
+    pub fn make_change_preview_string(&self, after: &ChannelData) -> String {
+        let row = |name: &str, before: usize, after: usize| {
+            format!(
+                "{}: {} -> {} ({:+})",
+                name,
+                before,
+                after,
+                after as i64 - before as i64
+            )
+        };
+        [
+            row(
+                "Messages",
+                self.general_stats.message_ids().count(),
+                after.general_stats.message_ids().count(),
+            ),
+            row(
+                "Words",
+                self.general_stats.word_count,
+                after.general_stats.word_count,
+            ),
+            row("Writers", self.author_stats.len(), after.author_stats.len()),
+        ]
+        .join("\n")
+    }
+
+    /// The channel's longest post, who wrote it and its words. Ties go to the earlier post
+    pub fn longest_message(&self) -> Option<(&User, MessageId, usize)> {
+        self.author_stats
+            .iter()
+            .filter_map(|(author, word_stats)| {
+                word_stats
+                    .longest_message()
+                    .map(|(message_id, words)| (author, message_id, words))
+            })
+            .max_by(|a, b| a.2.cmp(&b.2).then(b.1.cmp(&a.1)))
+    }
+
+    pub fn last_poster(&self) -> Option<&User> {
+        self.author_stats
+            .iter()
+            .filter_map(|(author, word_stats)| {
+                word_stats.last_message_time().map(|time| (time, author))
+            })
+            .max_by_key(|(time, _)| *time)
+            .map(|(_, author)| author)
+    }
+
     pub fn make_stats_string(
         &self,
         text_channel: &GuildChannel,
-        truncate_limit: Option<usize>,
+        server_data: &ServerData,
+        options: &StatsDisplayOptions,
     ) -> String {
-        let mut stats_iterator =
-            sort_by_last_message_and_maybe_truncate(&self.author_stats, truncate_limit);
+        let is_departed = |author: &User| server_data.departed_authors.contains(&author.id);
+        // Departed writers are folded before truncating, so they don't take up the places shown
+        let remaining_authors: HashMap<User, WordStats>;
+        let shown_authors = match options.fold_departed {
+            true => {
+                remaining_authors = self
+                    .author_stats
+                    .iter()
+                    .filter(|(author, _)| !is_departed(author))
+                    .map(|(author, stats)| (author.clone(), stats.clone()))
+                    .collect();
+                &remaining_authors
+            }
+            false => &self.author_stats,
+        };
+        let stats_iterator =
+            sort_by_last_message_and_maybe_truncate(shown_authors, options.truncate_limit);
         let mut builder = MessageBuilder::new();
         let base_builder = builder
             .push("For ")
@@ -208,6 +750,15 @@ impl ChannelData {
                 "Word count: {}",
                 self.general_stats.word_count
             ))
+            .push_line_safe(format!(
+                "Words today ({}): {}",
+                server_data.config.timezone(),
+                self.general_stats.words_on_day(&server_data.config.local_date(&Utc::now()))
+            ))
+            .push_line_safe(format!(
+                "Images shared: {}",
+                self.general_stats.media.images
+            ))
             .apply_if(stats_iterator.is_truncated(), |mb|
                 mb.newline().push_line(
                     format!("Not all authors are displayed below, just the {} most recent ones. Add [-full] to see all of them",
@@ -217,99 +768,1842 @@ impl ChannelData {
         let final_builder = stats_iterator.fold(base_builder, |builder, (author, stats)| {
             builder
                 .newline()
-                .user(author)
+                .push_bold_safe(server_data.shown_name(author))
+                .apply_if(is_departed(author), |b| b.push(" (departed)"))
                 .newline()
                 .push_line_safe(format!("Word count: {}", stats.word_count))
+                .push_line_safe(format!("Images shared: {}", stats.media.images))
                 .push_line_safe(format!("Top words: {}", stats.top_words(10)))
         });
+        if options.fold_departed {
+            let mut departed_stats = WordStats::default();
+            let mut departed_count = 0;
+            for (_, stats) in self.author_stats.iter().filter(|(a, _)| is_departed(a)) {
+                departed_stats.merge(stats.clone());
+                departed_count += 1;
+            }
+            if departed_count > 0 {
+                final_builder
+                    .newline()
+                    .push_bold_line(format!("(departed) {} writer(s)", departed_count))
+                    .push_line_safe(format!("Word count: {}", departed_stats.word_count))
+                    .push_line_safe(format!("Images shared: {}", departed_stats.media.images))
+                    .push_line_safe(format!("Top words: {}", departed_stats.top_words(10)));
+            }
+        }
         final_builder.build()
     }
-    pub fn get_user(&self, user: &User) -> Option<&WordStats> {
-        self.author_stats.get(user)
-    }
-}
-
-#[derive(Debug, Default, Serialize, Deserialize)]
-pub struct ServerData {
-    channels: HashMap<ChannelId, ChannelData>,
-}
 
-impl ServerData {
-    pub fn new() -> Self {
-        Self {
-            channels: HashMap::new(),
+    pub fn make_character_stats_string(
+        &self,
+        text_channel: &GuildChannel,
+        server_data: &ServerData,
+        options: &StatsDisplayOptions,
+    ) -> String {
+        let mut builder = MessageBuilder::new();
+        builder.push("For ").channel(text_channel).newline();
+        if self.character_stats.is_empty() {
+            return builder
+                .push("No posts have been attributed to characters yet, register them with [character register]")
+                .build();
+        }
+        let mut characters: Vec<(&String, &WordStats)> = self.character_stats.iter().collect();
+        characters.sort_by(|(name_a, stats_a), (name_b, stats_b)| {
+            stats_b
+                .word_count
+                .cmp(&stats_a.word_count)
+                .then(name_a.cmp(name_b))
+        });
+        if let Some(limit) = options.truncate_limit {
+            if characters.len() > limit {
+                builder.push_line(format!(
+                    "Not all characters are displayed below, just the {} most written. Add [-full] to see all of them",
+                    limit
+                ));
+                characters.truncate(limit);
+            }
+        }
+        let today = server_data.config.local_date(&Utc::now());
+        for (key, stats) in characters {
+            let character = server_data.config.characters.get(key);
+            builder
+                .newline()
+                .push_bold_safe(character.map_or(key.as_str(), |character| &character.name));
+            if let Some(player) = character.and_then(|character| character.player) {
+                builder.push_safe(format!(
+                    " (played by {})",
+                    server_data.shown_name_by_id(&player)
+                ));
+            }
+            builder
+                .newline()
+                .push_line_safe(format!("Word count: {}", stats.word_count))
+                .push_line_safe(format!("Words today: {}", stats.words_on_day(&today)))
+                .push_line_safe(format!("Top words: {}", stats.top_words(10)));
         }
+        builder.build()
     }
-    pub fn get_all_channel_ids(&self) -> Vec<ChannelId> {
-        self.channels.keys().map(|x| x.clone()).collect()
+    /// Pairs of authors by how often they mention or reply to each other, in either direction,
+    /// strongest first
+    pub fn strongest_pairings(&self, n: usize) -> Vec<((UserId, UserId), usize)> {
+        let mut pairings: HashMap<(UserId, UserId), usize> = HashMap::new();
+        for (author, stats) in self.author_stats.iter() {
+            for (other, count) in stats.interactions.iter() {
+                let pair = if author.id < *other {
+                    (author.id, *other)
+                } else {
+                    (*other, author.id)
+                };
+                *pairings.entry(pair).or_insert(0) += count;
+            }
+        }
+        let mut pairings: Vec<((UserId, UserId), usize)> = pairings.into_iter().collect();
+        pairings.sort_by(|(pair_a, count_a), (pair_b, count_b)| {
+            count_b.cmp(count_a).then(pair_a.cmp(pair_b))
+        });
+        pairings.truncate(n);
+        pairings
     }
 
-    pub fn channel_ids_with_last_message(&self) -> Vec<(ChannelId, MessageId)> {
-        self.channels
-            .iter()
-            .filter_map(|(channel_id, channel_data)| {
-                channel_data
-                    .general_stats
-                    .last_message()
-                    .map(|m_id| (channel_id.clone(), m_id.clone()))
-            })
-            .collect()
+    pub fn make_interactions_string(
+        &self,
+        text_channel: &GuildChannel,
+        server_data: &ServerData,
+        n: usize,
+    ) -> String {
+        let pairings = self.strongest_pairings(n);
+        let mut builder = MessageBuilder::new();
+        if pairings.is_empty() {
+            return builder
+                .push("No mentions or replies between writers in ")
+                .channel(text_channel)
+                .push(" yet")
+                .build();
+        }
+        builder
+            .push("Strongest writer pairings in ")
+            .channel(text_channel)
+            .newline();
+        for (i, ((user_a, user_b), count)) in pairings.iter().enumerate() {
+            builder
+                .push(i + 1)
+                .push(": ")
+                .push_bold_safe(server_data.shown_name_by_id(user_a))
+                .push(" & ")
+                .push_bold_safe(server_data.shown_name_by_id(user_b))
+                .push(" -> ")
+                .push(count)
+                .push_line(" interactions");
+        }
+        builder.build()
     }
 
-    pub fn insert(&mut self, channel_id: &ChannelId, channel_data: ChannelData) {
-        self.channels.insert(*channel_id, channel_data);
+    pub fn make_sessions_string(
+        &self,
+        text_channel: &GuildChannel,
+        server_data: &ServerData,
+        max_gap: Duration,
+    ) -> String {
+        let mut author_sessions: Vec<(&User, SessionStats)> = self
+            .author_stats
+            .iter()
+            .map(|(author, stats)| (author, stats.sessions(max_gap)))
+            .collect();
+        author_sessions
+            .sort_by_key(|(author, sessions)| (std::cmp::Reverse(sessions.count), author.id));
+        // Keep the message under discord's length limit
+        author_sessions.truncate(10);
+        let mut builder = MessageBuilder::new();
+        builder
+            .push("Writing sessions in ")
+            .channel(text_channel)
+            .push(format!(
+                ", a session ends after {} minutes without a message",
+                max_gap.num_minutes()
+            ))
+            .newline();
+        for (author, sessions) in author_sessions.iter() {
+            builder
+                .newline()
+                .push_bold_safe(server_data.shown_name(author))
+                .newline()
+                .push_line(format!("Sessions: {}", sessions.count))
+                .push_line(format!(
+                    "Average length: {}",
+                    format_duration(&sessions.average_length)
+                ))
+                .push_line(format!("Longest: {}", format_duration(&sessions.longest)));
+        }
+        builder.build()
     }
 
-    // Returns the sorted list of channel ids for a given user.
-    pub fn channel_ids_by_wordcount_for_user(&self, user: &User) -> Vec<(ChannelId, usize)> {
-        // Todo: Enable -recent- word count by supporting it in stats
-        let mut channels_by_wordcount: Vec<(ChannelId, usize)> = self
-            .channels
+    /// Each writer's response times, from the gaps before their posts that follow someone else's.
+    /// Only the first of several posts in a row counts, as that's when they picked the story up
+    pub fn response_times(&self) -> Vec<(&User, ResponseTimeStats)> {
+        let mut posts: Vec<(&MessageId, &User)> = self
+            .author_stats
             .iter()
-            .filter_map(|(channel_id, channel_data)| {
-                channel_data
-                    .get_user(user)
-                    .map(|stats| (channel_id.clone(), stats.word_count.clone()))
-            })
+            .flat_map(|(author, stats)| stats.message_ids().map(move |id| (id, author)))
             .collect();
-        channels_by_wordcount.sort_by_key(|(_id, count)| *count);
-        channels_by_wordcount.reverse();
-        channels_by_wordcount
+        // Co-written posts are credited to everyone, count them once for whoever posted them
+        let posted_by = |id: &MessageId, author: &User| {
+            self.co_written_posts
+                .get(id)
+                .is_none_or(|poster| *poster == author.id)
+        };
+        posts.sort_by_key(|(id, author)| (**id, !posted_by(id, author), author.id));
+        posts.dedup_by_key(|(id, _)| **id);
+        let mut latencies: HashMap<&User, Vec<Duration>> = HashMap::new();
+        for pair in posts.windows(2) {
+            let ((previous_id, previous_author), (id, author)) = (pair[0], pair[1]);
+            if previous_author.id != author.id {
+                latencies
+                    .entry(author)
+                    .or_default()
+                    .push(id.created_at() - previous_id.created_at());
+            }
+        }
+        latencies
+            .into_iter()
+            .filter_map(|(author, latencies)| {
+                ResponseTimeStats::from_latencies(latencies).map(|stats| (author, stats))
+            })
+            .collect()
     }
-    pub fn make_user_stats_string(
-        user: &User,
-        channels_by_wordcount: Vec<(Channel, usize)>,
+
+    pub fn make_response_times_string(
+        &self,
+        text_channel: &GuildChannel,
+        server_data: &ServerData,
     ) -> String {
+        let mut response_times = self.response_times();
+        response_times.sort_by_key(|(author, stats)| (stats.median, author.id));
+        // Keep the message under discord's length limit
+        response_times.truncate(15);
         let mut builder = MessageBuilder::new();
-        if channels_by_wordcount.len() == 0 {
-            builder
-                .user(user)
-                .push(" has no recorded activity in any initialised channels")
-                .build()
-        } else {
-            let max = std::cmp::min(5, channels_by_wordcount.len());
+        builder
+            .push("Response times in ")
+            .channel(text_channel)
+            .push(", how long each writer takes to post after someone else")
+            .newline();
+        if response_times.is_empty() {
+            builder.push("Nobody has replied to anyone else yet");
+        }
+        for (author, stats) in response_times.iter() {
             builder
-                .push("Top ")
-                .push(max)
-                .push(" channels on this server for: ")
-                .user(user)
                 .newline()
-                .push_line("By Wordcount for all time:");
-            let mut i = 0;
-            for (channel, word_count) in channels_by_wordcount.iter() {
-                builder
-                    .push(i + 1)
-                    .push(": ")
-                    .channel(channel)
-                    .push(" -> ")
-                    .push(word_count)
-                    .newline();
-                i += 1;
+                .push_bold_safe(server_data.shown_name(author))
+                .newline()
+                .push_line(format!("Median: {}", format_duration(&stats.median)))
+                .push_line(format!("Responses: {}", stats.count));
+        }
+        builder.build()
+    }
+
+    pub fn make_content_report_string(
+        &self,
+        text_channel: &GuildChannel,
+        server_data: &ServerData,
+        categories: &HashMap<String, HashSet<String>>,
+    ) -> String {
+        let count_in = |stats: &WordStats, words: &HashSet<String>| -> usize {
+            words
+                .iter()
+                .filter_map(|word| stats.word_frequencies.get(word))
+                .sum()
+        };
+        let mut categories: Vec<(&String, &HashSet<String>)> = categories.iter().collect();
+        categories.sort_by_key(|(category, _)| *category);
+        let mut builder = MessageBuilder::new();
+        builder
+            .push("Content report for ")
+            .channel(text_channel)
+            .newline();
+        for (category, words) in categories {
+            let mut author_counts: Vec<(&User, usize)> = self
+                .author_stats
+                .iter()
+                .map(|(author, stats)| (author, count_in(stats, words)))
+                .filter(|(_, count)| *count > 0)
+                .collect();
+            author_counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+            builder
+                .newline()
+                .push_bold_line_safe(category)
+                .push_line(format!("Total: {}", count_in(&self.general_stats, words)));
+            for (author, count) in author_counts.iter().take(5) {
+                builder
+                    .push_safe(server_data.shown_name(author))
+                    .push(": ")
+                    .push(count)
+                    .newline();
+            }
+        }
+        builder.build()
+    }
+
+    /// Every writer's and character's words are among the channel's, so nothing is pruned until
+    /// the channel's go over [max_words]. Then they lose the same words the channel does, so no
+    /// writer is left with a top word the channel no longer has
+    pub fn prune_words(&mut self, max_words: usize) -> usize {
+        let mut removed = self.general_stats.prune_words(max_words);
+        if removed == 0 {
+            return 0;
+        }
+        let kept = &self.general_stats.word_frequencies;
+        for stats in self
+            .author_stats
+            .values_mut()
+            .chain(self.character_stats.values_mut())
+        {
+            removed += stats.retain_words(|word| kept.contains_key(word));
+        }
+        removed
+    }
+
+    pub fn approx_memory_bytes(&self) -> usize {
+        self.general_stats.approx_memory_bytes()
+            + self
+                .author_stats
+                .values()
+                .map(|stats| stats.approx_memory_bytes() + std::mem::size_of::<User>())
+                .sum::<usize>()
+            + self
+                .character_stats
+                .iter()
+                .map(|(name, stats)| stats.approx_memory_bytes() + name.len())
+                .sum::<usize>()
+    }
+
+    pub fn get_user_by_id(&self, user_id: &UserId) -> Option<&WordStats> {
+        self.author_stats
+            .iter()
+            .find(|(author, _)| &author.id == user_id)
+            .map(|(_, stats)| stats)
+    }
+
+    pub fn merge(&mut self, other: ChannelData) {
+        self.general_stats.merge(other.general_stats);
+        for (author, stats) in other.author_stats {
+            match self.author_stats.get_mut(&author) {
+                Some(existing_stats) => existing_stats.merge(stats),
+                None => {
+                    self.author_stats.insert(author, stats);
+                }
+            }
+        }
+        self.co_written_posts.extend(other.co_written_posts);
+        for (character, stats) in other.character_stats {
+            match self.character_stats.get_mut(&character) {
+                Some(existing_stats) => existing_stats.merge(stats),
+                None => {
+                    self.character_stats.insert(character, stats);
+                }
+            }
+        }
+    }
+
+    /// A copy with each alt account's stats merged into its main account's, mentions between
+    /// linked accounts no longer count as interactions
+    pub fn with_aliases(&self, aliases: &HashMap<UserId, UserId>) -> ChannelData {
+        let main_account = |user_id: &UserId| *aliases.get(user_id).unwrap_or(user_id);
+        let mut merged = ChannelData {
+            author_stats: HashMap::new(),
+            general_stats: self.general_stats.clone(),
+            character_stats: self.character_stats.clone(),
+            co_written_posts: self
+                .co_written_posts
+                .iter()
+                .map(|(message_id, poster)| (*message_id, main_account(poster)))
+                .collect(),
+        };
+        for (author, stats) in self.author_stats.iter() {
+            let main_id = main_account(&author.id);
+            // The main account may never have posted here, its name comes from the display
+            // name cache anyway
+            let main = self
+                .author_stats
+                .keys()
+                .find(|other| other.id == main_id)
+                .cloned()
+                .unwrap_or_else(|| {
+                    let mut main = author.clone();
+                    main.id = main_id;
+                    main
+                });
+            let mut merged_stats = stats.clone();
+            merged_stats.interactions = HashMap::new();
+            for (other, count) in stats.interactions.iter() {
+                let other = main_account(other);
+                if other != main_id {
+                    *merged_stats.interactions.entry(other).or_insert(0) += count;
+                }
+            }
+            match merged.author_stats.get_mut(&main) {
+                Some(existing_stats) => existing_stats.merge(merged_stats),
+                None => {
+                    merged.author_stats.insert(main, merged_stats);
+                }
+            }
+        }
+        merged
+    }
+
+    /// Removes an author's stats, including their contribution to the general stats. Returns
+    /// whether there was anything to remove
+    pub fn check_integrity(&mut self, latest_day: NaiveDate) -> Vec<Anomaly> {
+        let mut anomalies: Vec<Anomaly> = self
+            .general_stats
+            .check_integrity(latest_day)
+            .into_iter()
+            .map(|anomaly| anomaly.within("general stats"))
+            .collect();
+        let empty_authors = self.author_stats.len();
+        self.author_stats
+            .retain(|_, word_stats| word_stats.message_ids().next().is_some());
+        let empty_authors = empty_authors - self.author_stats.len();
+        if empty_authors > 0 {
+            anomalies.push(Anomaly::repaired(format!(
+                "removed {} writers with no counted messages",
+                empty_authors
+            )));
+        }
+        for (author, word_stats) in self.author_stats.iter_mut() {
+            let context = format!("stats for {}", author.name);
+            anomalies.extend(
+                word_stats
+                    .check_integrity(latest_day)
+                    .into_iter()
+                    .map(|anomaly| anomaly.within(&context)),
+            );
+        }
+        for (character, word_stats) in self.character_stats.iter_mut() {
+            let context = format!("stats for character {}", character);
+            anomalies.extend(
+                word_stats
+                    .check_integrity(latest_day)
+                    .into_iter()
+                    .map(|anomaly| anomaly.within(&context)),
+            );
+        }
+        anomalies
+    }
+
+    pub fn forget_author(&mut self, user_id: &UserId) -> bool {
+        let author = self
+            .author_stats
+            .keys()
+            .find(|author| &author.id == user_id)
+            .cloned();
+        match author.and_then(|author| self.author_stats.remove(&author)) {
+            Some(stats) => {
+                self.general_stats.subtract(&stats);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CommandUsage {
+    pub uses: usize,
+    pub errors: usize,
+}
+
+/// A channel's stats as they were when [reset-stats] started a new season
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedSeason {
+    pub ended: DateTime<Utc>,
+    pub stats: ChannelData,
+}
+
+// A year of weekly shoutouts, enough to tell who has won before
+pub const MAX_SHOUTOUT_HISTORY: usize = 52;
+
+/// A week's shoutout, [winners] is empty if nobody wrote that week and has more than one writer
+/// if they tied
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShoutoutRecord {
+    // Server-local day it was posted, the week covered is the 7 days before
+    pub posted_on: NaiveDate,
+    pub winners: Vec<UserId>,
+    pub words: usize,
+}
+
+/// Days a deinitialised or deleted channel's stats are kept for, in case it's restored
+pub const TRASH_RETENTION_DAYS: i64 = 30;
+
+/// A channel's stats and seasons as they were when it was deinitialised or deleted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedChannel {
+    pub trashed: DateTime<Utc>,
+    pub stats: ChannelData,
+    #[serde(default)]
+    pub seasons: Vec<ArchivedSeason>,
+}
+
+impl TrashedChannel {
+    pub fn expires(&self) -> DateTime<Utc> {
+        self.trashed + Duration::days(TRASH_RETENTION_DAYS)
+    }
+}
+
+/// Oldest entries are dropped past this, a server's log isn't meant to be a full history
+pub const MAX_AUDIT_ENTRIES: usize = 500;
+
+/// An admin or config change, recorded by [record_admin_action]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub actor: UserId,
+    pub at: DateTime<Utc>,
+    pub command: String,
+    // The invocation as written, so the parameters are kept however the command takes them
+    pub invocation: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ServerData {
+    channels: HashMap<ChannelId, ChannelData>,
+    #[serde(default)]
+    pub command_usage: HashMap<String, CommandUsage>,
+    #[serde(default)]
+    pub display_names: DisplayNameCache,
+    #[serde(default)]
+    pub departed_authors: HashSet<UserId>,
+    #[serde(default)]
+    pub config: GuildConfig,
+    // Automated posts waiting for quiet hours to end
+    #[serde(default)]
+    pub deferred_posts: Vec<DeferredPost>,
+    #[serde(default)]
+    pub reminders: Vec<Reminder>,
+    #[serde(default)]
+    pub nudges: HashMap<ChannelId, NudgeState>,
+    // Position in the channel's turn order of whoever is up next
+    #[serde(default)]
+    pub turns: HashMap<ChannelId, usize>,
+    // Server-local day a scheduled word cloud was last posted in each channel
+    #[serde(default)]
+    pub wordcloud_posts: HashMap<ChannelId, NaiveDate>,
+    // Initialised channels replay couldn't read, until a message from them arrives again
+    #[serde(default)]
+    pub inaccessible_channels: HashSet<ChannelId>,
+    // Oldest first
+    #[serde(default)]
+    pub audit_log: Vec<AuditEntry>,
+    // Earlier seasons of each channel, oldest first so season 1 is at the front
+    #[serde(default)]
+    pub seasons: HashMap<ChannelId, Vec<ArchivedSeason>>,
+    // Oldest first
+    #[serde(default)]
+    pub shoutouts: Vec<ShoutoutRecord>,
+    // Deinitialised and deleted channels, until they're restored or [TRASH_RETENTION_DAYS] pass
+    #[serde(default)]
+    pub trash: HashMap<ChannelId, TrashedChannel>,
+}
+
+impl ServerData {
+    pub fn new() -> Self {
+        Self {
+            channels: HashMap::new(),
+            command_usage: HashMap::new(),
+            display_names: DisplayNameCache::default(),
+            departed_authors: HashSet::new(),
+            config: GuildConfig::default(),
+            deferred_posts: vec![],
+            reminders: vec![],
+            nudges: HashMap::new(),
+            turns: HashMap::new(),
+            wordcloud_posts: HashMap::new(),
+            inaccessible_channels: HashSet::new(),
+            audit_log: vec![],
+            seasons: HashMap::new(),
+            shoutouts: vec![],
+            trash: HashMap::new(),
+        }
+    }
+
+    pub fn record_audit(&mut self, entry: AuditEntry) {
+        self.audit_log.push(entry);
+        if self.audit_log.len() > MAX_AUDIT_ENTRIES {
+            let excess = self.audit_log.len() - MAX_AUDIT_ENTRIES;
+            self.audit_log.drain(..excess);
+        }
+    }
+    pub fn channels(&self) -> impl Iterator<Item = (&ChannelId, &ChannelData)> {
+        self.channels.iter()
+    }
+
+    pub fn get_all_channel_ids(&self) -> Vec<ChannelId> {
+        self.channels.keys().copied().collect()
+    }
+
+    pub fn channel_ids_with_last_message(&self) -> Vec<(ChannelId, MessageId)> {
+        self.channels
+            .iter()
+            .filter_map(|(channel_id, channel_data)| {
+                // A season that's just been reset has nothing yet, catch up from the one before
+                channel_data
+                    .general_stats
+                    .last_message()
+                    .or_else(|| {
+                        self.seasons
+                            .get(channel_id)?
+                            .iter()
+                            .rev()
+                            .find_map(|season| season.stats.general_stats.last_message())
+                    })
+                    .map(|m_id| (*channel_id, m_id))
+            })
+            .collect()
+    }
+
+    /// Checks every channel's stats, and drops state kept for channels that are no longer
+    /// initialised
+    pub fn check_integrity(&mut self, latest_day: NaiveDate) -> Vec<Anomaly> {
+        let mut anomalies = vec![];
+        for (channel_id, channel_data) in self.channels.iter_mut() {
+            let context = channel_id.mention().to_string();
+            anomalies.extend(
+                channel_data
+                    .check_integrity(latest_day)
+                    .into_iter()
+                    .map(|anomaly| anomaly.within(&context)),
+            );
+        }
+        let channels = &self.channels;
+        let orphans = {
+            let before =
+                self.nudges.len() + self.wordcloud_posts.len() + self.inaccessible_channels.len();
+            self.nudges
+                .retain(|channel_id, _| channels.contains_key(channel_id));
+            self.wordcloud_posts
+                .retain(|channel_id, _| channels.contains_key(channel_id));
+            self.inaccessible_channels
+                .retain(|channel_id| channels.contains_key(channel_id));
+            before
+                - (self.nudges.len()
+                    + self.wordcloud_posts.len()
+                    + self.inaccessible_channels.len())
+        };
+        if orphans > 0 {
+            anomalies.push(Anomaly::repaired(format!(
+                "removed {} nudge, word cloud or access records for channels that aren't initialised",
+                orphans
+            )));
+        }
+        // Kept rather than deleted, re-initialising the channel brings them back
+        for (channel_id, seasons) in self.seasons.iter() {
+            if !channels.contains_key(channel_id) && !seasons.is_empty() {
+                anomalies.push(Anomaly::unrepaired(format!(
+                    "{} has {} archived season(s) but isn't initialised",
+                    channel_id.mention(),
+                    seasons.len()
+                )));
+            }
+        }
+        anomalies
+    }
+
+    /// Archives the channel's stats as its latest season and starts counting afresh. Returns the
+    /// number of the archived season, [None] if the channel isn't initialised
+    pub fn archive_season(&mut self, channel_id: &ChannelId, now: DateTime<Utc>) -> Option<usize> {
+        let stats = std::mem::take(self.channels.get_mut(channel_id)?);
+        let seasons = self.seasons.entry(*channel_id).or_default();
+        seasons.push(ArchivedSeason { ended: now, stats });
+        Some(seasons.len())
+    }
+
+    /// Stops tracking the channel, keeping its stats and seasons in the trash. Returns whether it
+    /// was initialised. Trashing a channel again replaces what was kept from the last time
+    pub fn trash_channel(&mut self, channel_id: &ChannelId, now: DateTime<Utc>) -> bool {
+        match self.channels.remove(channel_id) {
+            Some(stats) => {
+                let seasons = self.seasons.remove(channel_id).unwrap_or_default();
+                self.trash.insert(
+                    *channel_id,
+                    TrashedChannel {
+                        trashed: now,
+                        stats,
+                        seasons,
+                    },
+                );
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Puts a trashed channel's stats and seasons back
+    pub fn restore_channel(&mut self, channel_id: &ChannelId) -> std::result::Result<(), String> {
+        if self.channels.contains_key(channel_id) {
+            return Err(String::from(
+                "Channel is initialised again, deinit it first to restore the old stats",
+            ));
+        }
+        match self.trash.remove(channel_id) {
+            Some(trashed) => {
+                self.insert(channel_id, trashed.stats);
+                if !trashed.seasons.is_empty() {
+                    self.seasons.insert(*channel_id, trashed.seasons);
+                }
+                Ok(())
+            }
+            None => Err(format!(
+                "Nothing in the trash for that channel, stats are kept for {} days",
+                TRASH_RETENTION_DAYS
+            )),
+        }
+    }
+
+    /// Deletes trashed channels past their retention, returning how many
+    pub fn empty_expired_trash(&mut self, now: &DateTime<Utc>) -> usize {
+        let before = self.trash.len();
+        self.trash.retain(|_, trashed| trashed.expires() > *now);
+        before - self.trash.len()
+    }
+
+    /// When the channel's current season began, [None] if it's on its first
+    pub fn season_started(&self, channel_id: &ChannelId) -> Option<DateTime<Utc>> {
+        self.seasons
+            .get(channel_id)?
+            .last()
+            .map(|season| season.ended)
+    }
+
+    /// Archived seasons are numbered from 1
+    pub fn season(&self, channel_id: &ChannelId, number: usize) -> Option<&ArchivedSeason> {
+        self.seasons.get(channel_id)?.get(number.checked_sub(1)?)
+    }
+
+    pub fn season_count(&self, channel_id: &ChannelId) -> usize {
+        self.seasons
+            .get(channel_id)
+            .map_or(0, |seasons| seasons.len())
+    }
+
+    pub fn insert(&mut self, channel_id: &ChannelId, channel_data: ChannelData) {
+        // Backfilled authors won't have come through [update_channel_data], so fall back to the
+        // username we stored with their stats until we see them post. Sorted so they're given
+        // anonymous labels in a stable order
+        let mut authors: Vec<&User> = channel_data.author_stats.keys().collect();
+        authors.sort_by_key(|author| author.id);
+        for author in authors {
+            if self.display_names.get_by_id(&author.id).is_none() {
+                self.display_names.update(&author.id, &author.name);
+            }
+        }
+        self.channels.insert(*channel_id, channel_data);
+    }
+
+    /// Takes the channels from [other] which this doesn't have, or has counted less of, along
+    /// with their archived seasons. The server's settings and logs are kept as they are here.
+    /// Returns how many channels were taken
+    pub fn merge_dump(&mut self, mut other: ServerData) -> usize {
+        let mut taken = 0;
+        for (channel_id, channel_data) in other.channels {
+            let newer = match self.channels.get(&channel_id) {
+                Some(existing) => {
+                    existing.general_stats.last_message()
+                        < channel_data.general_stats.last_message()
+                }
+                None => true,
+            };
+            if newer {
+                // A channel's seasons go with its stats, ours don't belong alongside theirs
+                match other.seasons.remove(&channel_id) {
+                    Some(seasons) => self.seasons.insert(channel_id, seasons),
+                    None => self.seasons.remove(&channel_id),
+                };
+                self.trash.remove(&channel_id);
+                self.insert(&channel_id, channel_data);
+                taken += 1;
+            }
+        }
+        taken
+    }
+
+    pub fn update_channel_data(&mut self, channel_id: &ChannelId, message: &Message) {
+        self.inaccessible_channels.remove(channel_id);
+        match self.channels.get_mut(channel_id) {
+            Some(_)
+                if !self
+                    .config
+                    .channel(channel_id)
+                    .counts_message(message, &self.config.word_rules) =>
+            {
+                debug!("Message too short to count towards stats")
+            }
+            Some(channel_data) => {
+                let timezone = self.config.timezone();
+                let rules = self.config.word_rules;
+                channel_data.update(message, &timezone, &rules);
+                let channel_config = self.config.channel(channel_id);
+                if let Some(character) = self.config.characters.attribute(message, &channel_config)
+                {
+                    channel_data.update_character(character, message, &timezone, &rules);
+                }
+                self.display_names.update_from_message(message);
+                // All we know of co-writers is their username, until they post themselves
+                for co_writer in co_writers(message) {
+                    if self.display_names.get_by_id(&co_writer.id).is_none() {
+                        self.display_names.update(&co_writer.id, &co_writer.name);
+                    }
+                }
+                self.departed_authors.remove(&message.author.id);
+            }
+            None => debug!("Message not in a channel that's been initialised"),
+        }
+    }
+
+    pub fn prune_channel_words(&mut self, channel_id: &ChannelId, max_words: usize) {
+        if let Some(channel_data) = self.channels.get_mut(channel_id) {
+            let removed = channel_data.prune_words(max_words);
+            if removed > 0 {
+                info!("Pruned {} infrequent words from {}", removed, channel_id);
+            }
+        }
+    }
+
+    pub fn approx_memory_bytes(&self) -> usize {
+        self.channels
+            .values()
+            .map(|channel_data| channel_data.approx_memory_bytes())
+            .sum()
+    }
+
+    /// Returns the number of channels the user was forgotten from
+    pub fn forget_author(&mut self, user_id: &UserId) -> usize {
+        for record in self.shoutouts.iter_mut() {
+            record.winners.retain(|winner| winner != user_id);
+        }
+        for trashed in self.trash.values_mut() {
+            trashed.stats.forget_author(user_id);
+            for season in trashed.seasons.iter_mut() {
+                season.stats.forget_author(user_id);
+            }
+        }
+        let seasons = &mut self.seasons;
+        self.channels
+            .iter_mut()
+            .map(|(channel_id, channel_data)| {
+                let mut forgotten = channel_data.forget_author(user_id);
+                for season in seasons.get_mut(channel_id).into_iter().flatten() {
+                    forgotten |= season.stats.forget_author(user_id);
+                }
+                forgotten
+            })
+            .filter(|forgotten| *forgotten)
+            .count()
+    }
+
+    /// Who is up next in the channel's turn order, if it has one
+    pub fn whose_turn(&self, channel_id: &ChannelId) -> Option<UserId> {
+        let writers = self.config.channel(channel_id).turn_order?.writers;
+        let turn = self.turns.get(channel_id).copied().unwrap_or(0);
+        writers.get(turn % writers.len().max(1)).copied()
+    }
+
+    /// Passes the turn on to whoever follows the message's author, even if they posted out of
+    /// turn. Returns who is up next if that changed
+    pub fn take_turn(&mut self, channel_id: &ChannelId, message: &Message) -> Option<UserId> {
+        let channel_config = self.config.channel(channel_id);
+        let writers = channel_config.turn_order.as_ref()?.writers.clone();
+        if !channel_config.counts_message(message, &self.config.word_rules) {
+            return None;
+        }
+        let position = writers.iter().position(|id| *id == message.author.id)?;
+        let current = self.whose_turn(channel_id);
+        let next = (position + 1) % writers.len();
+        self.turns.insert(*channel_id, next);
+        match current == Some(writers[next]) {
+            true => None,
+            false => Some(writers[next]),
+        }
+    }
+
+    /// The channel's stats with linked accounts merged, as is if nobody on the server has linked
+    /// any
+    pub fn aliased<'a>(&self, channel_data: &'a ChannelData) -> Cow<'a, ChannelData> {
+        match self.config.aliases.is_empty() {
+            true => Cow::Borrowed(channel_data),
+            false => Cow::Owned(channel_data.with_aliases(&self.config.aliases)),
+        }
+    }
+
+    /// The stats [user_id] has in [channel_data], along with those of their main account and its
+    /// other alts. Lets per-user queries take aliases into account without [aliased] copying the
+    /// whole channel
+    fn linked_user_stats<'a>(
+        &self,
+        channel_data: &'a ChannelData,
+        user_id: &UserId,
+    ) -> Vec<&'a WordStats> {
+        let main_id = self.config.main_account(user_id);
+        match self.config.aliases.is_empty() {
+            true => channel_data.get_user_by_id(user_id).into_iter().collect(),
+            false => channel_data
+                .author_stats
+                .iter()
+                .filter(|(author, _)| self.config.main_account(&author.id) == main_id)
+                .map(|(_, stats)| stats)
+                .collect(),
+        }
+    }
+
+    // Returns the sorted list of channel ids for a given user.
+    pub fn channel_ids_by_wordcount_for_user(&self, user_id: &UserId) -> Vec<(ChannelId, usize)> {
+        // Todo: Enable -recent- word count by supporting it in stats
+        let mut channels_by_wordcount: Vec<(ChannelId, usize)> = self
+            .channels
+            .iter()
+            .filter_map(|(channel_id, channel_data)| {
+                let stats = self.linked_user_stats(channel_data, user_id);
+                match stats.is_empty() {
+                    true => None,
+                    false => Some((
+                        *channel_id,
+                        stats.iter().map(|stats| stats.word_count).sum(),
+                    )),
+                }
+            })
+            .collect();
+        channels_by_wordcount.sort_by_key(|(_id, count)| *count);
+        channels_by_wordcount.reverse();
+        channels_by_wordcount
+    }
+    /// Each author's words across every initialised channel on the server, most first. Alts count
+    /// towards their main account
+    pub fn author_word_counts(&self) -> Vec<(UserId, usize)> {
+        let mut totals: HashMap<UserId, usize> = HashMap::new();
+        for channel_data in self.channels.values() {
+            for (author, word_stats) in channel_data.author_stats.iter() {
+                *totals
+                    .entry(self.config.main_account(&author.id))
+                    .or_default() += word_stats.word_count;
+            }
+        }
+        let mut totals: Vec<(UserId, usize)> = totals.into_iter().collect();
+        totals.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        totals
+    }
+
+    /// Each author's words on the server-local days [from] to [to] inclusive, most first. Alts
+    /// count towards their main account, and writers who've left are left out
+    pub fn author_words_between(&self, from: &NaiveDate, to: &NaiveDate) -> Vec<(UserId, usize)> {
+        let mut totals: HashMap<UserId, usize> = HashMap::new();
+        for channel_data in self.channels.values() {
+            for (author, word_stats) in channel_data.author_stats.iter() {
+                let words: usize = word_stats
+                    .daily_word_counts()
+                    .range(*from..=*to)
+                    .map(|(_, words)| words)
+                    .sum();
+                let main_id = self.config.main_account(&author.id);
+                if words > 0 && !self.departed_authors.contains(&main_id) {
+                    *totals.entry(main_id).or_default() += words;
+                }
+            }
+        }
+        let mut totals: Vec<(UserId, usize)> = totals.into_iter().collect();
+        totals.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        totals
+    }
+
+    /// Records whoever wrote the most in the 7 days before [posted_on] as that week's top writers,
+    /// all of them if they tied
+    pub fn record_shoutout(&mut self, posted_on: NaiveDate) -> &ShoutoutRecord {
+        let totals = self.author_words_between(
+            &(posted_on - Duration::days(7)),
+            &(posted_on - Duration::days(1)),
+        );
+        let words = totals.first().map_or(0, |(_, words)| *words);
+        let winners = totals
+            .iter()
+            .take_while(|(_, author_words)| *author_words == words)
+            .map(|(author, _)| *author)
+            .collect();
+        self.shoutouts.push(ShoutoutRecord {
+            posted_on,
+            winners,
+            words,
+        });
+        if self.shoutouts.len() > MAX_SHOUTOUT_HISTORY {
+            let excess = self.shoutouts.len() - MAX_SHOUTOUT_HISTORY;
+            self.shoutouts.drain(..excess);
+        }
+        self.shoutouts.last().unwrap()
+    }
+
+    /// How many weeks running, up to the latest, [user_id] has been among the top writers
+    pub fn shoutout_streak(&self, user_id: &UserId) -> usize {
+        self.shoutouts
+            .iter()
+            .rev()
+            .take_while(|record| record.winners.contains(user_id))
+            .count()
+    }
+
+    pub fn make_leaderboard_string(&self, limit: usize) -> String {
+        let totals = self.author_word_counts();
+        if totals.is_empty() {
+            return String::from("No words recorded in any initialised channels");
+        }
+        let mut builder = MessageBuilder::new();
+        builder.push_bold_line(format!(
+            "Top {} writers on this server, by words across {} channels",
+            std::cmp::min(limit, totals.len()),
+            self.channels.len()
+        ));
+        for (i, (author, word_count)) in totals.iter().take(limit).enumerate() {
+            builder
+                .push(format!("{}: ", i + 1))
+                .push_bold_safe(self.shown_name_by_id(author));
+            if self.departed_authors.contains(author) {
+                builder.push(" (departed)");
+            }
+            builder.push_line(format!(" -> {}", word_count));
+        }
+        builder.build()
+    }
+
+    /// How an author is named in stats posted to the server, anonymous in privacy mode
+    pub fn shown_name(&self, user: &User) -> String {
+        match self.config.privacy_mode {
+            true => self.display_names.anonymous_label(&user.id),
+            false => self.display_names.get(user).to_string(),
+        }
+    }
+
+    pub fn shown_name_by_id(&self, user_id: &UserId) -> String {
+        match self.config.privacy_mode {
+            true => self.display_names.anonymous_label(user_id),
+            false => self.author_name(user_id),
+        }
+    }
+
+    /// Cached display name for an author, or their id if we've never seen a name for them
+    pub fn author_name(&self, user_id: &UserId) -> String {
+        match self.display_names.get_by_id(user_id) {
+            Some(name) => name.to_string(),
+            None => user_id.to_string(),
+        }
+    }
+
+    pub fn images_shared_by_user(&self, user_id: &UserId) -> usize {
+        self.channels
+            .values()
+            .flat_map(|channel_data| self.linked_user_stats(channel_data, user_id))
+            .map(|stats| stats.media.images)
+            .sum()
+    }
+
+    /// Stopword filtered frequencies across all channels, or just [channel_id] if given
+    pub fn word_frequencies_for_user(
+        &self,
+        user_id: &UserId,
+        channel_id: Option<&ChannelId>,
+    ) -> HashMap<String, usize> {
+        let user_id = self.config.main_account(user_id);
+        let mut frequencies: HashMap<String, usize> = HashMap::new();
+        for (_, channel_data) in self
+            .channels
+            .iter()
+            .filter(|(id, _)| channel_id.is_none_or(|channel_id| channel_id == *id))
+        {
+            for stats in self.linked_user_stats(channel_data, &user_id) {
+                for (word, count) in stats.filtered_word_frequencies() {
+                    *frequencies.entry(word).or_insert(0) += count;
+                }
+            }
+        }
+        frequencies
+    }
+
+    pub fn make_usage_report_string(&self, server_name: &str) -> String {
+        let mut usage: Vec<(&String, &CommandUsage)> = self.command_usage.iter().collect();
+        usage.sort_by_key(|(_, usage)| usage.uses);
+        usage.reverse();
+        let mut builder = MessageBuilder::new();
+        builder.push_bold_line_safe(format!("Command usage for {}", server_name));
+        if usage.is_empty() {
+            builder.push_line("No commands used yet");
+        }
+        for (command_name, usage) in usage {
+            let error_rate = if usage.uses > 0 {
+                100.0 * usage.errors as f32 / usage.uses as f32
+            } else {
+                0.0
+            };
+            builder.push_line_safe(format!(
+                "{}: {} uses, {} errors ({:.1}%)",
+                command_name, usage.uses, usage.errors, error_rate
+            ));
+        }
+        builder.build()
+    }
+
+    pub fn make_user_stats_string(
+        &self,
+        user_id: &UserId,
+        channels_by_wordcount: Vec<(ChannelId, usize)>,
+    ) -> String {
+        let mut builder = MessageBuilder::new();
+        if channels_by_wordcount.is_empty() {
+            builder
+                .user(*user_id)
+                .push(" has no recorded activity in any initialised channels")
+                .build()
+        } else {
+            let max = std::cmp::min(5, channels_by_wordcount.len());
+            builder
+                .push("Top ")
+                .push(max)
+                .push(" channels on this server for: ");
+            match self.display_names.get_by_id(user_id) {
+                Some(name) => builder.push_bold_safe(name),
+                None => builder.user(*user_id),
+            };
+            if self.departed_authors.contains(user_id) {
+                builder.push(" (departed)");
+            }
+            builder.newline().push_line("By Wordcount for all time:");
+            let mut i = 0;
+            for (channel, word_count) in channels_by_wordcount.iter() {
+                builder
+                    .push(i + 1)
+                    .push(": ")
+                    .channel(*channel)
+                    .push(" -> ")
+                    .push(word_count)
+                    .newline();
+                i += 1;
                 if i >= max {
                     break;
                 }
             }
+            builder.push_line(format!(
+                "Images shared across all channels: {}",
+                self.images_shared_by_user(user_id)
+            ));
             builder.build()
         }
     }
 }
+
+fn format_duration(duration: &Duration) -> String {
+    match (duration.num_days(), duration.num_hours()) {
+        (0, 0) => format!("{}m", duration.num_minutes()),
+        (0, hours) => format!("{}h {:02}m", hours, duration.num_minutes() % 60),
+        (days, hours) => format!("{}d {:02}h", days, hours % 24),
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use crate::bench_corpus::synthetic_messages;
+    use crate::guild_config::{ChannelConfig, TurnOrder};
+    use crate::language_parsing::WordRules;
+    use crate::state::{
+        ArchivedSeason, AuditEntry, ChannelData, DisplayNameCache, DumpMerge, PersistedStoreRef,
+        PersonalLog, ServerData, StatsDisplayOptions, Store, MAX_AUDIT_ENTRIES,
+        TRASH_RETENTION_DAYS,
+    };
+    use chrono::{Duration, NaiveDate, Utc};
+    use chrono_tz::Tz;
+    use serenity::model::channel::GuildChannel;
+    use serenity::model::id::{ChannelId, GuildId, UserId};
+
+    fn text_channel() -> GuildChannel {
+        serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "bitrate": null,
+            "parent_id": null,
+            "guild_id": "1",
+            "type": 0,
+            "last_message_id": null,
+            "last_pin_timestamp": null,
+            "name": "the-fall-of-rome",
+            "permission_overwrites": [],
+            "position": 0,
+            "topic": null,
+            "user_limit": null,
+        }))
+        .unwrap()
+    }
+
+    fn server_data() -> ServerData {
+        let mut channel_data = ChannelData::default();
+        for message in synthetic_messages(30, 3).iter() {
+            channel_data.update(message, &Tz::UTC, &WordRules::default());
+        }
+        let mut server_data = ServerData::new();
+        server_data.insert(&ChannelId(1), channel_data);
+        server_data
+    }
+
+    #[test]
+    fn integrity_drops_orphans() {
+        let today = Utc::now().naive_utc().date();
+        let mut server_data = server_data();
+        assert!(server_data.check_integrity(today).is_empty());
+
+        server_data.wordcloud_posts.insert(ChannelId(9), today);
+        server_data.inaccessible_channels.insert(ChannelId(9));
+        server_data.inaccessible_channels.insert(ChannelId(1));
+        server_data.archive_season(&ChannelId(1), Utc::now());
+        let season = server_data.seasons.remove(&ChannelId(1)).unwrap();
+        server_data.seasons.insert(ChannelId(9), season);
+        let anomalies = server_data.check_integrity(today);
+        assert_eq!(anomalies.len(), 2);
+        assert!(anomalies[0].repaired && anomalies[0].description.contains("removed 2"));
+        assert!(!anomalies[1].repaired && anomalies[1].description.starts_with("<#9>"));
+        assert!(server_data.wordcloud_posts.is_empty());
+        assert_eq!(server_data.inaccessible_channels.len(), 1);
+    }
+
+    #[test]
+    fn merge_dumps_keeps_newer_channels() {
+        let messages = synthetic_messages(30, 3);
+        let mut behind = ChannelData::default();
+        for message in messages.iter().take(10) {
+            behind.update(message, &Tz::UTC, &WordRules::default());
+        }
+        let mut store = Store::default();
+        store.insert_channel_data_maybe_create_server_data(&(GuildId(1), ChannelId(1)), behind);
+        let mut season = ChannelData::default();
+        season.update(&messages[0], &Tz::UTC, &WordRules::default());
+        store.data.get_mut(&GuildId(1)).unwrap().seasons.insert(
+            ChannelId(1),
+            vec![ArchivedSeason {
+                ended: Utc::now(),
+                stats: season,
+            }],
+        );
+        store
+            .personal_logs
+            .insert(UserId(5), PersonalLog::default());
+
+        let mut other = Store::default();
+        other.data.insert(GuildId(1), server_data());
+        other
+            .data
+            .get_mut(&GuildId(1))
+            .unwrap()
+            .insert(&ChannelId(2), ChannelData::default());
+        other.data.insert(GuildId(2), server_data());
+        other
+            .personal_logs
+            .insert(UserId(5), PersonalLog::default());
+        other
+            .personal_logs
+            .insert(UserId(6), PersonalLog::default());
+
+        let merge = store.merge_dump(other);
+        assert_eq!(
+            merge,
+            DumpMerge {
+                servers_added: 1,
+                channels_taken: 2,
+                personal_logs_added: 1,
+            }
+        );
+        let words = server_data()
+            .channels()
+            .next()
+            .unwrap()
+            .1
+            .general_stats
+            .word_count;
+        let channel_data = store.get_channel_data(&(GuildId(1), ChannelId(1))).unwrap();
+        assert_eq!(channel_data.general_stats.word_count, words);
+        // The taken channel had no seasons in the other dump
+        assert_eq!(store.data[&GuildId(1)].season_count(&ChannelId(1)), 0);
+
+        // Merging what's already there changes nothing, ours are kept along with their seasons
+        store.data.get_mut(&GuildId(2)).unwrap().seasons.insert(
+            ChannelId(1),
+            vec![ArchivedSeason {
+                ended: Utc::now(),
+                stats: ChannelData::default(),
+            }],
+        );
+        let mut again = Store::default();
+        again.data.insert(GuildId(2), server_data());
+        assert_eq!(store.merge_dump(again), DumpMerge::default());
+        assert_eq!(store.data[&GuildId(2)].season_count(&ChannelId(1)), 1);
+    }
+
+    #[test]
+    fn trash_and_restore() {
+        let mut server_data = server_data();
+        let trashed = Utc::now();
+        server_data.archive_season(&ChannelId(1), trashed);
+        let words = server_data
+            .season(&ChannelId(1), 1)
+            .unwrap()
+            .stats
+            .general_stats
+            .word_count;
+        assert!(server_data.trash_channel(&ChannelId(1), trashed));
+        assert!(!server_data.trash_channel(&ChannelId(1), trashed));
+        assert_eq!(server_data.channels().count(), 0);
+        assert_eq!(server_data.season_count(&ChannelId(1)), 0);
+        assert!(server_data.restore_channel(&ChannelId(2)).is_err());
+
+        assert!(server_data.restore_channel(&ChannelId(1)).is_ok());
+        assert!(server_data.trash.is_empty());
+        let season = server_data.season(&ChannelId(1), 1).unwrap();
+        assert_eq!(season.stats.general_stats.word_count, words);
+
+        // Initialised again in the meantime, so the old stats stay in the trash
+        server_data.trash_channel(&ChannelId(1), trashed);
+        server_data.insert(&ChannelId(1), ChannelData::default());
+        assert!(server_data.restore_channel(&ChannelId(1)).is_err());
+        assert_eq!(server_data.trash.len(), 1);
+
+        let retention = Duration::days(TRASH_RETENTION_DAYS);
+        assert_eq!(
+            server_data.empty_expired_trash(&(trashed + retention / 2)),
+            0
+        );
+        assert_eq!(server_data.empty_expired_trash(&(trashed + retention)), 1);
+    }
+
+    #[test]
+    fn seasons_archive_and_start_afresh() {
+        let mut server_data = server_data();
+        let words = server_data
+            .channels()
+            .next()
+            .unwrap()
+            .1
+            .general_stats
+            .word_count;
+        let last_message = server_data.channel_ids_with_last_message();
+        assert_eq!(server_data.season_started(&ChannelId(1)), None);
+        assert_eq!(server_data.archive_season(&ChannelId(2), Utc::now()), None);
+
+        let ended = Utc::now();
+        assert_eq!(server_data.archive_season(&ChannelId(1), ended), Some(1));
+        assert_eq!(server_data.season_started(&ChannelId(1)), Some(ended));
+        let (_, current) = server_data.channels().next().unwrap();
+        assert_eq!(current.general_stats.word_count, 0);
+        let season = server_data.season(&ChannelId(1), 1).unwrap();
+        assert_eq!(season.stats.general_stats.word_count, words);
+        assert!(server_data.season(&ChannelId(1), 0).is_none());
+        assert!(server_data.season(&ChannelId(1), 2).is_none());
+        // Replay still catches up from where the archived season left off
+        assert_eq!(server_data.channel_ids_with_last_message(), last_message);
+
+        let author = synthetic_messages(1, 1).pop().unwrap().author.id;
+        assert_eq!(server_data.forget_author(&author), 1);
+        let season = server_data.season(&ChannelId(1), 1).unwrap();
+        assert!(season.stats.get_user_by_id(&author).is_none());
+    }
+
+    #[test]
+    fn leaderboard_adds_up_channels() {
+        let mut server_data = server_data();
+        let single_channel = server_data.author_word_counts();
+        let mut copy = ChannelData::default();
+        for message in synthetic_messages(30, 3).iter() {
+            copy.update(message, &Tz::UTC, &WordRules::default());
+        }
+        server_data.insert(&ChannelId(2), copy);
+        let totals = server_data.author_word_counts();
+        assert_eq!(totals.len(), 3);
+        for ((author, words), (single_author, single_words)) in
+            totals.iter().zip(single_channel.iter())
+        {
+            assert_eq!(author, single_author);
+            assert_eq!(*words, single_words * 2);
+        }
+        assert!(totals.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        let leaderboard = server_data.make_leaderboard_string(2);
+        assert!(leaderboard.contains("Top 2 writers on this server, by words across 2 channels"));
+        assert_eq!(leaderboard.lines().count(), 3);
+        assert_eq!(
+            ServerData::new().make_leaderboard_string(10),
+            "No words recorded in any initialised channels"
+        );
+    }
+
+    #[test]
+    fn merge_preview_leaves_store_alone() {
+        let mut store = Store::default();
+        let mut server_data = server_data();
+        let mut other = ChannelData::default();
+        for message in synthetic_messages(10, 5).iter() {
+            other.update(message, &Tz::UTC, &WordRules::default());
+        }
+        server_data.insert(&ChannelId(2), other);
+        store.data.insert(GuildId(1), server_data);
+        let (from, into) = ((GuildId(1), ChannelId(2)), (GuildId(1), ChannelId(1)));
+        assert!(store.preview_merge_channel_data(&from, &from).is_err());
+        assert!(store
+            .preview_merge_channel_data(&(GuildId(1), ChannelId(3)), &into)
+            .is_err());
+
+        let preview = store.preview_merge_channel_data(&from, &into).unwrap();
+        assert!(store.channel_data_exists(&from));
+        let before = store.get_channel_data(&into).unwrap().clone();
+        store.merge_channel_data(&from, &into).unwrap();
+        let merged = store.get_channel_data(&into).unwrap();
+        assert_eq!(
+            preview.general_stats.word_count,
+            merged.general_stats.word_count
+        );
+        assert_eq!(preview.author_stats.len(), merged.author_stats.len());
+        let change = before.make_change_preview_string(&preview);
+        assert!(change.starts_with("Messages: 30 -> "));
+        assert!(change.contains(&format!(
+            "Words: {} -> {}",
+            before.general_stats.word_count, merged.general_stats.word_count
+        )));
+    }
+
+    #[test]
+    fn inaccessible_until_a_message_arrives() {
+        let message = synthetic_messages(1, 1).pop().unwrap();
+        let story_key = (GuildId(1), message.channel_id);
+        let mut store = Store::default();
+        store.data.insert(story_key.0, server_data());
+        store.set_channel_accessible(&story_key, false);
+        let inaccessible = |store: &Store| {
+            store
+                .get_server_data(&story_key.0)
+                .unwrap()
+                .inaccessible_channels
+                .contains(&story_key.1)
+        };
+        assert!(inaccessible(&store));
+        store.update_channel_data(&story_key, &message);
+        assert!(!inaccessible(&store));
+        // Servers without initialised channels have nothing to mark
+        store.set_channel_accessible(&(GuildId(2), story_key.1), false);
+        assert!(store.get_server_data(&GuildId(2)).is_none());
+    }
+
+    #[test]
+    fn audit_log_keeps_newest() {
+        let mut server_data = ServerData::new();
+        for i in 0..MAX_AUDIT_ENTRIES + 5 {
+            server_data.record_audit(AuditEntry {
+                actor: UserId(1),
+                at: Utc::now(),
+                command: String::from("set-prefix"),
+                invocation: format!("!scriv set-prefix {}", i),
+            });
+        }
+        assert_eq!(server_data.audit_log.len(), MAX_AUDIT_ENTRIES);
+        assert_eq!(server_data.audit_log[0].invocation, "!scriv set-prefix 5");
+    }
+
+    // Stats output is what users see, changes to these snapshots should be deliberate
+    #[test]
+    fn snapshot_stats_string() {
+        let server_data = server_data();
+        let (_, channel_data) = server_data.channels().next().unwrap();
+        let options = StatsDisplayOptions {
+            truncate_limit: Some(2),
+            ..StatsDisplayOptions::default()
+        };
+        insta::assert_snapshot!(channel_data.make_stats_string(
+            &text_channel(),
+            &server_data,
+            &options
+        ));
+    }
+
+    #[test]
+    fn snapshot_sessions_string() {
+        let server_data = server_data();
+        let (_, channel_data) = server_data.channels().next().unwrap();
+        insta::assert_snapshot!(channel_data.make_sessions_string(
+            &text_channel(),
+            &server_data,
+            Duration::minutes(1)
+        ));
+    }
+
+    #[test]
+    fn aliases_merge_authors() {
+        let mut server_data = server_data();
+        server_data.config.aliases.insert(UserId(2), UserId(1));
+        let (_, channel_data) = server_data.channels().next().unwrap();
+        let merged = server_data.aliased(channel_data);
+        assert_eq!(merged.author_stats.len(), 2);
+        let words = |channel_data: &ChannelData, user_id: u64| {
+            channel_data
+                .get_user_by_id(&UserId(user_id))
+                .map_or(0, |stats| stats.word_count)
+        };
+        assert_eq!(
+            words(&merged, 1),
+            words(channel_data, 1) + words(channel_data, 2)
+        );
+        assert_eq!(words(&merged, 3), words(channel_data, 3));
+
+        // Per-user queries agree with the merged copy, looked up by either account
+        for user_id in [UserId(1), UserId(2)].iter() {
+            assert_eq!(
+                server_data.channel_ids_by_wordcount_for_user(user_id),
+                vec![(ChannelId(1), words(&merged, 1))]
+            );
+        }
+        let totals = server_data.author_word_counts();
+        assert_eq!(totals.len(), 2);
+        assert!(totals.contains(&(UserId(1), words(&merged, 1))));
+    }
+
+    #[test]
+    fn anonymous_labels() {
+        let mut display_names = DisplayNameCache::default();
+        for user_id in 1..=28 {
+            display_names.update(&UserId(user_id), "writer");
+        }
+        assert_eq!(display_names.anonymous_label(&UserId(1)), "Writer A");
+        assert_eq!(display_names.anonymous_label(&UserId(26)), "Writer Z");
+        assert_eq!(display_names.anonymous_label(&UserId(28)), "Writer AB");
+        assert_eq!(
+            display_names.anonymous_label(&UserId(99)),
+            "Anonymous writer"
+        );
+    }
+
+    #[test]
+    fn departed_writers_fold_before_truncating() {
+        let mut server_data = server_data();
+        let (_, channel_data) = server_data.channels().next().unwrap();
+        let channel_data = channel_data.clone();
+        let departed = channel_data.last_poster().unwrap().id;
+        server_data.departed_authors.insert(departed);
+        let options = StatsDisplayOptions {
+            truncate_limit: Some(2),
+            fold_departed: true,
+            ..StatsDisplayOptions::default()
+        };
+        let stats = channel_data.make_stats_string(&text_channel(), &server_data, &options);
+        // The most recent writer left, the other two still fill the places shown
+        for user_id in [1, 2, 3].iter().map(|id| UserId(*id)) {
+            let name = format!("**{}**", server_data.shown_name_by_id(&user_id));
+            assert_eq!(stats.contains(&name), user_id != departed);
+        }
+        assert!(stats.contains("(departed) 1 writer(s)"));
+        assert!(!stats.contains("Not all authors are displayed"));
+    }
+
+    #[test]
+    fn pruning_keeps_writers_in_step_with_the_channel() {
+        let server_data = server_data();
+        let (_, channel_data) = server_data.channels().next().unwrap();
+        let mut channel_data = channel_data.clone();
+        let words = channel_data.general_stats.word_frequencies.len();
+        assert_eq!(channel_data.prune_words(words), 0);
+
+        assert!(channel_data.prune_words(words / 2) > 0);
+        let general = &channel_data.general_stats.word_frequencies;
+        assert!(general.len() <= words / 2);
+        for stats in channel_data.author_stats.values() {
+            assert!(!stats.word_frequencies.is_empty());
+            for (word, count) in stats.word_frequencies.iter() {
+                assert!(general
+                    .get(word)
+                    .is_some_and(|general_count| count <= general_count));
+            }
+        }
+    }
+
+    #[test]
+    fn stats_use_cached_display_names() {
+        let messages = synthetic_messages(10, 1);
+        let story_key = (GuildId(1), ChannelId(1));
+        let mut store = Store::default();
+        store.insert_channel_data_maybe_create_server_data(&story_key, ChannelData::default());
+        store.finish_replay();
+        for message in messages.iter() {
+            store.process_message(&story_key, message);
+        }
+        let author = &messages[0].author;
+        let server_data = store.get_server_data(&GuildId(1)).unwrap();
+        assert_eq!(server_data.display_names.get(author), "writer1");
+
+        // A member changing their nickname, and someone who never wrote here
+        store.update_display_name(&GuildId(1), &author.id, "Caligula");
+        store.update_display_name(&GuildId(1), &UserId(99), "lurker");
+        let server_data = store.get_server_data(&GuildId(1)).unwrap();
+        assert_eq!(server_data.display_names.get_by_id(&UserId(99)), None);
+        let stats = store
+            .get_channel_data(&story_key)
+            .unwrap()
+            .make_stats_string(
+                &text_channel(),
+                server_data,
+                &StatsDisplayOptions::default(),
+            );
+        assert!(stats.contains("**Caligula**"));
+        assert!(!stats.contains("writer1"));
+    }
+
+    #[test]
+    fn content_reports_count_watched_words() {
+        let mut messages = synthetic_messages(2, 1);
+        messages[0].content = String::from("Damn the rain, damn it all to hell.");
+        messages[1].content = String::from("More rain, what crap weather.");
+        let mut channel_data = ChannelData::default();
+        for message in messages.iter() {
+            channel_data.update(message, &Tz::UTC, &WordRules::default());
+        }
+        let mut channel_config = ChannelConfig::default();
+        channel_config.watch_lists.insert(
+            String::from("weather"),
+            ["rain", "snow"]
+                .iter()
+                .map(|word| word.to_string())
+                .collect(),
+        );
+        let report = channel_data.make_content_report_string(
+            &text_channel(),
+            &ServerData::new(),
+            &channel_config.content_categories(),
+        );
+        assert_eq!(
+            report.lines().skip(1).collect::<Vec<_>>(),
+            vec![
+                "",
+                "**profanity**",
+                "Total: 3",
+                "writer1: 3",
+                "",
+                "**weather**",
+                "Total: 2",
+                "writer1: 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn privacy_mode_hides_names() {
+        let mut server_data = server_data();
+        server_data.config.privacy_mode = true;
+        server_data.display_names.update(&UserId(1), "renamed");
+        let (_, channel_data) = server_data.channels().next().unwrap();
+        let stats = channel_data.make_stats_string(
+            &text_channel(),
+            &server_data,
+            &StatsDisplayOptions::default(),
+        );
+        assert!(stats.contains("Writer A"));
+        assert!(!stats.contains("renamed") && !stats.contains("writer1"));
+        assert_eq!(server_data.shown_name_by_id(&UserId(1)), "Writer A");
+        assert_eq!(server_data.shown_name_by_id(&UserId(3)), "Writer C");
+    }
+
+    #[test]
+    fn personal_log_streaks() {
+        let day = |d: u32| NaiveDate::from_ymd(2024, 3, d);
+        let mut log = PersonalLog::default();
+        log.log("The cat sat on the mat", day(1));
+        log.log("It sat there all day", day(2));
+        log.log("Then it left", day(3));
+        assert_eq!(log.word_count, 14);
+        assert_eq!(log.streak(day(3)), 3);
+        // Nothing yet today doesn't break it, missing yesterday does
+        assert_eq!(log.streak(day(4)), 3);
+        assert_eq!(log.streak(day(5)), 0);
+        // Day 3 falls short of the goal
+        log.daily_goal = Some(5);
+        assert_eq!(log.streak(day(3)), 2);
+        assert_eq!(log.streak(day(4)), 0);
+    }
+
+    #[test]
+    fn persisted_formats_load() {
+        let mut store = Store::default();
+        store.data.insert(GuildId(1), server_data());
+        store
+            .personal_logs
+            .entry(UserId(5))
+            .or_default()
+            .log("Some words written here", NaiveDate::from_ymd(2024, 3, 1));
+        let persisted = PersistedStoreRef {
+            servers: &store.data,
+            personal_logs: &store.personal_logs,
+        };
+        let loaded = Store::from_pickle(&serde_pickle::to_vec(&persisted, true).unwrap()).unwrap();
+        assert_eq!(loaded.personal_logs[&UserId(5)].word_count, 4);
+        assert_eq!(loaded.get_all_channels_in_server(&GuildId(1)).len(), 1);
+
+        // From before personal logs, just the servers
+        let legacy = Store::from_pickle(&serde_pickle::to_vec(&store.data, true).unwrap()).unwrap();
+        assert!(legacy.personal_logs.is_empty());
+        assert_eq!(legacy.get_all_channels_in_server(&GuildId(1)).len(), 1);
+    }
+
+    #[test]
+    fn co_writers_are_credited() {
+        let messages = synthetic_messages(10, 3);
+        let mut message = messages[0].clone();
+        let co_writer = messages
+            .iter()
+            .find(|other| other.author.id != message.author.id)
+            .unwrap()
+            .author
+            .clone();
+        message.content = format!("The ship [WITH <@!{}>] sailed at dawn.", co_writer.id);
+        message.mentions = vec![co_writer.clone()];
+        let mut channel_data = ChannelData::default();
+        channel_data.update(&message, &Tz::UTC, &WordRules::default());
+        assert_eq!(channel_data.general_stats.word_count, 5);
+        for author in [&message.author, &co_writer] {
+            let stats = channel_data.get_user_by_id(&author.id).unwrap();
+            assert_eq!(stats.word_count, 5);
+            assert!(stats.interactions.is_empty());
+        }
+    }
+
+    #[test]
+    fn co_written_posts_respond_for_their_poster() {
+        let messages = synthetic_messages(2, 1);
+        let (first, mut second) = (messages[0].clone(), messages[1].clone());
+        let mut poster = first.author.clone();
+        poster.id = UserId(3);
+        second.author = poster.clone();
+        // Credited to the first writer too, whose id is lower
+        second.content = format!("The ship [WITH <@!{}>] sailed at dawn.", first.author.id);
+        second.mentions = vec![first.author.clone()];
+        let mut channel_data = ChannelData::default();
+        for message in [&first, &second] {
+            channel_data.update(message, &Tz::UTC, &WordRules::default());
+        }
+        let response_times = channel_data.response_times();
+        assert_eq!(response_times.len(), 1);
+        assert_eq!(response_times[0].0.id, poster.id);
+        assert_eq!(response_times[0].1.count, 1);
+    }
+
+    #[test]
+    fn command_usage_is_reported() {
+        let mut store = Store::default();
+        store.record_command_use(&GuildId(2), "init-channel");
+        assert!(store.get_server_data(&GuildId(2)).is_none());
+
+        store.insert_channel_data_maybe_create_server_data(
+            &(GuildId(1), ChannelId(1)),
+            ChannelData::default(),
+        );
+        assert!(store
+            .get_server_data(&GuildId(1))
+            .unwrap()
+            .make_usage_report_string("Rome")
+            .contains("No commands used yet"));
+        for _ in 0..4 {
+            store.record_command_use(&GuildId(1), "show-stats");
+        }
+        store.record_command_error(&GuildId(1), "show-stats");
+        store.record_command_use(&GuildId(1), "recap");
+        let report = store
+            .get_server_data(&GuildId(1))
+            .unwrap()
+            .make_usage_report_string("Rome");
+        assert_eq!(
+            report.lines().collect::<Vec<_>>(),
+            vec![
+                "**Command usage for Rome**",
+                "show-stats: 4 uses, 1 errors (25.0%)",
+                "recap: 1 uses, 0 errors (0.0%)",
+            ]
+        );
+    }
+
+    #[test]
+    fn turns_pass_in_order() {
+        let messages = synthetic_messages(30, 3);
+        let by = |user_id: u64| {
+            messages
+                .iter()
+                .find(|message| message.author.id == UserId(user_id))
+                .unwrap()
+        };
+        let mut server_data = ServerData::new();
+        let channel_id = ChannelId(1);
+        server_data
+            .config
+            .channels
+            .entry(channel_id)
+            .or_default()
+            .turn_order = Some(TurnOrder {
+            writers: vec![UserId(1), UserId(2), UserId(3)],
+            ping_next: true,
+        });
+        assert_eq!(server_data.whose_turn(&channel_id), Some(UserId(1)));
+        assert_eq!(server_data.take_turn(&channel_id, by(1)), Some(UserId(2)));
+        // Posting again doesn't pass the turn on twice
+        assert_eq!(server_data.take_turn(&channel_id, by(1)), None);
+        // Out of turn passes it on from whoever posted
+        assert_eq!(server_data.take_turn(&channel_id, by(3)), Some(UserId(1)));
+        assert_eq!(server_data.whose_turn(&channel_id), Some(UserId(1)));
+    }
+
+    #[test]
+    fn snapshot_response_times_string() {
+        let server_data = server_data();
+        let (_, channel_data) = server_data.channels().next().unwrap();
+        insta::assert_snapshot!(
+            channel_data.make_response_times_string(&text_channel(), &server_data)
+        );
+    }
+
+    #[test]
+    fn messages_during_a_rebuild_are_counted() {
+        let messages = synthetic_messages(42, 3);
+        let count = |messages: &[serenity::model::channel::Message]| {
+            let mut channel_data = ChannelData::default();
+            for message in messages.iter() {
+                channel_data.update(message, &Tz::UTC, &WordRules::default());
+            }
+            channel_data
+        };
+        let story_key = (GuildId(1), ChannelId(1));
+        let mut store = Store::default();
+        store.insert_channel_data_maybe_create_server_data(&story_key, count(&messages[..20]));
+        store.finish_replay();
+
+        // The history is read up to message 29, while 20 to 39 arrive live
+        store.start_rebuild(&story_key);
+        for message in messages[20..40].iter() {
+            store.process_message(&story_key, message);
+        }
+        assert!(store.finish_rebuild(
+            &story_key,
+            Some(count(&messages[..30])),
+            Some(messages[29].id)
+        ));
+        assert_eq!(
+            store
+                .get_channel_data(&story_key)
+                .unwrap()
+                .general_stats
+                .word_count,
+            count(&messages[..40]).general_stats.word_count
+        );
+
+        // A failed rebuild leaves the stats as they were, still counting live messages
+        let before = count(&messages[..40]).general_stats.word_count;
+        store.start_rebuild(&story_key);
+        store.process_message(&story_key, &messages[40]);
+        assert!(!store.finish_rebuild(&story_key, None, None));
+        store.process_message(&story_key, &messages[41]);
+        assert!(store.rebuilding_channels.is_empty());
+        assert_eq!(
+            store
+                .get_channel_data(&story_key)
+                .unwrap()
+                .general_stats
+                .word_count,
+            before + count(&messages[40..]).general_stats.word_count
+        );
+    }
+}
@@ -1,42 +1,179 @@
-use chrono::{DateTime, Utc};
+use crate::language_parsing::WordRules;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
 use log::{debug, info};
 use serde::{Deserialize, Serialize};
 use serenity::model::channel::Message;
-use serenity::model::id::MessageId;
-use std::collections::{HashMap, HashSet};
+use serenity::model::id::{MessageId, UserId};
+use serenity::model::user::User;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::iter::FromIterator;
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+/// Something wrong in stored stats found by an integrity check
+#[derive(Debug, Clone, PartialEq)]
+pub struct Anomaly {
+    pub description: String,
+    pub repaired: bool,
+}
+
+impl Anomaly {
+    pub fn repaired(description: String) -> Self {
+        Self {
+            description,
+            repaired: true,
+        }
+    }
+
+    pub fn unrepaired(description: String) -> Self {
+        Self {
+            description,
+            repaired: false,
+        }
+    }
+
+    /// Says where the anomaly was found, e.g. which channel or author
+    pub fn within(self, context: &str) -> Self {
+        Self {
+            description: format!("{}: {}", context, self.description),
+            ..self
+        }
+    }
+}
+
+// Discord launched in 2015, nothing can have been written there before
+fn first_possible_day() -> NaiveDate {
+    NaiveDate::from_ymd(2015, 1, 1)
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct WordStats {
     pub word_count: usize,
     pub word_frequencies: HashMap<String, usize>,
     last_message: Option<(MessageId, DateTime<Utc>)>,
     included_messages: HashSet<MessageId>,
+    // Words per day, where the day is in the server's timezone when the message was counted
+    #[serde(default)]
+    daily_word_counts: BTreeMap<NaiveDate, usize>,
+    #[serde(default)]
+    pub media: MediaStats,
+    // Number of messages mentioning or replying to each other user
+    #[serde(default)]
+    pub interactions: HashMap<UserId, usize>,
+    // The post with the most words and how many it had, its content is fetched when it's shown
+    #[serde(default)]
+    longest_message: Option<(MessageId, usize)>,
+}
+
+/// Counts of non-text posts, art and maps matter as much as words on some servers
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MediaStats {
+    pub images: usize,
+    pub files: usize,
+    pub embeds: usize,
+}
+
+impl MediaStats {
+    fn update(&mut self, message: &Message) {
+        for attachment in message.attachments.iter() {
+            // Only images (and videos) come with dimensions
+            match attachment.dimensions() {
+                Some(_) => self.images += 1,
+                None => self.files += 1,
+            }
+        }
+        self.embeds += message.embeds.len();
+    }
+
+    fn merge(&mut self, other: &MediaStats) {
+        self.images += other.images;
+        self.files += other.files;
+        self.embeds += other.embeds;
+    }
+
+    fn subtract(&mut self, other: &MediaStats) {
+        self.images = self.images.saturating_sub(other.images);
+        self.files = self.files.saturating_sub(other.files);
+        self.embeds = self.embeds.saturating_sub(other.embeds);
+    }
+}
+
+/// Contiguous writing sessions, where a session is a run of messages with no gap longer than
+/// the one asked for
+#[derive(Debug, PartialEq)]
+pub struct SessionStats {
+    pub count: usize,
+    pub average_length: Duration,
+    pub longest: Duration,
+}
+
+/// How long a writer takes to post after someone else has
+#[derive(Debug, PartialEq)]
+pub struct ResponseTimeStats {
+    pub count: usize,
+    pub median: Duration,
+}
+
+impl ResponseTimeStats {
+    pub fn from_latencies(mut latencies: Vec<Duration>) -> Option<Self> {
+        latencies.sort();
+        let count = latencies.len();
+        let median = match count {
+            0 => return None,
+            _ if count.is_multiple_of(2) => (latencies[count / 2 - 1] + latencies[count / 2]) / 2,
+            _ => latencies[count / 2],
+        };
+        Some(Self { count, median })
+    }
+}
+
+impl Default for SessionStats {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            average_length: Duration::zero(),
+            longest: Duration::zero(),
+        }
+    }
 }
 
 impl WordStats {
-    pub fn new_from_message(message: &Message) -> Self {
+    pub fn new_from_message(message: &Message, timezone: &Tz, rules: &WordRules) -> Self {
         let mut t = Self::default();
-        t.update(message);
+        t.update(message, timezone, rules);
         t
     }
-    pub fn update(&mut self, message: &Message) {
+    pub fn update(&mut self, message: &Message, timezone: &Tz, rules: &WordRules) {
+        self.update_counted(message, &counted_words(&message.content, rules), timezone)
+    }
+
+    /// [update] with the message's [counted_words] already worked out, for a message added to
+    /// several stats at once
+    pub fn update_counted(&mut self, message: &Message, words: &[String], timezone: &Tz) {
         if !self.included_messages.contains(&message.id) {
             debug!("Wordstats update. message: {:?}", message);
-            let words = crate::language_parsing::tokenise(&message.content);
             debug!("Parsed {} words from message {}", words.len(), message.id);
-            //self.word_count += words.len();
-            for word_ in words {
-                let word = word_.to_lowercase().to_string();
-                if has_at_least_one_letter(&word) {
-                    if let Some(existing_count) = self.word_frequencies.get_mut(&word) {
-                        *existing_count += 1;
-                    } else {
-                        self.word_frequencies.insert(word, 1);
-                    }
-                    self.word_count += 1;
+            for word in words {
+                if let Some(existing_count) = self.word_frequencies.get_mut(word) {
+                    *existing_count += 1;
+                } else {
+                    self.word_frequencies.insert(word.clone(), 1);
                 }
             }
+            self.word_count += words.len();
+            let message_word_count = words.len();
+            if message_word_count > 0 {
+                let day = message
+                    .timestamp
+                    .with_timezone(timezone)
+                    .date()
+                    .naive_local();
+                *self.daily_word_counts.entry(day).or_insert(0) += message_word_count;
+                self.update_longest_message((message.id, message_word_count));
+            }
+            self.media.update(message);
+            for user_id in interaction_targets(message) {
+                *self.interactions.entry(user_id).or_insert(0) += 1;
+            }
             self.included_messages.insert(message.id);
             let should_update_last_message = match self.last_message {
                 None => true,
@@ -53,17 +190,30 @@ impl WordStats {
         }
     }
 
+    /// Keeps the earlier post if they're as long
+    fn update_longest_message(&mut self, (message_id, words): (MessageId, usize)) {
+        let is_longer = self
+            .longest_message
+            .is_none_or(|(longest_id, longest_words)| {
+                words > longest_words || (words == longest_words && message_id < longest_id)
+            });
+        if is_longer {
+            self.longest_message = Some((message_id, words));
+        }
+    }
+
     pub fn top_words(&self, n: usize) -> String {
         // This is a bit gross considering the possible size of [word_frequencies] but this is due
         // a major overhaul and that HashMap will be replaced by some efficient Summary type soon
         // and this whole function will need redoing then anyway
         let mut word_vec = Vec::from_iter(self.word_frequencies.iter());
-        word_vec.sort_by_key(|(_, count)| *count);
+        // Ties broken alphabetically, so the output doesn't depend on HashMap ordering
+        word_vec.sort_by_key(|(word, count)| (*count, std::cmp::Reverse(*word)));
         let mut sorted_words: Vec<String> = word_vec
             .iter()
             .filter_map(|(word, _)| {
                 // That ref ref deref deref deref is ... ugly ...
-                if is_valid_word(*word) {
+                if is_valid_word(word) {
                     Some((*word).clone())
                 } else {
                     None
@@ -90,6 +240,242 @@ impl WordStats {
         out
     }
 
+    /// Fold [other] into these stats, assumes the two cover different messages (e.g. two channels)
+    pub fn merge(&mut self, other: WordStats) {
+        self.word_count += other.word_count;
+        for (word, count) in other.word_frequencies {
+            *self.word_frequencies.entry(word).or_insert(0) += count;
+        }
+        self.included_messages.extend(other.included_messages);
+        self.media.merge(&other.media);
+        for (user_id, count) in other.interactions {
+            *self.interactions.entry(user_id).or_insert(0) += count;
+        }
+        for (day, count) in other.daily_word_counts {
+            *self.daily_word_counts.entry(day).or_insert(0) += count;
+        }
+        let should_update_last_message = match (self.last_message, other.last_message) {
+            (_, None) => false,
+            (None, Some(_)) => true,
+            (Some((_, last_message_time)), Some((_, other_time))) => other_time > last_message_time,
+        };
+        if should_update_last_message {
+            self.last_message = other.last_message;
+        }
+        if let Some(longest_message) = other.longest_message {
+            self.update_longest_message(longest_message);
+        }
+    }
+
+    /// Remove the contribution of [other] from these stats, where [other] covers a subset of the
+    /// messages included here (e.g. one author's stats out of the general stats)
+    pub fn subtract(&mut self, other: &WordStats) {
+        self.word_count = self.word_count.saturating_sub(other.word_count);
+        for (word, count) in other.word_frequencies.iter() {
+            if let Some(existing_count) = self.word_frequencies.get_mut(word) {
+                *existing_count = existing_count.saturating_sub(*count);
+                if *existing_count == 0 {
+                    self.word_frequencies.remove(word);
+                }
+            }
+        }
+        for message_id in other.included_messages.iter() {
+            self.included_messages.remove(message_id);
+        }
+        // The next longest isn't known without recounting, so the record is dropped
+        if self
+            .longest_message
+            .is_some_and(|(message_id, _)| other.included_messages.contains(&message_id))
+        {
+            self.longest_message = None;
+        }
+        self.media.subtract(&other.media);
+        for (user_id, count) in other.interactions.iter() {
+            if let Some(existing_count) = self.interactions.get_mut(user_id) {
+                *existing_count = existing_count.saturating_sub(*count);
+                if *existing_count == 0 {
+                    self.interactions.remove(user_id);
+                }
+            }
+        }
+        for (day, count) in other.daily_word_counts.iter() {
+            if let Some(existing_count) = self.daily_word_counts.get_mut(day) {
+                *existing_count = existing_count.saturating_sub(*count);
+                if *existing_count == 0 {
+                    self.daily_word_counts.remove(day);
+                }
+            }
+        }
+    }
+
+    pub fn message_ids(&self) -> impl Iterator<Item = &MessageId> {
+        self.included_messages.iter()
+    }
+
+    /// Sessions are worked out from when the included messages were sent, which message ids carry
+    pub fn sessions(&self, max_gap: Duration) -> SessionStats {
+        let mut times: Vec<DateTime<Utc>> = self
+            .included_messages
+            .iter()
+            .map(|message_id| message_id.created_at())
+            .collect();
+        times.sort();
+        let mut session_lengths = vec![];
+        let mut times = times.into_iter();
+        if let Some(first) = times.next() {
+            let (mut session_start, mut previous) = (first, first);
+            for time in times {
+                if time - previous > max_gap {
+                    session_lengths.push(previous - session_start);
+                    session_start = time;
+                }
+                previous = time;
+            }
+            session_lengths.push(previous - session_start);
+        }
+        let count = session_lengths.len();
+        match count {
+            0 => SessionStats::default(),
+            _ => SessionStats {
+                count,
+                average_length: session_lengths
+                    .iter()
+                    .fold(Duration::zero(), |total, length| total + *length)
+                    / count as i32,
+                longest: session_lengths.into_iter().max().unwrap(),
+            },
+        }
+    }
+
+    /// Drops the least frequent words once there are more than [max_words] of them, so one huge
+    /// channel can't use up all the memory. Prunes down to 90% of [max_words] so it doesn't need
+    /// doing again on the next message. [word_count] still counts the dropped words, it's only the
+    /// long tail of frequencies that's lost. Returns how many words were dropped
+    pub fn prune_words(&mut self, max_words: usize) -> usize {
+        if self.word_frequencies.len() <= max_words {
+            return 0;
+        }
+        let to_remove = self.word_frequencies.len() - max_words * 9 / 10;
+        let mut counts: Vec<usize> = self.word_frequencies.values().copied().collect();
+        let (_, threshold, _) = counts.select_nth_unstable(to_remove - 1);
+        let threshold = *threshold;
+        let mut removed = 0;
+        self.word_frequencies.retain(|_, count| {
+            if *count < threshold {
+                removed += 1;
+                false
+            } else {
+                true
+            }
+        });
+        // Then enough of the words right on the threshold to get down to size
+        self.word_frequencies.retain(|_, count| {
+            if *count == threshold && removed < to_remove {
+                removed += 1;
+                false
+            } else {
+                true
+            }
+        });
+        removed
+    }
+
+    /// Drops the words [keep] doesn't, returning how many
+    pub fn retain_words<F: Fn(&str) -> bool>(&mut self, keep: F) -> usize {
+        let before = self.word_frequencies.len();
+        self.word_frequencies.retain(|word, _| keep(word));
+        before - self.word_frequencies.len()
+    }
+
+    /// Rough size in memory, for keeping an eye on which servers are getting big
+    pub fn approx_memory_bytes(&self) -> usize {
+        let word_bytes: usize = self
+            .word_frequencies
+            .keys()
+            .map(|word| word.len() + std::mem::size_of::<(String, usize)>())
+            .sum();
+        word_bytes
+            + self.included_messages.len() * std::mem::size_of::<MessageId>()
+            + self.daily_word_counts.len() * std::mem::size_of::<(NaiveDate, usize)>()
+            + self.interactions.len() * std::mem::size_of::<(UserId, usize)>()
+    }
+
+    /// Looks for counts that can't have come from counting messages, and puts right what it can.
+    /// Days after [latest_day] haven't happened yet anywhere
+    pub fn check_integrity(&mut self, latest_day: NaiveDate) -> Vec<Anomaly> {
+        let mut anomalies = vec![];
+        let empty_counts = {
+            let before = self.word_frequencies.len()
+                + self.interactions.len()
+                + self.daily_word_counts.len();
+            self.word_frequencies.retain(|_, count| *count > 0);
+            self.interactions.retain(|_, count| *count > 0);
+            self.daily_word_counts.retain(|_, count| *count > 0);
+            before
+                - (self.word_frequencies.len()
+                    + self.interactions.len()
+                    + self.daily_word_counts.len())
+        };
+        if empty_counts > 0 {
+            anomalies.push(Anomaly::repaired(format!(
+                "removed {} counts of zero",
+                empty_counts
+            )));
+        }
+        let impossible_days: Vec<NaiveDate> = self
+            .daily_word_counts
+            .keys()
+            .filter(|day| **day > latest_day || **day < first_possible_day())
+            .copied()
+            .collect();
+        if !impossible_days.is_empty() {
+            for day in impossible_days.iter() {
+                self.daily_word_counts.remove(day);
+            }
+            anomalies.push(Anomaly::repaired(format!(
+                "removed {} daily counts dated before discord or in the future",
+                impossible_days.len()
+            )));
+        }
+        let daily_total: usize = self.daily_word_counts.values().sum();
+        if daily_total > self.word_count {
+            anomalies.push(Anomaly::unrepaired(format!(
+                "daily counts add up to {} words, more than the {} counted, rebuild the channel to fix",
+                daily_total, self.word_count
+            )));
+        }
+        if self.last_message.is_none() {
+            if let Some(latest) = self.included_messages.iter().max() {
+                self.last_message = Some((*latest, latest.created_at()));
+                anomalies.push(Anomaly::repaired(String::from(
+                    "restored the missing last message from the counted ones",
+                )));
+            }
+        }
+        if self
+            .longest_message
+            .is_some_and(|(message_id, _)| !self.included_messages.contains(&message_id))
+        {
+            self.longest_message = None;
+            anomalies.push(Anomaly::repaired(String::from(
+                "dropped a longest post that isn't among the counted ones",
+            )));
+        }
+        anomalies
+    }
+
+    pub fn daily_word_counts(&self) -> &BTreeMap<NaiveDate, usize> {
+        &self.daily_word_counts
+    }
+
+    pub fn words_on_day(&self, day: &NaiveDate) -> usize {
+        self.daily_word_counts.get(day).copied().unwrap_or(0)
+    }
+
+    pub fn longest_message(&self) -> Option<(MessageId, usize)> {
+        self.longest_message
+    }
+
     pub fn last_message(&self) -> Option<MessageId> {
         self.last_message.map(|(mid, _date)| mid)
     }
@@ -101,6 +487,112 @@ impl WordStats {
     }
 }
 
+/// Other users a message is directed at, by mention or by reply. Replies usually mention the
+/// replied-to author too, so each user is only counted once per message
+fn interaction_targets(message: &Message) -> HashSet<UserId> {
+    message
+        .mentions
+        .iter()
+        .chain(message.referenced_message.iter().map(|m| &m.author))
+        .filter(|user| !user.bot && user.id != message.author.id)
+        .map(|user| user.id)
+        .collect()
+}
+
+/// Where a `[with @user @other]` tag is in the message, and who it names
+fn co_writing_tag(content: &str) -> Option<(std::ops::Range<usize>, Vec<UserId>)> {
+    // Lowercasing ascii only keeps the byte offsets the same as in [content]
+    let start = content.to_ascii_lowercase().find("[with ")?;
+    let end = start + content[start..].find(']')? + 1;
+    let user_ids: Vec<UserId> = content[start + "[with ".len()..end - 1]
+        .split_whitespace()
+        .filter_map(serenity::utils::parse_username)
+        .map(UserId)
+        .collect();
+    match user_ids.is_empty() {
+        true => None,
+        false => Some((start..end, user_ids)),
+    }
+}
+
+/// Other writers credited with a `[with @user]` tag, for posts written together elsewhere and
+/// posted by one of them. They're credited with all of its words, as is the poster
+pub fn co_writers(message: &Message) -> Vec<&User> {
+    let user_ids = match co_writing_tag(&message.content) {
+        Some((_, user_ids)) => user_ids,
+        None => return vec![],
+    };
+    message
+        .mentions
+        .iter()
+        .filter(|user| user_ids.contains(&user.id) && user.id != message.author.id && !user.bot)
+        .collect()
+}
+
+/// The message with its co-writing tag taken out, so the tag isn't counted as words or its
+/// mentions as interactions. None if it doesn't have one
+pub fn without_co_writing_tag(message: &Message) -> Option<Message> {
+    let (range, user_ids) = co_writing_tag(&message.content)?;
+    let mut message = message.clone();
+    message.content.replace_range(range, "");
+    message.mentions.retain(|user| !user_ids.contains(&user.id));
+    Some(message)
+}
+
+// A word's share of everything written is a tiny fraction, scaled up to whole counts for a wordcloud
+const DISTINCTIVE_SCALE: f64 = 100_000.0;
+
+/// Splits two sets of word frequencies into the words each side uses more of, as a share of its
+/// own words so a prolific side doesn't win everything, weighted by how much more
+pub fn distinctive_frequencies(
+    left: &HashMap<String, usize>,
+    right: &HashMap<String, usize>,
+) -> (HashMap<String, usize>, HashMap<String, usize>) {
+    let left_total = left.values().sum::<usize>().max(1) as f64;
+    let right_total = right.values().sum::<usize>().max(1) as f64;
+    let share = |frequencies: &HashMap<String, usize>, word: &str, total: f64| -> f64 {
+        frequencies.get(word).copied().unwrap_or(0) as f64 / total
+    };
+    let mut left_distinctive = HashMap::new();
+    let mut right_distinctive = HashMap::new();
+    for word in left.keys().chain(right.keys()) {
+        let difference = share(left, word, left_total) - share(right, word, right_total);
+        let weight = (difference.abs() * DISTINCTIVE_SCALE).round() as usize;
+        match weight {
+            0 => (),
+            _ if difference > 0.0 => {
+                left_distinctive.insert(word.clone(), weight);
+            }
+            _ => {
+                right_distinctive.insert(word.clone(), weight);
+            }
+        }
+    }
+    (left_distinctive, right_distinctive)
+}
+
+/// The words counted towards word counts and frequencies, lowercased
+pub fn counted_words(text: &str, rules: &WordRules) -> Vec<String> {
+    crate::language_parsing::tokenise_with_rules(text, rules)
+        .into_iter()
+        .map(str::to_lowercase)
+        .filter(|word| counts_as_word(word, rules))
+        .collect()
+}
+
+/// Words as counted towards word counts, without keeping track of which they were
+pub fn count_words(text: &str, rules: &WordRules) -> usize {
+    crate::language_parsing::tokenise_with_rules(text, rules)
+        .iter()
+        .filter(|word| counts_as_word(&word.to_lowercase(), rules))
+        .count()
+}
+
+fn counts_as_word(word: &str, rules: &WordRules) -> bool {
+    has_at_least_one_letter(word)
+        || (rules.counts_numbers() && word.contains(|c: char| c.is_ascii_digit()))
+}
+
 fn is_valid_word(word: &str) -> bool {
     has_at_least_one_letter(word) && is_not_stop_word(word)
 }
@@ -306,3 +798,206 @@ const STOP_WORDS: [&str; 192] = [
     "yourself",
     "yourselves",
 ];
+
+#[cfg(test)]
+mod testing {
+    use crate::bench_corpus::synthetic_messages;
+    use crate::language_parsing::WordRules;
+    use crate::stats::{distinctive_frequencies, SessionStats, WordStats};
+    use chrono::{Duration, NaiveDate, TimeZone, Utc};
+    use chrono_tz::Tz;
+    use serenity::model::id::MessageId;
+    use std::collections::HashMap;
+
+    fn make_stats(words: &[(&str, usize)], message_ids: &[u64]) -> WordStats {
+        let mut stats = WordStats::default();
+        for (word, count) in words {
+            stats.word_frequencies.insert(word.to_string(), *count);
+            stats.word_count += count;
+        }
+        stats.included_messages = message_ids.iter().map(|id| MessageId(*id)).collect();
+        stats.last_message = message_ids
+            .iter()
+            .max()
+            .map(|id| (MessageId(*id), Utc.timestamp(*id as i64, 0)));
+        stats
+    }
+
+    #[test]
+    fn integrity_repairs() {
+        let today = NaiveDate::from_ymd(2021, 3, 1);
+        let mut stats = make_stats(&[("cat", 2), ("mat", 0)], &[1, 2]);
+        assert_eq!(stats.check_integrity(today).len(), 1);
+        assert!(!stats.word_frequencies.contains_key("mat"));
+        assert!(stats.check_integrity(today).is_empty());
+
+        stats.last_message = None;
+        stats.daily_word_counts.insert(today, 1);
+        stats
+            .daily_word_counts
+            .insert(NaiveDate::from_ymd(2031, 1, 1), 1);
+        stats
+            .daily_word_counts
+            .insert(NaiveDate::from_ymd(1999, 1, 1), 1);
+        let anomalies = stats.check_integrity(today);
+        assert_eq!(anomalies.len(), 2);
+        assert!(anomalies.iter().all(|anomaly| anomaly.repaired));
+        assert_eq!(stats.last_message(), Some(MessageId(2)));
+        assert_eq!(stats.daily_word_counts.len(), 1);
+
+        stats.daily_word_counts.insert(today, 5);
+        let anomalies = stats.check_integrity(today);
+        assert_eq!(anomalies.len(), 1);
+        assert!(!anomalies[0].repaired);
+    }
+
+    #[test]
+    fn days_are_local_to_the_server() {
+        let mut message = synthetic_messages(1, 1).remove(0);
+        message.timestamp = Utc.ymd(2021, 1, 1).and_hms(23, 30, 0);
+        let words = crate::stats::count_words(&message.content, &WordRules::default());
+        let day = |d: u32| NaiveDate::from_ymd(2021, 1, d);
+
+        let mut utc_stats = WordStats::default();
+        utc_stats.update(&message, &Tz::UTC, &WordRules::default());
+        assert_eq!(utc_stats.words_on_day(&day(1)), words);
+
+        // Already the next morning in Tokyo
+        let mut tokyo_stats = WordStats::default();
+        tokyo_stats.update(&message, &Tz::Asia__Tokyo, &WordRules::default());
+        assert_eq!(tokyo_stats.words_on_day(&day(1)), 0);
+        assert_eq!(tokyo_stats.words_on_day(&day(2)), words);
+    }
+
+    #[test]
+    fn media_is_counted() {
+        let mut message = synthetic_messages(1, 1).remove(0);
+        let attachment = |filename: &str, dimensions: Option<u64>| {
+            serde_json::from_value(serde_json::json!({
+                "id": "1",
+                "filename": filename,
+                "height": dimensions,
+                "width": dimensions,
+                "proxy_url": "https://cdn.discordapp.com/attachments/1",
+                "size": 1024,
+                "url": "https://cdn.discordapp.com/attachments/1",
+            }))
+            .unwrap()
+        };
+        message.attachments = vec![
+            attachment("map.png", Some(512)),
+            attachment("notes.txt", None),
+        ];
+        message.embeds =
+            vec![serde_json::from_value(serde_json::json!({ "type": "rich" })).unwrap()];
+        let mut stats = WordStats::default();
+        stats.update(&message, &Tz::UTC, &WordRules::default());
+        assert_eq!(
+            (stats.media.images, stats.media.files, stats.media.embeds),
+            (1, 1, 1)
+        );
+        // The same message again isn't counted twice
+        stats.update(&message, &Tz::UTC, &WordRules::default());
+        assert_eq!(stats.media.images, 1);
+
+        let mut merged = stats.clone();
+        merged.merge(stats.clone());
+        assert_eq!(merged.media.images, 2);
+        merged.subtract(&stats);
+        assert_eq!(merged.media.files, 1);
+    }
+
+    #[test]
+    fn merge() {
+        let mut stats = make_stats(&[("cat", 2), ("mat", 1)], &[1, 2]);
+        stats.merge(make_stats(&[("cat", 1), ("hat", 3)], &[3]));
+        assert_eq!(stats.word_count, 7);
+        assert_eq!(stats.word_frequencies.get("cat"), Some(&3));
+        assert_eq!(stats.word_frequencies.get("hat"), Some(&3));
+        assert_eq!(stats.included_messages.len(), 3);
+        assert_eq!(stats.last_message(), Some(MessageId(3)));
+    }
+
+    #[test]
+    fn longest_message() {
+        let mut stats = make_stats(&[("cat", 2)], &[1, 2]);
+        stats.longest_message = Some((MessageId(2), 2));
+        let mut other = make_stats(&[("hat", 2)], &[3]);
+        other.longest_message = Some((MessageId(3), 2));
+        stats.merge(other.clone());
+        // Ties go to the earlier post
+        assert_eq!(stats.longest_message(), Some((MessageId(2), 2)));
+        other.longest_message = Some((MessageId(3), 5));
+        stats.merge(other.clone());
+        assert_eq!(stats.longest_message(), Some((MessageId(3), 5)));
+        stats.subtract(&other);
+        assert_eq!(stats.longest_message(), None);
+    }
+
+    #[test]
+    fn subtract() {
+        let mut stats = make_stats(&[("cat", 2), ("mat", 1)], &[1, 2]);
+        stats.subtract(&make_stats(&[("cat", 1), ("mat", 1)], &[2]));
+        assert_eq!(stats.word_count, 1);
+        assert_eq!(stats.word_frequencies.get("cat"), Some(&1));
+        assert_eq!(stats.word_frequencies.get("mat"), None);
+        assert_eq!(stats.included_messages.len(), 1);
+    }
+
+    #[test]
+    fn prune_words() {
+        let mut stats = make_stats(&[("a", 5), ("b", 1), ("c", 2), ("d", 1), ("e", 3)], &[1]);
+        assert_eq!(stats.prune_words(5), 0);
+        // Down to 90% of 4
+        assert_eq!(stats.prune_words(4), 2);
+        assert_eq!(stats.word_frequencies.len(), 3);
+        assert!(!stats.word_frequencies.contains_key("b"));
+        assert!(!stats.word_frequencies.contains_key("d"));
+        assert_eq!(stats.word_count, 12);
+    }
+
+    #[test]
+    fn sessions() {
+        // Message ids are snowflakes, with milliseconds since the discord epoch in the top bits
+        let minutes = |m: u64| (m * 60 * 1000) << 22;
+        let stats = WordStats {
+            included_messages: [0, 10, 25, 100, 200, 205]
+                .iter()
+                .map(|m| MessageId(minutes(*m)))
+                .collect(),
+            ..WordStats::default()
+        };
+        assert_eq!(
+            stats.sessions(Duration::minutes(30)),
+            SessionStats {
+                count: 3,
+                average_length: Duration::minutes(10),
+                longest: Duration::minutes(25),
+            }
+        );
+        assert_eq!(stats.sessions(Duration::minutes(200)).count, 1);
+        assert_eq!(
+            WordStats::default().sessions(Duration::minutes(30)),
+            SessionStats::default()
+        );
+    }
+    #[test]
+    fn distinctive_words() {
+        let frequencies = |words: &[(&str, usize)]| -> HashMap<String, usize> {
+            words
+                .iter()
+                .map(|(word, count)| (word.to_string(), *count))
+                .collect()
+        };
+        // Both say "sword" a quarter of the time, so it's neither's
+        let left = frequencies(&[("sword", 1), ("dragon", 3)]);
+        let right = frequencies(&[("sword", 10), ("castle", 30)]);
+        let (left_distinctive, right_distinctive) = distinctive_frequencies(&left, &right);
+        assert_eq!(left_distinctive, frequencies(&[("dragon", 75_000)]));
+        assert_eq!(right_distinctive, frequencies(&[("castle", 75_000)]));
+
+        let (left_distinctive, right_distinctive) = distinctive_frequencies(&left, &HashMap::new());
+        assert_eq!(left_distinctive.len(), 2);
+        assert!(right_distinctive.is_empty());
+    }
+}
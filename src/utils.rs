@@ -1,5 +1,3 @@
-use serenity::utils::MessageBuilder;
-
 pub mod helpers {
     pub fn strip_leading_trailing(s: &str, c: char) -> &str {
         let prefix_stripped: &str = match s.strip_prefix(c) {
@@ -79,7 +77,7 @@ pub mod iterators {
         pub fn sort_by_last_message_and_maybe_truncate(
             stats_map: &HashMap<User, WordStats>,
             truncate_limit: Option<usize>,
-        ) -> SortedHashMap<User, WordStats> {
+        ) -> SortedHashMap<'_, User, WordStats> {
             let epoch = DateTime::<Utc>::from_utc(NaiveDateTime::from_timestamp(0, 0), Utc);
             let mut ref_vector: Vec<(&User, Option<&DateTime<Utc>>)> = stats_map
                 .iter()
@@ -96,7 +94,6 @@ pub mod iterators {
 }
 
 pub mod unidecode_extended {
-    use serenity::framework::standard::HelpBehaviour::Strike;
 
     pub fn unidecode(input: &str) -> Option<String> {
         let unknown_char = "[?]";
@@ -148,6 +145,443 @@ pub mod unidecode_extended {
     }
 }
 
+pub mod render_options {
+    use serenity::framework::standard::{Args, Delimiter};
+    use std::str::FromStr;
+
+    // Comfortably above anything discord will preview, but stops someone asking for a poster
+    const MAX_DIMENSION: u32 = 4096;
+    const MIN_DIMENSION: u32 = 50;
+
+    #[derive(Debug, Default, Clone, PartialEq)]
+    pub enum ImageFormat {
+        #[default]
+        Png,
+        Webp,
+        Svg,
+    }
+    impl ImageFormat {
+        pub fn to_str(&self) -> &'static str {
+            match self {
+                Self::Png => "png",
+                Self::Webp => "webp",
+                Self::Svg => "svg",
+            }
+        }
+    }
+    impl FromStr for ImageFormat {
+        type Err = String;
+
+        fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+            match s {
+                "png" => Ok(Self::Png),
+                "webp" => Ok(Self::Webp),
+                "svg" => Ok(Self::Svg),
+                _ => Err(format!("Invalid image format {}, expected png|webp|svg", s)),
+            }
+        }
+    }
+
+    /// Options for commands which generate an image, given as [--size WxH] and [--format fmt]
+    /// anywhere in the args
+    #[derive(Debug, Default, Clone, PartialEq)]
+    pub struct RenderOptions {
+        pub size: Option<(u32, u32)>,
+        pub format: ImageFormat,
+    }
+
+    fn parse_size(s: &str) -> std::result::Result<(u32, u32), String> {
+        let invalid = || format!("Invalid size {}, expected WIDTHxHEIGHT e.g. 1920x1080", s);
+        let (width, height) = match s.split_once('x') {
+            Some((w, h)) => (
+                w.parse::<u32>().map_err(|_| invalid())?,
+                h.parse::<u32>().map_err(|_| invalid())?,
+            ),
+            None => return Err(invalid()),
+        };
+        let in_bounds = |d: u32| (MIN_DIMENSION..=MAX_DIMENSION).contains(&d);
+        if in_bounds(width) && in_bounds(height) {
+            Ok((width, height))
+        } else {
+            Err(format!(
+                "Size {} out of bounds, width and height must be between {} and {}",
+                s, MIN_DIMENSION, MAX_DIMENSION
+            ))
+        }
+    }
+
+    /// Pulls any render options out of [args], returning the remaining args untouched so the
+    /// command's own positional parsing doesn't need to know about them
+    pub fn extract_render_options(
+        args: &Args,
+    ) -> std::result::Result<(Args, RenderOptions), String> {
+        let mut options = RenderOptions::default();
+        let mut remaining: Vec<&str> = vec![];
+        let mut raw = args.raw();
+        while let Some(arg) = raw.next() {
+            match arg {
+                "--size" => match raw.next() {
+                    Some(size) => options.size = Some(parse_size(size)?),
+                    None => return Err(String::from("--size needs a value e.g. 1920x1080")),
+                },
+                "--format" => match raw.next() {
+                    Some(format) => options.format = format.parse::<ImageFormat>()?,
+                    None => return Err(String::from("--format needs a value: png|webp|svg")),
+                },
+                other => remaining.push(other),
+            }
+        }
+        let remaining_args = Args::new(&remaining.join(" "), &[Delimiter::Single(' ')]);
+        Ok((remaining_args, options))
+    }
+}
+
+pub mod confirmation {
+    use serenity::model::prelude::*;
+    use serenity::prelude::Context;
+    use std::time::Duration;
+
+    /// Given to a destructive command to report what it would change, without changing anything
+    /// or asking for confirmation
+    pub const DRY_RUN_FLAG: &str = "--dry-run";
+    const CONFIRM_EMOJI: &str = "✅";
+    const CONFIRM_TIMEOUT: Duration = Duration::from_secs(30);
+
+    fn confirm_reaction() -> ReactionType {
+        ReactionType::Unicode(String::from(CONFIRM_EMOJI))
+    }
+
+    /// Asks the author of [msg] to confirm they want to [action] by reacting to a prompt, so a
+    /// typo'd mention can't wipe anything. Returns whether they confirmed in time, the prompt is
+    /// edited to say so if not
+    pub async fn confirm_destructive_action(
+        ctx: &Context,
+        msg: &Message,
+        action: &str,
+    ) -> serenity::Result<bool> {
+        let mut prompt = msg
+            .reply(
+                ctx,
+                format!(
+                    "This will {}. React {} within {}s to confirm",
+                    action,
+                    CONFIRM_EMOJI,
+                    CONFIRM_TIMEOUT.as_secs()
+                ),
+            )
+            .await?;
+        prompt.react(ctx, confirm_reaction()).await?;
+        let confirmation = prompt
+            .await_reaction(ctx)
+            .author_id(msg.author.id)
+            .timeout(CONFIRM_TIMEOUT)
+            .filter(|reaction| reaction.emoji == confirm_reaction())
+            .await;
+        let confirmed = confirmation.is_some();
+        if !confirmed {
+            prompt
+                .edit(ctx, |m| {
+                    m.content(format!(
+                        "Not confirmed within {}s, nothing was changed",
+                        CONFIRM_TIMEOUT.as_secs()
+                    ))
+                })
+                .await?;
+        }
+        Ok(confirmed)
+    }
+}
+
+/// Replies to settings commands delete themselves after a while, so changing a setting in a story
+/// channel doesn't leave the exchange in the middle of the story
+pub mod transient {
+    use log::error;
+    use serenity::model::prelude::*;
+    use serenity::prelude::Context;
+    use std::fmt::Display;
+    use std::time::Duration;
+
+    const REPLY_LIFETIME: Duration = Duration::from_secs(60);
+
+    pub async fn transient_reply(
+        ctx: &Context,
+        msg: &Message,
+        content: impl Display,
+    ) -> serenity::Result<()> {
+        let reply = msg
+            .reply(
+                ctx,
+                format!("{}\n_This reply is removed after a minute_", content),
+            )
+            .await?;
+        let ctx = ctx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(REPLY_LIFETIME).await;
+            if let Err(why) = reply.delete(&ctx).await {
+                error!("Failed deleting transient reply: {:?}", why);
+            }
+        });
+        Ok(())
+    }
+}
+
+/// A command that has already told the user what went wrong can still fail with [AlreadyReplied],
+/// so the failure is counted in usage-report but the generic error reply isn't sent on top
+pub mod replied {
+    use serenity::framework::standard::{CommandError, CommandResult};
+    use serenity::model::prelude::*;
+    use serenity::prelude::Context;
+    use std::fmt;
+
+    #[derive(Debug)]
+    pub struct AlreadyReplied(pub String);
+
+    impl fmt::Display for AlreadyReplied {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl std::error::Error for AlreadyReplied {}
+
+    /// Replies to [msg] with [reply], then fails the command with it
+    pub async fn reply_and_fail(ctx: &Context, msg: &Message, reply: String) -> CommandResult {
+        msg.reply(ctx, &reply).await?;
+        Err(AlreadyReplied(reply).into())
+    }
+
+    pub fn already_replied(error: &CommandError) -> bool {
+        error.is::<AlreadyReplied>()
+    }
+}
+
+/// The discord calls commands make, behind a trait so the flow of a command can be tested against
+/// [mock::MockDiscord] without a live bot
+pub mod discord {
+    use chrono::{DateTime, Utc};
+    use serenity::async_trait;
+    use serenity::http::{AttachmentType, Http};
+    use serenity::model::prelude::*;
+    use serenity::prelude::Context;
+    use std::sync::Arc;
+
+    const DISCORD_EPOCH_MS: i64 = 1_420_070_400_000;
+
+    /// The lowest id a message sent at [time] could have, to page back from [time] with
+    /// [DiscordApi::messages_before]
+    pub fn message_id_at(time: DateTime<Utc>) -> MessageId {
+        MessageId(((time.timestamp_millis() - DISCORD_EPOCH_MS).max(0) as u64) << 22)
+    }
+
+    #[async_trait]
+    pub trait DiscordApi: Send + Sync {
+        async fn message(
+            &self,
+            channel_id: ChannelId,
+            message_id: MessageId,
+        ) -> serenity::Result<Message>;
+        /// Up to [limit] messages sent before [before], in no particular order
+        async fn messages_before(
+            &self,
+            channel_id: ChannelId,
+            before: MessageId,
+            limit: u64,
+        ) -> serenity::Result<Vec<Message>>;
+        async fn say(&self, channel_id: ChannelId, content: String) -> serenity::Result<()>;
+        async fn reply(&self, msg: &Message, content: String) -> serenity::Result<()>;
+        async fn send_file(
+            &self,
+            channel_id: ChannelId,
+            filename: String,
+            data: Vec<u8>,
+            content: String,
+        ) -> serenity::Result<()>;
+    }
+
+    #[async_trait]
+    impl DiscordApi for Arc<Http> {
+        async fn message(
+            &self,
+            channel_id: ChannelId,
+            message_id: MessageId,
+        ) -> serenity::Result<Message> {
+            channel_id.message(self, message_id).await
+        }
+
+        async fn messages_before(
+            &self,
+            channel_id: ChannelId,
+            before: MessageId,
+            limit: u64,
+        ) -> serenity::Result<Vec<Message>> {
+            channel_id
+                .messages(self, |get_messages_builder| {
+                    get_messages_builder.before(before).limit(limit)
+                })
+                .await
+        }
+
+        async fn say(&self, channel_id: ChannelId, content: String) -> serenity::Result<()> {
+            channel_id.say(self, content).await.map(|_| ())
+        }
+
+        async fn reply(&self, msg: &Message, content: String) -> serenity::Result<()> {
+            msg.reply(self, content).await.map(|_| ())
+        }
+
+        async fn send_file(
+            &self,
+            channel_id: ChannelId,
+            filename: String,
+            data: Vec<u8>,
+            content: String,
+        ) -> serenity::Result<()> {
+            let files = vec![AttachmentType::Bytes {
+                data: data.into(),
+                filename,
+            }];
+            channel_id
+                .send_files(self, files, |create_message| {
+                    create_message.content(content)
+                })
+                .await
+                .map(|_| ())
+        }
+    }
+
+    /// From the cache, or fetched if it isn't there. [None] if the bot can't see the channel
+    pub async fn channel_name(ctx: &Context, channel_id: ChannelId) -> Option<String> {
+        match channel_id.name(&ctx.cache).await {
+            Some(name) => Some(name),
+            None => channel_id
+                .to_channel(&ctx.http)
+                .await
+                .ok()?
+                .guild()
+                .map(|channel| channel.name),
+        }
+    }
+
+    /// Pages back through the channel from [before] until messages are older than [since], or there
+    /// are [max] of them. Oldest first
+    pub async fn fetch_messages_since(
+        discord: &dyn DiscordApi,
+        channel_id: ChannelId,
+        before: MessageId,
+        since: DateTime<Utc>,
+        max: usize,
+    ) -> serenity::Result<Vec<Message>> {
+        let mut messages: Vec<Message> = vec![];
+        let mut before = before;
+        while messages.len() < max {
+            let page = discord.messages_before(channel_id, before, 100).await?;
+            let oldest = match page.iter().min_by_key(|message| message.id) {
+                Some(oldest) => oldest.clone(),
+                None => break,
+            };
+            let page_len = page.len();
+            messages.extend(
+                page.into_iter()
+                    .filter(|message| message.timestamp >= since),
+            );
+            if page_len < 100 || oldest.timestamp < since {
+                break;
+            }
+            before = oldest.id;
+        }
+        messages.sort_by_key(|message| message.id);
+        Ok(messages)
+    }
+
+    #[cfg(test)]
+    pub mod mock {
+        use crate::utils::discord::DiscordApi;
+        use serenity::async_trait;
+        use serenity::model::prelude::*;
+        use std::collections::HashMap;
+        use std::sync::Mutex;
+
+        /// Serves channel history from memory and records the content of everything sent, for
+        /// tests. Uploaded files themselves aren't kept
+        #[derive(Debug, Default)]
+        pub struct MockDiscord {
+            history: HashMap<ChannelId, Vec<Message>>,
+            pub sent: Mutex<Vec<(ChannelId, String)>>,
+        }
+
+        impl MockDiscord {
+            pub fn with_history(messages: Vec<Message>) -> Self {
+                let mut history: HashMap<ChannelId, Vec<Message>> = HashMap::new();
+                for message in messages {
+                    history.entry(message.channel_id).or_default().push(message);
+                }
+                Self {
+                    history,
+                    ..Self::default()
+                }
+            }
+
+            pub fn last_sent(&self) -> Option<String> {
+                self.sent
+                    .lock()
+                    .unwrap()
+                    .last()
+                    .map(|(_, content)| content.clone())
+            }
+        }
+
+        #[async_trait]
+        impl DiscordApi for MockDiscord {
+            async fn message(
+                &self,
+                channel_id: ChannelId,
+                message_id: MessageId,
+            ) -> serenity::Result<Message> {
+                self.history
+                    .get(&channel_id)
+                    .and_then(|messages| messages.iter().find(|m| m.id == message_id))
+                    .cloned()
+                    .ok_or(serenity::Error::Other("No such message"))
+            }
+
+            async fn messages_before(
+                &self,
+                channel_id: ChannelId,
+                before: MessageId,
+                limit: u64,
+            ) -> serenity::Result<Vec<Message>> {
+                let mut messages: Vec<Message> =
+                    self.history.get(&channel_id).map_or(vec![], |messages| {
+                        messages.iter().filter(|m| m.id < before).cloned().collect()
+                    });
+                // Newest first, like discord
+                messages.sort_by_key(|m| std::cmp::Reverse(m.id));
+                messages.truncate(limit as usize);
+                Ok(messages)
+            }
+
+            async fn say(&self, channel_id: ChannelId, content: String) -> serenity::Result<()> {
+                self.sent.lock().unwrap().push((channel_id, content));
+                Ok(())
+            }
+
+            async fn reply(&self, msg: &Message, content: String) -> serenity::Result<()> {
+                self.say(msg.channel_id, content).await
+            }
+
+            async fn send_file(
+                &self,
+                channel_id: ChannelId,
+                _filename: String,
+                _data: Vec<u8>,
+                content: String,
+            ) -> serenity::Result<()> {
+                self.say(channel_id, content).await
+            }
+        }
+    }
+}
+
 pub mod trait_extensions {
     use serenity::utils::MessageBuilder;
 
@@ -174,6 +608,55 @@ pub mod trait_extensions {
     }
 }
 
+#[cfg(test)]
+mod test_render_options {
+    use crate::utils::render_options::{extract_render_options, ImageFormat, RenderOptions};
+    use serenity::framework::standard::{Args, Delimiter};
+
+    fn args(s: &str) -> Args {
+        Args::new(s, &[Delimiter::Single(' ')])
+    }
+
+    #[test]
+    fn no_options() {
+        let (mut remaining, options) = extract_render_options(&args("#channel wolf")).unwrap();
+        assert_eq!(options, RenderOptions::default());
+        assert_eq!(remaining.len(), 2);
+        assert_eq!(remaining.single::<String>().unwrap(), "#channel");
+    }
+
+    #[test]
+    fn options_anywhere_in_args() {
+        let (remaining, options) =
+            extract_render_options(&args("--format webp #channel --size 1920x1080 wolf")).unwrap();
+        assert_eq!(options.size, Some((1920, 1080)));
+        assert_eq!(options.format, ImageFormat::Webp);
+        assert_eq!(remaining.rest(), "#channel wolf");
+    }
+
+    #[test]
+    fn invalid_options() {
+        assert!(extract_render_options(&args("#channel --size 1920")).is_err());
+        assert!(extract_render_options(&args("#channel --size 10x10")).is_err());
+        assert!(extract_render_options(&args("#channel --format gif")).is_err());
+        assert!(extract_render_options(&args("#channel --format")).is_err());
+    }
+}
+
+#[cfg(test)]
+mod test_replied {
+    use crate::utils::replied::{already_replied, AlreadyReplied};
+    use serenity::framework::standard::CommandError;
+
+    #[test]
+    fn only_marked_errors_count_as_replied() {
+        let replied: CommandError = AlreadyReplied(String::from("Not rebuilt")).into();
+        assert!(already_replied(&replied));
+        let failed: CommandError = String::from("http error").into();
+        assert!(!already_replied(&failed));
+    }
+}
+
 #[cfg(test)]
 mod test_iter {
     use std::collections::HashMap;
@@ -195,11 +678,11 @@ mod test_iter {
     fn make_iter(
         map: &HashMap<String, (usize, f32)>,
         limit: Option<usize>,
-    ) -> SortedHashMap<String, (usize, f32)> {
+    ) -> SortedHashMap<'_, String, (usize, f32)> {
         let mut keys: Vec<(&String, &usize)> = map.iter().map(|(k, (v, _))| (k, v)).collect();
         keys.sort_by_key(|(_, v)| *v);
         let keys = keys.iter().map(|(k, _v)| *k).collect();
-        SortedHashMap::new(&map, keys, limit)
+        SortedHashMap::new(map, keys, limit)
     }
 
     #[test]
@@ -8,7 +8,8 @@ MASKS = [("d20.png", "d20"), ("bunny.png", "bunny"), ("shield.png", "shield"), (
 MAX_MASK_DIM = 500
 
 #file_regex = re.compile(r'(.*).generate.json')
-output_file_name_template = "{}.generated.png"
+output_file_name_template = "{}.generated.{}"
+DEFAULT_FORMAT = "png"
 
 file_regex = re.compile(r'([a-f0-9]{8}-[a-f0-9]{4}-[a-f0-9]{4}-[a-f0-9]{4}-[a-f0-9]{12}).(?:([A-z0-9]+).)?generate.json')
 
@@ -18,24 +19,39 @@ def load_masks():
     for mask, mask_name in MASKS:
         img = PIL.Image.open(os.path.join("wordcloud\\masks", mask))
         img = img.convert('L')
-        scale = MAX_MASK_DIM / max(img.size[0], img.size[1])
-        img = PIL.ImageOps.scale(img, scale)
         sys.stderr.write("{}".format(img.size))
-        masks[mask_name] = numpy.array(img)
+        masks[mask_name] = img
     return masks
 
 
-def make_image_and_save(freq_data, request_id, output_dir, mask):
-    wc = wordcloud.WordCloud(background_color="black", max_words=1000, mask=mask)
+def scale_mask(img, size):
+    # Masks decide the shape of the image, so a requested size scales the mask to fit inside it
+    if size is None:
+        scale = MAX_MASK_DIM / max(img.size[0], img.size[1])
+    else:
+        scale = min(size[0] / img.size[0], size[1] / img.size[1])
+    return numpy.array(PIL.ImageOps.scale(img, scale))
+
+
+def make_image_and_save(freq_data, request_id, output_dir, mask, size, image_format):
+    size_kwargs = {} if size is None else {"width": size[0], "height": size[1]}
+    wc = wordcloud.WordCloud(background_color="black", max_words=1000, mask=mask, **size_kwargs)
     wc.generate_from_frequencies(freq_data)
-    output_file_name = output_file_name_template.format(request_id)
+    output_file_name = output_file_name_template.format(request_id, image_format)
     output_path = os.path.join(output_dir, output_file_name)
-    wc.to_file(output_path)
+    if image_format == "svg":
+        with io.open(output_path, mode="w", encoding="utf-8") as f:
+            f.write(wc.to_svg(embed_font=True))
+    else:
+        wc.to_file(output_path)
 
 
-def read_freq_data_from_file(filename):
+def read_request_from_file(filename):
     with io.open(filename, mode="r", encoding="utf-8") as f:
-        return json.load(f)
+        request = json.load(f)
+    width, height = request.get("width"), request.get("height")
+    size = (width, height) if width is not None and height is not None else None
+    return request["frequencies"], size, request.get("format", DEFAULT_FORMAT)
 
 
 def search_for_new_files(watch_path, ids_handled, delay=0.4):
@@ -83,13 +99,18 @@ def main():
     while True:
         (file_to_process, request_id, mask_name) = search_for_new_files(request_path, ids_handled)
         print("Found {} to process".format(file_to_process))
-        data = read_freq_data_from_file(file_to_process)
+        try:
+            data, size, image_format = read_request_from_file(file_to_process)
+        except FileNotFoundError:
+            # The bot gave up waiting and removed the request, no-one wants this image anymore
+            print("Request {} was removed before processing, skipping".format(request_id))
+            continue
         print("Successfully read file, creating wordcloud")
-        if mask_name is not None:
-            mask = masks.get(mask_name)
+        if mask_name is not None and mask_name in masks:
+            mask = scale_mask(masks[mask_name], size)
         else:
             mask = None
-        make_image_and_save(data, request_id, generated_image_path, mask)
+        make_image_and_save(data, request_id, generated_image_path, mask, size, image_format)
         dump_handled_ids(request_path, ids_handled)
 
 